@@ -0,0 +1,222 @@
+use std::{
+    env,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use opencv::core::Point;
+
+use crate::{context::Context, minimap::Minimap, player::PlayerState};
+
+/// The interval between each exported summary
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+const ACTIVITY_CSV_FILE_NAME: &str = "activity";
+
+const ACTIVITY_CSV_HEADER: &str =
+    "timestamp,actions,position_x,position_y,mobs,runes,health,max_health";
+
+/// The activity accumulated within a single [`SUMMARY_INTERVAL`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ActivitySummary {
+    /// The number of normal and priority actions executed
+    actions: u32,
+    /// The sum of all player positions for computing the centroid
+    position_sum: (i64, i64),
+    /// The number of player positions summed
+    position_count: u32,
+    /// The number of mobs detected when auto mobbing
+    mobs: u32,
+    /// The number of times a rune appeared
+    runes: u32,
+    /// The last known player current and max health
+    health: Option<(u32, u32)>,
+}
+
+impl ActivitySummary {
+    fn position_centroid(&self) -> Option<Point> {
+        (self.position_count > 0).then(|| {
+            let count = self.position_count as i64;
+            Point::new(
+                (self.position_sum.0 / count) as i32,
+                (self.position_sum.1 / count) as i32,
+            )
+        })
+    }
+
+    fn to_csv_row(self, timestamp: u64) -> String {
+        let (x, y) = self
+            .position_centroid()
+            .map(|point| (point.x.to_string(), point.y.to_string()))
+            .unwrap_or_default();
+        let (health, max_health) = self
+            .health
+            .map(|(current, max)| (current.to_string(), max.to_string()))
+            .unwrap_or_default();
+        format!(
+            "{timestamp},{},{x},{y},{},{},{health},{max_health}",
+            self.actions, self.mobs, self.runes
+        )
+    }
+}
+
+/// Summarizes the bot activity per minute and exports them to a CSV file for offline analysis
+#[derive(Debug)]
+pub struct ActivityLog {
+    /// The id of the client whose activity is exported to its own CSV file
+    client: usize,
+    started: Instant,
+    summary: ActivitySummary,
+    last_action_ids: (Option<u32>, Option<u32>),
+    last_has_rune: bool,
+}
+
+impl ActivityLog {
+    pub fn new(client: usize) -> Self {
+        Self {
+            client,
+            started: Instant::now(),
+            summary: ActivitySummary::default(),
+            last_action_ids: (None, None),
+            last_has_rune: false,
+        }
+    }
+
+    /// Records the current tick activity and appends the summary to the CSV file each minute
+    pub fn update(&mut self, context: &Context, player: &PlayerState, mobs_detected: u32) {
+        let action_ids = (player.normal_action_id(), player.priority_action_id());
+        let has_rune = matches!(context.minimap, Minimap::Idle(idle) if idle.rune.is_some());
+        self.record(
+            action_ids,
+            player.last_known_pos,
+            mobs_detected,
+            has_rune,
+            player.health,
+        );
+        if let Some(row) = self.poll_row(Instant::now()) {
+            let _ = append_csv_row(self.client, &row);
+        }
+    }
+
+    /// Resets the current summary
+    pub fn reset(&mut self) {
+        *self = ActivityLog::new(self.client);
+    }
+
+    fn record(
+        &mut self,
+        action_ids: (Option<u32>, Option<u32>),
+        pos: Option<Point>,
+        mobs_detected: u32,
+        has_rune: bool,
+        health: Option<(u32, u32)>,
+    ) {
+        let (last_normal, last_priority) = self.last_action_ids;
+        if last_normal.is_some() && last_normal != action_ids.0 {
+            self.summary.actions += 1;
+        }
+        if last_priority.is_some() && last_priority != action_ids.1 {
+            self.summary.actions += 1;
+        }
+        if has_rune && !self.last_has_rune {
+            self.summary.runes += 1;
+        }
+        if let Some(pos) = pos {
+            self.summary.position_sum.0 += pos.x as i64;
+            self.summary.position_sum.1 += pos.y as i64;
+            self.summary.position_count += 1;
+        }
+        self.summary.mobs += mobs_detected;
+        self.summary.health = health.or(self.summary.health);
+        self.last_action_ids = action_ids;
+        self.last_has_rune = has_rune;
+    }
+
+    /// Takes the summary as a CSV row if [`SUMMARY_INTERVAL`] has elapsed
+    fn poll_row(&mut self, now: Instant) -> Option<String> {
+        if now.duration_since(self.started) < SUMMARY_INTERVAL {
+            return None;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let row = self.summary.to_csv_row(timestamp);
+        self.started = now;
+        self.summary = ActivitySummary::default();
+        Some(row)
+    }
+}
+
+fn append_csv_row(client: usize, row: &str) -> Result<()> {
+    let file = csv_file(client);
+    let path = env::current_exe()?
+        .parent()
+        .map(|dir| dir.join(&file))
+        .unwrap_or_else(|| PathBuf::from(file));
+    let write_header = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if write_header {
+        writeln!(file, "{ACTIVITY_CSV_HEADER}")?;
+    }
+    writeln!(file, "{row}")?;
+    Ok(())
+}
+
+/// The CSV file name of `client`
+///
+/// The first client keeps the file name without the client id.
+fn csv_file(client: usize) -> String {
+    match client {
+        0 => format!("{ACTIVITY_CSV_FILE_NAME}.csv"),
+        client => format!("{ACTIVITY_CSV_FILE_NAME}_{client}.csv"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activity_log_record() {
+        let mut log = ActivityLog::new(0);
+
+        log.record((Some(1), None), Some(Point::new(10, 20)), 2, false, None);
+        log.record(
+            (None, Some(2)),
+            Some(Point::new(20, 40)),
+            3,
+            true,
+            Some((50, 100)),
+        );
+        log.record((None, None), None, 0, true, None);
+
+        assert_eq!(
+            log.summary,
+            ActivitySummary {
+                actions: 2,
+                position_sum: (30, 60),
+                position_count: 2,
+                mobs: 5,
+                runes: 1,
+                health: Some((50, 100)),
+            }
+        );
+        assert_eq!(log.summary.to_csv_row(0), "0,2,15,30,5,1,50,100");
+    }
+
+    #[test]
+    fn activity_log_poll_row_after_interval() {
+        let mut log = ActivityLog::new(0);
+        let started = log.started;
+
+        log.record((None, None), None, 1, false, None);
+        assert_eq!(log.poll_row(started), None);
+        assert!(log.poll_row(started + SUMMARY_INTERVAL).is_some());
+        assert_eq!(log.summary, ActivitySummary::default());
+        assert_eq!(log.started, started + SUMMARY_INTERVAL);
+    }
+}
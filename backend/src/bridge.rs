@@ -1,14 +1,26 @@
+use std::collections::VecDeque;
 use std::fmt::Debug;
-use std::{any::Any, cell::RefCell};
+use std::time::{Duration, Instant};
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+};
 
-use anyhow::Result;
-#[cfg(test)]
+use anyhow::{Result, anyhow};
+use log::warn;
+#[cfg(any(test, feature = "test-support"))]
 use mockall::automock;
 use platforms::windows::{
-    BitBltCapture, Frame, Handle, KeyInputKind, KeyKind, Keys, WgcCapture, WindowBoxCapture,
+    BitBltCapture, Error, Frame, Handle, KeyInputKind, KeyKind, Keys, ScheduledKey, WgcCapture,
+    WindowBoxCapture,
 };
 
-use crate::{CaptureMode, context::MS_PER_TICK, rpc::KeysService};
+use crate::{
+    CaptureHealth, CaptureMode,
+    context::MS_PER_TICK,
+    key_log::{self, KeyEventKind},
+    rpc::KeysService,
+};
 
 /// The input method to use for key sender
 ///
@@ -28,10 +40,30 @@ enum KeySenderKind {
 /// A trait for sending keys
 ///
 /// Mostly needed for tests
-#[cfg_attr(test, automock)]
+#[cfg_attr(any(test, feature = "test-support"), automock)]
 pub trait KeySender: Debug + Any {
     fn set_method(&mut self, method: KeySenderMethod);
 
+    /// Sets the title or class the foreground window must match for keys to be sent
+    ///
+    /// Only applies to [`KeySenderMethod::Default`].
+    fn set_foreground_interlock(&mut self, interlock: Option<String>);
+
+    /// Sets the minimum milliseconds between two consecutive presses of each key
+    ///
+    /// A press sent too soon after the previous one is rejected. Key releases are never
+    /// rejected so that keys are not stuck down.
+    fn set_min_repeat_intervals(&mut self, intervals: Vec<(KeyKind, u64)>);
+
+    /// Sets the maximum number of key presses allowed within any one second
+    ///
+    /// Presses over the limit are dropped. Key releases are never dropped. A value of `0`
+    /// disables the limit.
+    fn set_max_keys_per_second(&mut self, max: u32);
+
+    /// Returns whether any press was dropped by the rate limit since the last call
+    fn take_rate_exceeded(&self) -> bool;
+
     fn send(&self, kind: KeyKind) -> Result<()>;
 
     fn send_click_to_focus(&self) -> Result<()>;
@@ -39,17 +71,143 @@ pub trait KeySender: Debug + Any {
     fn send_up(&self, kind: KeyKind) -> Result<()>;
 
     fn send_down(&self, kind: KeyKind) -> Result<()>;
+
+    /// Schedules a sequence of key inputs to be sent at exact tick offsets
+    ///
+    /// Useful for combos that need tighter timing than what [`crate::player::Player::UseKey`]
+    /// can provide. The whole sequence is dropped if its presses exceed the rate limit or the
+    /// minimum repeat intervals.
+    fn schedule(&self, sequence: &[ScheduledKey]) -> Result<()>;
+
+    /// Sends the scheduled key inputs due in the current tick
+    ///
+    /// Must be called exactly once per tick.
+    fn update_scheduled(&self);
 }
 
 #[derive(Debug)]
 pub struct DefaultKeySender {
     kind: KeySenderKind,
+    foreground_interlock: Option<String>,
+    /// The minimum milliseconds between two consecutive presses of a key
+    min_repeat_intervals: Vec<(KeyKind, u64)>,
+    /// The last [`Instant`] each key in [`Self::min_repeat_intervals`] was pressed
+    last_pressed: RefCell<Vec<(KeyKind, Instant)>>,
+    /// The maximum number of key presses within one second, `0` if unlimited
+    max_keys_per_second: u32,
+    /// The [`Instant`]s of key presses within the last second
+    recent_presses: RefCell<VecDeque<Instant>>,
+    /// Whether a press was dropped since the last [`KeySender::take_rate_exceeded`]
+    rate_exceeded: Cell<bool>,
+    /// The keys sent down and not yet sent up
+    held_keys: RefCell<Vec<KeyKind>>,
 }
 
 impl DefaultKeySender {
     pub fn new(method: KeySenderMethod) -> Self {
         Self {
-            kind: to_key_sender_kind_from(method),
+            kind: to_key_sender_kind_from(method, None),
+            foreground_interlock: None,
+            min_repeat_intervals: vec![],
+            last_pressed: RefCell::new(vec![]),
+            max_keys_per_second: 0,
+            recent_presses: RefCell::new(VecDeque::new()),
+            rate_exceeded: Cell::new(false),
+            held_keys: RefCell::new(vec![]),
+        }
+    }
+
+    /// Updates whether `kind` is held and returns whether it changed
+    #[inline]
+    fn set_held(&self, kind: KeyKind, held: bool) -> bool {
+        let mut held_keys = self.held_keys.borrow_mut();
+        match (held_keys.iter().position(|key| *key == kind), held) {
+            (None, true) => {
+                held_keys.push(kind);
+                true
+            }
+            (Some(index), false) => {
+                held_keys.swap_remove(index);
+                true
+            }
+            (None, false) | (Some(_), true) => false,
+        }
+    }
+
+    /// Checks whether pressing `kinds` now exceeds the maximum key presses per second
+    ///
+    /// Either all or none of `kinds` are counted as pressed.
+    #[inline]
+    fn check_rate_limit(&self, kinds: &[KeyKind]) -> Result<()> {
+        if self.max_keys_per_second == 0 {
+            return Ok(());
+        }
+        let mut recent_presses = self.recent_presses.borrow_mut();
+        while recent_presses
+            .front()
+            .is_some_and(|instant| instant.elapsed() >= Duration::from_secs(1))
+        {
+            recent_presses.pop_front();
+        }
+        if recent_presses.len() + kinds.len() > self.max_keys_per_second as usize {
+            if !self.rate_exceeded.replace(true) {
+                warn!(
+                    target: "key_sender",
+                    "more than {} key presses per second, dropping {kinds:?}",
+                    self.max_keys_per_second
+                );
+            }
+            return Err(anyhow!(
+                "keys {kinds:?} exceeded {} key presses per second",
+                self.max_keys_per_second
+            ));
+        }
+        let now = Instant::now();
+        recent_presses.extend(kinds.iter().map(|_| now));
+        Ok(())
+    }
+
+    /// Checks whether pressing `kind` now violates its minimum repeat interval
+    #[inline]
+    fn check_min_repeat_interval(&self, kind: KeyKind) -> Result<()> {
+        let Some((_, millis)) = self
+            .min_repeat_intervals
+            .iter()
+            .find(|(key, _)| *key == kind)
+        else {
+            return Ok(());
+        };
+        let is_too_soon = self
+            .last_pressed
+            .borrow()
+            .iter()
+            .find(|(key, _)| *key == kind)
+            .is_some_and(|(_, instant)| instant.elapsed().as_millis() < *millis as u128);
+        if is_too_soon {
+            warn!(
+                target: "key_sender",
+                "{kind:?} pressed again within {millis} milliseconds, dropping"
+            );
+            return Err(anyhow!(
+                "key {kind:?} was pressed again within {millis} milliseconds"
+            ));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn record_pressed(&self, kind: KeyKind) {
+        if !self
+            .min_repeat_intervals
+            .iter()
+            .any(|(key, _)| *key == kind)
+        {
+            return;
+        }
+        let mut last_pressed = self.last_pressed.borrow_mut();
+        match last_pressed.iter_mut().find(|(key, _)| *key == kind) {
+            Some((_, instant)) => *instant = Instant::now(),
+            None => last_pressed.push((kind, Instant::now())),
         }
     }
 }
@@ -71,32 +229,54 @@ impl KeySender for DefaultKeySender {
             }
             KeySenderMethod::Default(_, _) => (),
         }
-        self.kind = to_key_sender_kind_from(method);
+        self.kind = to_key_sender_kind_from(method, self.foreground_interlock.clone());
+    }
+
+    fn set_foreground_interlock(&mut self, interlock: Option<String>) {
+        if let KeySenderKind::Default(ref mut keys) = self.kind {
+            keys.set_foreground_interlock(interlock.clone());
+        }
+        self.foreground_interlock = interlock;
+    }
+
+    fn set_min_repeat_intervals(&mut self, intervals: Vec<(KeyKind, u64)>) {
+        self.min_repeat_intervals = intervals;
+        self.last_pressed.borrow_mut().clear();
+    }
+
+    fn set_max_keys_per_second(&mut self, max: u32) {
+        self.max_keys_per_second = max;
+        self.recent_presses.borrow_mut().clear();
+    }
+
+    fn take_rate_exceeded(&self) -> bool {
+        self.rate_exceeded.take()
     }
 
     fn send(&self, kind: KeyKind) -> Result<()> {
+        self.check_min_repeat_interval(kind)?;
+        self.check_rate_limit(&[kind])?;
         match &self.kind {
             KeySenderKind::Rpc(service) => {
                 if let Some(cell) = service {
                     cell.borrow_mut().send(kind)?;
                 }
-                Ok(())
-            }
-            KeySenderKind::Default(keys) => {
-                keys.send(kind)?;
-                Ok(())
             }
+            KeySenderKind::Default(keys) => keys.send(kind)?,
         }
+        self.record_pressed(kind);
+        self.set_held(kind, false);
+        key_log::record(KeyEventKind::Press, Some(kind));
+        Ok(())
     }
 
     fn send_click_to_focus(&self) -> Result<()> {
         match &self.kind {
-            KeySenderKind::Rpc(_) => Ok(()),
-            KeySenderKind::Default(keys) => {
-                keys.send_click_to_focus()?;
-                Ok(())
-            }
+            KeySenderKind::Rpc(_) => return Ok(()),
+            KeySenderKind::Default(keys) => keys.send_click_to_focus()?,
         }
+        key_log::record(KeyEventKind::ClickToFocus, None);
+        Ok(())
     }
 
     fn send_up(&self, kind: KeyKind) -> Result<()> {
@@ -105,29 +285,79 @@ impl KeySender for DefaultKeySender {
                 if let Some(cell) = service {
                     cell.borrow_mut().send_up(kind)?;
                 }
-                Ok(())
-            }
-            KeySenderKind::Default(keys) => {
-                keys.send_up(kind)?;
-                Ok(())
             }
+            KeySenderKind::Default(keys) => keys.send_up(kind)?,
+        }
+        // Only logs the release of a held key since keys are released defensively every tick
+        if self.set_held(kind, false) {
+            key_log::record(KeyEventKind::Up, Some(kind));
         }
+        Ok(())
     }
 
     fn send_down(&self, kind: KeyKind) -> Result<()> {
+        // Held keys are sent down every tick so only the first down counts as a press
+        let is_held = self.held_keys.borrow().contains(&kind);
+        if !is_held {
+            self.check_min_repeat_interval(kind)?;
+            self.check_rate_limit(&[kind])?;
+        }
         match &self.kind {
             KeySenderKind::Rpc(service) => {
                 if let Some(cell) = service {
                     cell.borrow_mut().send_down(kind)?;
                 }
-                Ok(())
             }
+            KeySenderKind::Default(keys) => keys.send_down(kind)?,
+        }
+        if self.set_held(kind, true) {
+            self.record_pressed(kind);
+            key_log::record(KeyEventKind::Down, Some(kind));
+        }
+        Ok(())
+    }
+
+    fn schedule(&self, sequence: &[ScheduledKey]) -> Result<()> {
+        match &self.kind {
+            KeySenderKind::Rpc(_) => Err(anyhow!(
+                "scheduling keys is not supported by the RPC input method"
+            )),
             KeySenderKind::Default(keys) => {
-                keys.send_down(kind)?;
+                // The presses are limited when queued so that a combo is either sent entirely or
+                // dropped instead of being cut off midway
+                let presses = sequence
+                    .iter()
+                    .filter(|key| key.is_down)
+                    .map(|key| key.kind)
+                    .collect::<Vec<_>>();
+                for kind in presses.iter().copied() {
+                    self.check_min_repeat_interval(kind)?;
+                }
+                self.check_rate_limit(&presses)?;
+                keys.schedule(sequence);
+                for kind in presses {
+                    self.record_pressed(kind);
+                }
                 Ok(())
             }
         }
     }
+
+    fn update_scheduled(&self) {
+        if let KeySenderKind::Default(keys) = &self.kind {
+            for key in keys.update_scheduled() {
+                if !self.set_held(key.kind, key.is_down) {
+                    continue;
+                }
+                let kind = if key.is_down {
+                    KeyEventKind::Down
+                } else {
+                    KeyEventKind::Up
+                };
+                key_log::record(kind, Some(key.kind));
+            }
+        }
+    }
 }
 
 /// A bridge enum for platform and database
@@ -138,16 +368,31 @@ pub enum ImageCaptureKind {
     BitBltArea(WindowBoxCapture),
 }
 
+/// The interval between each check of the captured window process
+const PROCESS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 /// A struct for managing different capture modes
 #[derive(Debug)]
 pub struct ImageCapture {
     kind: ImageCaptureKind,
+    health: CaptureHealth,
+    handle: Handle,
+    /// The process id of the captured window when it is first found after binding
+    process_id: Option<u32>,
+    /// Whether the captured window now belongs to a different process than [`Self::process_id`]
+    process_changed: bool,
+    process_checked: Option<Instant>,
 }
 
 impl ImageCapture {
     pub fn new(handle: Handle, mode: CaptureMode) -> Self {
         Self {
             kind: to_image_capture_kind_from(handle, mode),
+            health: CaptureHealth::default(),
+            handle,
+            process_id: handle.process_id(),
+            process_changed: false,
+            process_checked: None,
         }
     }
 
@@ -155,28 +400,88 @@ impl ImageCapture {
         &self.kind
     }
 
+    /// The health of the last [`Self::grab`]
+    pub fn health(&self) -> CaptureHealth {
+        self.health
+    }
+
     pub fn grab(&mut self) -> Option<Frame> {
-        match &mut self.kind {
-            ImageCaptureKind::BitBlt(capture) => capture.grab().ok(),
-            ImageCaptureKind::Wgc(capture) => {
-                capture.as_mut().and_then(|capture| capture.grab().ok())
-            }
-            ImageCaptureKind::BitBltArea(capture) => capture.grab().ok(),
+        // Stops capturing until re-selected so a different game client is not captured silently
+        if self.process_changed {
+            self.health = CaptureHealth::WindowChanged;
+            return None;
         }
+        let result = match &mut self.kind {
+            ImageCaptureKind::BitBlt(capture) => capture.grab(),
+            ImageCaptureKind::Wgc(Some(capture)) => capture.grab(),
+            ImageCaptureKind::Wgc(None) => {
+                self.health = CaptureHealth::Unavailable;
+                return None;
+            }
+            ImageCaptureKind::BitBltArea(capture) => capture.grab(),
+        };
+        self.health = match &result {
+            Ok(_) => CaptureHealth::Healthy,
+            Err(Error::WindowOccluded) => CaptureHealth::WindowOccluded,
+            Err(Error::WindowNotFound) => CaptureHealth::WindowNotFound,
+            Err(_) => CaptureHealth::FrameNotAvailable,
+        };
+        result.ok()
     }
 
     pub fn set_mode(&mut self, handle: Handle, mode: CaptureMode) {
         self.kind = to_image_capture_kind_from(handle, mode);
+        self.health = CaptureHealth::default();
+        self.handle = handle;
+        self.process_id = handle.process_id();
+        self.process_changed = false;
+    }
+
+    /// Checks whether the captured window process has changed (e.g. game client restarted)
+    ///
+    /// Returns `true` only once when the change is first detected. The capture is stopped until
+    /// [`Self::set_mode`] re-binds the window.
+    pub fn update_process(&mut self) -> bool {
+        if self.process_changed
+            || matches!(self.kind, ImageCaptureKind::BitBltArea(_))
+            || self
+                .process_checked
+                .is_some_and(|instant| instant.elapsed() < PROCESS_CHECK_INTERVAL)
+        {
+            return false;
+        }
+        self.process_checked = Some(Instant::now());
+        let Some(process_id) = self.handle.process_id() else {
+            return false;
+        };
+        match self.process_id {
+            Some(bound) if bound != process_id => {
+                self.process_changed = true;
+                true
+            }
+            Some(_) => false,
+            None => {
+                self.process_id = Some(process_id);
+                false
+            }
+        }
     }
 }
 
 #[inline]
-fn to_key_sender_kind_from(method: KeySenderMethod) -> KeySenderKind {
+fn to_key_sender_kind_from(
+    method: KeySenderMethod,
+    foreground_interlock: Option<String>,
+) -> KeySenderKind {
     match method {
         KeySenderMethod::Rpc(url) => {
             KeySenderKind::Rpc(KeysService::connect(url).map(RefCell::new).ok())
         }
-        KeySenderMethod::Default(handle, kind) => KeySenderKind::Default(Keys::new(handle, kind)),
+        KeySenderMethod::Default(handle, kind) => {
+            let mut keys = Keys::new(handle, kind);
+            keys.set_foreground_interlock(foreground_interlock);
+            KeySenderKind::Default(keys)
+        }
     }
 }
 
@@ -190,3 +495,57 @@ fn to_image_capture_kind_from(handle: Handle, mode: CaptureMode) -> ImageCapture
         CaptureMode::BitBltArea => ImageCaptureKind::BitBltArea(WindowBoxCapture::default()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(tick_offset: u32, kind: KeyKind) -> [ScheduledKey; 2] {
+        [
+            ScheduledKey {
+                tick_offset,
+                kind,
+                is_down: true,
+            },
+            ScheduledKey {
+                tick_offset: tick_offset + 1,
+                kind,
+                is_down: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn key_sender_schedule_drops_burst_over_rate_limit() {
+        let mut keys = DefaultKeySender::new(KeySenderMethod::Default(
+            Handle::new(""),
+            KeyInputKind::Fixed,
+        ));
+        keys.set_max_keys_per_second(3);
+        let burst = [
+            press(0, KeyKind::A),
+            press(1, KeyKind::B),
+            press(2, KeyKind::C),
+        ]
+        .concat();
+
+        assert!(keys.schedule(&burst[..4]).is_ok());
+        assert!(!keys.take_rate_exceeded());
+        assert!(keys.schedule(&burst).is_err());
+        assert!(keys.take_rate_exceeded());
+        assert!(keys.schedule(&burst[..2]).is_ok());
+    }
+
+    #[test]
+    fn key_sender_schedule_drops_press_within_min_repeat_interval() {
+        let mut keys = DefaultKeySender::new(KeySenderMethod::Default(
+            Handle::new(""),
+            KeyInputKind::Fixed,
+        ));
+        keys.set_min_repeat_intervals(vec![(KeyKind::A, 60_000)]);
+
+        assert!(keys.schedule(&press(0, KeyKind::A)).is_ok());
+        assert!(keys.schedule(&press(0, KeyKind::A)).is_err());
+        assert!(keys.schedule(&press(0, KeyKind::B)).is_ok());
+    }
+}
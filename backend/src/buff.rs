@@ -1,10 +1,8 @@
-use std::{
-    mem,
-    ops::{Index, IndexMut},
-};
+use std::ops::{Index, IndexMut};
 
 use anyhow::Result;
-use strum::EnumIter;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumCount, EnumIter, EnumString};
 
 use crate::{
     Configuration, Settings,
@@ -85,12 +83,24 @@ pub enum Buff {
     HasBuff,
 }
 
-#[derive(Clone, Copy, Debug, EnumIter)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    EnumCount,
+    Display,
+    EnumString,
+)]
 #[repr(usize)]
 pub enum BuffKind {
     /// NOTE: Upon failing to solving rune, there is a cooldown
     /// that looks exactly like the normal rune buff
+    #[default]
     Rune,
     SayramElixir,
     AureliaElixir,
@@ -106,10 +116,6 @@ pub enum BuffKind {
     ExtremeGoldPotion,
 }
 
-impl BuffKind {
-    pub const COUNT: usize = mem::variant_count::<BuffKind>();
-}
-
 impl Index<BuffKind> for [Buff; BuffKind::COUNT] {
     type Output = Buff;
 
@@ -0,0 +1,211 @@
+use std::time::Instant;
+
+/// The statistics of a preset recorded while comparing rotations
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PresetComparison {
+    pub preset: String,
+    /// The number of measured normal actions loops
+    pub loops: u32,
+    /// The total duration in milliseconds of the measured loops
+    pub loops_millis: u64,
+    /// The number of mobs detected when auto mobbing
+    pub mobs: u32,
+    /// The number of times a rune appeared
+    pub runes: u32,
+}
+
+impl PresetComparison {
+    fn new(preset: String) -> Self {
+        Self {
+            preset,
+            ..Self::default()
+        }
+    }
+
+    /// The average duration in milliseconds of a loop or [`None`] if no loop is measured
+    pub fn average_loop_millis(&self) -> Option<u64> {
+        (self.loops > 0).then(|| self.loops_millis / self.loops as u64)
+    }
+}
+
+/// Alternates between two presets every fixed number of loops and records their statistics
+///
+/// The first loop after switching preset or resuming from halt is not measured because it
+/// includes the time to walk to the start position and warming up.
+#[derive(Debug, Default)]
+pub struct AbComparison {
+    /// The statistics of the two compared presets, empty if not comparing
+    stats: Vec<PresetComparison>,
+    /// The number of loops before switching to the other preset
+    loops_per_switch: u32,
+    /// The index in [`Self::stats`] of the preset currently rotating
+    current: usize,
+    loops_since_switch: u32,
+    last_loops: Option<u32>,
+    loop_started: Option<Instant>,
+    last_has_rune: bool,
+    /// Whether switching to the other preset is pending
+    switch_pending: bool,
+}
+
+impl AbComparison {
+    /// Starts comparing `preset_a` with `preset_b` switching every `loops_per_switch` loops
+    ///
+    /// Statistics are kept if the same presets are already being compared.
+    pub fn start(&mut self, preset_a: String, preset_b: String, loops_per_switch: u32) {
+        if self.is_comparing(&preset_a, &preset_b) {
+            self.loops_per_switch = loops_per_switch;
+            return;
+        }
+        *self = AbComparison {
+            stats: vec![
+                PresetComparison::new(preset_a),
+                PresetComparison::new(preset_b),
+            ],
+            loops_per_switch,
+            ..AbComparison::default()
+        };
+    }
+
+    /// Stops comparing and clears the statistics
+    pub fn stop(&mut self) {
+        *self = AbComparison::default();
+    }
+
+    /// The preset currently rotating if comparing
+    pub fn current_preset(&self) -> Option<&str> {
+        self.stats
+            .get(self.current)
+            .map(|stats| stats.preset.as_str())
+    }
+
+    /// The statistics of the compared presets, empty if not comparing
+    pub fn stats(&self) -> &[PresetComparison] {
+        &self.stats
+    }
+
+    /// Records the current tick given the rotator loop count and the detected mobs count
+    pub fn update(
+        &mut self,
+        rotating: bool,
+        normal_loops: u32,
+        mobs_detected: u32,
+        has_rune: bool,
+        now: Instant,
+    ) {
+        if self.stats.is_empty() {
+            return;
+        }
+        if !rotating {
+            self.last_loops = None;
+            self.loop_started = None;
+            self.last_has_rune = has_rune;
+            return;
+        }
+
+        let stats = &mut self.stats[self.current];
+        stats.mobs += mobs_detected;
+        if has_rune && !self.last_has_rune {
+            stats.runes += 1;
+        }
+        self.last_has_rune = has_rune;
+
+        let Some(last_loops) = self.last_loops.replace(normal_loops) else {
+            return;
+        };
+        if last_loops == normal_loops {
+            return;
+        }
+        if let Some(started) = self.loop_started {
+            stats.loops += 1;
+            stats.loops_millis += now.duration_since(started).as_millis() as u64;
+        }
+        self.loop_started = Some(now);
+        self.loops_since_switch += 1;
+        if self.loops_since_switch >= self.loops_per_switch.max(1) {
+            self.switch_pending = true;
+        }
+    }
+
+    /// Takes the preset to switch to if switching is pending
+    pub fn take_switch(&mut self) -> Option<String> {
+        if !self.switch_pending {
+            return None;
+        }
+        self.switch_pending = false;
+        self.current = (self.current + 1) % self.stats.len();
+        self.loops_since_switch = 0;
+        self.last_loops = None;
+        self.loop_started = None;
+        self.current_preset().map(str::to_string)
+    }
+
+    #[inline]
+    fn is_comparing(&self, preset_a: &str, preset_b: &str) -> bool {
+        matches!(self.stats.as_slice(), [a, b] if a.preset == preset_a && b.preset == preset_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn ab_comparison_switch_and_stats() {
+        let mut comparison = AbComparison::default();
+        comparison.start("a".to_string(), "b".to_string(), 2);
+        let now = Instant::now();
+
+        comparison.update(true, 0, 1, false, now);
+        // First loop is not measured
+        comparison.update(true, 1, 2, true, now + Duration::from_secs(5));
+        assert_eq!(comparison.take_switch(), None);
+        comparison.update(true, 2, 0, true, now + Duration::from_secs(15));
+        assert_eq!(comparison.take_switch(), Some("b".to_string()));
+        assert_eq!(comparison.current_preset(), Some("b"));
+
+        assert_eq!(
+            comparison.stats()[0],
+            PresetComparison {
+                preset: "a".to_string(),
+                loops: 1,
+                loops_millis: 10_000,
+                mobs: 3,
+                runes: 1,
+            }
+        );
+        assert_eq!(comparison.stats()[0].average_loop_millis(), Some(10_000));
+        assert_eq!(comparison.stats()[1].average_loop_millis(), None);
+    }
+
+    #[test]
+    fn ab_comparison_halting_discards_partial_loop() {
+        let mut comparison = AbComparison::default();
+        comparison.start("a".to_string(), "b".to_string(), 5);
+        let now = Instant::now();
+
+        comparison.update(true, 0, 0, false, now);
+        comparison.update(true, 1, 0, false, now);
+        comparison.update(false, 1, 0, false, now + Duration::from_secs(60));
+        comparison.update(true, 1, 0, false, now + Duration::from_secs(60));
+        comparison.update(true, 2, 0, false, now + Duration::from_secs(70));
+
+        assert_eq!(comparison.stats()[0].loops, 0);
+    }
+
+    #[test]
+    fn ab_comparison_start_keeps_stats_of_same_presets() {
+        let mut comparison = AbComparison::default();
+        comparison.start("a".to_string(), "b".to_string(), 1);
+        comparison.update(true, 0, 4, false, Instant::now());
+
+        comparison.start("a".to_string(), "b".to_string(), 3);
+        assert_eq!(comparison.stats()[0].mobs, 4);
+
+        comparison.start("a".to_string(), "c".to_string(), 3);
+        assert_eq!(comparison.stats()[0].mobs, 0);
+        assert_eq!(comparison.stats()[1].preset, "c");
+    }
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+use anyhow::{Error, Result, anyhow, bail};
+use strum::IntoEnumIterator;
+
+use crate::KeyBinding;
+
+/// The usage shown when a console command cannot be parsed
+const CONSOLE_USAGE: &str = "commands: halt, start, goto <x> <y>, usekey <key>, dump state";
+
+/// A command typed into the developer console
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConsoleCommand {
+    /// Halts rotating actions
+    Halt,
+    /// Starts rotating actions
+    Start,
+    /// Moves the player to the position relative to the minimap bottom-left corner
+    Goto(i32, i32),
+    /// Sends a single key press
+    UseKey(KeyBinding),
+    /// Dumps the current game state
+    DumpState,
+}
+
+impl FromStr for ConsoleCommand {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut args = s.split_whitespace();
+        let Some(name) = args.next() else {
+            bail!("empty command, {CONSOLE_USAGE}");
+        };
+        let command = match name.to_ascii_lowercase().as_str() {
+            "halt" => ConsoleCommand::Halt,
+            "start" => ConsoleCommand::Start,
+            "goto" => {
+                let x = parse_arg::<i32>(args.next(), "x")?;
+                let y = parse_arg::<i32>(args.next(), "y")?;
+                ConsoleCommand::Goto(x, y)
+            }
+            "usekey" => {
+                let key = args.next().ok_or(anyhow!("missing argument key"))?;
+                // Case-insensitive so `usekey f` and `usekey F` are the same
+                let key = KeyBinding::iter()
+                    .find(|binding| binding.to_string().eq_ignore_ascii_case(key))
+                    .ok_or(anyhow!("unknown key {key}"))?;
+                ConsoleCommand::UseKey(key)
+            }
+            "dump" => match args.next() {
+                Some(arg) if arg.eq_ignore_ascii_case("state") => ConsoleCommand::DumpState,
+                _ => bail!("unknown dump target, expected dump state"),
+            },
+            _ => bail!("unknown command {name}, {CONSOLE_USAGE}"),
+        };
+        if let Some(arg) = args.next() {
+            bail!("unexpected argument {arg}");
+        }
+        Ok(command)
+    }
+}
+
+#[inline]
+fn parse_arg<T: FromStr>(arg: Option<&str>, name: &str) -> Result<T> {
+    let arg = arg.ok_or(anyhow!("missing argument {name}"))?;
+    arg.parse::<T>()
+        .map_err(|_| anyhow!("invalid argument {name} {arg}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            "halt".parse::<ConsoleCommand>().unwrap(),
+            ConsoleCommand::Halt
+        );
+        assert_eq!(
+            " START ".parse::<ConsoleCommand>().unwrap(),
+            ConsoleCommand::Start
+        );
+        assert_eq!(
+            "goto 12 -3".parse::<ConsoleCommand>().unwrap(),
+            ConsoleCommand::Goto(12, -3)
+        );
+        assert_eq!(
+            "usekey f".parse::<ConsoleCommand>().unwrap(),
+            ConsoleCommand::UseKey(KeyBinding::F)
+        );
+        assert_eq!(
+            "dump state".parse::<ConsoleCommand>().unwrap(),
+            ConsoleCommand::DumpState
+        );
+    }
+
+    #[test]
+    fn parse_invalid_commands() {
+        assert!("".parse::<ConsoleCommand>().is_err());
+        assert!("jump".parse::<ConsoleCommand>().is_err());
+        assert!("goto 12".parse::<ConsoleCommand>().is_err());
+        assert!("goto a 3".parse::<ConsoleCommand>().is_err());
+        assert!("usekey NotAKey".parse::<ConsoleCommand>().is_err());
+        assert!("dump".parse::<ConsoleCommand>().is_err());
+        assert!("halt now".parse::<ConsoleCommand>().is_err());
+    }
+}
@@ -3,42 +3,112 @@ use std::{
     env,
     fs::File,
     io::Write,
+    mem,
+    panic::{self, AssertUnwindSafe},
     rc::Rc,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+        mpsc::sync_channel,
+    },
     thread,
     time::{Duration, Instant},
 };
 
 use dyn_clone::clone_box;
+use log::{error, info, warn};
 use opencv::{
-    core::{Vector, VectorToVec},
+    core::{MatTraitConst, Point, Size, Vector, VectorToVec},
     imgcodecs::imencode_def,
 };
-use platforms::windows::{self, Handle, KeyInputKind, KeyReceiver};
-use strum::IntoEnumIterator;
-use tokio::sync::broadcast;
+use platforms::windows::{self, Handle, KeyInputKind, KeyKind, KeyReceiver};
+use strum::{EnumCount, IntoEnumIterator};
+use tokio::sync::{broadcast, mpsc};
 
+#[cfg(debug_assertions)]
+use crate::frame_history::FrameHistory;
+#[cfg(feature = "audio-capture")]
+use crate::rune_chime::RuneChimeListener;
 use crate::{
-    Action, RequestHandler,
+    Action, AutoMobLearned, CaptureHandle, Configuration, HaltEvent, HaltReason, MinimapData,
+    RequestHandler, RequestItem, RotationMode, Settings,
+    activity::ActivityLog,
     bridge::{DefaultKeySender, ImageCapture, ImageCaptureKind, KeySender, KeySenderMethod},
     buff::{Buff, BuffKind, BuffState},
-    database::{CaptureMode, InputMethod, KeyBinding},
-    detect::{CachedDetector, Detector},
+    comparison::AbComparison,
+    database::{
+        CaptureHandleIdentity, CaptureMode, InputMethod, KeyBinding, WindowLayout,
+        query_action_timestamps, upsert_action_timestamps, upsert_auto_mob_learned,
+    },
+    detect::{
+        CachedDetector, Detector, ResolutionPreset, is_minimap_model_available,
+        is_mob_model_available, is_rune_model_available,
+    },
+    freeze::FrameFreeze,
+    health::{self, TickHealth},
+    keep_alive::KeepAlive,
+    key_log,
     mat::OwnedMat,
     minimap::{Minimap, MinimapState},
-    network::{DiscordNotification, NotificationKind},
+    navigation::{Navigation, NavigationUpdate},
+    network::{DiscordNotification, NotificationKind, local_minute_of_day},
     player::{Player, PlayerState},
     query_configs, query_settings,
+    quota::RunTimeQuota,
+    register_client,
     request_handler::{DefaultRequestHandler, config_buffs},
     rotator::Rotator,
+    scheduler::{ScheduleEvent, Scheduler},
     skill::{Skill, SkillKind, SkillState},
+    state_stream::GameStateStream,
+    sync, transition_log, upsert_settings,
 };
-#[cfg(test)]
-use crate::{Settings, bridge::MockKeySender, detect::MockDetector};
+#[cfg(any(test, feature = "test-support"))]
+use crate::{bridge::MockKeySender, detect::MockDetector};
 
 const FPS: u32 = 30;
 pub const MS_PER_TICK: u64 = 1000 / FPS as u64;
 
+/// The class prefix of the game window
+///
+/// MapleStoryClass <- GMS
+/// MapleStoryClassSG <- MSEA
+/// MapleStoryClassTW <- TMS
+pub(crate) const GAME_WINDOW_CLASS: &str = "MapleStoryClass";
+
+/// The number of seconds without a tick before the update loop is considered stalled and its
+/// actions are halted
+const WATCHDOG_STALL_SECS: u64 = 10;
+
+/// The seconds between attempts to apply [`WindowLayout`] while the game window is unavailable
+const WINDOW_LAYOUT_RETRY_SECS: u64 = 5;
+
+/// The number of consecutive restarts after which the update loop halts actions and stops
+/// restarting if it panics again
+const MAX_RESTARTS: u32 = 5;
+
+/// The seconds to wait before the first restart, doubled on each consecutive restart
+const RESTART_BACKOFF_SECS: u64 = 1;
+
+/// The seconds the update loop must run without panicking to reset the consecutive restarts
+const RESTART_RESET_SECS: u64 = 600;
+
+/// The seconds between persisting the learned auto mobbing data into the current map
+const AUTO_MOB_LEARNED_SAVE_SECS: u64 = 60;
+
+thread_local! {
+    /// The last state provided by UI to the client update loop on this thread
+    static PRESERVED: RefCell<PreservedState> = RefCell::default();
+}
+
+/// The state provided by UI that is restored when [`update_loop`] is restarted
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PreservedState {
+    pub config: Option<Configuration>,
+    pub settings: Option<Settings>,
+    pub minimap: Option<(Option<String>, MinimapData)>,
+}
+
 /// Represents a control flow after a context update
 pub enum ControlFlow<T> {
     /// The context is updated immediately
@@ -50,7 +120,7 @@ pub enum ControlFlow<T> {
 /// Represents a context-based state
 pub trait Contextual {
     /// Represents a state that is persistent through each `update` tick.
-    type Persistent = ();
+    type Persistent;
 
     /// Updates the contextual state.
     ///
@@ -77,10 +147,12 @@ pub struct Context {
     pub skills: [Skill; SkillKind::COUNT],
     pub buffs: [Buff; BuffKind::COUNT],
     pub halting: bool,
+    /// The reason [`Self::halting`] is set if the bot halted by itself
+    pub halt_event: Option<HaltEvent>,
 }
 
 impl Context {
-    #[cfg(test)]
+    #[cfg(any(test, feature = "test-support"))]
     pub fn new(keys: Option<MockKeySender>, detector: Option<MockDetector>) -> Self {
         Context {
             handle: Handle::new(""),
@@ -92,6 +164,7 @@ impl Context {
             skills: [Skill::Detecting; SkillKind::COUNT],
             buffs: [Buff::NoBuff; BuffKind::COUNT],
             halting: false,
+            halt_event: None,
         }
     }
 
@@ -127,49 +200,217 @@ pub fn init() {
         }
         ort::init_from(dll.to_str().unwrap()).commit().unwrap();
         windows::init();
-        thread::spawn(|| {
-            let tokio_rt = tokio::runtime::Builder::new_multi_thread()
-                .enable_all()
-                .build()
-                .unwrap();
-            let _tokio_guard = tokio_rt.enter();
-            tokio_rt.block_on(async {
-                update_loop();
-            });
-        });
+        sync::spawn_sync_loop();
+        spawn_client(None);
+        // Clients whose game window is gone are dropped so that the saved windows stay in the
+        // same order as the running clients
+        let mut settings = query_settings();
+        let count = settings.additional_clients.len();
+        settings
+            .additional_clients
+            .retain(|window| spawn_client(Some(window)).is_some());
+        if settings.additional_clients.len() != count
+            && let Err(err) = upsert_settings(&mut settings)
+        {
+            warn!(target: "context", "failed to persist removed clients {err}");
+        }
     }
 }
 
+/// Spawns the update loop of a new client botting `window` and returns its id
+///
+/// Only the first client is spawned without `window`, botting the default game window or the
+/// capture handle saved in [`Settings::capture_handle`]. Returns [`None`] if `window` cannot be
+/// found. The update loop stops once the client is removed.
+pub(crate) fn spawn_client(window: Option<&CaptureHandleIdentity>) -> Option<usize> {
+    let window = window.cloned();
+    let (id_tx, id_rx) = sync_channel(1);
+    let last_tick = Arc::new(Mutex::new(Instant::now()));
+    let loop_last_tick = last_tick.clone();
+    let stalled = Arc::new(AtomicBool::new(false));
+    let loop_stalled = stalled.clone();
+    thread::spawn(move || {
+        // Handles cannot be sent across threads so the window is found on the loop thread
+        let window = match window {
+            Some(identity) => {
+                let handles = windows::query_capture_handles();
+                let Some(handle) = find_capture_handle(&handles, &identity) else {
+                    warn!(
+                        target: "context",
+                        "window {} of process {} not found for new client",
+                        identity.title,
+                        identity.process_id
+                    );
+                    let _ = id_tx.send(None);
+                    return;
+                };
+                Some(handle)
+            }
+            None => None,
+        };
+        let (id, mut requests) = register_client();
+        let _ = id_tx.send(Some(id));
+        let tokio_rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let _tokio_guard = tokio_rt.enter();
+        let mut restarts = 0;
+        // Restarts the loop with the last state provided by UI if it ever panics
+        loop {
+            let started = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                tokio_rt.block_on(async {
+                    update_loop(
+                        id,
+                        window,
+                        &mut requests,
+                        &loop_last_tick,
+                        &loop_stalled,
+                        restarts,
+                    );
+                });
+            }));
+            if result.is_ok() {
+                break;
+            }
+            if started.elapsed().as_secs() >= RESTART_RESET_SECS {
+                restarts = 0;
+            }
+            if restarts >= MAX_RESTARTS {
+                error!(
+                    target: "context",
+                    "update loop of client {id} panicked after {restarts} restarts, stopping"
+                );
+                break;
+            }
+            let backoff = Duration::from_secs(RESTART_BACKOFF_SECS << restarts);
+            restarts += 1;
+            error!(
+                target: "context",
+                "update loop of client {id} panicked, restarting in {backoff:?}"
+            );
+            thread::sleep(backoff);
+        }
+        health::remove(id);
+        info!(target: "context", "client {id} removed");
+    });
+    let id = id_rx.recv().ok().flatten()?;
+    thread::spawn(move || {
+        let mut was_stalled = false;
+        // Stops watching once the update loop thread exits and drops its reference
+        while Arc::strong_count(&last_tick) > 1 {
+            thread::sleep(Duration::from_secs(WATCHDOG_STALL_SECS));
+            let elapsed = last_tick
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .elapsed();
+            let is_stalled = elapsed.as_secs() >= WATCHDOG_STALL_SECS;
+            if is_stalled && !was_stalled {
+                error!(
+                    target: "context",
+                    "update loop of client {id} has not ticked for {elapsed:?}"
+                );
+                // Halts actions once the update loop ticks again
+                stalled.store(true, Ordering::Release);
+            }
+            was_stalled = is_stalled;
+        }
+    });
+    Some(id)
+}
+
+/// Updates the state to restore when [`update_loop`] of the client on this thread is restarted
+pub(crate) fn preserve_state(update: impl FnOnce(&mut PreservedState)) {
+    PRESERVED.with_borrow_mut(update);
+}
+
+/// The state provided by UI to the client on this thread
+pub(crate) fn preserved_state() -> PreservedState {
+    PRESERVED.with_borrow(Clone::clone)
+}
+
 #[inline]
-fn update_loop() {
-    // MapleStoryClass <- GMS
-    // MapleStoryClassSG <- MSEA
-    // MapleStoryClassTW <- TMS
-    let handle = Handle::new("MapleStoryClass");
+fn update_loop(
+    id: usize,
+    window: Option<Handle>,
+    requests: &mut mpsc::Receiver<RequestItem>,
+    last_tick: &Mutex<Instant>,
+    stalled: &AtomicBool,
+    restarts: u32,
+) {
+    let mut restarted = restarts > 0;
+    let mut restart_limit_reached = restarts >= MAX_RESTARTS;
+    let handle = window.unwrap_or(Handle::new(GAME_WINDOW_CLASS));
+    // Loads the models up front so failures are reported before the first detection
+    if !is_mob_model_available() {
+        warn!(target: "context", "mob detection model unavailable, auto mobbing is disabled");
+    }
+    if !is_minimap_model_available() {
+        warn!(target: "context", "minimap detection model unavailable, using fallback detection");
+    }
+    if !is_rune_model_available() {
+        warn!(target: "context", "rune detection model unavailable, rune solving is disabled");
+    }
     let mut rotator = Rotator::default();
+    rotator.load_fired_timestamps(query_action_timestamps().unwrap_or_default());
+    let mut navigation = Navigation::default();
+    let mut frame_freeze = FrameFreeze::default();
+    // Whether actions were running before the current freeze halted them
+    let mut halted_by_freeze = false;
+    let mut keep_alive = KeepAlive::default();
+    let mut activity_log = ActivityLog::new(id);
+    let mut ab_comparison = AbComparison::default();
+    let mut state_stream = GameStateStream::default();
+    let mut map_imports = sync::map_import_receiver();
+    let mut run_time_quota = RunTimeQuota::default();
+    let mut scheduler = Scheduler::default();
+    #[cfg(feature = "audio-capture")]
+    let mut rune_chime = RuneChimeListener::default();
     let mut actions = Vec::<Action>::new();
     let mut config = query_configs().unwrap().into_iter().next().unwrap(); // Override by UI
     let mut buffs = config_buffs(&config);
     let settings = query_settings(); // Override by UI
 
+    let mut capture_handles = Vec::<(String, Handle)>::new();
+    let mut selected_capture_handle = None;
+    if window.is_none()
+        && let Some(identity) = settings.capture_handle.as_ref()
+    {
+        capture_handles = windows::query_capture_handles();
+        selected_capture_handle = find_capture_handle(&capture_handles, identity);
+        if selected_capture_handle.is_none() {
+            warn!(
+                target: "context",
+                "capture handle {} of process {} not found for client {id}, re-select capture handle",
+                identity.title,
+                identity.process_id
+            );
+        }
+    }
+    let capture_handle = selected_capture_handle.unwrap_or(handle);
+
     let key_sender_method = if let InputMethod::Rpc = settings.input_method {
         KeySenderMethod::Rpc(settings.input_method_rpc_server_url.clone())
     } else {
         match settings.capture_mode {
             CaptureMode::BitBlt | CaptureMode::WindowsGraphicsCapture => {
-                KeySenderMethod::Default(handle, KeyInputKind::Fixed)
+                KeySenderMethod::Default(capture_handle, KeyInputKind::Fixed)
             }
             // This shouldn't matter because we have to get the Handle from the box capture anyway
-            CaptureMode::BitBltArea => KeySenderMethod::Default(handle, KeyInputKind::Foreground),
+            CaptureMode::BitBltArea => {
+                KeySenderMethod::Default(capture_handle, KeyInputKind::Foreground)
+            }
         }
     };
     let mut keys = DefaultKeySender::new(key_sender_method);
+    keys.set_foreground_interlock(
+        (!settings.foreground_interlock.is_empty()).then(|| settings.foreground_interlock.clone()),
+    );
+    keys.set_max_keys_per_second(settings.max_keys_per_second);
     let key_sender = broadcast::channel::<KeyBinding>(1).0; // Callback to UI
-    let mut key_receiver = KeyReceiver::new(handle, KeyInputKind::Fixed);
-
-    let mut capture_handles = Vec::<(String, Handle)>::new();
-    let mut selected_capture_handle = None;
-    let mut image_capture = ImageCapture::new(handle, settings.capture_mode);
+    let mut key_receiver = KeyReceiver::new(capture_handle, KeyInputKind::Fixed);
+    let mut image_capture = ImageCapture::new(capture_handle, settings.capture_mode);
     if let ImageCaptureKind::BitBltArea(capture) = image_capture.kind() {
         key_receiver = KeyReceiver::new(capture.handle(), KeyInputKind::Foreground);
         keys.set_method(KeySenderMethod::Default(
@@ -189,9 +430,19 @@ fn update_loop() {
         skills: [Skill::Detecting],
         buffs: [Buff::NoBuff; BuffKind::COUNT],
         halting: true,
+        halt_event: None,
     };
     let mut player_state = PlayerState::default();
+    player_state.config.tuning = settings.borrow().tuning;
+    player_state.config.rune_manual_fallback = settings
+        .borrow()
+        .notifications
+        .notify_on_rune_low_confidence;
     let mut minimap_state = MinimapState::default();
+    minimap_state.set_stranger_grace(
+        settings.borrow().stranger_grace_count,
+        settings.borrow().stranger_grace_window_millis,
+    );
     let mut skill_states = SkillKind::iter()
         .map(SkillState::new)
         .collect::<Vec<SkillState>>();
@@ -205,18 +456,121 @@ fn update_loop() {
     #[cfg(debug_assertions)]
     let mut recording_images_id = None;
     #[cfg(debug_assertions)]
+    let mut frame_history =
+        FrameHistory::new(settings.borrow().frame_history_budget_mb as usize * 1024 * 1024);
+    #[cfg(debug_assertions)]
     let mut infering_rune = None;
 
+    let mut last_frame_size = None::<Size>;
+    let mut applied_window_layout = None::<WindowLayout>;
+    let mut window_layout_attempted = None::<Instant>;
+    let mut auto_mob_learned_saved = Instant::now();
+    let mut auto_mob_learned_last = None::<(i64, AutoMobLearned)>;
+    let mut preserved = restarted.then(|| PRESERVED.with_borrow(Clone::clone));
+
     loop_with_fps(FPS, || {
+        *last_tick.lock().unwrap_or_else(PoisonError::into_inner) = Instant::now();
+        key_log::advance_tick();
+        // Applies the window layout before capturing so the frame is already in the new size
+        let window_layout = settings.borrow().window_layout;
+        if !window_layout.enabled {
+            applied_window_layout = None;
+        } else if applied_window_layout != Some(window_layout)
+            && window_layout_attempted
+                .is_none_or(|instant| instant.elapsed().as_secs() >= WINDOW_LAYOUT_RETRY_SECS)
+        {
+            window_layout_attempted = Some(Instant::now());
+            if windows::set_window_client_layout(
+                selected_capture_handle.unwrap_or(context.handle),
+                window_layout.x,
+                window_layout.y,
+                window_layout.width,
+                window_layout.height,
+            )
+            .is_ok()
+            {
+                info!(target: "context", "applied window layout {window_layout:?}");
+                applied_window_layout = Some(window_layout);
+            }
+        }
         let mat = image_capture.grab().map(OwnedMat::new);
+        let frame_captured = mat.is_some();
+        if let Some(size) = mat.as_ref().and_then(|mat| mat.size().ok())
+            && last_frame_size != Some(size)
+        {
+            last_frame_size = Some(size);
+            match ResolutionPreset::from_supported(size) {
+                Some(_) => info!(
+                    target: "context",
+                    "selected detection preset for resolution {}x{}", size.width, size.height
+                ),
+                None => warn!(
+                    target: "context",
+                    "resolution {}x{} is not supported, detection may fail (use 1366x768 or 1920x1080 with Ideal Ratio)",
+                    size.width,
+                    size.height
+                ),
+            }
+        }
         let was_player_alive = !player_state.is_dead;
         let was_minimap_idle = matches!(context.minimap, Minimap::Idle(_));
-        let detector = mat.map(CachedDetector::new);
+        let was_halting = context.halting;
+        let was_frozen = frame_freeze.is_frozen();
+        let frozen = frame_freeze.update(mat.as_ref(), || {
+            selected_capture_handle
+                .unwrap_or(context.handle)
+                .is_minimized()
+        });
+        let freeze_started = frozen && !was_frozen;
+        let freeze_ended = !frozen && was_frozen;
+        if freeze_started {
+            // Releases held keys so they are not stuck when the game unfreezes
+            release_direction_keys(context.keys.as_ref());
+        }
+        // Skips updating while frozen because keys sent would be queued until the game unfreezes
+        let profile = minimap_state
+            .data()
+            .map(|data| data.detection_profile)
+            .unwrap_or_default();
+        let half_resolution = settings.borrow().half_resolution_detection;
+        let other_player_colors = settings.borrow().other_player_colors;
+        let buffs_region_offset = Point::new(
+            settings.borrow().buffs_region_offset_x,
+            settings.borrow().buffs_region_offset_y,
+        );
+        let detector = mat.filter(|_| !frozen).map(|mat| {
+            CachedDetector::new(mat)
+                .with_profile(profile)
+                .with_half_resolution(half_resolution)
+                .with_other_player_colors(other_player_colors)
+                .with_buffs_region_offset(buffs_region_offset)
+        });
 
         if let Some(detector) = detector {
             context.detector = Some(Box::new(detector));
+            #[cfg(feature = "audio-capture")]
+            let had_rune = matches!(context.minimap, Minimap::Idle(idle) if idle.rune.is_some());
             context.minimap = fold_context(&context, context.minimap, &mut minimap_state);
-            context.player = fold_context(&context, context.player, &mut player_state);
+            #[cfg(feature = "audio-capture")]
+            {
+                let has_rune =
+                    matches!(context.minimap, Minimap::Idle(idle) if idle.rune.is_some());
+                let enabled = settings.borrow().rune_chime_detection;
+                if rune_chime.update(enabled, !had_rune && has_rune, has_rune)
+                    && matches!(context.minimap, Minimap::Idle(_))
+                {
+                    info!(target: "context", "rune chime heard without rune on the minimap");
+                    minimap_state.expedite_rune_detection();
+                    if !context.halting {
+                        let _ = context
+                            .notification
+                            .schedule_notification(NotificationKind::RuneAppear);
+                    }
+                }
+            }
+            let player = fold_context(&context, context.player, &mut player_state);
+            transition_log::record(&context.player, &player, player_state.last_known_pos);
+            context.player = player;
             for (i, state) in skill_states
                 .iter_mut()
                 .enumerate()
@@ -229,12 +583,47 @@ fn update_loop() {
             }
             // Rotating action must always be done last
             rotator.rotate_action(&context, &mut player_state);
+
+            let fired = rotator.take_unsaved_fired_timestamps();
+            if !fired.is_empty()
+                && let Err(err) = upsert_action_timestamps(&fired)
+            {
+                error!(target: "context", "failed to persist action timestamps {err}");
+            }
+            if auto_mob_learned_saved.elapsed().as_secs() >= AUTO_MOB_LEARNED_SAVE_SECS {
+                auto_mob_learned_saved = Instant::now();
+                save_auto_mob_learned(&minimap_state, &player_state, &mut auto_mob_learned_last);
+            }
+            let mobs_detected = rotator.take_auto_mob_detected_count();
+            if settings.borrow().export_activity_csv {
+                activity_log.update(&context, &player_state, mobs_detected);
+            } else {
+                activity_log.reset();
+            }
+            ab_comparison.update(
+                !context.halting,
+                rotator.normal_loops(),
+                mobs_detected,
+                matches!(context.minimap, Minimap::Idle(idle) if idle.rune.is_some()),
+                Instant::now(),
+            );
+        }
+        // Advances every tick so the scheduled keys stay on time when no frame is detected
+        context.keys.update_scheduled();
+
+        let keep_alive_minutes = settings.borrow().keep_alive_minutes;
+        if let Some(key) = keep_alive.update(
+            context.halting && frame_captured && !frozen,
+            keep_alive_minutes,
+        ) {
+            let _ = context.keys.send(key);
         }
 
         // Poll requests, keys and update scheduled notifications frames
         let mut settings_borrow_mut = settings.borrow_mut();
         // I know what you are thinking...
         let mut handler = DefaultRequestHandler {
+            client: id,
             context: &mut context,
             config: &mut config,
             settings: &mut settings_borrow_mut,
@@ -249,13 +638,60 @@ fn update_loop() {
             image_capture: &mut image_capture,
             capture_handles: &mut capture_handles,
             selected_capture_handle: &mut selected_capture_handle,
+            ab_comparison: &mut ab_comparison,
+            state_stream: &mut state_stream,
+            map_imports: &mut map_imports,
+            scheduler: &mut scheduler,
             #[cfg(debug_assertions)]
             recording_images_id: &mut recording_images_id,
             #[cfg(debug_assertions)]
+            frame_history: &mut frame_history,
+            #[cfg(debug_assertions)]
             infering_rune: &mut infering_rune,
         };
-        handler.poll_request();
+        if let Some(PreservedState {
+            config,
+            settings,
+            minimap,
+        }) = preserved.take()
+        {
+            if let Some(config) = config {
+                handler.on_update_configuration(config);
+            }
+            if let Some(settings) = settings {
+                handler.on_update_settings(settings);
+            }
+            if let Some((preset, minimap)) = minimap {
+                handler.on_update_minimap(preset, minimap);
+            }
+        }
+        let notify_restart_limit_reached = mem::take(&mut restart_limit_reached);
+        if notify_restart_limit_reached {
+            error!(
+                target: "context",
+                "update loop of client {id} restarted {restarts} times, halting actions"
+            );
+            handler.halt_with_reason(HaltReason::RestartLimitReached);
+        }
+        if stalled.swap(false, Ordering::AcqRel) {
+            warn!(
+                target: "context",
+                "update loop of client {id} resumed after stalling, halting actions"
+            );
+            // Releases held keys since the stall may have interrupted an action midway
+            release_direction_keys(handler.context.keys.as_ref());
+            handler.halt_with_reason(HaltReason::Stalled);
+        }
+        if !handler.poll_request(requests) {
+            // Releases held keys so they are not stuck after the client is removed
+            release_direction_keys(handler.context.keys.as_ref());
+            return false;
+        }
         handler.poll_key();
+        handler.poll_ab_comparison();
+        handler.poll_state_stream();
+        handler.poll_map_imports();
+        handler.update_detected_map(!was_minimap_idle);
         #[cfg(debug_assertions)]
         handler.poll_debug();
         handler.context.notification.update_scheduled_frames(|| {
@@ -273,23 +709,221 @@ fn update_loop() {
         let minimap_changed =
             was_minimap_idle && matches!(handler.context.minimap, Minimap::Detecting);
         let player_died = was_player_alive && handler.player.is_dead;
-        if handler.minimap.data().is_some() && !handler.context.halting {
-            if (minimap_changed || player_died) && handler.settings.stop_on_fail_or_change_map {
-                handler.on_rotate_actions(true);
+        let player_respawned = !was_player_alive && !handler.player.is_dead;
+        let mut fail_or_map_change = false;
+        if let Some(data) = handler.minimap.data()
+            && !handler.context.halting
+        {
+            if minimap_changed && !data.return_macro.is_empty() {
+                // Halts while navigating back to the farm map
+                handler.halt_with_reason(HaltReason::NavigatingBack);
+                navigation.start();
+            } else if (minimap_changed || player_died)
+                && handler.settings.stop_on_fail_or_change_map
+            {
+                handler.halt_with_reason(if player_died {
+                    HaltReason::PlayerDied
+                } else {
+                    HaltReason::MapChanged
+                });
+            } else if player_respawned {
+                // The player is at the spawn point or in town after respawning, so the
+                // interrupted action is dropped and the farm map is verified before resuming
+                info!(target: "context", "player respawned, re-detecting minimap");
+                handler.halt_with_reason(HaltReason::PlayerRespawned);
+                handler.player.reset_keep_auto_mob_learned();
+                handler.context.minimap = Minimap::Detecting;
+                navigation.start();
             }
-
-            if minimap_changed {
-                drop(settings_borrow_mut); // For notification to borrow immutably
-                let _ = context
-                    .notification
-                    .schedule_notification(NotificationKind::FailOrMapChange);
+            fail_or_map_change = minimap_changed;
+        }
+        if freeze_started {
+            halted_by_freeze = !handler.context.halting;
+            handler.halt_with_reason(HaltReason::GameFrozen);
+            navigation.abort();
+        }
+        if freeze_ended
+            && mem::take(&mut halted_by_freeze)
+            && handler.context.halting
+            && handler
+                .context
+                .halt_event
+                .is_some_and(|event| event.reason == HaltReason::GameFrozen)
+        {
+            info!(target: "context", "game unfrozen, resuming actions");
+            handler.on_rotate_actions(false);
+        }
+        let mut navigation_failed = false;
+        if navigation.is_active() {
+            if !handler.context.halting {
+                // Aborts if actions are resumed by user
+                navigation.abort();
+            } else if let Some(data) = handler.minimap.data() {
+                match navigation.update(handler.context, data) {
+                    NavigationUpdate::Resumed => handler.on_rotate_actions(false),
+                    NavigationUpdate::Failed => {
+                        navigation_failed = true;
+                        handler.halt_with_reason(HaltReason::NavigationFailed);
+                    }
+                    NavigationUpdate::Pending => (),
+                }
+            }
+        }
+        let quota_reached = run_time_quota.update(
+            !handler.context.halting,
+            handler.settings.daily_quota_minutes,
+            handler.settings.weekly_quota_minutes,
+        );
+        if quota_reached {
+            handler.halt_with_reason(HaltReason::RunTimeQuotaReached);
+        }
+        // Navigation resumes actions by itself so starting is deferred until it finishes
+        let schedule_event = handler.scheduler.update(
+            !handler.context.halting,
+            !navigation.is_active(),
+            &handler.settings.schedule,
+            local_minute_of_day(),
+        );
+        match schedule_event {
+            Some(ScheduleEvent::Start) => {
+                info!(target: "context", "starting actions as scheduled");
+                handler.on_rotate_actions(false);
             }
+            Some(ScheduleEvent::Break) => handler.halt_with_reason(HaltReason::ScheduledBreak),
+            Some(ScheduleEvent::Stop) => handler.halt_with_reason(HaltReason::ScheduledStop),
+            None => (),
+        }
+        if handler.context.keys.take_rate_exceeded()
+            && handler.settings.halt_on_key_rate_exceeded
+            && !handler.context.halting
+        {
+            warn!(target: "context", "key presses per second exceeded, halting actions");
+            handler.halt_with_reason(HaltReason::KeyRateExceeded);
+        }
+        if handler.image_capture.update_process() {
+            warn!(target: "context", "captured window process changed, halting actions");
+            handler.halt_with_reason(HaltReason::CaptureWindowChanged);
+        }
+        let halted = (!was_halting
+            && handler.context.halting
+            && !navigation.is_active()
+            && !freeze_started
+            && !quota_reached
+            && schedule_event != Some(ScheduleEvent::Break))
+            || navigation_failed;
+        let notify_restarted = mem::take(&mut restarted);
+
+        health::update(
+            id,
+            TickHealth {
+                frame_captured,
+                minimap_detected: matches!(handler.context.minimap, Minimap::Idle(_)),
+                player_detected: !matches!(handler.context.player, Player::Detecting),
+                queued_priority_actions: handler.rotator.queued_priority_actions_count(),
+                scheduled_notifications: handler.context.notification.scheduled_count(),
+            },
+        );
+
+        drop(settings_borrow_mut); // For notification to borrow immutably
+        context.notification.update_quiet_hours();
+        if fail_or_map_change {
+            let _ = context
+                .notification
+                .schedule_notification(NotificationKind::FailOrMapChange);
+        }
+        if halted {
+            let _ = context
+                .notification
+                .schedule_notification(NotificationKind::Halted);
         }
+        if freeze_started {
+            let _ = context
+                .notification
+                .schedule_notification(NotificationKind::Frozen);
+        }
+        if quota_reached {
+            let _ = context
+                .notification
+                .schedule_notification(NotificationKind::QuotaReached);
+        }
+        if notify_restart_limit_reached {
+            let _ = context
+                .notification
+                .schedule_notification(NotificationKind::RestartLimitReached);
+        } else if notify_restarted {
+            let _ = context
+                .notification
+                .schedule_notification(NotificationKind::Restarted);
+        }
+        true
     });
 }
 
 #[inline]
-fn fold_context<C>(
+fn release_direction_keys(keys: &dyn KeySender) {
+    let _ = keys.send_up(KeyKind::Up);
+    let _ = keys.send_up(KeyKind::Down);
+    let _ = keys.send_up(KeyKind::Left);
+    let _ = keys.send_up(KeyKind::Right);
+}
+
+/// The windows that can be botted by a new client
+pub(crate) fn client_windows() -> Vec<CaptureHandle> {
+    windows::query_capture_handles()
+        .into_iter()
+        .map(|(title, handle)| CaptureHandle {
+            title,
+            process_id: handle.process_id(),
+            is_game: handle.is_class(GAME_WINDOW_CLASS),
+        })
+        .collect()
+}
+
+/// Finds the capture handle matching both the title and process id of `identity`
+fn find_capture_handle(
+    handles: &[(String, Handle)],
+    identity: &CaptureHandleIdentity,
+) -> Option<Handle> {
+    handles
+        .iter()
+        .find(|(title, handle)| {
+            *title == identity.title && handle.process_id() == Some(identity.process_id)
+        })
+        .map(|(_, handle)| *handle)
+}
+
+/// Persists the auto mobbing data learned by `player` on the current map if it changed since
+/// `last` was saved
+fn save_auto_mob_learned(
+    minimap_state: &MinimapState,
+    player: &PlayerState,
+    last: &mut Option<(i64, AutoMobLearned)>,
+) {
+    let Some(minimap) = minimap_state.data() else {
+        return;
+    };
+    let Some(id) = minimap.id else {
+        return;
+    };
+    if !matches!(minimap.rotation_mode, RotationMode::AutoMobbing(_)) {
+        return;
+    }
+    let learned = player.auto_mob_learned();
+    if last
+        .as_ref()
+        .is_some_and(|(last_id, last_learned)| *last_id == id && *last_learned == learned)
+    {
+        return;
+    }
+    if let Err(err) = upsert_auto_mob_learned(id, &learned) {
+        error!(target: "context", "failed to persist learned auto mobbing data {err}");
+        return;
+    }
+    *last = Some((id, learned));
+}
+
+#[inline]
+pub(crate) fn fold_context<C>(
     context: &Context,
     contextual: C,
     persistent: &mut <C as Contextual>::Persistent,
@@ -308,13 +942,16 @@ where
     }
 }
 
+/// Calls `on_tick` at `fps` until it returns `false`
 #[inline]
-fn loop_with_fps(fps: u32, mut on_tick: impl FnMut()) {
+fn loop_with_fps(fps: u32, mut on_tick: impl FnMut() -> bool) {
     let nanos_per_frame = (1_000_000_000 / fps) as u128;
     loop {
         let start = Instant::now();
 
-        on_tick();
+        if !on_tick() {
+            break;
+        }
 
         let now = Instant::now();
         let elapsed_nanos = now.duration_since(start).as_nanos();
@@ -325,7 +962,7 @@ fn loop_with_fps(fps: u32, mut on_tick: impl FnMut()) {
 }
 
 #[inline]
-fn to_png(frame: Option<&OwnedMat>) -> Option<Vec<u8>> {
+pub(crate) fn to_png(frame: Option<&OwnedMat>) -> Option<Vec<u8>> {
     frame.and_then(|image| {
         let mut bytes = Vector::new();
         imencode_def(".png", image, &mut bytes).ok()?;
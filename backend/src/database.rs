@@ -1,18 +1,21 @@
 use std::{
     collections::HashMap,
-    env,
+    env, fmt,
+    ops::RangeInclusive,
     sync::{LazyLock, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
-use opencv::core::Rect;
+use log::{debug, warn};
+use opencv::core::{Point, Rect};
 use platforms::windows::KeyKind;
 use rand::distr::{Alphanumeric, SampleString};
-use rusqlite::{Connection, Params, Statement, types::Null};
+use rusqlite::{Connection, OptionalExtension, Params, Statement, Transaction, types::Null};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use strum::{Display, EnumIter, EnumString};
 
-use crate::pathing;
+use crate::{buff::BuffKind, pathing, sync};
 
 static CONNECTION: LazyLock<Mutex<Connection>> = LazyLock::new(|| {
     let path = env::current_exe()
@@ -21,8 +24,62 @@ static CONNECTION: LazyLock<Mutex<Connection>> = LazyLock::new(|| {
         .unwrap()
         .join("local.db")
         .to_path_buf();
-    let conn = Connection::open(path.to_str().unwrap()).expect("failed to open local.db");
-    conn.execute_batch(
+    let mut conn = Connection::open(path.to_str().unwrap()).expect("failed to open local.db");
+    // WAL allows reading while writing and busy timeout waits for the other writer instead of
+    // failing immediately when both UI and backend write at the same time
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+        .expect("failed to enable WAL mode for local.db");
+    conn.busy_timeout(Duration::from_secs(5)).unwrap();
+    migrate(&mut conn).expect("failed to migrate local.db");
+    Mutex::new(conn)
+});
+
+type Migration = fn(&Transaction<'_>) -> rusqlite::Result<()>;
+
+/// The schema migrations applied in order
+///
+/// The migration at index `i` migrates the database from schema version `i` to `i + 1`. The
+/// current schema version is stored in SQLite `user_version` pragma. Existing migrations
+/// must not be changed, new migrations must only be appended.
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+];
+
+/// The maximum number of backups kept for each map
+const MAX_MAP_BACKUPS: usize = 10;
+
+/// The maximum difference in pixels of the minimap width or height to be considered the same map
+const MAP_IDENTITY_SIZE_TOLERANCE: i32 = 2;
+
+/// The maximum number of differing bits between two minimap fingerprints to be considered the
+/// same map
+const MAP_IDENTITY_MAX_FINGERPRINT_DISTANCE: u32 = 8;
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version = conn.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0))?;
+    if version > MIGRATIONS.len() {
+        warn!(target: "database", "schema version {version} is newer than supported {}", MIGRATIONS.len());
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        debug!(target: "database", "migrated schema version {i} to {}", i + 1);
+    }
+    Ok(())
+}
+
+/// Creates the initial tables
+///
+/// Databases created before versioning was introduced already have these tables and
+/// will have version 0.
+fn migrate_v0_to_v1(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
         r#"
         CREATE TABLE IF NOT EXISTS maps (
             id INTEGER PRIMARY KEY,
@@ -38,16 +95,91 @@ static CONNECTION: LazyLock<Mutex<Connection>> = LazyLock::new(|| {
         );
         "#,
     )
-    .unwrap();
-    Mutex::new(conn)
-});
+}
+
+/// Creates the table for map backups taken before destructive edits
+fn migrate_v1_to_v2(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS map_backups (
+            id INTEGER PRIMARY KEY,
+            map_id INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            data TEXT NOT NULL
+        );
+        "#,
+    )
+}
+
+fn migrate_v2_to_v3(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS action_timestamps (
+            key TEXT PRIMARY KEY,
+            fired_at INTEGER NOT NULL
+        );
+        "#,
+    )
+}
+
+fn migrate_v3_to_v4(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS run_times (
+            day INTEGER PRIMARY KEY,
+            seconds INTEGER NOT NULL
+        );
+        "#,
+    )
+}
 
-trait Identifiable {
+/// Moves the learned auto mobbing data out of the maps into its own table
+///
+/// The learned data is updated periodically while running so storing it in the map would
+/// back up and sync the whole map on every update.
+fn migrate_v4_to_v5(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS auto_mob_learned (
+            map_id INTEGER PRIMARY KEY,
+            data TEXT NOT NULL
+        );
+        INSERT OR IGNORE INTO auto_mob_learned (map_id, data)
+            SELECT id, json_extract(data, '$.auto_mob_learned') FROM maps
+            WHERE json_type(data, '$.auto_mob_learned') = 'object';
+        UPDATE maps SET data = json_remove(data, '$.auto_mob_learned');
+        "#,
+    )
+}
+
+pub(crate) trait Identifiable {
     fn id(&self) -> Option<i64>;
 
     fn set_id(&mut self, id: i64);
 }
 
+/// Identifies data across the machines sharing the sync folder
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct SyncMetadata {
+    /// The random id matching the data to its exported file in the sync folder
+    pub id: String,
+    /// The wall-clock time in milliseconds since Unix epoch of the last local modification
+    pub modified_at: u64,
+}
+
+impl SyncMetadata {
+    /// Marks the data as modified now and assigns a new id if there is none
+    pub(crate) fn touch(&mut self) {
+        if self.id.is_empty() {
+            self.id = Alphanumeric.sample_string(&mut rand::rng(), 16);
+        }
+        self.modified_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+    }
+}
+
 #[derive(
     Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize, EnumIter, Display, EnumString,
 )]
@@ -57,6 +189,18 @@ pub enum InputMethod {
     Rpc,
 }
 
+#[derive(
+    Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize, EnumIter, Display, EnumString,
+)]
+pub enum Language {
+    #[default]
+    English,
+    #[strum(to_string = "한국어")]
+    Korean,
+    #[strum(to_string = "中文")]
+    Chinese,
+}
+
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct Notifications {
     pub discord_webhook_url: String,
@@ -68,6 +212,97 @@ pub struct Notifications {
     pub notify_on_player_guildie_appear: bool,
     pub notify_on_player_stranger_appear: bool,
     pub notify_on_player_friend_appear: bool,
+    #[serde(default)]
+    pub notify_on_halt: bool,
+    #[serde(default)]
+    pub notify_on_restart: bool,
+    #[serde(default)]
+    pub notify_on_freeze: bool,
+    #[serde(default)]
+    pub notify_on_quota_reached: bool,
+    /// Whether to wait for the rune arrows submitted remotely and notify when rune detection
+    /// confidence is low
+    #[serde(default)]
+    pub notify_on_rune_low_confidence: bool,
+    /// Whether to also announce enabled notifications with text-to-speech
+    #[serde(default)]
+    pub text_to_speech: bool,
+    #[serde(default)]
+    pub quiet_hours: QuietHours,
+}
+
+/// The hours of the day in local time during which non-critical notifications are suppressed
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    /// The local hour from `0` to `23` quiet hours start at
+    pub start_hour: u32,
+    /// The local hour from `0` to `23` quiet hours end at, can be before [`Self::start_hour`]
+    /// to span midnight
+    pub end_hour: u32,
+    /// Whether to send the suppressed notifications as a single summary once quiet hours end
+    /// instead of dropping them
+    pub batch: bool,
+}
+
+impl QuietHours {
+    /// Whether `minute_of_day` in local time is within quiet hours
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let start = (self.start_hour % 24) * 60;
+        let end = (self.end_hour % 24) * 60;
+        if start <= end {
+            (start..end).contains(&minute_of_day)
+        } else {
+            minute_of_day >= start || minute_of_day < end
+        }
+    }
+}
+
+/// The timed start, stop and break windows of rotating actions
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Schedule {
+    /// Whether to start rotating actions daily at [`Self::start_hour`] and
+    /// [`Self::start_minute`]
+    pub start_enabled: bool,
+    /// The local hour from `0` to `23` to start rotating actions at
+    pub start_hour: u32,
+    /// The minute from `0` to `59` of [`Self::start_hour`] to start rotating actions at
+    pub start_minute: u32,
+    /// The minutes of rotating actions before stopping or `0` to not stop
+    pub stop_after_minutes: u32,
+    /// The approximate minutes of rotating actions between breaks or `0` to disable breaks
+    pub break_every_minutes: u32,
+    /// The minimum minutes of a break
+    pub break_min_minutes: u32,
+    /// The maximum minutes of a break
+    pub break_max_minutes: u32,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            start_enabled: false,
+            start_hour: 0,
+            start_minute: 0,
+            stop_after_minutes: 0,
+            break_every_minutes: 0,
+            break_min_minutes: 5,
+            break_max_minutes: 15,
+        }
+    }
+}
+
+impl Schedule {
+    /// The local minute of the day to start rotating actions at
+    #[inline]
+    pub fn start_minute_of_day(&self) -> u32 {
+        (self.start_hour % 24) * 60 + self.start_minute % 60
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -88,12 +323,111 @@ pub struct Settings {
     pub notifications: Notifications,
     #[serde(default = "toggle_actions_key_default")]
     pub toggle_actions_key: KeyBindingConfiguration,
+    /// Whether [`Self::toggle_actions_key`] also works when neither the bot nor the game window
+    /// is focused
+    ///
+    /// Only the client selected in the UI is toggled when running multiple clients.
+    #[serde(default)]
+    pub toggle_actions_key_global: bool,
+    /// The key to pause or resume only auto mobbing
+    #[serde(default = "toggle_auto_mob_key_default")]
+    pub toggle_auto_mob_key: KeyBindingConfiguration,
     #[serde(default = "platform_start_key_default")]
     pub platform_start_key: KeyBindingConfiguration,
     #[serde(default = "platform_end_key_default")]
     pub platform_end_key: KeyBindingConfiguration,
     #[serde(default = "platform_add_key_default")]
     pub platform_add_key: KeyBindingConfiguration,
+    /// The folder to export saved maps and configurations to and import from on startup
+    #[serde(default)]
+    pub sync_folder_path: String,
+    #[serde(default)]
+    pub language: Language,
+    /// The maximum memory in megabytes for the frames kept before debug recording starts
+    ///
+    /// Recorded frames are always written to disk as they arrive. A value of 0 disables the
+    /// history.
+    #[serde(default = "frame_history_budget_mb_default")]
+    pub frame_history_budget_mb: u32,
+    /// Whether to export per-minute activity summaries to a CSV file
+    #[serde(default)]
+    pub export_activity_csv: bool,
+    /// Whether to detect ESC settings, tomb and Erda Shower on a half resolution grayscale frame
+    #[serde(default)]
+    pub half_resolution_detection: bool,
+    #[serde(default)]
+    pub other_player_colors: OtherPlayerColors,
+    /// Whether to listen to the game audio for the rune spawn chime when the rune is not visible
+    /// on the minimap
+    ///
+    /// Only used when built with the `audio-capture` feature.
+    #[serde(default)]
+    pub rune_chime_detection: bool,
+    /// Runtime parameters tuned live through [`TuningParameter`]
+    #[serde(default)]
+    pub tuning: TuningParameters,
+    /// The maximum minutes of rotating actions per day or `0` for unlimited
+    #[serde(default)]
+    pub daily_quota_minutes: u32,
+    /// The maximum minutes of rotating actions per week or `0` for unlimited
+    #[serde(default)]
+    pub weekly_quota_minutes: u32,
+    #[serde(default)]
+    pub schedule: Schedule,
+    /// The horizontal offset in pixels of the buffs region from the default top right position
+    #[serde(default)]
+    pub buffs_region_offset_x: i32,
+    /// The vertical offset in pixels of the buffs region from the default top right position
+    #[serde(default)]
+    pub buffs_region_offset_y: i32,
+    /// The actions merged into the rotation of every preset
+    #[serde(default)]
+    pub global_actions: Vec<GlobalAction>,
+    /// The title or class the foreground window must match for keys to be sent
+    ///
+    /// An empty string disables this interlock.
+    #[serde(default)]
+    pub foreground_interlock: String,
+    /// The interval in minutes to tap a movement key while halting or `0` to disable
+    ///
+    /// Avoids the character being disconnected for idling when actions are resumed later.
+    #[serde(default)]
+    pub keep_alive_minutes: u32,
+    /// The seconds after rotation starts during which only movement and buffs are allowed or `0`
+    /// to disable
+    ///
+    /// Lets the user verify the rotation before it commits long cooldown skills.
+    #[serde(default)]
+    pub safe_mode_seconds: u32,
+    #[serde(default)]
+    pub window_layout: WindowLayout,
+    /// The number of stranger detections within [`Self::stranger_grace_window_millis`] before
+    /// reacting to a stranger
+    ///
+    /// Detections happen every 5 seconds so this filters out strangers passing by.
+    #[serde(default = "stranger_grace_count_default")]
+    pub stranger_grace_count: u32,
+    #[serde(default = "stranger_grace_window_millis_default")]
+    pub stranger_grace_window_millis: u64,
+    /// The maximum number of key presses within one second or `0` to disable
+    ///
+    /// Presses over this limit usually indicate a logic bug or a state loop and are dropped.
+    #[serde(default)]
+    pub max_keys_per_second: u32,
+    /// Whether to halt actions when a key press is dropped by [`Self::max_keys_per_second`]
+    #[serde(default)]
+    pub halt_on_key_rate_exceeded: bool,
+    /// Whether to show the developer console for typing commands
+    #[serde(default)]
+    pub enable_console: bool,
+    /// The capture handle selected by the user to restore on start
+    #[serde(default)]
+    pub capture_handle: Option<CaptureHandleIdentity>,
+    /// The game windows of the clients added after the first client to restore on start
+    ///
+    /// Unlike the first client, each added client bots an explicitly selected window.
+    #[serde(default)]
+    pub additional_clients: Vec<CaptureHandleIdentity>,
 }
 
 impl Default for Settings {
@@ -107,9 +441,224 @@ impl Default for Settings {
             stop_on_fail_or_change_map: false,
             notifications: Notifications::default(),
             toggle_actions_key: toggle_actions_key_default(),
+            toggle_auto_mob_key: toggle_auto_mob_key_default(),
             platform_start_key: platform_start_key_default(),
             platform_end_key: platform_end_key_default(),
             platform_add_key: platform_add_key_default(),
+            sync_folder_path: String::default(),
+            language: Language::default(),
+            frame_history_budget_mb: frame_history_budget_mb_default(),
+            export_activity_csv: false,
+            half_resolution_detection: false,
+            other_player_colors: OtherPlayerColors::default(),
+            rune_chime_detection: false,
+            tuning: TuningParameters::default(),
+            daily_quota_minutes: 0,
+            weekly_quota_minutes: 0,
+            schedule: Schedule::default(),
+            buffs_region_offset_x: 0,
+            buffs_region_offset_y: 0,
+            global_actions: vec![],
+            foreground_interlock: String::default(),
+            keep_alive_minutes: 0,
+            safe_mode_seconds: 0,
+            window_layout: WindowLayout::default(),
+            stranger_grace_count: stranger_grace_count_default(),
+            stranger_grace_window_millis: stranger_grace_window_millis_default(),
+            max_keys_per_second: 0,
+            halt_on_key_rate_exceeded: false,
+            enable_console: false,
+            capture_handle: None,
+            additional_clients: vec![],
+        }
+    }
+}
+
+impl Settings {
+    /// The capture handle selected for the client at `index` in the order clients are added
+    ///
+    /// The first client uses [`Self::capture_handle`] and the others
+    /// [`Self::additional_clients`].
+    pub fn client_capture_handle(&self, index: usize) -> Option<&CaptureHandleIdentity> {
+        match index {
+            0 => self.capture_handle.as_ref(),
+            index => self.additional_clients.get(index - 1),
+        }
+    }
+
+    /// Sets the capture handle selected for the client at `index` in the order clients are added
+    ///
+    /// Added clients always bot a window so [`None`] only clears the handle of the first client.
+    pub fn set_client_capture_handle(
+        &mut self,
+        index: usize,
+        handle: Option<CaptureHandleIdentity>,
+    ) {
+        match (index, handle) {
+            (0, handle) => self.capture_handle = handle,
+            (index, Some(handle)) => {
+                if let Some(window) = self.additional_clients.get_mut(index - 1) {
+                    *window = handle;
+                }
+            }
+            (_, None) => (),
+        }
+    }
+}
+
+/// Identifies a capture handle across restarts by its window title and process id
+///
+/// Window handles change when the game client restarts so the process id is used to avoid
+/// silently restoring to a different client with the same title.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct CaptureHandleIdentity {
+    pub title: String,
+    pub process_id: u32,
+}
+
+/// The position and size to move and resize the game window to before capturing
+///
+/// Keeps the detection environment consistent across sessions. The position and size are of
+/// the client area that gets captured.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowLayout {
+    pub enabled: bool,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Default for WindowLayout {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            x: 0,
+            y: 0,
+            width: 1366,
+            height: 768,
+        }
+    }
+}
+
+/// Minimap dot colors for detecting other players when the templates fail
+///
+/// The templates rely on the dot colors which can change with certain graphics settings.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OtherPlayerColors {
+    /// Whether to fall back to the color ranges when the templates fail
+    pub enabled: bool,
+    pub stranger: HsvRange,
+    pub guildie: HsvRange,
+    pub friend: HsvRange,
+}
+
+impl Default for OtherPlayerColors {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stranger: HsvRange {
+                lower: [0, 150, 150],
+                upper: [10, 255, 255],
+            },
+            guildie: HsvRange {
+                lower: [180, 100, 150],
+                upper: [215, 255, 255],
+            },
+            friend: HsvRange {
+                lower: [70, 100, 150],
+                upper: [110, 255, 255],
+            },
+        }
+    }
+}
+
+/// An inclusive HSV color range with each channel from 0 to 255
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct HsvRange {
+    pub lower: [u8; 3],
+    pub upper: [u8; 3],
+}
+
+/// Runtime parameters that can be tuned while the bot is running without recompiling
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuningParameters {
+    /// The acceptable y range above and below a mob position when matched with a reachable y
+    pub auto_mob_reachable_y_threshold: i32,
+    /// The number of times moving can be transitioned to without changing position before
+    /// unstucking
+    pub unstuck_count_threshold: u32,
+    /// The number of times unstucking can be transitioned to before entering GAMBA MODE
+    pub unstuck_gamba_mode_count: u32,
+    /// The milliseconds between each potion spam when there is an elite boss
+    pub elite_boss_potion_interval_millis: u64,
+}
+
+impl Default for TuningParameters {
+    fn default() -> Self {
+        Self {
+            auto_mob_reachable_y_threshold: 10,
+            unstuck_count_threshold: 7,
+            unstuck_gamba_mode_count: 3,
+            elite_boss_potion_interval_millis: 2_000,
+        }
+    }
+}
+
+impl TuningParameters {
+    pub fn get(&self, parameter: TuningParameter) -> u64 {
+        match parameter {
+            TuningParameter::AutoMobReachableYThreshold => {
+                self.auto_mob_reachable_y_threshold as u64
+            }
+            TuningParameter::UnstuckCountThreshold => self.unstuck_count_threshold as u64,
+            TuningParameter::UnstuckGambaModeCount => self.unstuck_gamba_mode_count as u64,
+            TuningParameter::ElitePotionIntervalMillis => self.elite_boss_potion_interval_millis,
+        }
+    }
+
+    /// Sets `parameter` to `value` if it is within [`TuningParameter::range`]
+    ///
+    /// Returns `false` without changing anything if `value` is out of range.
+    pub fn set(&mut self, parameter: TuningParameter, value: u64) -> bool {
+        if !parameter.range().contains(&value) {
+            return false;
+        }
+        match parameter {
+            TuningParameter::AutoMobReachableYThreshold => {
+                self.auto_mob_reachable_y_threshold = value as i32
+            }
+            TuningParameter::UnstuckCountThreshold => self.unstuck_count_threshold = value as u32,
+            TuningParameter::UnstuckGambaModeCount => self.unstuck_gamba_mode_count = value as u32,
+            TuningParameter::ElitePotionIntervalMillis => {
+                self.elite_boss_potion_interval_millis = value
+            }
+        }
+        true
+    }
+}
+
+/// A parameter of [`TuningParameters`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, EnumString, EnumIter)]
+pub enum TuningParameter {
+    AutoMobReachableYThreshold,
+    UnstuckCountThreshold,
+    UnstuckGambaModeCount,
+    ElitePotionIntervalMillis,
+}
+
+impl TuningParameter {
+    /// The inclusive range of accepted values
+    pub fn range(&self) -> RangeInclusive<u64> {
+        match self {
+            TuningParameter::AutoMobReachableYThreshold => 0..=50,
+            TuningParameter::UnstuckCountThreshold | TuningParameter::UnstuckGambaModeCount => {
+                1..=100
+            }
+            TuningParameter::ElitePotionIntervalMillis => 100..=60_000,
         }
     }
 }
@@ -128,6 +677,18 @@ fn enable_rune_solving_default() -> bool {
     true
 }
 
+fn frame_history_budget_mb_default() -> u32 {
+    0
+}
+
+fn stranger_grace_count_default() -> u32 {
+    1
+}
+
+fn stranger_grace_window_millis_default() -> u64 {
+    30_000
+}
+
 fn toggle_actions_key_default() -> KeyBindingConfiguration {
     KeyBindingConfiguration {
         key: KeyBinding::Comma,
@@ -135,6 +696,13 @@ fn toggle_actions_key_default() -> KeyBindingConfiguration {
     }
 }
 
+fn toggle_auto_mob_key_default() -> KeyBindingConfiguration {
+    KeyBindingConfiguration {
+        key: KeyBinding::Period,
+        enabled: false,
+    }
+}
+
 fn platform_start_key_default() -> KeyBindingConfiguration {
     KeyBindingConfiguration {
         key: KeyBinding::J,
@@ -184,6 +752,26 @@ pub struct Configuration {
     pub potion_key: KeyBindingConfiguration,
     pub potion_mode: PotionMode,
     pub health_update_millis: u64,
+    #[serde(default)]
+    pub damage_grace_millis: u64,
+    /// Detects the player mana for [`ActionCondition::MpAbovePercent`] conditions
+    #[serde(default)]
+    pub mana_detection: bool,
+    /// Offset added to the double jump threshold when moving left
+    ///
+    /// Positive values stop double jumping earlier to avoid overshooting.
+    #[serde(default)]
+    pub double_jump_left_offset: i32,
+    /// Offset added to the double jump threshold when moving right
+    ///
+    /// Positive values stop double jumping earlier to avoid overshooting.
+    #[serde(default)]
+    pub double_jump_right_offset: i32,
+    /// The Erda Shower cooldown to estimate its state when the skill icon is not visible
+    ///
+    /// A value of `0` disables the estimation.
+    #[serde(default)]
+    pub erda_shower_cooldown_millis: u64,
     pub sayram_elixir_key: KeyBindingConfiguration,
     pub aurelia_elixir_key: KeyBindingConfiguration,
     pub exp_x3_key: KeyBindingConfiguration,
@@ -206,6 +794,29 @@ pub struct Configuration {
     pub class: Class,
     #[serde(default)]
     pub actions: Vec<ActionConfiguration>,
+    /// The minimum intervals between two consecutive presses of keys
+    #[serde(default)]
+    pub key_repeat_intervals: Vec<KeyRepeatInterval>,
+    #[serde(default)]
+    pub sync: SyncMetadata,
+}
+
+/// The minimum interval between two consecutive presses of a key
+///
+/// Prevents accidental key spam when overlapping states send the same key.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct KeyRepeatInterval {
+    pub key: KeyBinding,
+    pub millis: u64,
+}
+
+impl Default for KeyRepeatInterval {
+    fn default() -> Self {
+        Self {
+            key: KeyBinding::default(),
+            millis: 100,
+        }
+    }
 }
 
 fn jump_key_default() -> KeyBindingConfiguration {
@@ -231,6 +842,11 @@ impl Default for Configuration {
             potion_key: KeyBindingConfiguration::default(),
             potion_mode: PotionMode::EveryMillis(180000),
             health_update_millis: 1000,
+            damage_grace_millis: 0,
+            mana_detection: false,
+            double_jump_left_offset: 0,
+            double_jump_right_offset: 0,
+            erda_shower_cooldown_millis: 0,
             sayram_elixir_key: KeyBindingConfiguration::default(),
             aurelia_elixir_key: KeyBindingConfiguration::default(),
             exp_x3_key: KeyBindingConfiguration::default(),
@@ -245,6 +861,8 @@ impl Default for Configuration {
             extreme_gold_potion_key: KeyBindingConfiguration::default(),
             class: Class::default(),
             actions: vec![],
+            key_repeat_intervals: vec![],
+            sync: SyncMetadata::default(),
         }
     }
 }
@@ -289,7 +907,9 @@ impl From<ActionConfiguration> for Action {
     fn from(value: ActionConfiguration) -> Self {
         Self::Key(ActionKey {
             key: value.key,
+            modifier: KeyModifier::None,
             link_key: None,
+            link_key_modifier: KeyModifier::None,
             count: 1,
             position: None,
             condition: ActionCondition::EveryMillis(value.every_millis),
@@ -304,6 +924,10 @@ impl From<ActionConfiguration> for Action {
             wait_before_use_millis_random_range: 0,
             wait_after_use_millis: value.wait_after_use_millis,
             wait_after_use_millis_random_range: 0,
+            y_band: None,
+            hold_millis: 0,
+            heavy: false,
+            position_anchor: None,
         })
     }
 }
@@ -339,14 +963,47 @@ impl From<Rect> for Bound {
     }
 }
 
+/// A vertex of a polygon bound relative to the minimap top-left corner
+#[derive(Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub struct BoundVertex {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl From<BoundVertex> for Point {
+    fn from(value: BoundVertex) -> Self {
+        Self::new(value.x, value.y)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub struct AutoMobbing {
     pub bound: Bound,
     pub key: KeyBinding,
+    /// The key to use instead of [`Self::key`] when the player is mid-air (e.g. after double jump)
+    #[serde(default)]
+    pub airborne_key: Option<KeyBinding>,
     #[serde(default = "auto_mobbing_key_count_default")]
     pub key_count: u32,
     pub key_wait_before_millis: u64,
     pub key_wait_after_millis: u64,
+    #[serde(default)]
+    pub split_bound_by_friend: bool,
+    #[serde(default)]
+    pub target: AutoMobbingTarget,
+    /// The minutes to farm before resting with `0` indicating no resting
+    #[serde(default)]
+    pub farm_minutes: u32,
+    /// The minutes to rest by walking casually around before farming again
+    #[serde(default)]
+    pub rest_minutes: u32,
+    /// Whether to bias mob targets toward platforms the player has spent less time on
+    #[serde(default)]
+    pub balance_platforms: bool,
+    /// How strongly to prefer mobs on the player's current y band over mobs requiring vertical
+    /// movement, from `0` (no preference) to `100` (only leave the band when it has no mobs)
+    #[serde(default)]
+    pub same_y_preference: u32,
 }
 
 impl Default for AutoMobbing {
@@ -354,13 +1011,32 @@ impl Default for AutoMobbing {
         Self {
             bound: Bound::default(),
             key: KeyBinding::default(),
+            airborne_key: None,
             key_count: auto_mobbing_key_count_default(),
             key_wait_before_millis: 0,
             key_wait_after_millis: 0,
+            split_bound_by_friend: false,
+            target: AutoMobbingTarget::default(),
+            farm_minutes: 0,
+            rest_minutes: 0,
+            balance_platforms: false,
+            same_y_preference: 0,
         }
     }
 }
 
+/// The source used to find mobs to target when auto mobbing
+#[derive(
+    Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize, EnumIter, Display, EnumString,
+)]
+pub enum AutoMobbingTarget {
+    /// Detects mobs on the whole screen using the mob detection model
+    #[default]
+    Model,
+    /// Detects mobs from the red dots on the minimap
+    MinimapDots,
+}
+
 fn auto_mobbing_key_count_default() -> u32 {
     1
 }
@@ -372,6 +1048,7 @@ pub enum RotationMode {
     StartToEnd,
     #[default]
     StartToEndThenReverse,
+    NearestFirst,
     AutoMobbing(AutoMobbing),
 }
 
@@ -394,15 +1071,140 @@ pub struct Minimap {
     pub name: String,
     pub width: i32,
     pub height: i32,
+    /// The fingerprint of the detected minimap identifying this map together with
+    /// [`Self::width`] and [`Self::height`]
+    ///
+    /// The map is automatically reloaded when a minimap with the same identity is detected.
+    /// [`None`] for maps saved before the fingerprint was recorded.
+    pub fingerprint: Option<u64>,
     pub rotation_mode: RotationMode,
     pub platforms: Vec<Platform>,
+    /// The detected portals saved with how each is used when moving
+    pub portals: Vec<Portal>,
     pub rune_platforms_pathing: bool,
     pub rune_platforms_pathing_up_jump_only: bool,
     pub auto_mob_platforms_pathing: bool,
     pub auto_mob_platforms_pathing_up_jump_only: bool,
     pub auto_mob_platforms_bound: bool,
+    /// The vertices of a polygon bound for auto mobbing maps with non-rectangular farmable areas
+    ///
+    /// Replaces [`AutoMobbing::bound`] when there are at least 3 vertices.
+    pub auto_mob_bound_polygon: Vec<BoundVertex>,
     pub actions_any_reset_on_erda_condition: bool,
+    /// Whether to snap Erda Shower condition actions to the midpoint of the platform they are on
+    pub actions_erda_snap_to_platform_midpoint: bool,
+    /// Whether to cast all buffs and verify Erda Shower state before the first rotation loop
+    pub actions_warm_up: bool,
+    /// The start point to walk to during warm up
+    pub actions_warm_up_position: Option<Position>,
     pub actions: HashMap<String, Vec<Action>>,
+    pub presets_metadata: HashMap<String, PresetMetadata>,
+    /// The start point of each preset to move to first when the rotation starts or resumes
+    pub presets_start_position: HashMap<String, Position>,
+    /// The preset to alternate with the selected preset for comparing the two rotations
+    pub ab_comparison_preset: Option<String>,
+    /// The number of normal actions loops before switching between the compared presets or `0`
+    /// to disable comparing
+    pub ab_comparison_loops: u32,
+    /// The recorded key presses to navigate from town back to this map
+    ///
+    /// NPCs are interacted with using keys since the macro does not click.
+    pub return_macro: Vec<NavigationStep>,
+    pub detection_profile: DetectionProfile,
+    pub pathing_profile: PathingProfile,
+    pub sync: SyncMetadata,
+}
+
+/// The auto mobbing data learned on a map
+///
+/// Stored separately from [`Minimap`] so that learning continues across sessions without
+/// modifying the map.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoMobLearned {
+    /// The reachable y positions and the number of times each is confirmed
+    pub reachable_ys: HashMap<i32, u32>,
+    /// The `(start, end, count)` ranges of x that can be ignored for each reachable y
+    pub ignore_xs: HashMap<i32, Vec<(i32, i32, u32)>>,
+    /// The points to periodically move to for detecting more mobs
+    pub pathing_points: Vec<(i32, i32)>,
+}
+
+impl AutoMobLearned {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.reachable_ys.is_empty() && self.ignore_xs.is_empty() && self.pathing_points.is_empty()
+    }
+}
+
+/// A copy of a map taken before it was deleted or overwritten with fewer actions
+#[derive(Clone, Debug)]
+pub struct MinimapBackup {
+    pub id: i64,
+    /// The time the backup was taken in seconds since Unix epoch
+    pub created_at: u64,
+    /// The map at the time of backup with [`Minimap::id`] being the original map id
+    pub map: Minimap,
+}
+
+/// Template matching threshold overrides for maps that break the default thresholds
+/// (e.g. maps with dark overlays)
+///
+/// Each threshold is a percentage with [`None`] indicating the default threshold.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectionProfile {
+    pub player_threshold: Option<f32>,
+    pub rune_threshold: Option<f32>,
+    pub portal_threshold: Option<f32>,
+}
+
+/// Platforms pathing threshold overrides for maps that need tighter or looser jump planning
+/// (e.g. maps with moving platforms or slopes)
+///
+/// Each threshold is a distance in minimap pixels with [`None`] indicating the default threshold.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathingProfile {
+    /// The minimum x distance between two platforms to double jump
+    pub double_jump_threshold: Option<i32>,
+    /// The minimum y distance between two platforms to jump
+    pub jump_threshold: Option<i32>,
+    /// The maximum y distance to grapple when pathing with up jump only
+    pub grappling_threshold: Option<i32>,
+    /// The maximum y distance between two platforms to grapple
+    pub grappling_max_threshold: Option<i32>,
+}
+
+/// A single key press of a navigation macro
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct NavigationStep {
+    pub key: KeyBinding,
+    pub wait_after_millis: u64,
+}
+
+/// Freeform information about an actions preset for sharing
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetMetadata {
+    pub author: String,
+    pub note: String,
+    /// The character level the preset is intended for, `0` if unspecified
+    pub target_level: u32,
+}
+
+impl Minimap {
+    /// The number of differing fingerprint bits if this map has the identity of the detected
+    /// minimap with `width`, `height` and `fingerprint`
+    pub fn identity_distance(&self, width: i32, height: i32, fingerprint: u64) -> Option<u32> {
+        if (self.width - width).abs() > MAP_IDENTITY_SIZE_TOLERANCE
+            || (self.height - height).abs() > MAP_IDENTITY_SIZE_TOLERANCE
+        {
+            return None;
+        }
+        let distance = (self.fingerprint? ^ fingerprint).count_ones();
+        (distance <= MAP_IDENTITY_MAX_FINGERPRINT_DISTANCE).then_some(distance)
+    }
 }
 
 impl Identifiable for Minimap {
@@ -428,6 +1230,170 @@ impl From<Platform> for pathing::Platform {
     }
 }
 
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}-{}, {})", self.x_start, self.x_end, self.y)
+    }
+}
+
+/// A change made to malformed platforms by [`repair_platforms`]
+#[derive(Clone, PartialEq, Debug)]
+pub enum PlatformRepair {
+    /// The platform start and end were swapped
+    Swapped(Platform),
+    /// The platform was clipped to the minimap width
+    Clipped(Platform, Platform),
+    /// The platform was removed for having zero length or being outside of the minimap
+    Removed(Platform),
+    /// The two platforms on the same y were overlapping and merged into one
+    Merged(Platform, Platform, Platform),
+    /// The platform was removed for exceeding [`pathing::MAX_PLATFORMS_COUNT`]
+    Truncated(Platform),
+}
+
+impl fmt::Display for PlatformRepair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlatformRepair::Swapped(platform) => write!(f, "swapped start and end of {platform}"),
+            PlatformRepair::Clipped(from, to) => write!(f, "clipped {from} to {to}"),
+            PlatformRepair::Removed(platform) => write!(f, "removed invalid {platform}"),
+            PlatformRepair::Merged(first, second, merged) => {
+                write!(f, "merged overlapping {first} and {second} into {merged}")
+            }
+            PlatformRepair::Truncated(platform) => {
+                write!(f, "removed {platform} exceeding the maximum platforms")
+            }
+        }
+    }
+}
+
+/// Validates `platforms` of a minimap with `width` and `height` and repairs the malformed ones
+///
+/// Reversed platforms are swapped, platforms are clipped to the minimap width, zero-length or
+/// out of bound platforms are removed and overlapping platforms on the same y are merged. The
+/// bounds are not checked if `width` or `height` is not positive.
+///
+/// Returns the repaired platforms and what changed, which is empty if `platforms` are valid.
+pub fn repair_platforms(
+    platforms: &[Platform],
+    width: i32,
+    height: i32,
+) -> (Vec<Platform>, Vec<PlatformRepair>) {
+    let has_bounds = width > 0 && height > 0;
+    let mut repairs = vec![];
+    let mut repaired = Vec::<Platform>::with_capacity(platforms.len());
+    for platform in platforms.iter().copied() {
+        let mut current = platform;
+        if current.x_start > current.x_end {
+            repairs.push(PlatformRepair::Swapped(current));
+            current = Platform {
+                x_start: current.x_end,
+                x_end: current.x_start,
+                ..current
+            };
+        }
+        if has_bounds && (current.x_start < 0 || current.x_end > width) {
+            let clipped = Platform {
+                x_start: current.x_start.max(0),
+                x_end: current.x_end.min(width),
+                ..current
+            };
+            repairs.push(PlatformRepair::Clipped(current, clipped));
+            current = clipped;
+        }
+        if current.x_start >= current.x_end || (has_bounds && !(0..=height).contains(&current.y)) {
+            repairs.push(PlatformRepair::Removed(current));
+            continue;
+        }
+        repaired.push(current);
+    }
+
+    // Merging can make a platform overlap with another so it repeats until nothing overlaps
+    while let Some((i, j)) = (0..repaired.len()).find_map(|i| {
+        (i + 1..repaired.len())
+            .find(|&j| {
+                let (first, second) = (repaired[i], repaired[j]);
+                first.y == second.y && first.x_start < second.x_end && second.x_start < first.x_end
+            })
+            .map(|j| (i, j))
+    }) {
+        let (first, second) = (repaired[i], repaired.remove(j));
+        let merged = Platform {
+            x_start: first.x_start.min(second.x_start),
+            x_end: first.x_end.max(second.x_end),
+            ..first
+        };
+        repairs.push(PlatformRepair::Merged(first, second, merged));
+        repaired[i] = merged;
+    }
+
+    if repaired.len() > pathing::MAX_PLATFORMS_COUNT {
+        repairs.extend(
+            repaired
+                .drain(pathing::MAX_PLATFORMS_COUNT..)
+                .map(PlatformRepair::Truncated),
+        );
+    }
+
+    (repaired, repairs)
+}
+
+/// A portal detected on the minimap
+///
+/// The position is relative to the minimap bottom-left corner with `y` being the portal top edge.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Portal {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub usage: PortalUsage,
+    /// The index of the portal in [`Minimap::portals`] this portal leads to when used as
+    /// [`PortalUsage::Shortcut`]
+    #[serde(default)]
+    pub destination: Option<usize>,
+}
+
+impl Portal {
+    /// The maximum distance between two detections of the same portal
+    const SAME_POSITION_TOLERANCE: i32 = 2;
+
+    /// Whether `x`, `y` is likely the position of this portal from another detection
+    pub fn is_same_position(&self, x: i32, y: i32) -> bool {
+        (self.x - x).abs() <= Self::SAME_POSITION_TOLERANCE
+            && (self.y - y).abs() <= Self::SAME_POSITION_TOLERANCE
+    }
+
+    /// The point the player stands at to enter this portal
+    pub fn entry_point(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y - self.height + 1)
+    }
+}
+
+/// How a detected portal is used when moving
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    Debug,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    Display,
+    EnumString,
+)]
+pub enum PortalUsage {
+    /// Aborts up jumping near the portal to avoid accidentally entering it
+    #[default]
+    Dangerous,
+    /// Enters the portal as a pathing shortcut to its destination
+    Shortcut,
+    /// Treats the portal as if it does not exist
+    Ignore,
+}
+
 #[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
@@ -435,6 +1401,12 @@ pub struct Position {
     pub x_random_range: i32,
     pub y: i32,
     pub allow_adjusting: bool,
+    /// The x distance from the position considered as arrived
+    ///
+    /// Overrides [`Self::allow_adjusting`] when [`Some`] so imprecise positions do not waste time
+    /// adjusting while precise positions can use `0`.
+    #[serde(default)]
+    pub arrival_tolerance: Option<u32>,
 }
 
 #[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
@@ -442,13 +1414,43 @@ pub struct ActionMove {
     pub position: Position,
     pub condition: ActionCondition,
     pub wait_after_move_millis: u64,
+    /// The player y band this action is restricted to
+    #[serde(default)]
+    pub y_band: Option<YBand>,
+    /// The index of another action in the same preset whose position x and y are used instead
+    ///
+    /// Resolved when the rotation is built so editing the anchor updates this action too.
+    #[serde(default)]
+    pub position_anchor: Option<usize>,
+}
+
+/// An inclusive vertical slice of the minimap in player coordinate
+///
+/// An action with this band set is skipped when the player is outside of it.
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct YBand {
+    pub y_min: i32,
+    pub y_max: i32,
+}
+
+impl YBand {
+    #[inline]
+    pub fn contains(&self, y: i32) -> bool {
+        (self.y_min..=self.y_max).contains(&y)
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ActionKey {
     pub key: KeyBinding,
+    /// The modifier held while pressing [`Self::key`]
+    #[serde(default)]
+    pub modifier: KeyModifier,
     #[serde(default)]
     pub link_key: Option<LinkKeyBinding>,
+    /// The modifier held while pressing the key of [`Self::link_key`]
+    #[serde(default)]
+    pub link_key_modifier: KeyModifier,
     #[serde(default = "count_default")]
     pub count: u32,
     pub position: Option<Position>,
@@ -462,13 +1464,29 @@ pub struct ActionKey {
     #[serde(default)]
     pub wait_after_use_millis_random_range: u64,
     pub queue_to_front: Option<bool>,
+    /// The player y band this action is restricted to
+    #[serde(default)]
+    pub y_band: Option<YBand>,
+    /// The milliseconds to hold down the key before releasing for charge skills or `0` to tap
+    #[serde(default)]
+    pub hold_millis: u64,
+    /// Whether this key is a heavy skill waiting at least its class cast lock duration after use
+    #[serde(default)]
+    pub heavy: bool,
+    /// The index of another action in the same preset whose position x and y are used instead
+    ///
+    /// Only applies when [`Self::position`] is [`Some`].
+    #[serde(default)]
+    pub position_anchor: Option<usize>,
 }
 
 impl Default for ActionKey {
     fn default() -> Self {
         Self {
             key: KeyBinding::default(),
+            modifier: KeyModifier::default(),
             link_key: None,
+            link_key_modifier: KeyModifier::default(),
             count: count_default(),
             position: None,
             condition: ActionCondition::default(),
@@ -479,6 +1497,10 @@ impl Default for ActionKey {
             wait_after_use_millis: 0,
             wait_after_use_millis_random_range: 0,
             queue_to_front: None,
+            y_band: None,
+            hold_millis: 0,
+            heavy: false,
+            position_anchor: None,
         }
     }
 }
@@ -532,6 +1554,27 @@ pub enum Class {
     Generic,
 }
 
+/// An action shared by the rotation of every preset (e.g. buffs and consumables)
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GlobalAction {
+    /// The stable id for keeping the action last queued time across edits and presets
+    pub id: u32,
+    pub action: Action,
+    #[serde(default)]
+    pub slot: GlobalActionSlot,
+    pub enabled: bool,
+}
+
+/// Where a [`GlobalAction`] is merged relative to the preset actions
+#[derive(
+    Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize, EnumIter, Display, EnumString,
+)]
+pub enum GlobalActionSlot {
+    #[default]
+    Before,
+    After,
+}
+
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, EnumIter, Display, EnumString)]
 pub enum Action {
     Move(ActionMove),
@@ -547,6 +1590,30 @@ pub enum ActionCondition {
     EveryMillis(u64),
     ErdaShowerOffCooldown,
     Linked,
+    AnyOf(ActionConditionOperand, ActionConditionOperand),
+    AllOf(ActionConditionOperand, ActionConditionOperand),
+    /// Queues when the player mana is above a percentage
+    ///
+    /// Requires [`Configuration::mana_detection`] and is always satisfied when the mana is not
+    /// detected.
+    MpAbovePercent(f32),
+}
+
+/// An operand of [`ActionCondition::AnyOf`] or [`ActionCondition::AllOf`]
+#[derive(
+    Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize, EnumIter, Display, EnumString,
+)]
+pub enum ActionConditionOperand {
+    EveryMillis(u64),
+    #[default]
+    ErdaShowerOffCooldown,
+    MpAbovePercent(f32),
+    /// Satisfied when the buff is detected
+    ///
+    /// Requires the buff key to be enabled in [`Configuration`] for the buff to be detected.
+    BuffActive(BuffKind),
+    /// Satisfied when the buff is not detected
+    BuffMissing(BuffKind),
 }
 
 #[derive(
@@ -646,6 +1713,39 @@ pub enum KeyBinding {
     Alt,
 }
 
+/// The modifier key held down while pressing a [`KeyBinding`] to form a chord (e.g. Shift+F1)
+#[derive(
+    Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize, EnumIter, Display, EnumString,
+)]
+pub enum KeyModifier {
+    #[default]
+    None,
+    Shift,
+    Ctrl,
+    Alt,
+}
+
+impl KeyModifier {
+    /// Formats `key` as a chord with this modifier (e.g. Shift+F1)
+    pub fn chord(self, key: KeyBinding) -> String {
+        match self {
+            KeyModifier::None => key.to_string(),
+            KeyModifier::Shift | KeyModifier::Ctrl | KeyModifier::Alt => format!("{self}+{key}"),
+        }
+    }
+}
+
+impl From<KeyModifier> for Option<KeyKind> {
+    fn from(value: KeyModifier) -> Self {
+        match value {
+            KeyModifier::None => None,
+            KeyModifier::Shift => Some(KeyKind::Shift),
+            KeyModifier::Ctrl => Some(KeyKind::Ctrl),
+            KeyModifier::Alt => Some(KeyKind::Alt),
+        }
+    }
+}
+
 impl From<KeyBinding> for KeyKind {
     fn from(value: KeyBinding) -> Self {
         match value {
@@ -801,12 +1901,17 @@ impl From<KeyKind> for KeyBinding {
 }
 
 pub fn query_settings() -> Settings {
-    let mut settings = query_from_table("settings").unwrap().into_iter().next();
-    if settings.is_none() {
-        settings = Some(Settings::default());
-        upsert_settings(settings.as_mut().unwrap()).unwrap();
+    if let Some(settings) = query_from_table("settings").unwrap().into_iter().next() {
+        return settings;
     }
-    settings.unwrap()
+    let mut settings = Settings::default();
+    // The existing row failed to deserialize and is kept as is instead of inserting another row
+    if count_from_table("settings").unwrap() > 0 {
+        warn!(target: "database", "using unsaved default settings because the saved row is invalid");
+        return settings;
+    }
+    upsert_settings(&mut settings).unwrap();
+    settings
 }
 
 pub fn upsert_settings(settings: &mut Settings) -> Result<()> {
@@ -836,34 +1941,340 @@ pub fn query_configs() -> Result<Vec<Configuration>> {
 }
 
 pub fn upsert_config(config: &mut Configuration) -> Result<()> {
+    config.sync.touch();
+    upsert_config_row(config)?;
+    sync::queue_export(config);
+    Ok(())
+}
+
+/// Saves `config` without marking it as modified or exporting it to the sync folder
+pub(crate) fn upsert_config_row(config: &mut Configuration) -> Result<()> {
     upsert_to_table("configurations", config)
 }
 
+/// Deletes `config` without exporting the deletion to the sync folder
+pub(crate) fn delete_config_row(config: &Configuration) -> Result<()> {
+    delete_from_table("configurations", config)
+}
+
 pub fn query_maps() -> Result<Vec<Minimap>> {
     query_from_table("maps")
 }
 
+/// Queries the saved map with the closest identity to the detected minimap with `width`,
+/// `height` and `fingerprint`
+pub fn query_map_by_identity(width: i32, height: i32, fingerprint: u64) -> Result<Option<Minimap>> {
+    Ok(query_maps()?
+        .into_iter()
+        .filter_map(|map| {
+            map.identity_distance(width, height, fingerprint)
+                .map(|distance| (distance, map))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, map)| map))
+}
+
 pub fn upsert_map(map: &mut Minimap) -> Result<()> {
+    map.sync.touch();
+    upsert_map_row(map)?;
+    sync::queue_export(map);
+    Ok(())
+}
+
+/// Saves `map` without marking it as modified or exporting it to the sync folder
+pub(crate) fn upsert_map_row(map: &mut Minimap) -> Result<()> {
+    if let Err(err) = backup_map_if_fewer_actions(&CONNECTION.lock().unwrap(), map) {
+        warn!(target: "database", "failed to backup map before upsert {err}");
+    }
     upsert_to_table("maps", map)
 }
 
 pub fn delete_map(map: &Minimap) -> Result<()> {
-    delete_from_table("maps", map)
+    delete_map_row(map)?;
+    sync::queue_deletion(map);
+    Ok(())
+}
+
+/// Deletes `map` without exporting the deletion to the sync folder
+pub(crate) fn delete_map_row(map: &Minimap) -> Result<()> {
+    match map.id {
+        Some(id) => delete_map_row_from(&mut CONNECTION.lock().unwrap(), id),
+        None => Ok(()),
+    }
 }
 
-fn map_data<T>(mut stmt: Statement<'_>, params: impl Params) -> Result<Vec<T>>
+/// Backs up and deletes the map with `id` along with its learned auto mobbing data
+///
+/// The learned data is deleted in the same transaction so that a map later reusing `id` does
+/// not inherit it.
+fn delete_map_row_from(conn: &mut Connection, id: i64) -> Result<()> {
+    let tx = conn.transaction()?;
+    if let Some(data) = query_map_data(&tx, id)? {
+        insert_map_backup(&tx, id, &data)?;
+    }
+    tx.execute("DELETE FROM maps WHERE id = ?1;", [id])?;
+    tx.execute("DELETE FROM auto_mob_learned WHERE map_id = ?1;", [id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Lists all map backups from newest to oldest
+pub fn list_map_backups() -> Result<Vec<MinimapBackup>> {
+    query_map_backups(&CONNECTION.lock().unwrap())
+}
+
+/// Restores `backup` to its original map, recreating the map if it was deleted
+pub fn restore_map_backup(backup: &MinimapBackup) -> Result<Minimap> {
+    let mut map = backup.map.clone();
+    upsert_map(&mut map)?;
+    Ok(map)
+}
+
+/// Exports `map` including its action presets and platforms to JSON for sharing or backing up
+pub fn export_map(map: &Minimap) -> Result<String> {
+    Ok(serde_json::to_string_pretty(map)?)
+}
+
+/// Imports a map exported by [`export_map`] from `json` and saves it as a new map
+///
+/// The imported map never replaces an existing map. If a map with the same name exists, the
+/// name is suffixed with a number. Returns the saved map.
+pub fn import_map(json: &str) -> Result<Minimap> {
+    let mut map = serde_json::from_str::<Minimap>(json)?;
+    let names = query_maps()?
+        .into_iter()
+        .map(|map| map.name)
+        .collect::<Vec<_>>();
+    map.id = None;
+    map.name = unique_name(&map.name, &names);
+    map.sync = SyncMetadata::default();
+    upsert_map(&mut map)?;
+    Ok(map)
+}
+
+/// Suffixes `name` with the first number not making it one of `names`
+pub(crate) fn unique_name(name: &str, names: &[String]) -> String {
+    if !names.iter().any(|existing| existing == name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|i| format!("{name} ({i})"))
+        .find(|candidate| !names.contains(candidate))
+        .unwrap()
+}
+
+/// Queries the auto mobbing data learned on the map with `map_id`
+pub(crate) fn query_auto_mob_learned(map_id: i64) -> Result<AutoMobLearned> {
+    query_auto_mob_learned_from(&CONNECTION.lock().unwrap(), map_id)
+}
+
+/// Inserts or replaces the auto mobbing data `learned` on the map with `map_id`
+pub(crate) fn upsert_auto_mob_learned(map_id: i64, learned: &AutoMobLearned) -> Result<()> {
+    upsert_auto_mob_learned_to(&CONNECTION.lock().unwrap(), map_id, learned)
+}
+
+fn query_auto_mob_learned_from(conn: &Connection, map_id: i64) -> Result<AutoMobLearned> {
+    let data = conn
+        .query_row(
+            "SELECT data FROM auto_mob_learned WHERE map_id = ?1;",
+            [map_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    Ok(match data {
+        Some(data) => serde_json::from_str(&data)?,
+        None => AutoMobLearned::default(),
+    })
+}
+
+fn upsert_auto_mob_learned_to(
+    conn: &Connection,
+    map_id: i64,
+    learned: &AutoMobLearned,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO auto_mob_learned (map_id, data) VALUES (?1, ?2)
+        ON CONFLICT (map_id) DO UPDATE SET data = excluded.data;",
+        (map_id, serde_json::to_string(learned)?),
+    )?;
+    Ok(())
+}
+
+/// Queries the last fired wall-clock timestamps in milliseconds keyed by action key
+pub(crate) fn query_action_timestamps() -> Result<HashMap<String, u64>> {
+    query_action_timestamps_from(&CONNECTION.lock().unwrap())
+}
+
+/// Inserts or replaces the last fired wall-clock timestamps in milliseconds of actions
+pub(crate) fn upsert_action_timestamps(timestamps: &[(String, u64)]) -> Result<()> {
+    upsert_action_timestamps_to(&CONNECTION.lock().unwrap(), timestamps)
+}
+
+fn query_action_timestamps_from(conn: &Connection) -> Result<HashMap<String, u64>> {
+    let mut stmt = conn.prepare("SELECT key, fired_at FROM action_timestamps;")?;
+    Ok(stmt
+        .query_map([], |row| {
+            let key = row.get::<_, String>(0)?;
+            let fired_at = row.get::<_, i64>(1)?;
+            Ok((key, fired_at as u64))
+        })?
+        .filter_map(|row| row.ok())
+        .collect())
+}
+
+fn upsert_action_timestamps_to(conn: &Connection, timestamps: &[(String, u64)]) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO action_timestamps (key, fired_at) VALUES (?1, ?2)
+        ON CONFLICT (key) DO UPDATE SET fired_at = excluded.fired_at;",
+    )?;
+    for (key, fired_at) in timestamps {
+        stmt.execute((key, *fired_at as i64))?;
+    }
+    Ok(())
+}
+
+/// Sums the run time in seconds from `from_day` to `to_day` inclusively
+///
+/// Days are counted since Unix epoch in UTC.
+pub(crate) fn query_run_time_secs(from_day: u64, to_day: u64) -> Result<u64> {
+    query_run_time_secs_from(&CONNECTION.lock().unwrap(), from_day, to_day)
+}
+
+/// Adds `secs` of run time to `day`
+pub(crate) fn add_run_time_secs(day: u64, secs: u64) -> Result<()> {
+    add_run_time_secs_to(&CONNECTION.lock().unwrap(), day, secs)
+}
+
+fn query_run_time_secs_from(conn: &Connection, from_day: u64, to_day: u64) -> Result<u64> {
+    let secs = conn.query_row(
+        "SELECT COALESCE(SUM(seconds), 0) FROM run_times WHERE day BETWEEN ?1 AND ?2;",
+        (from_day as i64, to_day as i64),
+        |row| row.get::<_, i64>(0),
+    )?;
+    Ok(secs as u64)
+}
+
+fn add_run_time_secs_to(conn: &Connection, day: u64, secs: u64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO run_times (day, seconds) VALUES (?1, ?2)
+        ON CONFLICT (day) DO UPDATE SET seconds = seconds + excluded.seconds;",
+        (day as i64, secs as i64),
+    )?;
+    Ok(())
+}
+
+#[inline]
+fn actions_count(map: &Minimap) -> usize {
+    map.actions.values().map(Vec::len).sum()
+}
+
+fn backup_map_if_fewer_actions(conn: &Connection, map: &Minimap) -> Result<()> {
+    let Some(id) = map.id else {
+        return Ok(());
+    };
+    let Some(data) = query_map_data(conn, id)? else {
+        return Ok(());
+    };
+    let existing = serde_json::from_str::<Minimap>(&data)?;
+    if actions_count(&existing) > actions_count(map) {
+        insert_map_backup(conn, id, &data)?;
+    }
+    Ok(())
+}
+
+fn query_map_data(conn: &Connection, id: i64) -> Result<Option<String>> {
+    Ok(conn
+        .query_row("SELECT data FROM maps WHERE id = ?1;", [id], |row| {
+            row.get::<_, String>(0)
+        })
+        .optional()?)
+}
+
+fn insert_map_backup(conn: &Connection, map_id: i64, data: &str) -> Result<()> {
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    conn.execute(
+        "INSERT INTO map_backups (map_id, created_at, data) VALUES (?1, ?2, ?3);",
+        (map_id, created_at as i64, data),
+    )?;
+    conn.execute(
+        r#"
+        DELETE FROM map_backups WHERE map_id = ?1 AND id NOT IN (
+            SELECT id FROM map_backups WHERE map_id = ?1 ORDER BY id DESC LIMIT ?2
+        );
+        "#,
+        (map_id, MAX_MAP_BACKUPS as i64),
+    )?;
+    debug!(target: "database", "backed up map {map_id}");
+    Ok(())
+}
+
+fn query_map_backups(conn: &Connection) -> Result<Vec<MinimapBackup>> {
+    let mut stmt =
+        conn.prepare("SELECT id, map_id, created_at, data FROM map_backups ORDER BY id DESC;")?;
+    Ok(stmt
+        .query_map([], |row| {
+            let id = row.get::<_, i64>(0)?;
+            let map_id = row.get::<_, i64>(1)?;
+            let created_at = row.get::<_, i64>(2)?;
+            let data = row.get::<_, String>(3)?;
+            Ok((id, map_id, created_at, data))
+        })?
+        .filter_map(|row| row.ok())
+        .filter_map(|(id, map_id, created_at, data)| {
+            let mut map = serde_json::from_str::<Minimap>(&data)
+                .inspect_err(|err| {
+                    warn!(target: "database", "skipping backup {id} that failed to deserialize {err}")
+                })
+                .ok()?;
+            map.id = Some(map_id);
+            Some(MinimapBackup {
+                id,
+                created_at: created_at as u64,
+                map,
+            })
+        })
+        .collect())
+}
+
+/// The rows skipped since startup because they failed to deserialize
+static INVALID_ROWS: LazyLock<Mutex<Vec<String>>> = LazyLock::new(|| Mutex::new(vec![]));
+
+/// The rows that failed to deserialize and are skipped when querying
+///
+/// The rows are kept in the database untouched so that they can be recovered manually.
+pub fn invalid_rows() -> Vec<String> {
+    INVALID_ROWS.lock().unwrap().clone()
+}
+
+fn map_data<T>(table: &str, mut stmt: Statement<'_>, params: impl Params) -> Result<Vec<T>>
 where
     T: DeserializeOwned + Identifiable + Default,
 {
     Ok(stmt
-        .query_map::<T, _, _>(params, |row| {
+        .query_map::<Option<T>, _, _>(params, |row| {
             let id = row.get::<_, i64>(0).unwrap();
             let data = row.get::<_, String>(1).unwrap();
-            let mut value = serde_json::from_str::<'_, T>(data.as_str()).unwrap_or_default();
-            value.set_id(id);
+            // Skips instead of defaulting so that the row is not overwritten on next upsert
+            let value = serde_json::from_str::<'_, T>(data.as_str())
+                .inspect_err(|err| {
+                    warn!(target: "database", "skipping {table} row {id} that failed to deserialize {err}");
+                    let row = format!("{table} row {id}: {err}");
+                    let mut invalid_rows = INVALID_ROWS.lock().unwrap();
+                    if !invalid_rows.contains(&row) {
+                        invalid_rows.push(row);
+                    }
+                })
+                .ok()
+                .map(|mut value| {
+                    value.set_id(id);
+                    value
+                });
             Ok(value)
         })?
-        .filter_map(|c| c.ok())
+        .filter_map(|c| c.ok().flatten())
         .collect::<Vec<_>>())
 }
 
@@ -874,7 +2285,13 @@ where
     let conn = CONNECTION.lock().unwrap();
     let stmt = format!("SELECT id, data FROM {table}");
     let stmt = conn.prepare(&stmt).unwrap();
-    map_data(stmt, [])
+    map_data(table, stmt, [])
+}
+
+fn count_from_table(table: &str) -> Result<usize> {
+    let conn = CONNECTION.lock().unwrap();
+    let stmt = format!("SELECT COUNT(*) FROM {table}");
+    Ok(conn.query_row(&stmt, [], |row| row.get::<_, usize>(0))?)
 }
 
 fn upsert_to_table<T>(table: &str, data: &mut T) -> Result<()>
@@ -910,3 +2327,327 @@ fn delete_from_table<T: Identifiable>(table: &str, data: &T) -> Result<()> {
     }
     inner(table, data.id())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuning_parameters_set_within_range_only() {
+        let mut tuning = TuningParameters::default();
+
+        assert!(tuning.set(TuningParameter::UnstuckCountThreshold, 10));
+        assert_eq!(tuning.unstuck_count_threshold, 10);
+        assert!(!tuning.set(TuningParameter::UnstuckCountThreshold, 0));
+        assert_eq!(tuning.get(TuningParameter::UnstuckCountThreshold), 10);
+        assert!(!tuning.set(TuningParameter::ElitePotionIntervalMillis, 10));
+        assert_eq!(tuning.elite_boss_potion_interval_millis, 2_000);
+    }
+
+    #[test]
+    fn repair_platforms_fixes_malformed() {
+        let platform = |x_start, x_end, y| Platform { x_start, x_end, y };
+        let (platforms, repairs) = repair_platforms(
+            &[
+                platform(30, 10, 5),
+                platform(-5, 20, 10),
+                platform(15, 15, 20),
+                platform(10, 20, 200),
+                platform(20, 40, 5),
+                platform(50, 60, 30),
+            ],
+            100,
+            50,
+        );
+
+        assert_eq!(
+            platforms,
+            vec![
+                platform(10, 40, 5),
+                platform(0, 20, 10),
+                platform(50, 60, 30)
+            ]
+        );
+        assert_eq!(
+            repairs,
+            vec![
+                PlatformRepair::Swapped(platform(30, 10, 5)),
+                PlatformRepair::Clipped(platform(-5, 20, 10), platform(0, 20, 10)),
+                PlatformRepair::Removed(platform(15, 15, 20)),
+                PlatformRepair::Removed(platform(10, 20, 200)),
+                PlatformRepair::Merged(
+                    platform(10, 30, 5),
+                    platform(20, 40, 5),
+                    platform(10, 40, 5)
+                ),
+            ]
+        );
+
+        let valid = [platform(0, 10, 5), platform(10, 20, 5)];
+        assert_eq!(repair_platforms(&valid, 100, 50), (valid.to_vec(), vec![]));
+    }
+
+    #[test]
+    fn quiet_hours_contains_spanning_midnight() {
+        let quiet_hours = QuietHours {
+            enabled: true,
+            start_hour: 22,
+            end_hour: 6,
+            batch: false,
+        };
+
+        assert!(quiet_hours.contains(23 * 60));
+        assert!(quiet_hours.contains(0));
+        assert!(quiet_hours.contains(6 * 60 - 1));
+        assert!(!quiet_hours.contains(6 * 60));
+        assert!(!quiet_hours.contains(12 * 60));
+        assert!(
+            !QuietHours {
+                enabled: false,
+                ..quiet_hours
+            }
+            .contains(0)
+        );
+        assert!(
+            QuietHours {
+                start_hour: 1,
+                end_hour: 5,
+                ..quiet_hours
+            }
+            .contains(2 * 60)
+        );
+    }
+
+    #[test]
+    fn settings_client_capture_handle_per_client() {
+        let identity = |process_id| CaptureHandleIdentity {
+            title: "MapleStory".to_string(),
+            process_id,
+        };
+        let mut settings = Settings {
+            additional_clients: vec![identity(2), identity(3)],
+            ..Settings::default()
+        };
+
+        settings.set_client_capture_handle(0, Some(identity(1)));
+        settings.set_client_capture_handle(2, Some(identity(4)));
+        settings.set_client_capture_handle(1, None);
+        settings.set_client_capture_handle(3, Some(identity(5)));
+
+        assert_eq!(settings.capture_handle, Some(identity(1)));
+        assert_eq!(settings.additional_clients, vec![identity(2), identity(4)]);
+        assert_eq!(settings.client_capture_handle(1), Some(&identity(2)));
+        assert_eq!(settings.client_capture_handle(2), Some(&identity(4)));
+        assert_eq!(settings.client_capture_handle(3), None);
+    }
+
+    #[test]
+    fn export_map_round_trips_without_id() {
+        let map = Minimap {
+            id: Some(1),
+            name: "Map".to_string(),
+            width: 200,
+            height: 100,
+            platforms: vec![Platform {
+                x_start: 10,
+                x_end: 50,
+                y: 20,
+            }],
+            actions: HashMap::from([("Preset".to_string(), vec![])]),
+            ..Minimap::default()
+        };
+
+        let imported = serde_json::from_str::<Minimap>(&export_map(&map).unwrap()).unwrap();
+
+        assert_eq!(imported, Minimap { id: None, ..map });
+    }
+
+    #[test]
+    fn unique_name_suffixes_existing() {
+        let names = vec!["Map".to_string(), "Map (2)".to_string()];
+
+        assert_eq!(unique_name("Other", &names), "Other");
+        assert_eq!(unique_name("Map", &names), "Map (3)");
+    }
+
+    #[test]
+    fn minimap_identity_distance_within_tolerance_only() {
+        let map = Minimap {
+            width: 200,
+            height: 100,
+            fingerprint: Some(0b1111),
+            ..Minimap::default()
+        };
+
+        assert_eq!(map.identity_distance(201, 99, 0b1111), Some(0));
+        assert_eq!(map.identity_distance(200, 100, 0b0011), Some(2));
+        assert_eq!(map.identity_distance(210, 100, 0b1111), None);
+        assert_eq!(map.identity_distance(200, 100, u64::MAX << 4), None);
+        assert_eq!(
+            Minimap {
+                fingerprint: None,
+                ..map
+            }
+            .identity_distance(200, 100, 0b1111),
+            None
+        );
+    }
+
+    #[test]
+    fn migrate_from_unversioned() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE maps (id INTEGER PRIMARY KEY, data TEXT NOT NULL);")
+            .unwrap();
+        conn.execute("INSERT INTO maps (id, data) VALUES (1, '{}')", [])
+            .unwrap();
+
+        migrate(&mut conn).unwrap();
+        let version = conn
+            .pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0))
+            .unwrap();
+        let count = conn
+            .query_row("SELECT COUNT(*) FROM maps", [], |row| row.get::<_, i64>(0))
+            .unwrap();
+
+        assert_eq!(version, MIGRATIONS.len());
+        assert_eq!(count, 1);
+        assert!(conn.prepare("SELECT id, data FROM settings").is_ok());
+
+        // Idempotent
+        migrate(&mut conn).unwrap();
+    }
+
+    #[test]
+    fn upsert_action_timestamps_replaces_existing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        upsert_action_timestamps_to(&conn, &[("a".to_string(), 1), ("b".to_string(), 2)]).unwrap();
+        upsert_action_timestamps_to(&conn, &[("a".to_string(), 3)]).unwrap();
+        let timestamps = query_action_timestamps_from(&conn).unwrap();
+
+        assert_eq!(timestamps.len(), 2);
+        assert_eq!(timestamps["a"], 3);
+        assert_eq!(timestamps["b"], 2);
+    }
+
+    #[test]
+    fn migrate_moves_auto_mob_learned_out_of_maps() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE maps (id INTEGER PRIMARY KEY, data TEXT NOT NULL);")
+            .unwrap();
+        conn.execute(
+            "INSERT INTO maps (id, data) VALUES (1, ?1), (2, '{}')",
+            [r#"{"auto_mob_learned":{"pathing_points":[[1,2]]}}"#],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        let data = query_map_data(&conn, 1).unwrap().unwrap();
+
+        assert_eq!(
+            query_auto_mob_learned_from(&conn, 1)
+                .unwrap()
+                .pathing_points,
+            vec![(1, 2)]
+        );
+        assert!(query_auto_mob_learned_from(&conn, 2).unwrap().is_empty());
+        assert!(!data.contains("auto_mob_learned"));
+    }
+
+    #[test]
+    fn upsert_auto_mob_learned_replaces_existing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        let mut learned = AutoMobLearned::default();
+        learned.reachable_ys.insert(10, 1);
+
+        upsert_auto_mob_learned_to(&conn, 1, &learned).unwrap();
+        learned.reachable_ys.insert(10, 2);
+        upsert_auto_mob_learned_to(&conn, 1, &learned).unwrap();
+
+        assert_eq!(query_auto_mob_learned_from(&conn, 1).unwrap(), learned);
+    }
+
+    #[test]
+    fn delete_map_row_deletes_auto_mob_learned() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute("INSERT INTO maps (id, data) VALUES (1, '{}')", [])
+            .unwrap();
+        let mut learned = AutoMobLearned::default();
+        learned.reachable_ys.insert(10, 1);
+        upsert_auto_mob_learned_to(&conn, 1, &learned).unwrap();
+
+        delete_map_row_from(&mut conn, 1).unwrap();
+
+        assert!(query_map_data(&conn, 1).unwrap().is_none());
+        assert!(query_auto_mob_learned_from(&conn, 1).unwrap().is_empty());
+        assert_eq!(query_map_backups(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn add_run_time_secs_accumulates_per_day() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        add_run_time_secs_to(&conn, 1, 30).unwrap();
+        add_run_time_secs_to(&conn, 1, 15).unwrap();
+        add_run_time_secs_to(&conn, 3, 60).unwrap();
+        add_run_time_secs_to(&conn, 10, 100).unwrap();
+
+        assert_eq!(query_run_time_secs_from(&conn, 1, 1).unwrap(), 45);
+        assert_eq!(query_run_time_secs_from(&conn, 1, 7).unwrap(), 105);
+        assert_eq!(query_run_time_secs_from(&conn, 4, 7).unwrap(), 0);
+    }
+
+    #[test]
+    fn backup_map_if_fewer_actions_only() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        let mut map = Minimap {
+            id: Some(1),
+            name: "map".to_string(),
+            ..Minimap::default()
+        };
+        map.actions.insert(
+            "preset".to_string(),
+            vec![Action::Move(ActionMove::default()); 2],
+        );
+        conn.execute(
+            "INSERT INTO maps (id, data) VALUES (1, ?1)",
+            [serde_json::to_string(&map).unwrap()],
+        )
+        .unwrap();
+
+        map.actions
+            .get_mut("preset")
+            .unwrap()
+            .push(Action::Move(ActionMove::default()));
+        backup_map_if_fewer_actions(&conn, &map).unwrap();
+        assert!(query_map_backups(&conn).unwrap().is_empty());
+
+        map.actions.get_mut("preset").unwrap().clear();
+        backup_map_if_fewer_actions(&conn, &map).unwrap();
+        let backups = query_map_backups(&conn).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].map.id, Some(1));
+        assert_eq!(actions_count(&backups[0].map), 2);
+    }
+
+    #[test]
+    fn insert_map_backup_prunes_oldest() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        for _ in 0..MAX_MAP_BACKUPS + 2 {
+            insert_map_backup(&conn, 1, "{}").unwrap();
+        }
+        insert_map_backup(&conn, 2, "{}").unwrap();
+
+        let backups = query_map_backups(&conn).unwrap();
+        assert_eq!(backups.len(), MAX_MAP_BACKUPS + 1);
+        assert_eq!(backups[0].map.id, Some(2));
+        assert_eq!(backups.last().unwrap().id, 3);
+    }
+}
@@ -5,14 +5,14 @@ use std::{
     fmt::Debug,
     sync::{
         Arc, LazyLock, Mutex,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
     },
 };
 
 use anyhow::{Result, anyhow, bail};
 use dyn_clone::DynClone;
 use log::{debug, error, info};
-#[cfg(test)]
+#[cfg(any(test, feature = "test-support"))]
 use mockall::mock;
 use opencv::{
     boxed_ref::BoxedRef,
@@ -20,8 +20,8 @@ use opencv::{
         BORDER_CONSTANT, CMP_EQ, CMP_GT, CV_8U, CV_32FC3, CV_32S, Mat, MatExprTraitConst, MatTrait,
         MatTraitConst, MatTraitConstManual, ModifyInplace, Point, Point2f, Range, Rect, Scalar,
         Size, ToInputArray, Vec3b, Vec4b, Vector, add, add_weighted_def, bitwise_and_def, compare,
-        copy_make_border, divide2_def, extract_channel, find_non_zero, min_max_loc, no_array,
-        subtract_def, transpose_nd,
+        copy_make_border, divide2_def, extract_channel, find_non_zero, in_range, min_max_loc,
+        no_array, subtract_def, transpose_nd,
     },
     dnn::{
         ModelTrait, TextRecognitionModel, TextRecognitionModelTrait,
@@ -31,10 +31,10 @@ use opencv::{
     imgproc::{
         CC_STAT_AREA, CC_STAT_HEIGHT, CC_STAT_LEFT, CC_STAT_TOP, CC_STAT_WIDTH,
         CHAIN_APPROX_SIMPLE, COLOR_BGR2HSV_FULL, COLOR_BGRA2BGR, COLOR_BGRA2GRAY, COLOR_BGRA2RGB,
-        INTER_CUBIC, INTER_LINEAR, MORPH_RECT, RETR_EXTERNAL, THRESH_BINARY, TM_CCOEFF_NORMED,
-        bounding_rect, connected_components_with_stats, cvt_color_def, dilate_def,
-        find_contours_def, get_structuring_element_def, match_template, min_area_rect, resize,
-        threshold,
+        INTER_AREA, INTER_CUBIC, INTER_LINEAR, MORPH_RECT, RETR_EXTERNAL, THRESH_BINARY,
+        TM_CCOEFF_NORMED, bounding_rect, connected_components_with_stats, cvt_color_def,
+        dilate_def, find_contours_def, get_structuring_element_def, match_template, min_area_rect,
+        resize, threshold,
     },
 };
 use ort::{
@@ -42,14 +42,62 @@ use ort::{
     value::Tensor,
 };
 use platforms::windows::KeyKind;
+use strum::IntoEnumIterator;
 
 #[cfg(debug_assertions)]
 use crate::debug::{debug_mat, debug_spinning_arrows};
-use crate::{array::Array, buff::BuffKind, mat::OwnedMat};
+use crate::{
+    array::Array,
+    buff::BuffKind,
+    database::{DetectionProfile, HsvRange, OtherPlayerColors},
+    mat::{OwnedMat, mat_bytes},
+};
+
+/// The default template matching thresholds when not overridden by [`DetectionProfile`]
+const PLAYER_THRESHOLD: f64 = 0.75;
+const RUNE_THRESHOLD: f64 = 0.75;
+const PORTAL_THRESHOLD: f64 = 0.8;
 
 const MAX_ARROWS: usize = 4;
 const MAX_SPIN_ARROWS: usize = 2; // PRAY
 
+/// The maximum number of other player dots of each kind to detect
+pub const MAX_OTHER_PLAYERS: usize = 8;
+
+/// The vertical gap in pixels between the bottom of the HP bar and the top of the MP bar
+const MANA_BAR_GAP: i32 = 1;
+
+static MOB_MODEL: LazyLock<Option<Session>> =
+    LazyLock::new(|| build_session("mob detection", include_bytes!(env!("MOB_MODEL"))));
+static MINIMAP_MODEL: LazyLock<Option<Session>> =
+    LazyLock::new(|| build_session("minimap detection", include_bytes!(env!("MINIMAP_MODEL"))));
+static RUNE_MODEL: LazyLock<Option<Session>> =
+    LazyLock::new(|| build_session("rune detection", include_bytes!(env!("RUNE_MODEL"))));
+
+/// Whether the mob detection model is loaded
+///
+/// Auto mobbing with [`crate::AutoMobbingTarget::Model`] does not detect any mob otherwise.
+#[inline]
+pub fn is_mob_model_available() -> bool {
+    MOB_MODEL.is_some()
+}
+
+/// Whether the minimap detection model is loaded
+///
+/// The minimap is searched in the top-left quarter of the screen otherwise.
+#[inline]
+pub fn is_minimap_model_available() -> bool {
+    MINIMAP_MODEL.is_some()
+}
+
+/// Whether the rune detection model is loaded
+///
+/// Rune solving is disabled otherwise.
+#[inline]
+pub fn is_rune_model_available() -> bool {
+    RUNE_MODEL.is_some()
+}
+
 /// Struct for storing information about the spinning arrows
 #[derive(Debug, Copy, Clone)]
 struct SpinArrow {
@@ -68,6 +116,8 @@ struct SpinArrow {
 #[derive(Debug)]
 pub enum ArrowsState {
     Calibrating(ArrowsCalibrating),
+    /// The arrows are detected but not all of them pass the score threshold
+    LowConfidence,
     Complete([KeyKind; MAX_ARROWS]),
 }
 
@@ -104,10 +154,33 @@ pub enum OtherPlayerKind {
 pub trait Detector: 'static + Send + DynClone + Debug {
     fn mat(&self) -> &OwnedMat;
 
+    /// The bytes used by the frame and the derived images computed so far.
+    fn memory_usage(&self) -> usize;
+
     /// Detects a list of mobs.
     ///
+    /// Mobs outside of `polygon` are also excluded if it has at least 3 vertices.
+    ///
     /// Returns a list of mobs coordinate relative to minimap coordinate.
-    fn detect_mobs(&self, minimap: Rect, bound: Rect, player: Point) -> Result<Vec<Point>>;
+    fn detect_mobs(
+        &self,
+        minimap: Rect,
+        bound: Rect,
+        polygon: &[Point],
+        player: Point,
+    ) -> Result<Vec<Point>>;
+
+    /// Detects a list of mobs from the red dots on the given `minimap` rectangle.
+    ///
+    /// Mobs outside of `polygon` are also excluded if it has at least 3 vertices.
+    ///
+    /// Returns a list of mobs coordinate relative to minimap coordinate.
+    fn detect_minimap_mobs(
+        &self,
+        minimap: Rect,
+        bound: Rect,
+        polygon: &[Point],
+    ) -> Result<Vec<Point>>;
 
     /// Detects whether to press ESC for unstucking.
     fn detect_esc_settings(&self) -> bool;
@@ -121,6 +194,12 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     /// the minimap's white border.
     fn detect_minimap(&self, border_threshold: u8) -> Result<Rect>;
 
+    /// Detects the fingerprint of the given `minimap` rectangle for identifying the map.
+    ///
+    /// The fingerprint is a 64-bit average hash of the minimap content so that two
+    /// fingerprints of the same map only differ by a few bits from moving dots.
+    fn detect_minimap_fingerprint(&self, minimap: Rect) -> Result<u64>;
+
     /// Detects the portals from the given `minimap` rectangle.
     ///
     /// Returns `Rect` relative to `minimap` coordinate.
@@ -136,8 +215,10 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     /// Returns `Rect` relative to `minimap` coordinate.
     fn detect_player(&self, minimap: Rect) -> Result<Rect>;
 
-    /// Detects whether a player of `kind` is in the minimap.
-    fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> bool;
+    /// Detects the players of `kind` in the provided `minimap` rectangle.
+    ///
+    /// Returns a non-empty list of `Rect` relative to `minimap` coordinate.
+    fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> Result<Vec<Rect>>;
 
     /// Detects whether the player is dead.
     fn detect_player_is_dead(&self) -> bool;
@@ -154,9 +235,21 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     /// Detects the player current health and max health.
     fn detect_player_health(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)>;
 
+    /// Detects the player mana bar below the `health_bar`.
+    ///
+    /// The returned bar can be used in place of the health bar to detect the current and max
+    /// mana.
+    fn detect_player_mana_bar(&self, health_bar: Rect) -> Result<Rect>;
+
     /// Detects whether the player has a buff specified by `kind`.
     fn detect_player_buff(&self, kind: BuffKind) -> bool;
 
+    /// Detects the offset of the buffs region from its default top right position.
+    ///
+    /// The offset is found by locating any active buff in the top half of the frame and is the
+    /// smallest shift for the default region to contain all of them.
+    fn detect_buffs_region_offset(&self) -> Result<Point>;
+
     /// Detects arrows from the given RGBA `Mat` image.
     ///
     /// `calibrating` represents the previous calibrating state returned by
@@ -167,26 +260,42 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     fn detect_erda_shower(&self) -> Result<Rect>;
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-support"))]
 mock! {
     pub Detector {}
 
     impl Detector for Detector {
         fn mat(&self) -> &OwnedMat;
-        fn detect_mobs(&self, minimap: Rect, bound: Rect, player: Point) -> Result<Vec<Point>>;
+        fn memory_usage(&self) -> usize;
+        fn detect_mobs<'a>(
+            &self,
+            minimap: Rect,
+            bound: Rect,
+            polygon: &'a [Point],
+            player: Point,
+        ) -> Result<Vec<Point>>;
+        fn detect_minimap_mobs<'a>(
+            &self,
+            minimap: Rect,
+            bound: Rect,
+            polygon: &'a [Point],
+        ) -> Result<Vec<Point>>;
         fn detect_esc_settings(&self) -> bool;
         fn detect_elite_boss_bar(&self) -> bool;
         fn detect_minimap(&self, border_threshold: u8) -> Result<Rect>;
+        fn detect_minimap_fingerprint(&self, minimap: Rect) -> Result<u64>;
         fn detect_minimap_portals(&self, minimap: Rect) -> Result<Vec<Rect>>;
         fn detect_minimap_rune(&self, minimap: Rect) -> Result<Rect>;
         fn detect_player(&self, minimap: Rect) -> Result<Rect>;
-        fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> bool;
+        fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> Result<Vec<Rect>>;
         fn detect_player_is_dead(&self) -> bool;
         fn detect_player_in_cash_shop(&self) -> bool;
         fn detect_player_health_bar(&self) -> Result<Rect>;
         fn detect_player_current_max_health_bars(&self, health_bar: Rect) -> Result<(Rect, Rect)>;
         fn detect_player_health(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)>;
+        fn detect_player_mana_bar(&self, health_bar: Rect) -> Result<Rect>;
         fn detect_player_buff(&self, kind: BuffKind) -> bool;
+        fn detect_buffs_region_offset(&self) -> Result<Point>;
         fn detect_rune_arrows<'a>(
             &self,
             calibrating: ArrowsCalibrating,
@@ -205,36 +314,182 @@ mock! {
 
 type MatFn = Box<dyn FnOnce() -> Mat + Send>;
 
-/// A detector that temporary caches the transformed `Mat`.
+/// Creates a lazily computed [`Mat`] that adds its bytes to `bytes` once computed
+fn derived_mat(
+    bytes: &Arc<AtomicUsize>,
+    f: impl FnOnce() -> Mat + Send + 'static,
+) -> Arc<LazyLock<Mat, MatFn>> {
+    let bytes = bytes.clone();
+    Arc::new(LazyLock::new(Box::new(move || {
+        let mat = f();
+        bytes.fetch_add(mat_bytes(&mat), Ordering::Relaxed);
+        mat
+    })))
+}
+
+/// The key of a detection call cached by [`DetectionCache`]
 ///
-/// It is useful when there are multiple detections in a single tick that
-/// rely on grayscale (e.g. buffs).
+/// [`Rect`] arguments are stored as `(x, y, width, height)` because [`Rect`] is not hashable.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum DetectionKey {
+    EscSettings,
+    EliteBossBar,
+    Minimap(u8),
+    MinimapRune((i32, i32, i32, i32)),
+    Player((i32, i32, i32, i32)),
+    PlayerIsDead,
+    PlayerInCashShop,
+    PlayerHealthBar,
+    PlayerBuff(usize),
+    ErdaShower,
+}
+
+impl DetectionKey {
+    #[inline]
+    fn rect(rect: Rect) -> (i32, i32, i32, i32) {
+        (rect.x, rect.y, rect.width, rect.height)
+    }
+}
+
+/// The results of detection calls on the same frame
 ///
-/// TODO: Is it really useful?
+/// Errors are stored as their messages since [`anyhow::Error`] is not cloneable.
+#[derive(Debug, Default)]
+struct DetectionCache {
+    bools: Mutex<HashMap<DetectionKey, bool>>,
+    rects: Mutex<HashMap<DetectionKey, Result<Rect, String>>>,
+}
+
+impl DetectionCache {
+    /// Gets the cached result of `key` or detects and caches it with `detect`
+    ///
+    /// The lock is not held while detecting so that detections of other keys on other threads
+    /// are not blocked.
+    fn get_or_detect_bool(&self, key: DetectionKey, detect: impl FnOnce() -> bool) -> bool {
+        if let Some(value) = self.bools.lock().unwrap().get(&key) {
+            return *value;
+        }
+        let value = detect();
+        self.bools.lock().unwrap().insert(key, value);
+        value
+    }
+
+    /// Same as [`Self::get_or_detect_bool`] but for detections returning [`Rect`]
+    fn get_or_detect_rect(
+        &self,
+        key: DetectionKey,
+        detect: impl FnOnce() -> Result<Rect>,
+    ) -> Result<Rect> {
+        if let Some(value) = self.rects.lock().unwrap().get(&key) {
+            return value.clone().map_err(|err| anyhow!(err));
+        }
+        let value = detect();
+        let cached = match &value {
+            Ok(rect) => Ok(*rect),
+            Err(err) => Err(err.to_string()),
+        };
+        self.rects.lock().unwrap().insert(key, cached);
+        value
+    }
+}
+
+/// A detector that temporary caches the transformed `Mat` and detection results.
+///
+/// It is useful when there are multiple detections in a single tick that
+/// rely on grayscale (e.g. buffs) or when multiple contextual states perform the same
+/// detection in a single tick. Since a new detector is created for every frame, the cached
+/// results never outlive the tick.
 #[derive(Clone, Debug)]
 pub struct CachedDetector {
     mat: Arc<OwnedMat>,
+    /// The detection results shared between clones
+    cache: Arc<DetectionCache>,
     grayscale: Arc<LazyLock<Mat, MatFn>>,
     buffs_grayscale: Arc<LazyLock<Mat, MatFn>>,
+    half_grayscale: Arc<LazyLock<Mat, MatFn>>,
+    /// The bytes of the grayscale images computed so far shared between clones
+    derived_bytes: Arc<AtomicUsize>,
+    profile: DetectionProfile,
+    /// Whether to use [`Self::half_grayscale`] for detections that do not need full details
+    half_resolution: bool,
+    other_player_colors: OtherPlayerColors,
+    /// The offset of the buffs region from the default top right position
+    buffs_region_offset: Point,
 }
 
 impl CachedDetector {
     pub fn new(mat: OwnedMat) -> CachedDetector {
         let mat = Arc::new(mat);
+        let derived_bytes = Arc::new(AtomicUsize::new(0));
         let grayscale = mat.clone();
-        let grayscale = Arc::new(LazyLock::<Mat, MatFn>::new(Box::new(move || {
-            to_grayscale(&*grayscale, true)
-        })));
+        let grayscale = derived_mat(&derived_bytes, move || to_grayscale(&*grayscale, true));
         let buffs_grayscale = grayscale.clone();
-        let buffs_grayscale = Arc::new(LazyLock::<Mat, MatFn>::new(Box::new(move || {
-            crop_to_buffs_region(&**buffs_grayscale).clone_pointee()
-        })));
+        let buffs_grayscale = derived_mat(&derived_bytes, move || {
+            crop_to_buffs_region(&**buffs_grayscale, Point::default()).clone_pointee()
+        });
+        let half_grayscale = grayscale.clone();
+        let half_grayscale = derived_mat(&derived_bytes, move || {
+            to_half_resolution(&**half_grayscale)
+        });
         Self {
             mat,
+            cache: Arc::new(DetectionCache::default()),
             grayscale,
             buffs_grayscale,
+            half_grayscale,
+            derived_bytes,
+            profile: DetectionProfile::default(),
+            half_resolution: false,
+            other_player_colors: OtherPlayerColors::default(),
+            buffs_region_offset: Point::default(),
         }
     }
+
+    /// Shifts the buffs region by `offset` for users with a moved buff bar
+    pub fn with_buffs_region_offset(self, offset: Point) -> CachedDetector {
+        let buffs_grayscale = self.grayscale.clone();
+        let buffs_grayscale = derived_mat(&self.derived_bytes, move || {
+            crop_to_buffs_region(&**buffs_grayscale, offset).clone_pointee()
+        });
+        Self {
+            buffs_grayscale,
+            buffs_region_offset: offset,
+            ..self
+        }
+    }
+
+    /// Falls back to `colors` when detecting other players with templates fails
+    pub fn with_other_player_colors(
+        self,
+        other_player_colors: OtherPlayerColors,
+    ) -> CachedDetector {
+        Self {
+            other_player_colors,
+            ..self
+        }
+    }
+
+    /// Detects ESC settings, tomb and Erda Shower on a half resolution grayscale frame
+    pub fn with_half_resolution(self, half_resolution: bool) -> CachedDetector {
+        Self {
+            half_resolution,
+            ..self
+        }
+    }
+
+    #[inline]
+    fn reduced_grayscale(&self) -> &Mat {
+        if self.half_resolution {
+            &self.half_grayscale
+        } else {
+            &self.grayscale
+        }
+    }
+
+    /// Applies the thresholds overrides of `profile` to the subsequent detections
+    pub fn with_profile(self, profile: DetectionProfile) -> CachedDetector {
+        Self { profile, ..self }
+    }
 }
 
 impl Detector for CachedDetector {
@@ -242,52 +497,113 @@ impl Detector for CachedDetector {
         &self.mat
     }
 
-    fn detect_mobs(&self, minimap: Rect, bound: Rect, player: Point) -> Result<Vec<Point>> {
-        detect_mobs(&*self.mat, minimap, bound, player)
+    fn memory_usage(&self) -> usize {
+        mat_bytes(&*self.mat) + self.derived_bytes.load(Ordering::Relaxed)
+    }
+
+    fn detect_mobs(
+        &self,
+        minimap: Rect,
+        bound: Rect,
+        polygon: &[Point],
+        player: Point,
+    ) -> Result<Vec<Point>> {
+        detect_mobs(&*self.mat, minimap, bound, polygon, player)
+    }
+
+    fn detect_minimap_mobs(
+        &self,
+        minimap: Rect,
+        bound: Rect,
+        polygon: &[Point],
+    ) -> Result<Vec<Point>> {
+        let minimap_color = to_bgr(&self.mat.roi(minimap)?);
+        detect_minimap_mobs(&minimap_color, bound, polygon)
     }
 
     fn detect_esc_settings(&self) -> bool {
-        detect_esc_settings(&**self.grayscale)
+        self.cache
+            .get_or_detect_bool(DetectionKey::EscSettings, || {
+                detect_esc_settings(self.reduced_grayscale(), self.half_resolution)
+            })
     }
 
     fn detect_elite_boss_bar(&self) -> bool {
-        detect_elite_boss_bar(&**self.grayscale)
+        self.cache
+            .get_or_detect_bool(DetectionKey::EliteBossBar, || {
+                detect_elite_boss_bar(&**self.grayscale)
+            })
     }
 
     fn detect_minimap(&self, border_threshold: u8) -> Result<Rect> {
-        detect_minimap(&*self.mat, border_threshold)
+        self.cache
+            .get_or_detect_rect(DetectionKey::Minimap(border_threshold), || {
+                detect_minimap(&*self.mat, border_threshold)
+            })
+    }
+
+    fn detect_minimap_fingerprint(&self, minimap: Rect) -> Result<u64> {
+        detect_minimap_fingerprint(&self.grayscale.roi(minimap)?)
     }
 
     fn detect_minimap_portals(&self, minimap: Rect) -> Result<Vec<Rect>> {
         let minimap_color = to_bgr(&self.mat.roi(minimap)?);
-        detect_minimap_portals(minimap_color)
+        let threshold = profile_threshold(self.profile.portal_threshold, PORTAL_THRESHOLD);
+        detect_minimap_portals(minimap_color, threshold)
     }
 
     fn detect_minimap_rune(&self, minimap: Rect) -> Result<Rect> {
-        let minimap_color = to_bgr(&self.mat.roi(minimap)?);
-        detect_minimap_rune(&minimap_color)
+        let key = DetectionKey::MinimapRune(DetectionKey::rect(minimap));
+        self.cache.get_or_detect_rect(key, || {
+            let minimap_color = to_bgr(&self.mat.roi(minimap)?);
+            let threshold = profile_threshold(self.profile.rune_threshold, RUNE_THRESHOLD);
+            detect_minimap_rune(&minimap_color, threshold)
+        })
     }
 
     fn detect_player(&self, minimap: Rect) -> Result<Rect> {
-        let minimap_color = to_bgr(&self.mat.roi(minimap)?);
-        detect_player(&minimap_color)
+        let key = DetectionKey::Player(DetectionKey::rect(minimap));
+        self.cache.get_or_detect_rect(key, || {
+            let minimap_color = to_bgr(&self.mat.roi(minimap)?);
+            let threshold = profile_threshold(self.profile.player_threshold, PLAYER_THRESHOLD);
+            detect_player(&minimap_color, threshold)
+        })
     }
 
-    fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> bool {
-        let minimap_color = to_bgr(&self.mat.roi(minimap).unwrap());
-        detect_player_kind(&minimap_color, kind)
+    fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> Result<Vec<Rect>> {
+        let minimap_color = to_bgr(&self.mat.roi(minimap)?);
+        detect_player_kind(&minimap_color, kind).or_else(|err| {
+            if !self.other_player_colors.enabled {
+                return Err(err);
+            }
+            let range = match kind {
+                OtherPlayerKind::Guildie => self.other_player_colors.guildie,
+                OtherPlayerKind::Stranger => self.other_player_colors.stranger,
+                OtherPlayerKind::Friend => self.other_player_colors.friend,
+            };
+            detect_player_kind_by_color(&minimap_color, range)
+        })
     }
 
     fn detect_player_is_dead(&self) -> bool {
-        detect_player_is_dead(&**self.grayscale)
+        self.cache
+            .get_or_detect_bool(DetectionKey::PlayerIsDead, || {
+                detect_player_is_dead(self.reduced_grayscale(), self.half_resolution)
+            })
     }
 
     fn detect_player_in_cash_shop(&self) -> bool {
-        detect_player_in_cash_shop(&**self.grayscale)
+        self.cache
+            .get_or_detect_bool(DetectionKey::PlayerInCashShop, || {
+                detect_player_in_cash_shop(&**self.grayscale)
+            })
     }
 
     fn detect_player_health_bar(&self) -> Result<Rect> {
-        detect_player_health_bar(&**self.grayscale)
+        self.cache
+            .get_or_detect_rect(DetectionKey::PlayerHealthBar, || {
+                detect_player_health_bar(&**self.grayscale)
+            })
     }
 
     fn detect_player_current_max_health_bars(&self, health_bar: Rect) -> Result<(Rect, Rect)> {
@@ -298,23 +614,28 @@ impl Detector for CachedDetector {
         detect_player_health(&*self.mat, current_bar, max_bar)
     }
 
+    fn detect_player_mana_bar(&self, health_bar: Rect) -> Result<Rect> {
+        detect_player_mana_bar(&**self.grayscale, health_bar)
+    }
+
     fn detect_player_buff(&self, kind: BuffKind) -> bool {
-        let mat = match kind {
-            BuffKind::Rune
-            | BuffKind::SayramElixir
-            | BuffKind::AureliaElixir
-            | BuffKind::ExpCouponX3
-            | BuffKind::BonusExpCoupon => &**self.buffs_grayscale,
-            BuffKind::LegionWealth
-            | BuffKind::LegionLuck
-            | BuffKind::WealthAcquisitionPotion
-            | BuffKind::ExpAccumulationPotion
-            | BuffKind::ExtremeRedPotion
-            | BuffKind::ExtremeBluePotion
-            | BuffKind::ExtremeGreenPotion
-            | BuffKind::ExtremeGoldPotion => &to_bgr(&crop_to_buffs_region(&*self.mat)),
-        };
-        detect_player_buff(mat, kind)
+        self.cache
+            .get_or_detect_bool(DetectionKey::PlayerBuff(kind as usize), || {
+                let mat = if is_grayscale_buff(kind) {
+                    &**self.buffs_grayscale
+                } else {
+                    &to_bgr(&crop_to_buffs_region(&*self.mat, self.buffs_region_offset))
+                };
+                detect_player_buff(mat, kind).is_ok()
+            })
+    }
+
+    fn detect_buffs_region_offset(&self) -> Result<Point> {
+        let size = self.mat.size()?;
+        let search_bbox = Rect::new(0, 0, size.width, size.height / 2);
+        let grayscale = self.grayscale.roi(search_bbox)?.clone_pointee();
+        let bgr = to_bgr(&self.mat.roi(search_bbox)?);
+        detect_buffs_region_offset(&grayscale, &bgr, size)
     }
 
     fn detect_rune_arrows(&self, calibrating: ArrowsCalibrating) -> Result<ArrowsState> {
@@ -322,31 +643,129 @@ impl Detector for CachedDetector {
     }
 
     fn detect_erda_shower(&self) -> Result<Rect> {
-        detect_erda_shower(&**self.grayscale)
+        self.cache.get_or_detect_rect(DetectionKey::ErdaShower, || {
+            detect_erda_shower(self.reduced_grayscale(), self.half_resolution)
+        })
     }
 }
 
-fn crop_to_buffs_region(mat: &impl MatTraitConst) -> BoxedRef<Mat> {
+/// Converts the percentage threshold override in [`DetectionProfile`] to template matching
+/// score or uses `default` if there is no override
+#[inline]
+fn profile_threshold(threshold: Option<f32>, default: f64) -> f64 {
+    threshold.map_or(default, |percent| percent as f64 / 100.0)
+}
+
+fn crop_to_buffs_region(mat: &impl MatTraitConst, offset: Point) -> BoxedRef<Mat> {
     let size = mat.size().unwrap();
-    // crop to top right of the image for buffs region
-    let crop_x = size.width / 3;
-    let crop_y = size.height / 4;
-    let crop_bbox = Rect::new(size.width - crop_x, 0, crop_x, crop_y);
-    mat.roi(crop_bbox).unwrap()
+    mat.roi(buffs_region_bbox(size, offset)).unwrap()
+}
+
+/// Detection parameters for a game resolution
+///
+/// Templates are captured in `Ideal Ratio` where the game UI keeps the same pixel size across
+/// resolutions so only the parameters relative to the frame size differ between resolutions.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ResolutionPreset {
+    pub width: i32,
+    pub height: i32,
+    /// The size of the top right region to crop for detecting buffs
+    buffs_region_size: (i32, i32),
+    /// The scales for mapping the on-screen delta of a detected mob to the minimap delta
+    mob_scale: (f32, f32),
+}
+
+/// The scales for mapping detected mobs approximated in 1280x720 resolution
+///
+/// They are approximated by going from one point to another point from the middle of the screen
+/// with both points visible on screen before traveling. Take the distance traveled on the
+/// minimap and divide it by half of the resolution (e.g. tralveled minimap x / 640). Whether it
+/// is correct or not, time will tell.
+const MOB_SCALE: (f32, f32) = (0.059_375, 0.036_111);
+
+/// The `Ideal Ratio` resolutions that detection resources are made for
+const RESOLUTION_PRESETS: [ResolutionPreset; 4] = [
+    ResolutionPreset {
+        width: 1280,
+        height: 720,
+        buffs_region_size: (426, 180),
+        mob_scale: MOB_SCALE,
+    },
+    ResolutionPreset {
+        width: 1366,
+        height: 768,
+        buffs_region_size: (455, 192),
+        mob_scale: MOB_SCALE,
+    },
+    ResolutionPreset {
+        width: 1600,
+        height: 900,
+        buffs_region_size: (533, 225),
+        mob_scale: MOB_SCALE,
+    },
+    ResolutionPreset {
+        width: 1920,
+        height: 1080,
+        buffs_region_size: (640, 270),
+        mob_scale: MOB_SCALE,
+    },
+];
+
+impl ResolutionPreset {
+    /// Selects the preset matching a frame with `size`
+    ///
+    /// Returns [`None`] if the resolution is not supported.
+    pub fn from_supported(size: Size) -> Option<ResolutionPreset> {
+        RESOLUTION_PRESETS
+            .into_iter()
+            .find(|preset| preset.width == size.width && preset.height == size.height)
+    }
+
+    /// Same as [`Self::from_supported`] but approximates the parameters from `size` for an
+    /// unsupported resolution
+    fn from_size(size: Size) -> ResolutionPreset {
+        Self::from_supported(size).unwrap_or(ResolutionPreset {
+            width: size.width,
+            height: size.height,
+            buffs_region_size: (size.width / 3, size.height / 4),
+            mob_scale: MOB_SCALE,
+        })
+    }
+}
+
+/// Computes the buffs region of a frame with `size` shifted by `offset`
+///
+/// The region is clamped to stay inside the frame.
+fn buffs_region_bbox(size: Size, offset: Point) -> Rect {
+    // top right of the image for buffs region
+    let (width, height) = ResolutionPreset::from_size(size).buffs_region_size;
+    let width = width.min(size.width);
+    let height = height.min(size.height);
+    let x = (size.width - width + offset.x).clamp(0, size.width - width);
+    let y = offset.y.clamp(0, size.height - height);
+    Rect::new(x, y, width, height)
+}
+
+/// Whether the buff `kind` is detected on a grayscale instead of BGR image
+#[inline]
+fn is_grayscale_buff(kind: BuffKind) -> bool {
+    matches!(
+        kind,
+        BuffKind::Rune
+            | BuffKind::SayramElixir
+            | BuffKind::AureliaElixir
+            | BuffKind::ExpCouponX3
+            | BuffKind::BonusExpCoupon
+    )
 }
 
 fn detect_mobs(
     mat: &impl MatTraitConst,
     minimap: Rect,
     bound: Rect,
+    polygon: &[Point],
     player: Point,
 ) -> Result<Vec<Point>> {
-    static MOB_MODEL: LazyLock<Session> = LazyLock::new(|| {
-        Session::builder()
-            .and_then(|b| b.commit_from_memory(include_bytes!(env!("MOB_MODEL"))))
-            .expect("unable to build mob detection session")
-    });
-
     /// Approximates the mob coordinate on screen to mob coordinate on minimap.
     ///
     /// This function tries to approximate the delta (dx, dy) that the player needs to move
@@ -360,16 +779,12 @@ fn detect_mobs(
         mob_bbox: Rect,
         minimap_bbox: Rect,
         mobbing_bound: Rect,
+        mobbing_polygon: &[Point],
         player: Point,
         mat_size: Size,
     ) -> Option<Point> {
-        // These numbers are for scaling dx/dy on the screen to dx/dy on the minimap.
-        // They are approximated in 1280x720 resolution by going from one point to another point
-        // from the middle of the screen with both points visible on screen before traveling. Take
-        // the distance traveled on the minimap and divide it by half of the resolution
-        // (e.g. tralveled minimap x / 640). Whether it is correct or not, time will tell.
-        const X_SCALE: f32 = 0.059_375;
-        const Y_SCALE: f32 = 0.036_111;
+        // These numbers are for scaling dx/dy on the screen to dx/dy on the minimap
+        let (x_scale, y_scale) = ResolutionPreset::from_size(mat_size).mob_scale;
 
         // The main idea is to calculate the offset of the detected mob from the middle of screen
         // and use that distance as dx/dy to move the player. This assumes the player will
@@ -381,7 +796,7 @@ fn detect_mobs(
         let x_screen_mid = mat_size.width / 2;
         let x_mob_mid = mob_bbox.x + mob_bbox.width / 2;
         let x_screen_delta = x_screen_mid - x_mob_mid;
-        let x_minimap_delta = (x_screen_delta as f32 * X_SCALE) as i32;
+        let x_minimap_delta = (x_screen_delta as f32 * x_scale) as i32;
 
         // For dy, if the whole mob bounding box is above the screen mid point, then the
         // box top edge is used to increase the dy distance as to help the player move up. The same
@@ -396,7 +811,7 @@ fn detect_mobs(
             mob_bbox.y + mob_bbox.height / 2
         };
         let y_screen_delta = y_screen_mid - y_mob;
-        let y_minimap_delta = (y_screen_delta as f32 * Y_SCALE) as i32;
+        let y_minimap_delta = (y_screen_delta as f32 * y_scale) as i32;
 
         let point_x = if x_minimap_delta > 0 {
             (player.x - x_minimap_delta).max(0)
@@ -410,6 +825,7 @@ fn detect_mobs(
             || point.x > mobbing_bound.x + mobbing_bound.width
             || point.y < mobbing_bound.y
             || point.y > mobbing_bound.y + mobbing_bound.height
+            || !is_inside_polygon(point, mobbing_polygon)
         {
             None
         } else {
@@ -417,21 +833,96 @@ fn detect_mobs(
         }
     }
 
+    let Some(model) = MOB_MODEL.as_ref() else {
+        bail!("mob detection model is unavailable");
+    };
     let size = mat.size().unwrap();
     let (mat_in, w_ratio, h_ratio, left, top) = preprocess_for_yolo(mat);
-    let result = MOB_MODEL.run([norm_rgb_to_input_value(&mat_in)]).unwrap();
+    let result = model.run([norm_rgb_to_input_value(&mat_in)])?;
     let result = from_output_value(&result);
     // SAFETY: 0..result.rows() is within Mat bounds
     let points = (0..result.rows())
         .map(|i| unsafe { result.at_row_unchecked::<f32>(i).unwrap() })
         .filter(|pred| pred[4] >= 0.5)
         .map(|pred| remap_from_yolo(pred, size, w_ratio, h_ratio, left, top))
-        .filter_map(|bbox| to_minimap_coordinate(bbox, minimap, bound, player, size))
+        .filter_map(|bbox| to_minimap_coordinate(bbox, minimap, bound, polygon, player, size))
+        .collect::<Vec<_>>();
+    Ok(points)
+}
+
+fn detect_minimap_mobs(
+    minimap: &impl ToInputArray,
+    bound: Rect,
+    polygon: &[Point],
+) -> Result<Vec<Point>> {
+    /// The minimum and maximum pixel area of a red blob to be considered a mob dot
+    const MOB_DOT_MIN_AREA: i32 = 4;
+    const MOB_DOT_MAX_AREA: i32 = 64;
+
+    // Mob dots are pure red in BGR with a dark outline
+    let mut mask = Mat::default();
+    in_range(
+        minimap,
+        &Scalar::new(0.0, 0.0, 200.0, 0.0),
+        &Scalar::new(80.0, 80.0, 255.0, 0.0),
+        &mut mask,
+    )?;
+
+    let mut centroids = Mat::default();
+    let mut stats = Mat::default();
+    let labels_count = connected_components_with_stats(
+        &mask,
+        &mut Mat::default(),
+        &mut stats,
+        &mut centroids,
+        8,
+        CV_32S,
+    )?;
+    let points = (1..labels_count)
+        .filter(|i| {
+            let area = *stats.at_2d::<i32>(*i, CC_STAT_AREA).unwrap();
+            (MOB_DOT_MIN_AREA..=MOB_DOT_MAX_AREA).contains(&area)
+        })
+        .map(|i| {
+            let centroid = centroids.row(i).unwrap();
+            let centroid = centroid.data_typed::<f64>().unwrap();
+            Point::new(centroid[0] as i32, centroid[1] as i32)
+        })
+        .filter(|point| {
+            point.x >= bound.x
+                && point.x <= bound.x + bound.width
+                && point.y >= bound.y
+                && point.y <= bound.y + bound.height
+                && is_inside_polygon(*point, polygon)
+        })
         .collect::<Vec<_>>();
     Ok(points)
 }
 
-fn detect_esc_settings(mat: &impl ToInputArray) -> bool {
+/// Checks whether `point` is inside `polygon` using ray casting
+///
+/// A `polygon` with less than 3 vertices is treated as unbounded.
+#[inline]
+fn is_inside_polygon(point: Point, polygon: &[Point]) -> bool {
+    if polygon.len() < 3 {
+        return true;
+    }
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a.y > point.y) != (b.y > point.y)
+            && (point.x as f32)
+                < (b.x - a.x) as f32 * (point.y - a.y) as f32 / (b.y - a.y) as f32 + a.x as f32
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+fn detect_esc_settings(mat: &impl ToInputArray, half_resolution: bool) -> bool {
     /// TODO: Support default ratio
     static ESC_SETTINGS: LazyLock<[Mat; 7]> = LazyLock::new(|| {
         [
@@ -463,7 +954,15 @@ fn detect_esc_settings(mat: &impl ToInputArray) -> bool {
         ]
     });
 
-    for template in &*ESC_SETTINGS {
+    static ESC_SETTINGS_HALF: LazyLock<[Mat; 7]> =
+        LazyLock::new(|| ESC_SETTINGS.each_ref().map(to_half_resolution));
+
+    let templates = if half_resolution {
+        &*ESC_SETTINGS_HALF
+    } else {
+        &*ESC_SETTINGS
+    };
+    for template in templates {
         if detect_template(mat, template, Point::default(), 0.85).is_ok() {
             return true;
         }
@@ -500,12 +999,6 @@ fn detect_elite_boss_bar(mat: &impl MatTraitConst) -> bool {
 }
 
 fn detect_minimap(mat: &impl MatTraitConst, border_threshold: u8) -> Result<Rect> {
-    static MINIMAP_MODEL: LazyLock<Session> = LazyLock::new(|| {
-        Session::builder()
-            .and_then(|b| b.commit_from_memory(include_bytes!(env!("MINIMAP_MODEL"))))
-            .expect("unable to build minimap detection session")
-    });
-
     enum Border {
         Top,
         Bottom,
@@ -567,25 +1060,32 @@ fn detect_minimap(mat: &impl MatTraitConst, border_threshold: u8) -> Result<Rect
     }
 
     let size = mat.size().unwrap();
-    let (mat_in, w_ratio, h_ratio, left, top) = preprocess_for_yolo(mat);
-    let result = MINIMAP_MODEL
-        .run([norm_rgb_to_input_value(&mat_in)])
-        .unwrap();
-    let mat_out = from_output_value(&result);
-    let pred = (0..mat_out.rows())
-        // SAFETY: 0..result.rows() is within Mat bounds
-        .map(|i| unsafe { mat_out.at_row_unchecked::<f32>(i).unwrap() })
-        .max_by(|&a, &b| {
-            // a and b have shapes [bbox(4) + class(1)]
-            a[4].total_cmp(&b[4])
-        })
-        .filter(|pred| pred[4] >= 0.7)
-        .ok_or(anyhow!("minimap detection failed"))?;
+    let yolo_bbox = match MINIMAP_MODEL.as_ref() {
+        Some(model) => {
+            let (mat_in, w_ratio, h_ratio, left, top) = preprocess_for_yolo(mat);
+            let result = model.run([norm_rgb_to_input_value(&mat_in)])?;
+            let mat_out = from_output_value(&result);
+            let pred = (0..mat_out.rows())
+                // SAFETY: 0..result.rows() is within Mat bounds
+                .map(|i| unsafe { mat_out.at_row_unchecked::<f32>(i).unwrap() })
+                .max_by(|&a, &b| {
+                    // a and b have shapes [bbox(4) + class(1)]
+                    a[4].total_cmp(&b[4])
+                })
+                .filter(|pred| pred[4] >= 0.7)
+                .ok_or(anyhow!("minimap detection failed"))?;
 
-    debug!(target: "minimap", "yolo detection: {pred:?}");
+            debug!(target: "minimap", "yolo detection: {pred:?}");
+            Some(remap_from_yolo(pred, size, w_ratio, h_ratio, left, top))
+        }
+        // Without the model, falls back to searching the top-left quarter of the screen where
+        // the minimap usually is
+        None => None,
+    };
+    let minimap_bbox =
+        yolo_bbox.unwrap_or_else(|| Rect::new(0, 0, size.width / 2, size.height / 2));
 
     // Extract the thresholded minimap
-    let minimap_bbox = remap_from_yolo(pred, size, w_ratio, h_ratio, left, top);
     let mut minimap_thresh = to_grayscale(&mat.roi(minimap_bbox).unwrap(), true);
     unsafe {
         // SAFETY: threshold can be called in place.
@@ -609,11 +1109,13 @@ fn detect_minimap(mat: &impl MatTraitConst, border_threshold: u8) -> Result<Rect
         .max_by_key(|bbox| bbox.area())
         .ok_or(anyhow!("minimap contours is empty"))?
         + minimap_bbox.tl();
-    let intersection = (contour_bbox & minimap_bbox).area() as f32;
-    let union = (contour_bbox | minimap_bbox).area() as f32;
-    let iou = intersection / union;
-    if iou < 0.8 {
-        bail!("wrong minimap likely caused by detection during map switching")
+    if yolo_bbox.is_some() {
+        let intersection = (contour_bbox & minimap_bbox).area() as f32;
+        let union = (contour_bbox | minimap_bbox).area() as f32;
+        let iou = intersection / union;
+        if iou < 0.8 {
+            bail!("wrong minimap likely caused by detection during map switching")
+        }
     }
 
     // Scan the 4 borders and crop
@@ -637,7 +1139,34 @@ fn detect_minimap(mat: &impl MatTraitConst, border_threshold: u8) -> Result<Rect
     Ok(bbox + contour_bbox.tl())
 }
 
-fn detect_minimap_portals<T: MatTraitConst + ToInputArray>(minimap: T) -> Result<Vec<Rect>> {
+/// Computes the average hash of the grayscale `minimap`
+///
+/// Each bit is set if the corresponding pixel of the downscaled minimap is brighter than the
+/// mean.
+fn detect_minimap_fingerprint(minimap: &impl ToInputArray) -> Result<u64> {
+    let mut downscaled = Mat::default();
+    resize(
+        minimap,
+        &mut downscaled,
+        Size::new(MINIMAP_FINGERPRINT_SIZE, MINIMAP_FINGERPRINT_SIZE),
+        0.0,
+        0.0,
+        INTER_AREA,
+    )?;
+    let pixels = downscaled.data_bytes()?;
+    let mean = pixels.iter().map(|pixel| *pixel as u32).sum::<u32>() / pixels.len() as u32;
+    let fingerprint = pixels
+        .iter()
+        .enumerate()
+        .filter(|(_, pixel)| **pixel as u32 > mean)
+        .fold(0, |fingerprint, (i, _)| fingerprint | (1 << i));
+    Ok(fingerprint)
+}
+
+fn detect_minimap_portals<T: MatTraitConst + ToInputArray>(
+    minimap: T,
+    score_threshold: f64,
+) -> Result<Vec<Rect>> {
     /// TODO: Support default ratio
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("PORTAL_TEMPLATE")), IMREAD_COLOR).unwrap()
@@ -657,7 +1186,7 @@ fn detect_minimap_portals<T: MatTraitConst + ToInputArray>(minimap: T) -> Result
     // SAFETY: threshold can be called inplace
     unsafe {
         result.modify_inplace(|mat, mat_mut| {
-            threshold(mat, mat_mut, 0.8, 1.0, THRESH_BINARY).unwrap();
+            threshold(mat, mat_mut, score_threshold, 1.0, THRESH_BINARY).unwrap();
         });
     }
     find_non_zero(&result, &mut points).unwrap();
@@ -677,7 +1206,7 @@ fn detect_minimap_portals<T: MatTraitConst + ToInputArray>(minimap: T) -> Result
     Ok(portals)
 }
 
-fn detect_minimap_rune(minimap: &impl ToInputArray) -> Result<Rect> {
+fn detect_minimap_rune(minimap: &impl ToInputArray, threshold: f64) -> Result<Rect> {
     /// TODO: Support default ratio
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("RUNE_TEMPLATE")), IMREAD_COLOR).unwrap()
@@ -688,11 +1217,17 @@ fn detect_minimap_rune(minimap: &impl ToInputArray) -> Result<Rect> {
 
     // Expands by 2 pixels to preserve previous position calculation. Previous template is 11x11
     // while the current template is 9x9
-    detect_template_single(minimap, &*TEMPLATE, &*TEMPLATE_MASK, Point::default(), 0.75)
-        .map(|(rect, _)| Rect::new(rect.x - 1, rect.y - 1, rect.width + 2, rect.height + 2))
+    detect_template_single(
+        minimap,
+        &*TEMPLATE,
+        &*TEMPLATE_MASK,
+        Point::default(),
+        threshold,
+    )
+    .map(|(rect, _)| Rect::new(rect.x - 1, rect.y - 1, rect.width + 2, rect.height + 2))
 }
 
-fn detect_player(mat: &impl ToInputArray) -> Result<Rect> {
+fn detect_player(mat: &impl ToInputArray, threshold: f64) -> Result<Rect> {
     /// TODO: Support default ratio
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("PLAYER_TEMPLATE")), IMREAD_COLOR).unwrap()
@@ -700,11 +1235,11 @@ fn detect_player(mat: &impl ToInputArray) -> Result<Rect> {
 
     // Expands by 2 pixels to preserve previous position calculation. Previous template is 10x10
     // while the current template is 8x8.
-    detect_template_single(mat, &*TEMPLATE, no_array(), Point::default(), 0.75)
+    detect_template_single(mat, &*TEMPLATE, no_array(), Point::default(), threshold)
         .map(|(rect, _)| Rect::new(rect.x - 1, rect.y - 1, rect.width + 2, rect.height + 2))
 }
 
-fn detect_player_kind(mat: &impl ToInputArray, kind: OtherPlayerKind) -> bool {
+fn detect_player_kind(mat: &impl ToInputArray, kind: OtherPlayerKind) -> Result<Vec<Rect>> {
     /// TODO: Support default ratio
     static STRANGER_TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
@@ -724,26 +1259,88 @@ fn detect_player_kind(mat: &impl ToInputArray, kind: OtherPlayerKind) -> bool {
         imgcodecs::imdecode(include_bytes!(env!("PLAYER_FRIEND_TEMPLATE")), IMREAD_COLOR).unwrap()
     });
 
-    match kind {
-        OtherPlayerKind::Stranger => {
-            detect_template(mat, &*STRANGER_TEMPLATE, Point::default(), 0.85).is_ok()
-        }
-        OtherPlayerKind::Guildie => {
-            detect_template(mat, &*GUILDIE_TEMPLATE, Point::default(), 0.85).is_ok()
-        }
-        OtherPlayerKind::Friend => {
-            detect_template(mat, &*FRIEND_TEMPLATE, Point::default(), 0.85).is_ok()
-        }
+    let template = match kind {
+        OtherPlayerKind::Stranger => &*STRANGER_TEMPLATE,
+        OtherPlayerKind::Guildie => &*GUILDIE_TEMPLATE,
+        OtherPlayerKind::Friend => &*FRIEND_TEMPLATE,
+    };
+    let players = detect_template_multiple(
+        mat,
+        template,
+        no_array(),
+        Point::default(),
+        MAX_OTHER_PLAYERS,
+        0.85,
+    )
+    .into_iter()
+    .filter_map(|result| result.ok().map(|(bbox, _)| bbox))
+    .collect::<Vec<_>>();
+    if players.is_empty() {
+        bail!("player not found");
     }
+    Ok(players)
 }
 
-fn detect_player_is_dead(mat: &impl ToInputArray) -> bool {
+/// Detects the other player dots within the HSV color `range` from the BGR `minimap`
+fn detect_player_kind_by_color(minimap: &impl ToInputArray, range: HsvRange) -> Result<Vec<Rect>> {
+    /// The minimum and maximum pixel area of a blob to be considered a player dot
+    const PLAYER_DOT_MIN_AREA: i32 = 4;
+    const PLAYER_DOT_MAX_AREA: i32 = 64;
+
+    let mut hsv = Mat::default();
+    cvt_color_def(minimap, &mut hsv, COLOR_BGR2HSV_FULL)?;
+    let [lower_h, lower_s, lower_v] = range.lower.map(f64::from);
+    let [upper_h, upper_s, upper_v] = range.upper.map(f64::from);
+    let mut mask = Mat::default();
+    in_range(
+        &hsv,
+        &Scalar::new(lower_h, lower_s, lower_v, 0.0),
+        &Scalar::new(upper_h, upper_s, upper_v, 0.0),
+        &mut mask,
+    )?;
+
+    let mut stats = Mat::default();
+    let labels_count = connected_components_with_stats(
+        &mask,
+        &mut Mat::default(),
+        &mut stats,
+        &mut Mat::default(),
+        8,
+        CV_32S,
+    )?;
+    let players = (1..labels_count)
+        .filter(|i| {
+            let area = *stats.at_2d::<i32>(*i, CC_STAT_AREA).unwrap();
+            (PLAYER_DOT_MIN_AREA..=PLAYER_DOT_MAX_AREA).contains(&area)
+        })
+        .map(|i| {
+            Rect::new(
+                *stats.at_2d::<i32>(i, CC_STAT_LEFT).unwrap(),
+                *stats.at_2d::<i32>(i, CC_STAT_TOP).unwrap(),
+                *stats.at_2d::<i32>(i, CC_STAT_WIDTH).unwrap(),
+                *stats.at_2d::<i32>(i, CC_STAT_HEIGHT).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    if players.is_empty() {
+        bail!("player not found by color");
+    }
+    Ok(players)
+}
+
+fn detect_player_is_dead(mat: &impl ToInputArray, half_resolution: bool) -> bool {
     /// TODO: Support default ratio
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("TOMB_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
     });
+    static TEMPLATE_HALF: LazyLock<Mat> = LazyLock::new(|| to_half_resolution(&*TEMPLATE));
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.8).is_ok()
+    let template = if half_resolution {
+        &*TEMPLATE_HALF
+    } else {
+        &*TEMPLATE
+    };
+    detect_template(mat, template, Point::default(), 0.8).is_ok()
 }
 
 fn detect_player_in_cash_shop(mat: &impl ToInputArray) -> bool {
@@ -884,7 +1481,62 @@ fn detect_player_health(
     Ok((current_health.min(max_health), max_health))
 }
 
-fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind) -> bool {
+fn detect_player_mana_bar(grayscale: &impl MatTraitConst, hp_bar: Rect) -> Result<Rect> {
+    // The MP bar has the same layout as the HP bar and sits right below it
+    let mp_bar = Rect::new(
+        hp_bar.x,
+        hp_bar.y + hp_bar.height + MANA_BAR_GAP,
+        hp_bar.width,
+        hp_bar.height,
+    );
+    if mp_bar.y + mp_bar.height > grayscale.rows() {
+        bail!("mana bar is outside of the frame");
+    }
+    Ok(mp_bar)
+}
+
+fn detect_buffs_region_offset(grayscale: &Mat, bgr: &Mat, frame_size: Size) -> Result<Point> {
+    let (left, top, right, bottom) = BuffKind::iter()
+        .filter_map(|kind| {
+            let mat = if is_grayscale_buff(kind) {
+                grayscale
+            } else {
+                bgr
+            };
+            detect_player_buff(mat, kind).ok()
+        })
+        .fold(None, |acc: Option<(i32, i32, i32, i32)>, bbox| {
+            let br = bbox.br();
+            Some(match acc {
+                Some((left, top, right, bottom)) => (
+                    left.min(bbox.x),
+                    top.min(bbox.y),
+                    right.max(br.x),
+                    bottom.max(br.y),
+                ),
+                None => (bbox.x, bbox.y, br.x, br.y),
+            })
+        })
+        .ok_or(anyhow!("no buff found for calibrating buffs region"))?;
+    let region = buffs_region_bbox(frame_size, Point::default());
+    let region_br = region.br();
+    let offset = |start: i32, end: i32, region_start: i32, region_end: i32| {
+        if start < region_start {
+            start - region_start
+        } else if end > region_end {
+            end - region_end
+        } else {
+            0
+        }
+    };
+
+    Ok(Point::new(
+        offset(left, right, region.x, region_br.x),
+        offset(top, bottom, region.y, region_br.y),
+    ))
+}
+
+fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind) -> Result<Rect> {
     /// TODO: Support default ratio
     static RUNE_BUFF: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("RUNE_BUFF_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
@@ -1042,11 +1694,11 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
             .filter_map(|result| result.ok())
             .collect::<Vec<_>>();
             if matches.is_empty() {
-                return false;
+                bail!("buff not found");
             }
             // Likely both potions are active
             if matches.len() == 2 {
-                return true;
+                return Ok(matches[0].0);
             }
 
             let template_other = if matches!(kind, BuffKind::WealthAcquisitionPotion) {
@@ -1063,9 +1715,14 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
                 threshold,
             );
 
-            match_other.is_err()
+            if match_other.is_err()
                 || match_other.as_ref().copied().unwrap().0 != match_current.0
                 || match_other.unwrap().1 < match_current.1
+            {
+                Ok(match_current.0)
+            } else {
+                bail!("buff mis-detected for the other potion")
+            }
         }
         BuffKind::LegionWealth | BuffKind::LegionLuck => detect_template_single(
             mat,
@@ -1074,18 +1731,12 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
             Point::default(),
             threshold,
         )
-        .is_ok(),
-        _ => detect_template(mat, template, Point::default(), threshold).is_ok(),
+        .map(|(bbox, _)| bbox),
+        _ => detect_template(mat, template, Point::default(), threshold),
     }
 }
 
 fn detect_rune_arrows_with_scores_regions(mat: &impl MatTraitConst) -> Vec<(Rect, KeyKind, f32)> {
-    static RUNE_MODEL: LazyLock<Session> = LazyLock::new(|| {
-        Session::builder()
-            .and_then(|b| b.commit_from_memory(include_bytes!(env!("RUNE_MODEL"))))
-            .expect("unable to build rune detection session")
-    });
-
     fn map_arrow(pred: &[f32]) -> KeyKind {
         match pred[5] as i32 {
             0 => KeyKind::Up,
@@ -1096,9 +1747,14 @@ fn detect_rune_arrows_with_scores_regions(mat: &impl MatTraitConst) -> Vec<(Rect
         }
     }
 
+    let Some(model) = RUNE_MODEL.as_ref() else {
+        return vec![];
+    };
     let size = mat.size().unwrap();
     let (mat_in, w_ratio, h_ratio, left, top) = preprocess_for_yolo(mat);
-    let result = RUNE_MODEL.run([norm_rgb_to_input_value(&mat_in)]).unwrap();
+    let Ok(result) = model.run([norm_rgb_to_input_value(&mat_in)]) else {
+        return vec![];
+    };
     let mat_out = from_output_value(&result);
     let mut vec = (0..mat_out.rows())
         // SAFETY: 0..outputs.rows() is within Mat bounds
@@ -1239,7 +1895,9 @@ fn detect_rune_arrows(
         mat = BoxedRef::from(mat_copy);
     }
 
-    let result = detect_rune_arrows_with_scores_regions(&mat)
+    let result = detect_rune_arrows_with_scores_regions(&mat);
+    let has_arrows = !result.is_empty();
+    let result = result
         .into_iter()
         .filter_map(|(rect, arrow, score)| (score >= SCORE_THRESHOLD).then_some((rect, arrow)))
         .collect::<Vec<_>>();
@@ -1264,6 +1922,8 @@ fn detect_rune_arrows(
 
     if result.len() == MAX_ARROWS {
         Ok(ArrowsState::Complete(extract_rune_arrows_to_slice(result)))
+    } else if has_arrows {
+        Ok(ArrowsState::LowConfidence)
     } else {
         Err(anyhow!("no rune arrow detected"))
     }
@@ -1525,7 +2185,7 @@ fn extract_rune_arrows_to_slice(vec: Vec<(Rect, KeyKind)>) -> [KeyKind; MAX_ARRO
     [first, second, third, fourth]
 }
 
-fn detect_erda_shower(mat: &impl MatTraitConst) -> Result<Rect> {
+fn detect_erda_shower(mat: &impl MatTraitConst, half_resolution: bool) -> Result<Rect> {
     /// TODO: Support default ratio
     static ERDA_SHOWER: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
@@ -1534,6 +2194,7 @@ fn detect_erda_shower(mat: &impl MatTraitConst) -> Result<Rect> {
         )
         .unwrap()
     });
+    static ERDA_SHOWER_HALF: LazyLock<Mat> = LazyLock::new(|| to_half_resolution(&*ERDA_SHOWER));
 
     let size = mat.size().unwrap();
     // crop to bottom right of the image for skill bar
@@ -1541,7 +2202,13 @@ fn detect_erda_shower(mat: &impl MatTraitConst) -> Result<Rect> {
     let crop_y = size.height / 5;
     let crop_bbox = Rect::new(size.width - crop_x, size.height - crop_y, crop_x, crop_y);
     let skill_bar = mat.roi(crop_bbox).unwrap();
-    detect_template(&skill_bar, &*ERDA_SHOWER, crop_bbox.tl(), 0.96)
+    if half_resolution {
+        // Scales back to the full resolution coordinate
+        detect_template(&skill_bar, &*ERDA_SHOWER_HALF, crop_bbox.tl(), 0.96)
+            .map(|bbox| Rect::new(bbox.x * 2, bbox.y * 2, bbox.width * 2, bbox.height * 2))
+    } else {
+        detect_template(&skill_bar, &*ERDA_SHOWER, crop_bbox.tl(), 0.96)
+    }
 }
 
 /// Detects a single match from `template` with the given BGR image `Mat`.
@@ -1910,6 +2577,17 @@ fn remap_from_yolo(
 ///
 /// Returns a triplet of `(Mat, width_ratio, height_ratio, left, top)`
 #[inline]
+/// Builds an ONNX session from the `model` bytes
+///
+/// Returns [`None`] if the model fails to load (e.g. corrupt download or unsupported CPU
+/// instructions) so that only the features depending on it are disabled.
+fn build_session(name: &str, model: &[u8]) -> Option<Session> {
+    Session::builder()
+        .and_then(|builder| builder.commit_from_memory(model))
+        .inspect_err(|err| error!(target: "detect", "unable to build {name} session: {err}"))
+        .ok()
+}
+
 fn preprocess_for_yolo(mat: &impl MatTraitConst) -> (Mat, f32, f32, i32, i32) {
     // https://github.com/ultralytics/ultralytics/blob/main/ultralytics/data/augment.py
     let mut mat = mat.try_clone().unwrap();
@@ -2031,6 +2709,14 @@ fn to_bgr(mat: &impl MatTraitConst) -> Mat {
     mat
 }
 
+/// Downscales `mat` to half of its resolution
+#[inline]
+fn to_half_resolution(mat: &impl ToInputArray) -> Mat {
+    let mut half = Mat::default();
+    resize(mat, &mut half, Size::default(), 0.5, 0.5, INTER_AREA).unwrap();
+    half
+}
+
 /// Converts an BGRA `Mat` image to grayscale.
 ///
 /// `add_contrast` can be set to `true` in order to increase contrast by a fixed amount
@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+
+use opencv::core::Mat;
+
+use crate::mat::mat_bytes;
+
+/// A bounded history of frames
+///
+/// Frames are kept in insertion order. When pushing a frame would exceed
+/// [`Self::budget_bytes`], the oldest frames are evicted until the new frame fits.
+#[derive(Debug, Default)]
+pub struct FrameHistory {
+    frames: VecDeque<Mat>,
+    bytes: usize,
+    budget_bytes: usize,
+}
+
+impl FrameHistory {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            bytes: 0,
+            budget_bytes,
+        }
+    }
+
+    /// The current memory usage of all frames in bytes
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+
+    /// Updates the budget and evicts the oldest frames if the new budget is exceeded
+    pub fn set_budget(&mut self, budget_bytes: usize) {
+        self.budget_bytes = budget_bytes;
+        self.evict(0);
+    }
+
+    /// Pushes `frame` to the history
+    ///
+    /// Frame larger than the budget is not pushed.
+    pub fn push(&mut self, frame: Mat) {
+        let frame_bytes = mat_bytes(&frame);
+        if frame_bytes > self.budget_bytes {
+            return;
+        }
+        self.evict(frame_bytes);
+        self.bytes += frame_bytes;
+        self.frames.push_back(frame);
+    }
+
+    /// Takes all the frames out of the history, oldest first
+    pub fn take(&mut self) -> Vec<Mat> {
+        self.bytes = 0;
+        self.frames.drain(..).collect()
+    }
+
+    fn evict(&mut self, additional_bytes: usize) {
+        while self.bytes + additional_bytes > self.budget_bytes {
+            let Some(frame) = self.frames.pop_front() else {
+                break;
+            };
+            self.bytes -= mat_bytes(&frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::{CV_8UC4, Scalar};
+
+    use super::*;
+
+    fn frame() -> Mat {
+        Mat::new_rows_cols_with_default(10, 10, CV_8UC4, Scalar::default()).unwrap()
+    }
+
+    #[test]
+    fn frame_history_evicts_oldest_over_budget() {
+        let mut history = FrameHistory::new(1000);
+
+        history.push(frame());
+        history.push(frame());
+        assert_eq!(history.memory_usage(), 800);
+
+        history.push(frame());
+        assert_eq!(history.memory_usage(), 800);
+        assert_eq!(history.take().len(), 2);
+        assert_eq!(history.memory_usage(), 0);
+    }
+
+    #[test]
+    fn frame_history_set_budget_evicts() {
+        let mut history = FrameHistory::new(1000);
+
+        history.push(frame());
+        history.push(frame());
+        history.set_budget(500);
+        assert_eq!(history.memory_usage(), 400);
+
+        history.set_budget(100);
+        history.push(frame());
+        assert_eq!(history.memory_usage(), 0);
+    }
+}
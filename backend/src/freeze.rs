@@ -0,0 +1,144 @@
+use std::hash::{DefaultHasher, Hasher};
+
+use opencv::core::MatTraitConst;
+
+use crate::context::MS_PER_TICK;
+
+/// The number of ticks of identical frames before the game is considered frozen
+const FREEZE_THRESHOLD_TICKS: u32 = 5000 / MS_PER_TICK as u32;
+
+/// The stride in pixels between the rows and columns sampled when hashing a frame
+const SAMPLE_STRIDE: usize = 4;
+
+/// Detects the game being frozen from prolonged identical frames
+///
+/// Even when the player is idling, the game still animates the character, mobs and
+/// the map so consecutive frames are rarely identical. A client freezing due to lag spike or
+/// disconnection, on the other hand, keeps presenting the exact same frame.
+#[derive(Debug, Default)]
+pub struct FrameFreeze {
+    last_hash: Option<u64>,
+    identical_ticks: u32,
+}
+
+impl FrameFreeze {
+    #[inline]
+    pub fn is_frozen(&self) -> bool {
+        self.identical_ticks >= FREEZE_THRESHOLD_TICKS
+    }
+
+    /// Updates with the current frame and returns whether the game is frozen
+    ///
+    /// A [`None`] `mat` means no new frame is captured and is counted as identical to the last
+    /// frame since capture such as Windows Graphics Capture only provides a frame when the game
+    /// presents one. Identical frames are not counted while `is_minimized` because a minimized
+    /// window stops presenting new frames without the game being frozen.
+    pub fn update(
+        &mut self,
+        mat: Option<&impl MatTraitConst>,
+        is_minimized: impl FnOnce() -> bool,
+    ) -> bool {
+        let hash = match mat {
+            Some(mat) => frame_hash(mat),
+            None => self.last_hash,
+        };
+        if hash.is_some() && hash == self.last_hash && !is_minimized() {
+            self.identical_ticks = self.identical_ticks.saturating_add(1);
+        } else {
+            self.identical_ticks = 0;
+        }
+        self.last_hash = hash;
+        self.is_frozen()
+    }
+}
+
+/// Hashes the pixels every [`SAMPLE_STRIDE`] rows and columns of `mat`
+///
+/// A frozen frame is identical in every pixel so a sparse grid is enough to tell frames apart.
+#[inline]
+fn frame_hash(mat: &impl MatTraitConst) -> Option<u64> {
+    let bytes = mat.data_bytes().ok()?;
+    let pixel_bytes = mat.elem_size().ok()?;
+    let row_bytes = mat.cols() as usize * pixel_bytes;
+    if row_bytes == 0 {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    for row in bytes.chunks_exact(row_bytes).step_by(SAMPLE_STRIDE) {
+        for pixel in row.chunks_exact(pixel_bytes).step_by(SAMPLE_STRIDE) {
+            hasher.write(pixel);
+        }
+    }
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::{CV_8UC4, Mat, Scalar};
+
+    use super::*;
+
+    fn frame(value: f64) -> Mat {
+        Mat::new_rows_cols_with_default(10, 10, CV_8UC4, Scalar::all(value)).unwrap()
+    }
+
+    #[test]
+    fn frame_freeze_after_threshold() {
+        let mut freeze = FrameFreeze::default();
+        let mat = frame(0.0);
+
+        freeze.update(Some(&mat), || false);
+        for _ in 0..FREEZE_THRESHOLD_TICKS - 1 {
+            assert!(!freeze.update(Some(&mat), || false));
+        }
+        assert!(freeze.update(Some(&mat), || false));
+    }
+
+    #[test]
+    fn frame_freeze_reset_on_change() {
+        let mut freeze = FrameFreeze::default();
+        let mat = frame(0.0);
+
+        for _ in 0..=FREEZE_THRESHOLD_TICKS {
+            freeze.update(Some(&mat), || false);
+        }
+        assert!(freeze.is_frozen());
+        assert!(!freeze.update(Some(&frame(1.0)), || false));
+    }
+
+    #[test]
+    fn frame_freeze_skipped_while_minimized() {
+        let mut freeze = FrameFreeze::default();
+        let mat = frame(0.0);
+
+        for _ in 0..=FREEZE_THRESHOLD_TICKS {
+            assert!(!freeze.update(Some(&mat), || true));
+        }
+    }
+
+    #[test]
+    fn frame_freeze_without_new_frames() {
+        let mut freeze = FrameFreeze::default();
+
+        for _ in 0..=FREEZE_THRESHOLD_TICKS {
+            assert!(!freeze.update(None::<&Mat>, || false));
+        }
+
+        freeze.update(Some(&frame(0.0)), || false);
+        for _ in 0..FREEZE_THRESHOLD_TICKS - 1 {
+            assert!(!freeze.update(None::<&Mat>, || false));
+        }
+        assert!(freeze.update(None::<&Mat>, || false));
+        assert!(!freeze.update(Some(&frame(1.0)), || false));
+    }
+
+    #[test]
+    fn frame_freeze_without_new_frames_skipped_while_minimized() {
+        let mut freeze = FrameFreeze::default();
+
+        freeze.update(Some(&frame(0.0)), || false);
+        for _ in 0..=FREEZE_THRESHOLD_TICKS {
+            assert!(!freeze.update(None::<&Mat>, || true));
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// The window in which ticks are counted for measuring the tick rate
+const TICK_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// The update loop health of each client updated on each tick
+///
+/// Stored outside of the update loop so that it can still be read when the loop stalls.
+static HEALTH: LazyLock<Mutex<HashMap<usize, Health>>> = LazyLock::new(Mutex::default);
+
+/// A snapshot of the update loop health
+///
+/// Useful for external supervisors or the UI to detect degraded states such as capturing
+/// frames but failing to detect the minimap for a long time.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Heartbeat {
+    /// The number of ticks per second measured over the last window
+    pub tick_rate: f32,
+    /// Milliseconds since the update loop last ticked or [`None`] if it never ticked
+    pub last_tick_age_millis: Option<u64>,
+    /// Milliseconds since a frame was last captured or [`None`] if never captured
+    pub last_frame_age_millis: Option<u64>,
+    /// Milliseconds since the minimap was last detected or [`None`] if never detected
+    pub last_minimap_detected_age_millis: Option<u64>,
+    /// Milliseconds since the player was last detected or [`None`] if never detected
+    pub last_player_detected_age_millis: Option<u64>,
+    /// The number of UI requests waiting to be handled by the update loop
+    pub pending_requests: usize,
+    /// The number of priority actions queued by the rotator
+    pub queued_priority_actions: usize,
+    /// The number of notifications scheduled but not yet sent
+    pub scheduled_notifications: usize,
+}
+
+/// The health observed in a single tick
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TickHealth {
+    pub frame_captured: bool,
+    pub minimap_detected: bool,
+    pub player_detected: bool,
+    pub queued_priority_actions: usize,
+    pub scheduled_notifications: usize,
+}
+
+#[derive(Debug)]
+struct Health {
+    last_tick: Option<Instant>,
+    window_started: Instant,
+    window_ticks: u32,
+    tick_rate: f32,
+    last_frame: Option<Instant>,
+    last_minimap_detected: Option<Instant>,
+    last_player_detected: Option<Instant>,
+    queued_priority_actions: usize,
+    scheduled_notifications: usize,
+}
+
+impl Health {
+    fn new(now: Instant) -> Self {
+        Self {
+            last_tick: None,
+            window_started: now,
+            window_ticks: 0,
+            tick_rate: 0.0,
+            last_frame: None,
+            last_minimap_detected: None,
+            last_player_detected: None,
+            queued_priority_actions: 0,
+            scheduled_notifications: 0,
+        }
+    }
+
+    fn update(&mut self, tick: TickHealth, now: Instant) {
+        self.last_tick = Some(now);
+        self.window_ticks += 1;
+        let elapsed = now.duration_since(self.window_started);
+        if elapsed >= TICK_RATE_WINDOW {
+            self.tick_rate = self.window_ticks as f32 / elapsed.as_secs_f32();
+            self.window_ticks = 0;
+            self.window_started = now;
+        }
+        if tick.frame_captured {
+            self.last_frame = Some(now);
+        }
+        if tick.minimap_detected {
+            self.last_minimap_detected = Some(now);
+        }
+        if tick.player_detected {
+            self.last_player_detected = Some(now);
+        }
+        self.queued_priority_actions = tick.queued_priority_actions;
+        self.scheduled_notifications = tick.scheduled_notifications;
+    }
+
+    fn heartbeat(&self, now: Instant, pending_requests: usize) -> Heartbeat {
+        let age_millis =
+            |instant: Option<Instant>| instant.map(|i| now.duration_since(i).as_millis() as u64);
+        Heartbeat {
+            tick_rate: self.tick_rate,
+            last_tick_age_millis: age_millis(self.last_tick),
+            last_frame_age_millis: age_millis(self.last_frame),
+            last_minimap_detected_age_millis: age_millis(self.last_minimap_detected),
+            last_player_detected_age_millis: age_millis(self.last_player_detected),
+            pending_requests,
+            queued_priority_actions: self.queued_priority_actions,
+            scheduled_notifications: self.scheduled_notifications,
+        }
+    }
+}
+
+/// Records the health of `client` observed in the current tick
+pub(crate) fn update(client: usize, tick: TickHealth) {
+    let now = Instant::now();
+    HEALTH
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(client)
+        .or_insert_with(|| Health::new(now))
+        .update(tick, now);
+}
+
+/// Removes the health of `client` once its update loop stops
+pub(crate) fn remove(client: usize) {
+    HEALTH
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&client);
+}
+
+/// Takes a [`Heartbeat`] snapshot of the current health of `client`
+pub(crate) fn heartbeat(client: usize, pending_requests: usize) -> Heartbeat {
+    let now = Instant::now();
+    let health = HEALTH.lock().unwrap_or_else(PoisonError::into_inner);
+    match health.get(&client) {
+        Some(health) => health.heartbeat(now, pending_requests),
+        None => Health::new(now).heartbeat(now, pending_requests),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_heartbeat_tick_rate_and_ages() {
+        let start = Instant::now();
+        let mut health = Health::new(start);
+
+        for i in 1..=30 {
+            health.update(
+                TickHealth {
+                    frame_captured: true,
+                    minimap_detected: i <= 10,
+                    queued_priority_actions: 2,
+                    ..TickHealth::default()
+                },
+                start + Duration::from_millis(i * 1000 / 30),
+            );
+        }
+        let heartbeat = health.heartbeat(start + Duration::from_secs(2), 1);
+
+        assert_eq!(heartbeat.tick_rate, 30.0);
+        assert_eq!(heartbeat.last_tick_age_millis, Some(1000));
+        assert_eq!(heartbeat.last_frame_age_millis, Some(1000));
+        assert_eq!(heartbeat.last_minimap_detected_age_millis, Some(1667));
+        assert_eq!(heartbeat.last_player_detected_age_millis, None);
+        assert_eq!(heartbeat.pending_requests, 1);
+        assert_eq!(heartbeat.queued_priority_actions, 2);
+    }
+}
@@ -0,0 +1,74 @@
+use platforms::windows::KeyKind;
+
+use crate::context::MS_PER_TICK;
+
+/// Sends a harmless movement periodically while actions are halting
+///
+/// The game disconnects the character after idling for a while. When the user intends to
+/// resume later, tapping left and right alternately every few minutes keeps the character
+/// online without drifting away from its position.
+#[derive(Debug, Default)]
+pub struct KeepAlive {
+    idle_ticks: u32,
+    last_right: bool,
+}
+
+impl KeepAlive {
+    /// Updates the idle ticks and returns the key to tap if the interval has passed
+    ///
+    /// The idle ticks are reset when `halting` is `false` or `interval_minutes` is `0`.
+    pub fn update(&mut self, halting: bool, interval_minutes: u32) -> Option<KeyKind> {
+        if !halting || interval_minutes == 0 {
+            self.idle_ticks = 0;
+            return None;
+        }
+        let interval_ticks = (interval_minutes as u64 * 60_000 / MS_PER_TICK) as u32;
+        self.idle_ticks = self.idle_ticks.saturating_add(1);
+        if self.idle_ticks < interval_ticks {
+            return None;
+        }
+        self.idle_ticks = 0;
+        self.last_right = !self.last_right;
+        Some(if self.last_right {
+            KeyKind::Right
+        } else {
+            KeyKind::Left
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL_TICKS: u32 = (60_000 / MS_PER_TICK) as u32;
+
+    #[test]
+    fn keep_alive_alternates_after_interval() {
+        let mut keep_alive = KeepAlive::default();
+
+        for _ in 0..INTERVAL_TICKS - 1 {
+            assert_eq!(keep_alive.update(true, 1), None);
+        }
+        assert_eq!(keep_alive.update(true, 1), Some(KeyKind::Right));
+        for _ in 0..INTERVAL_TICKS - 1 {
+            assert_eq!(keep_alive.update(true, 1), None);
+        }
+        assert_eq!(keep_alive.update(true, 1), Some(KeyKind::Left));
+    }
+
+    #[test]
+    fn keep_alive_reset_when_not_halting_or_disabled() {
+        let mut keep_alive = KeepAlive::default();
+
+        for _ in 0..INTERVAL_TICKS - 1 {
+            keep_alive.update(true, 1);
+        }
+        assert_eq!(keep_alive.update(false, 1), None);
+        assert_eq!(keep_alive.update(true, 1), None);
+        assert_eq!(keep_alive.update(true, 0), None);
+        for _ in 0..INTERVAL_TICKS * 2 {
+            assert_eq!(keep_alive.update(true, 0), None);
+        }
+    }
+}
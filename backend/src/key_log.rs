@@ -0,0 +1,157 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    env,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use platforms::windows::KeyKind;
+use strum::Display;
+
+/// The maximum number of key events kept before the oldest is dropped
+const MAX_KEY_EVENTS: usize = 5_000;
+
+const KEY_EVENTS_CSV_FILE_NAME: &str = "key_events";
+
+const KEY_EVENTS_CSV_HEADER: &str = "tick,timestamp,kind,key";
+
+thread_local! {
+    /// The key events sent by [`crate::bridge::DefaultKeySender`]
+    ///
+    /// Each client update loop runs on its own thread so the events are kept per client.
+    static KEY_LOG: RefCell<KeyLog> = RefCell::default();
+}
+
+/// The kind of a sent [`KeyEvent`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum KeyEventKind {
+    Press,
+    Down,
+    Up,
+    ClickToFocus,
+}
+
+/// A key event sent to the game
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyEvent {
+    /// The update loop tick the event was sent in
+    pub tick: u64,
+    /// The wall-clock time in milliseconds since Unix epoch
+    pub timestamp: u64,
+    pub kind: KeyEventKind,
+    /// The key sent or [`None`] for [`KeyEventKind::ClickToFocus`]
+    pub key: Option<String>,
+}
+
+impl KeyEvent {
+    fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.tick,
+            self.timestamp,
+            self.kind,
+            self.key.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// A bounded log of sent key events for verifying what the bot pressed
+#[derive(Debug, Default)]
+struct KeyLog {
+    tick: u64,
+    events: VecDeque<KeyEvent>,
+}
+
+impl KeyLog {
+    fn record(&mut self, kind: KeyEventKind, key: Option<KeyKind>, timestamp: u64) {
+        if self.events.len() >= MAX_KEY_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(KeyEvent {
+            tick: self.tick,
+            timestamp,
+            kind,
+            key: key.map(|key| format!("{key:?}")),
+        });
+    }
+}
+
+/// Advances the tick used to timestamp subsequent key events
+///
+/// Called once at the start of each update loop tick.
+pub(crate) fn advance_tick() {
+    KEY_LOG.with_borrow_mut(|log| log.tick = log.tick.wrapping_add(1));
+}
+
+/// The current update loop tick
+pub(crate) fn tick() -> u64 {
+    KEY_LOG.with_borrow(|log| log.tick)
+}
+
+/// Records a key event sent in the current tick
+pub(crate) fn record(kind: KeyEventKind, key: Option<KeyKind>) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    KEY_LOG.with_borrow_mut(|log| log.record(kind, key, timestamp));
+}
+
+/// Clones the recorded key events from oldest to newest
+pub(crate) fn events() -> Vec<KeyEvent> {
+    KEY_LOG.with_borrow(|log| log.events.iter().cloned().collect())
+}
+
+/// Exports the recorded key events of `client` to a CSV file next to the executable
+///
+/// Each client exports to its own file and the first client keeps the file name without the
+/// client id. Returns the path of the exported file.
+pub(crate) fn export_events(client: usize) -> Result<PathBuf> {
+    let file = match client {
+        0 => format!("{KEY_EVENTS_CSV_FILE_NAME}.csv"),
+        client => format!("{KEY_EVENTS_CSV_FILE_NAME}_{client}.csv"),
+    };
+    let path = env::current_exe()?
+        .parent()
+        .map(|dir| dir.join(&file))
+        .unwrap_or_else(|| PathBuf::from(file));
+    let mut file = BufWriter::new(File::create(&path)?);
+    writeln!(file, "{KEY_EVENTS_CSV_HEADER}")?;
+    for event in events() {
+        writeln!(file, "{}", event.to_csv_row())?;
+    }
+    file.flush()?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_log_record_bounded() {
+        let mut log = KeyLog::default();
+
+        for _ in 0..MAX_KEY_EVENTS {
+            log.record(KeyEventKind::Press, Some(KeyKind::A), 0);
+        }
+        log.tick = 1;
+        log.record(KeyEventKind::ClickToFocus, None, 10);
+
+        assert_eq!(log.events.len(), MAX_KEY_EVENTS);
+        assert_eq!(
+            log.events.back(),
+            Some(&KeyEvent {
+                tick: 1,
+                timestamp: 10,
+                kind: KeyEventKind::ClickToFocus,
+                key: None,
+            })
+        );
+        assert_eq!(log.events.front().unwrap().to_csv_row(), "0,0,Press,A");
+    }
+}
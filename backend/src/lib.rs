@@ -1,62 +1,111 @@
-#![feature(new_range_api)]
-#![feature(slice_pattern)]
-#![feature(map_try_insert)]
-#![feature(variant_count)]
-#![feature(let_chains)]
-#![feature(iter_array_chunks)]
-#![feature(associated_type_defaults)]
-#![feature(assert_matches)]
+#![cfg_attr(not(feature = "stable"), feature(new_range_api))]
+#![cfg_attr(not(feature = "stable"), feature(let_chains))]
+#![cfg_attr(test, feature(assert_matches))]
 
-use std::sync::{LazyLock, Mutex};
+use std::sync::{
+    LazyLock, PoisonError, RwLock,
+    atomic::{AtomicUsize, Ordering},
+};
 
 use anyhow::{Result, anyhow};
+use strum::Display;
 use tokio::sync::{
-    broadcast, mpsc,
+    broadcast,
+    mpsc::{self, error::TryRecvError},
     oneshot::{self, Sender},
 };
 
+use crate::console::ConsoleCommand;
+
+mod activity;
 mod array;
 mod bridge;
 mod buff;
+mod comparison;
+mod console;
 mod context;
 mod database;
 #[cfg(debug_assertions)]
 mod debug;
 mod detect;
+#[cfg(debug_assertions)]
+mod frame_history;
+mod freeze;
+mod health;
+mod keep_alive;
+mod key_log;
+mod localization;
 mod mat;
 mod minimap;
+mod navigation;
 mod network;
 mod pathing;
 mod player;
+mod quota;
+mod range;
 mod request_handler;
 mod rotator;
 mod rpc;
+#[cfg(feature = "audio-capture")]
+mod rune_chime;
+#[cfg(any(test, feature = "test-support"))]
+pub mod scenario;
+mod scheduler;
 mod skill;
+mod state_stream;
+mod sync;
 mod task;
+mod transition_log;
 
 pub use {
+    buff::BuffKind,
+    comparison::PresetComparison,
     context::init,
     database::{
-        Action, ActionCondition, ActionConfiguration, ActionKey, ActionKeyDirection, ActionKeyWith,
-        ActionMove, AutoMobbing, Bound, CaptureMode, Class, Configuration, InputMethod, KeyBinding,
-        KeyBindingConfiguration, LinkKeyBinding, Minimap, Notifications, Platform, Position,
-        PotionMode, RotationMode, Settings, delete_map, query_configs, query_maps, query_settings,
+        Action, ActionCondition, ActionConditionOperand, ActionConfiguration, ActionKey,
+        ActionKeyDirection, ActionKeyWith, ActionMove, AutoMobLearned, AutoMobbing,
+        AutoMobbingTarget, Bound, BoundVertex, CaptureHandleIdentity, CaptureMode, Class,
+        Configuration, DetectionProfile, GlobalAction, GlobalActionSlot, HsvRange, InputMethod,
+        KeyBinding, KeyBindingConfiguration, KeyModifier, KeyRepeatInterval, Language,
+        LinkKeyBinding, Minimap, MinimapBackup, NavigationStep, Notifications, OtherPlayerColors,
+        PathingProfile, Platform, Portal, PortalUsage, Position, PotionMode, PresetMetadata,
+        QuietHours, RotationMode, Schedule, Settings, SyncMetadata, TuningParameter,
+        TuningParameters, WindowLayout, YBand, delete_map, export_map, import_map,
+        list_map_backups, query_configs, query_maps, query_settings, restore_map_backup,
         upsert_config, upsert_map, upsert_settings,
     },
+    health::Heartbeat,
+    key_log::{KeyEvent, KeyEventKind},
+    localization::localize,
     pathing::MAX_PLATFORMS_COUNT,
+    player::DOUBLE_JUMP_OFFSET_RANGE,
     rotator::RotatorMode,
+    state_stream::GameStateDelta,
     strum::{EnumMessage, IntoEnumIterator, ParseError},
+    sync::map_import_receiver,
+    transition_log::PlayerTransition,
 };
 
-type RequestItem = (Request, Sender<Response>);
+pub(crate) type RequestItem = (Request, Sender<Response>);
+
+/// The running clients in the order they are added
+static CLIENTS: LazyLock<RwLock<Vec<Client>>> = LazyLock::new(RwLock::default);
+
+/// The id of the next registered client
+///
+/// Ids are never reused so that the state kept by id of a removed client is not mistaken for
+/// the state of a newly added client.
+static NEXT_CLIENT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The id of the client requests are sent to
+static SELECTED_CLIENT: AtomicUsize = AtomicUsize::new(0);
 
-static REQUESTS: LazyLock<(
-    mpsc::Sender<RequestItem>,
-    Mutex<mpsc::Receiver<RequestItem>>,
-)> = LazyLock::new(|| {
-    let (tx, rx) = mpsc::channel::<RequestItem>(10);
-    (tx, Mutex::new(rx))
-});
+/// A running client botting its own game window
+#[derive(Debug)]
+struct Client {
+    id: usize,
+    sender: mpsc::Sender<RequestItem>,
+}
 
 macro_rules! expect_unit_variant {
     ($e:expr, $p:path) => {
@@ -81,6 +130,9 @@ macro_rules! expect_value_variant {
 enum Request {
     RotateActions(bool),
     RotateActionsHalting,
+    ToggleAutoMob,
+    ClearAutoMobLearned,
+    CancelScheduledStart,
     CreateMinimap(String),
     UpdateMinimap(Option<String>, Minimap),
     UpdateConfiguration(Configuration),
@@ -88,10 +140,26 @@ enum Request {
     RedetectMinimap,
     GameState,
     MinimapFrame,
+    Screenshot,
     MinimapPlatformsBound,
+    MinimapPlatformsBounds,
+    RotatorSchedule,
     KeyReceiver,
+    GameStateReceiver,
     QueryCaptureHandles,
     SelectCaptureHandle(Option<usize>),
+    QueryClientState,
+    TuneParameter(TuningParameter, u64),
+    KeyEvents,
+    ExportKeyEvents,
+    PlayerTransitions(usize),
+    TestNotification,
+    CalibrateBuffsRegion,
+    ManualControl(ManualControl),
+    SubmitRuneKeys([KeyBinding; 4]),
+    CurrentPlayerPosition,
+    AppendActionToPreset(String, Action),
+    ConsoleCommand(String),
     #[cfg(debug_assertions)]
     CaptureImage(bool),
     #[cfg(debug_assertions)]
@@ -111,7 +179,10 @@ enum Request {
 #[derive(Debug)]
 enum Response {
     RotateActions,
-    RotateActionsHalting(bool),
+    RotateActionsHalting(bool, Option<HaltEvent>),
+    ToggleAutoMob(bool),
+    ClearAutoMobLearned,
+    CancelScheduledStart,
     CreateMinimap(Option<Minimap>),
     UpdateMinimap,
     UpdateConfiguration,
@@ -119,10 +190,26 @@ enum Response {
     RedetectMinimap,
     GameState(GameState),
     MinimapFrame(Option<(Vec<u8>, usize, usize)>),
+    Screenshot(Option<Vec<u8>>),
     MinimapPlatformsBound(Option<Bound>),
+    MinimapPlatformsBounds(PlatformsBounds),
+    RotatorSchedule(RotatorSchedule),
     KeyReceiver(broadcast::Receiver<KeyBinding>),
-    QueryCaptureHandles((Vec<String>, Option<usize>)),
+    GameStateReceiver(broadcast::Receiver<GameStateDelta>),
+    QueryCaptureHandles((Vec<CaptureHandle>, Option<usize>)),
     SelectCaptureHandle,
+    QueryClientState((Option<Configuration>, Option<(Option<String>, Minimap)>)),
+    TuneParameter(Option<Settings>),
+    KeyEvents(Vec<KeyEvent>),
+    ExportKeyEvents(Option<String>),
+    PlayerTransitions(Vec<PlayerTransition>),
+    TestNotification(Result<()>),
+    CalibrateBuffsRegion(Option<Settings>),
+    ManualControl(bool),
+    SubmitRuneKeys(bool),
+    CurrentPlayerPosition(Option<(i32, i32)>),
+    AppendActionToPreset(Option<Minimap>),
+    ConsoleCommand(Result<String>),
     #[cfg(debug_assertions)]
     CaptureImage,
     #[cfg(debug_assertions)]
@@ -138,7 +225,13 @@ enum Response {
 pub(crate) trait RequestHandler {
     fn on_rotate_actions(&mut self, halting: bool);
 
-    fn on_rotate_actions_halting(&self) -> bool;
+    fn on_rotate_actions_halting(&self) -> (bool, Option<HaltEvent>);
+
+    fn on_toggle_auto_mob(&mut self) -> bool;
+
+    fn on_clear_auto_mob_learned(&mut self);
+
+    fn on_cancel_scheduled_start(&mut self);
 
     fn on_create_minimap(&self, name: String) -> Option<Minimap>;
 
@@ -154,14 +247,46 @@ pub(crate) trait RequestHandler {
 
     fn on_minimap_frame(&self) -> Option<(Vec<u8>, usize, usize)>;
 
+    fn on_screenshot(&self) -> Option<Vec<u8>>;
+
     fn on_minimap_platforms_bound(&self) -> Option<Bound>;
 
+    fn on_minimap_platforms_bounds(&self) -> PlatformsBounds;
+
+    fn on_rotator_schedule(&self) -> RotatorSchedule;
+
     fn on_key_receiver(&self) -> broadcast::Receiver<KeyBinding>;
 
-    fn on_query_capture_handles(&mut self) -> (Vec<String>, Option<usize>);
+    fn on_game_state_receiver(&self) -> broadcast::Receiver<GameStateDelta>;
+
+    fn on_query_capture_handles(&mut self) -> (Vec<CaptureHandle>, Option<usize>);
 
     fn on_select_capture_handle(&mut self, index: Option<usize>);
 
+    fn on_query_client_state(&self) -> (Option<Configuration>, Option<(Option<String>, Minimap)>);
+
+    fn on_tune_parameter(&mut self, parameter: TuningParameter, value: u64) -> Option<Settings>;
+
+    fn on_key_events(&self) -> Vec<KeyEvent>;
+
+    fn on_export_key_events(&self) -> Option<String>;
+
+    fn on_player_transitions(&self, count: usize) -> Vec<PlayerTransition>;
+
+    fn on_test_notification(&self) -> Result<()>;
+
+    fn on_calibrate_buffs_region(&mut self) -> Option<Settings>;
+
+    fn on_manual_control(&mut self, control: ManualControl) -> bool;
+
+    fn on_submit_rune_keys(&mut self, keys: [KeyBinding; 4]) -> bool;
+
+    fn on_current_player_position(&self) -> Option<(i32, i32)>;
+
+    fn on_append_action_to_preset(&self, preset: String, action: Action) -> Option<Minimap>;
+
+    fn on_console_command(&mut self, command: ConsoleCommand) -> Result<String>;
+
     #[cfg(debug_assertions)]
     fn on_capture_image(&self, is_grayscale: bool);
 
@@ -182,11 +307,181 @@ pub(crate) trait RequestHandler {
 pub struct GameState {
     pub position: Option<(i32, i32)>,
     pub health: Option<(u32, u32)>,
+    /// The player current mana and max mana if mana detection is enabled
+    pub mana: Option<(u32, u32)>,
+    /// The id of the saved map identified from the detected minimap if any
+    pub detected_map_id: Option<i64>,
+    /// The width, height and fingerprint of the detected minimap if any
+    pub detected_map_identity: Option<(i32, i32, u64)>,
+    /// The changes made to the malformed platforms of the current map when it was loaded
+    pub platforms_repairs: Vec<String>,
+    /// The actions of the current preset whose positions can never be reached by moving between
+    /// the platforms of the current map
+    pub unreachable_actions: Vec<String>,
+    /// The database rows skipped because they failed to deserialize
+    pub invalid_database_rows: Vec<String>,
     pub state: String,
     pub normal_action: Option<String>,
     pub priority_action: Option<String>,
     pub erda_shower_state: String,
+    /// Whether Erda Shower is currently on cooldown
+    pub erda_shower_cooldown: bool,
+    /// The enabled buffs and whether each of them is currently active
+    pub buffs: Vec<(String, bool)>,
+    /// Whether a rune is currently detected on the minimap
+    pub has_rune: bool,
+    /// The number of consecutive failed rune solving attempts
+    pub rune_failed_count: u32,
+    /// Whether rune solving is waiting for the arrows submitted through [`submit_rune_keys`]
+    pub rune_awaiting_keys: bool,
+    /// The number of stranger episodes reacted to in this session
+    pub stranger_episodes: u32,
+    /// Whether auto mobbing is paused through [`toggle_auto_mob`]
+    pub auto_mob_paused: bool,
+    /// The number of distinct strangers currently on the minimap
+    pub stranger_count: u32,
+    /// The number of distinct strangers seen on the minimap in this session
+    pub distinct_strangers: u32,
+    /// The number of consecutive stuck detections before unstucking
+    pub unstuck_count: u32,
+    /// The auto mobbing bound if the current rotation mode is auto mobbing
+    pub auto_mob_bound: Option<Bound>,
+    /// The vertices of the auto mobbing polygon bound if it replaces [`Self::auto_mob_bound`]
+    pub auto_mob_bound_polygon: Vec<(i32, i32)>,
     pub destinations: Vec<(i32, i32)>,
+    /// The memory used by capture and detection buffers in bytes
+    pub memory_usage: usize,
+    /// The width and height of the last captured frame
+    pub resolution: Option<(i32, i32)>,
+    /// Whether [`Self::resolution`] has a matching detection preset
+    pub resolution_supported: bool,
+    /// The health of the last capture, detection is paused while it is not healthy
+    pub capture_health: CaptureHealth,
+    /// Whether platforms pathing is enabled but unused because the map has no platforms
+    pub platforms_pathing_unavailable: bool,
+    /// Whether the mob detection model is loaded, auto mobbing is disabled otherwise
+    pub mob_model_available: bool,
+    /// Whether the minimap detection model is loaded, a less accurate fallback is used otherwise
+    pub minimap_model_available: bool,
+    /// Whether the rune detection model is loaded, rune solving is disabled otherwise
+    pub rune_model_available: bool,
+    /// The reason actions are halting if the bot halted by itself
+    pub halt_event: Option<HaltEvent>,
+    /// Whether actions will be started by the schedule after a break or navigation
+    pub scheduled_start_pending: bool,
+    /// The currently detected portals with their saved usages if any
+    pub portals: Vec<Portal>,
+    /// The preset currently rotating if comparing presets
+    pub ab_comparison_preset: Option<String>,
+    /// The statistics of the compared presets, empty if not comparing
+    pub ab_comparison: Vec<PresetComparison>,
+}
+
+/// The platforms bounds of the current minimap
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlatformsBounds {
+    /// The bound of each group of platforms reachable from each other, largest first
+    pub groups: Vec<Bound>,
+    /// The bound containing all the platforms
+    pub union: Option<Bound>,
+}
+
+/// The resolved actions of the current rotator
+///
+/// Built from the current preset actions after conditions are applied and linked
+/// actions are merged.
+#[derive(Debug, Clone)]
+pub struct RotatorSchedule {
+    /// The normal actions in the order they are executed in one full rotation loop
+    pub normal_actions: Vec<ScheduledAction>,
+    /// The priority actions in the order their conditions are checked
+    pub priority_actions: Vec<ScheduledAction>,
+}
+
+/// The reason the bot halted actions by itself
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum HaltReason {
+    #[strum(to_string = "Map changed")]
+    MapChanged,
+    #[strum(to_string = "Map changed, navigating back")]
+    NavigatingBack,
+    #[strum(to_string = "Navigation failed")]
+    NavigationFailed,
+    #[strum(to_string = "Player died")]
+    PlayerDied,
+    #[strum(to_string = "Player respawned, navigating back")]
+    PlayerRespawned,
+    #[strum(to_string = "Game frozen")]
+    GameFrozen,
+    #[strum(to_string = "Run time quota reached")]
+    RunTimeQuotaReached,
+    #[strum(to_string = "Key presses per second exceeded")]
+    KeyRateExceeded,
+    #[strum(to_string = "Game window changed")]
+    CaptureWindowChanged,
+    #[strum(to_string = "Scheduled break")]
+    ScheduledBreak,
+    #[strum(to_string = "Scheduled stop")]
+    ScheduledStop,
+    #[strum(to_string = "Restarted too many times after errors")]
+    RestartLimitReached,
+    #[strum(to_string = "Stopped responding")]
+    Stalled,
+}
+
+/// The health of the image capture
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Display)]
+pub enum CaptureHealth {
+    #[default]
+    #[strum(to_string = "Healthy")]
+    Healthy,
+    #[strum(to_string = "Frame not available")]
+    FrameNotAvailable,
+    #[strum(to_string = "Window minimized or occluded")]
+    WindowOccluded,
+    #[strum(to_string = "Window not found")]
+    WindowNotFound,
+    #[strum(to_string = "Game window changed, re-select capture handle")]
+    WindowChanged,
+    #[strum(to_string = "Unavailable")]
+    Unavailable,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HaltEvent {
+    pub reason: HaltReason,
+    /// The wall-clock time in milliseconds since Unix epoch
+    pub timestamp: u64,
+}
+
+/// A window that can be captured
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CaptureHandle {
+    pub title: String,
+    /// The id of the process owning the window or [`None`] if the window is already closed
+    pub process_id: Option<u32>,
+    /// Whether the window class matches the game window class
+    pub is_game: bool,
+}
+
+/// A direct movement command from UI to nudge the player while actions are halting
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ManualControl {
+    Left,
+    Right,
+    Up,
+    Down,
+    Jump,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledAction {
+    pub id: u32,
+    /// The condition the action was derived from if it is a user-provided priority action
+    pub condition: Option<ActionCondition>,
+    pub queue_to_front: bool,
+    /// The actions executed in order, more than one if this is a linked action
+    pub actions: Vec<String>,
 }
 
 pub async fn rotate_actions(halting: bool) {
@@ -196,10 +491,38 @@ pub async fn rotate_actions(halting: bool) {
     )
 }
 
-pub async fn rotate_actions_halting() -> bool {
+/// Whether actions are halting along with the [`HaltEvent`] if the bot halted by itself
+pub async fn rotate_actions_halting() -> (bool, Option<HaltEvent>) {
+    let response = request(Request::RotateActionsHalting).await;
+    let Response::RotateActionsHalting(halting, event) = response else {
+        unreachable!()
+    };
+    (halting, event)
+}
+
+/// Pauses or resumes only auto mobbing while other actions keep rotating
+///
+/// Returns whether auto mobbing is paused after toggling.
+pub async fn toggle_auto_mob() -> bool {
     expect_value_variant!(
-        request(Request::RotateActionsHalting).await,
-        Response::RotateActionsHalting
+        request(Request::ToggleAutoMob).await,
+        Response::ToggleAutoMob
+    )
+}
+
+/// Clears the auto mobbing data learned on the current map so learning starts from zero
+pub async fn clear_auto_mob_learned() {
+    expect_unit_variant!(
+        request(Request::ClearAutoMobLearned).await,
+        Response::ClearAutoMobLearned
+    )
+}
+
+/// Cancels starting actions after the current scheduled break or a deferred scheduled start
+pub async fn cancel_scheduled_start() {
+    expect_unit_variant!(
+        request(Request::CancelScheduledStart).await,
+        Response::CancelScheduledStart
     )
 }
 
@@ -247,6 +570,12 @@ pub async fn minimap_frame() -> Result<(Vec<u8>, usize, usize)> {
         .ok_or(anyhow!("minimap frame not found"))
 }
 
+/// Captures the current full-resolution frame encoded as PNG bytes
+pub async fn screenshot() -> Result<Vec<u8>> {
+    expect_value_variant!(request(Request::Screenshot).await, Response::Screenshot)
+        .ok_or(anyhow!("frame not found"))
+}
+
 pub async fn minimap_platforms_bound() -> Option<Bound> {
     expect_value_variant!(
         request(Request::MinimapPlatformsBound).await,
@@ -254,11 +583,34 @@ pub async fn minimap_platforms_bound() -> Option<Bound> {
     )
 }
 
+pub async fn minimap_platforms_bounds() -> PlatformsBounds {
+    expect_value_variant!(
+        request(Request::MinimapPlatformsBounds).await,
+        Response::MinimapPlatformsBounds
+    )
+}
+
+pub async fn rotator_schedule() -> RotatorSchedule {
+    expect_value_variant!(
+        request(Request::RotatorSchedule).await,
+        Response::RotatorSchedule
+    )
+}
+
 pub async fn key_receiver() -> broadcast::Receiver<KeyBinding> {
     expect_value_variant!(request(Request::KeyReceiver).await, Response::KeyReceiver)
 }
 
-pub async fn query_capture_handles() -> (Vec<String>, Option<usize>) {
+/// Receives the changes of [`GameState`] at tick rate in addition to polling [`player_state`]
+pub async fn game_state_receiver() -> broadcast::Receiver<GameStateDelta> {
+    expect_value_variant!(
+        request(Request::GameStateReceiver).await,
+        Response::GameStateReceiver
+    )
+}
+
+/// The windows that can be captured along with the index of the selected one if any
+pub async fn query_capture_handles() -> (Vec<CaptureHandle>, Option<usize>) {
     expect_value_variant!(
         request(Request::QueryCaptureHandles).await,
         Response::QueryCaptureHandles
@@ -272,6 +624,187 @@ pub async fn select_capture_handle(index: Option<usize>) {
     )
 }
 
+/// The configuration and the map with its preset last provided to the selected client
+///
+/// Each is [`None`] if it has not been provided to the client yet.
+pub async fn query_client_state() -> (Option<Configuration>, Option<(Option<String>, Minimap)>) {
+    expect_value_variant!(
+        request(Request::QueryClientState).await,
+        Response::QueryClientState
+    )
+}
+
+/// Sets a runtime [`TuningParameter`] to `value` without restarting the bot
+///
+/// Returns the persisted [`Settings`] if `value` is accepted or [`None`] if it is out of
+/// [`TuningParameter::range`].
+pub async fn tune_parameter(parameter: TuningParameter, value: u64) -> Option<Settings> {
+    expect_value_variant!(
+        request(Request::TuneParameter(parameter, value)).await,
+        Response::TuneParameter
+    )
+}
+
+/// The key events recently sent by the bot from oldest to newest
+pub async fn key_events() -> Vec<KeyEvent> {
+    expect_value_variant!(request(Request::KeyEvents).await, Response::KeyEvents)
+}
+
+/// Exports the recent key events to a CSV file and returns the file path
+pub async fn export_key_events() -> Result<String> {
+    expect_value_variant!(
+        request(Request::ExportKeyEvents).await,
+        Response::ExportKeyEvents
+    )
+    .ok_or(anyhow!("failed to export key events"))
+}
+
+/// The last `count` transitions between player states from oldest to newest
+///
+/// Useful for diagnosing which states the player loops through when it gets stuck.
+pub async fn player_transitions(count: usize) -> Vec<PlayerTransition> {
+    expect_value_variant!(
+        request(Request::PlayerTransitions(count)).await,
+        Response::PlayerTransitions
+    )
+}
+
+/// Takes a [`Heartbeat`] snapshot of the update loop health
+///
+/// Unlike other requests, this does not go through the update loop so it is still available
+/// when the loop stalls.
+pub fn heartbeat() -> Heartbeat {
+    let sender = selected_client_sender();
+    health::heartbeat(selected_client(), sender.max_capacity() - sender.capacity())
+}
+
+/// The ids of the running clients in the order they are added, each botting its own game window
+pub fn client_ids() -> Vec<usize> {
+    CLIENTS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|client| client.id)
+        .collect()
+}
+
+/// Starts a new client with its own player, minimap and rotator botting `window`
+///
+/// Returns the id of the new client or [`None`] if `window` cannot be found.
+pub fn add_client(window: &CaptureHandleIdentity) -> Option<usize> {
+    context::spawn_client(Some(window))
+}
+
+/// The windows that can be botted by a new client
+///
+/// Unlike [`query_capture_handles`], the capture handles of the selected client are unchanged.
+pub fn query_client_windows() -> Vec<CaptureHandle> {
+    context::client_windows()
+}
+
+/// Stops the last added client
+///
+/// The first client always runs so returns `false` if it is the only client.
+pub fn remove_client() -> bool {
+    let mut clients = CLIENTS.write().unwrap_or_else(PoisonError::into_inner);
+    if clients.len() <= 1 {
+        return false;
+    }
+    // Dropping the sender stops the client update loop once its pending requests are handled
+    let removed = clients.pop().unwrap();
+    let _ = SELECTED_CLIENT.compare_exchange(
+        removed.id,
+        clients.last().unwrap().id,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    );
+    true
+}
+
+/// Selects the client with `id` to send subsequent requests to
+pub fn select_client(id: usize) {
+    if client_ids().contains(&id) {
+        SELECTED_CLIENT.store(id, Ordering::Release);
+    }
+}
+
+/// The id of the client requests are sent to
+pub fn selected_client() -> usize {
+    SELECTED_CLIENT.load(Ordering::Acquire)
+}
+
+/// Sends a test notification using the current notification settings
+///
+/// Returns an error if the notification cannot be scheduled (e.g. invalid webhook URL).
+pub async fn test_notification() -> Result<()> {
+    expect_value_variant!(
+        request(Request::TestNotification).await,
+        Response::TestNotification
+    )
+}
+
+/// Calibrates the buffs region offset by locating any active buff in the current frame
+///
+/// Returns the persisted [`Settings`] with the new offset or [`None`] if no buff is found.
+pub async fn calibrate_buffs_region() -> Option<Settings> {
+    expect_value_variant!(
+        request(Request::CalibrateBuffsRegion).await,
+        Response::CalibrateBuffsRegion
+    )
+}
+
+/// Sends a [`ManualControl`] movement command through the current input method
+///
+/// Returns whether the command was sent. Commands are only sent while actions are halting.
+pub async fn manual_control(control: ManualControl) -> bool {
+    expect_value_variant!(
+        request(Request::ManualControl(control)).await,
+        Response::ManualControl
+    )
+}
+
+/// Submits the rune arrows typed by the user when rune detection confidence is low
+///
+/// Returns whether rune solving is awaiting and has accepted the arrows.
+pub async fn submit_rune_keys(keys: [KeyBinding; 4]) -> bool {
+    expect_value_variant!(
+        request(Request::SubmitRuneKeys(keys)).await,
+        Response::SubmitRuneKeys
+    )
+}
+
+/// The current player position relative to the minimap bottom-left corner
+///
+/// Cheaper than [`player_state`] when only the position is needed.
+pub async fn current_player_position() -> Option<(i32, i32)> {
+    expect_value_variant!(
+        request(Request::CurrentPlayerPosition).await,
+        Response::CurrentPlayerPosition
+    )
+}
+
+/// Appends `action` to the end of `preset` in the current minimap
+///
+/// Returns the updated minimap or [`None`] if there is no current minimap or `preset` does not
+/// exist. Similar to [`create_minimap`], the returned minimap is neither applied nor saved.
+pub async fn append_action_to_preset(preset: String, action: Action) -> Option<Minimap> {
+    expect_value_variant!(
+        request(Request::AppendActionToPreset(preset, action)).await,
+        Response::AppendActionToPreset
+    )
+}
+
+/// Executes a command typed into the developer console
+///
+/// The command is parsed in the backend and the returned output is shown in the console. Returns
+/// an error if the command is invalid or cannot be executed.
+pub async fn console_command(command: String) -> Result<String> {
+    expect_value_variant!(
+        request(Request::ConsoleCommand(command)).await,
+        Response::ConsoleCommand
+    )
+}
+
 #[cfg(debug_assertions)]
 pub async fn capture_image(is_grayscale: bool) {
     expect_unit_variant!(
@@ -303,84 +836,159 @@ pub async fn test_spin_rune() {
     expect_unit_variant!(request(Request::TestSpinRune).await, Response::TestSpinRune)
 }
 
-pub(crate) fn poll_request(handler: &mut dyn RequestHandler) {
-    if let Ok((request, sender)) = LazyLock::force(&REQUESTS).1.lock().unwrap().try_recv() {
-        let result = match request {
-            Request::RotateActions(halting) => {
-                handler.on_rotate_actions(halting);
-                Response::RotateActions
-            }
-            Request::RotateActionsHalting => {
-                Response::RotateActionsHalting(handler.on_rotate_actions_halting())
-            }
-            Request::CreateMinimap(name) => {
-                Response::CreateMinimap(handler.on_create_minimap(name))
-            }
-            Request::UpdateMinimap(preset, minimap) => {
-                handler.on_update_minimap(preset, minimap);
-                Response::UpdateMinimap
-            }
-            Request::UpdateConfiguration(config) => {
-                handler.on_update_configuration(config);
-                Response::UpdateConfiguration
-            }
-            Request::UpdateSettings(settings) => {
-                handler.on_update_settings(settings);
-                Response::UpdateSettings
-            }
-            Request::RedetectMinimap => {
-                handler.on_redetect_minimap();
-                Response::RedetectMinimap
-            }
-            Request::GameState => Response::GameState(handler.on_game_state()),
-            Request::MinimapFrame => Response::MinimapFrame(handler.on_minimap_frame()),
-            Request::MinimapPlatformsBound => {
-                Response::MinimapPlatformsBound(handler.on_minimap_platforms_bound())
-            }
-            Request::KeyReceiver => Response::KeyReceiver(handler.on_key_receiver()),
-            Request::QueryCaptureHandles => {
-                Response::QueryCaptureHandles(handler.on_query_capture_handles())
-            }
-            Request::SelectCaptureHandle(index) => {
-                handler.on_select_capture_handle(index);
-                Response::SelectCaptureHandle
-            }
-            #[cfg(debug_assertions)]
-            Request::CaptureImage(is_grayscale) => {
-                handler.on_capture_image(is_grayscale);
-                Response::CaptureImage
-            }
-            #[cfg(debug_assertions)]
-            Request::InferRune => {
-                handler.on_infer_rune();
-                Response::InferRune
-            }
-            #[cfg(debug_assertions)]
-            Request::InferMinimap => {
-                handler.on_infer_minimap();
-                Response::InferMinimap
-            }
-            #[cfg(debug_assertions)]
-            Request::RecordImages(start) => {
-                handler.on_record_images(start);
-                Response::RecordImages
-            }
-            #[cfg(debug_assertions)]
-            Request::TestSpinRune => {
-                handler.on_test_spin_rune();
-                Response::TestSpinRune
-            }
-        };
-        let _ = sender.send(result);
+/// Registers a new client and returns its id along with the receiver of its requests
+pub(crate) fn register_client() -> (usize, mpsc::Receiver<RequestItem>) {
+    let (sender, rx) = mpsc::channel::<RequestItem>(10);
+    let id = NEXT_CLIENT_ID.fetch_add(1, Ordering::AcqRel);
+    CLIENTS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Client { id, sender });
+    (id, rx)
+}
+
+/// Handles a pending request from `requests` with `handler`
+///
+/// Returns `false` if the client is removed and has no more pending requests.
+pub(crate) fn poll_request(
+    handler: &mut dyn RequestHandler,
+    requests: &mut mpsc::Receiver<RequestItem>,
+) -> bool {
+    match requests.try_recv() {
+        Ok(item) => handle_request(handler, item),
+        Err(TryRecvError::Empty) => (),
+        Err(TryRecvError::Disconnected) => return false,
     }
+    true
+}
+
+fn handle_request(handler: &mut dyn RequestHandler, (request, sender): RequestItem) {
+    let result = match request {
+        Request::RotateActions(halting) => {
+            handler.on_rotate_actions(halting);
+            Response::RotateActions
+        }
+        Request::RotateActionsHalting => {
+            let (halting, event) = handler.on_rotate_actions_halting();
+            Response::RotateActionsHalting(halting, event)
+        }
+        Request::ToggleAutoMob => Response::ToggleAutoMob(handler.on_toggle_auto_mob()),
+        Request::ClearAutoMobLearned => {
+            handler.on_clear_auto_mob_learned();
+            Response::ClearAutoMobLearned
+        }
+        Request::CancelScheduledStart => {
+            handler.on_cancel_scheduled_start();
+            Response::CancelScheduledStart
+        }
+        Request::CreateMinimap(name) => Response::CreateMinimap(handler.on_create_minimap(name)),
+        Request::UpdateMinimap(preset, minimap) => {
+            handler.on_update_minimap(preset, minimap);
+            Response::UpdateMinimap
+        }
+        Request::UpdateConfiguration(config) => {
+            handler.on_update_configuration(config);
+            Response::UpdateConfiguration
+        }
+        Request::UpdateSettings(settings) => {
+            handler.on_update_settings(settings);
+            Response::UpdateSettings
+        }
+        Request::RedetectMinimap => {
+            handler.on_redetect_minimap();
+            Response::RedetectMinimap
+        }
+        Request::GameState => Response::GameState(handler.on_game_state()),
+        Request::MinimapFrame => Response::MinimapFrame(handler.on_minimap_frame()),
+        Request::Screenshot => Response::Screenshot(handler.on_screenshot()),
+        Request::MinimapPlatformsBound => {
+            Response::MinimapPlatformsBound(handler.on_minimap_platforms_bound())
+        }
+        Request::MinimapPlatformsBounds => {
+            Response::MinimapPlatformsBounds(handler.on_minimap_platforms_bounds())
+        }
+        Request::RotatorSchedule => Response::RotatorSchedule(handler.on_rotator_schedule()),
+        Request::KeyReceiver => Response::KeyReceiver(handler.on_key_receiver()),
+        Request::GameStateReceiver => Response::GameStateReceiver(handler.on_game_state_receiver()),
+        Request::QueryCaptureHandles => {
+            Response::QueryCaptureHandles(handler.on_query_capture_handles())
+        }
+        Request::SelectCaptureHandle(index) => {
+            handler.on_select_capture_handle(index);
+            Response::SelectCaptureHandle
+        }
+        Request::QueryClientState => Response::QueryClientState(handler.on_query_client_state()),
+        Request::TuneParameter(parameter, value) => {
+            Response::TuneParameter(handler.on_tune_parameter(parameter, value))
+        }
+        Request::KeyEvents => Response::KeyEvents(handler.on_key_events()),
+        Request::ExportKeyEvents => Response::ExportKeyEvents(handler.on_export_key_events()),
+        Request::PlayerTransitions(count) => {
+            Response::PlayerTransitions(handler.on_player_transitions(count))
+        }
+        Request::TestNotification => Response::TestNotification(handler.on_test_notification()),
+        Request::CalibrateBuffsRegion => {
+            Response::CalibrateBuffsRegion(handler.on_calibrate_buffs_region())
+        }
+        Request::ManualControl(control) => {
+            Response::ManualControl(handler.on_manual_control(control))
+        }
+        Request::SubmitRuneKeys(keys) => {
+            Response::SubmitRuneKeys(handler.on_submit_rune_keys(keys))
+        }
+        Request::CurrentPlayerPosition => {
+            Response::CurrentPlayerPosition(handler.on_current_player_position())
+        }
+        Request::AppendActionToPreset(preset, action) => {
+            Response::AppendActionToPreset(handler.on_append_action_to_preset(preset, action))
+        }
+        Request::ConsoleCommand(command) => Response::ConsoleCommand(
+            command
+                .parse::<ConsoleCommand>()
+                .and_then(|command| handler.on_console_command(command)),
+        ),
+        #[cfg(debug_assertions)]
+        Request::CaptureImage(is_grayscale) => {
+            handler.on_capture_image(is_grayscale);
+            Response::CaptureImage
+        }
+        #[cfg(debug_assertions)]
+        Request::InferRune => {
+            handler.on_infer_rune();
+            Response::InferRune
+        }
+        #[cfg(debug_assertions)]
+        Request::InferMinimap => {
+            handler.on_infer_minimap();
+            Response::InferMinimap
+        }
+        #[cfg(debug_assertions)]
+        Request::RecordImages(start) => {
+            handler.on_record_images(start);
+            Response::RecordImages
+        }
+        #[cfg(debug_assertions)]
+        Request::TestSpinRune => {
+            handler.on_test_spin_rune();
+            Response::TestSpinRune
+        }
+    };
+    let _ = sender.send(result);
 }
 
 async fn request(request: Request) -> Response {
     let (tx, rx) = oneshot::channel();
-    LazyLock::force(&REQUESTS)
-        .0
-        .send((request, tx))
-        .await
-        .unwrap();
+    selected_client_sender().send((request, tx)).await.unwrap();
     rx.await.unwrap()
 }
+
+fn selected_client_sender() -> mpsc::Sender<RequestItem> {
+    let clients = CLIENTS.read().unwrap_or_else(PoisonError::into_inner);
+    let selected = selected_client();
+    clients
+        .iter()
+        .find(|client| client.id == selected)
+        .or(clients.first())
+        .map(|client| client.sender.clone())
+        .expect("no client is running, call init first")
+}
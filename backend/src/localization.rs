@@ -0,0 +1,98 @@
+use crate::database::Language;
+
+/// Translations of enum display strings and state names
+///
+/// Each entry is `(English, Korean, Chinese)` where English is the `Display` string.
+const TRANSLATIONS: &[(&str, &str, &str)] = &[
+    // Player and skill states
+    ("Detecting", "감지 중", "检测中"),
+    ("Idle", "대기", "空闲"),
+    ("UseKey", "키 사용", "使用按键"),
+    ("Moving", "이동 중", "移动中"),
+    ("Adjusting", "위치 조정", "调整位置"),
+    ("DoubleJumping", "더블 점프 중", "二段跳中"),
+    ("Grappling", "그래플링", "钩索"),
+    ("Jumping", "점프", "跳跃"),
+    ("UpJumping", "윗점프", "上跳"),
+    ("Falling", "낙하", "下落"),
+    ("Unstucking", "끼임 해제", "脱困"),
+    ("Stalling", "대기 중", "等待中"),
+    ("SolvingRune", "룬 해제", "解符文"),
+    ("CashShopThenExit", "캐시샵 입장 후 퇴장", "进入商城后退出"),
+    ("Cooldown", "재사용 대기", "冷却中"),
+    // Settings
+    ("Default", "기본", "默认"),
+    (
+        "Windows 10 (1903 and up)",
+        "윈도우 10 (1903 이상)",
+        "Windows 10 (1903 及以上)",
+    ),
+    // Rotation modes
+    ("StartToEnd", "처음부터 끝까지", "从头到尾"),
+    (
+        "StartToEndThenReverse",
+        "처음부터 끝까지 후 역순",
+        "从头到尾再反向",
+    ),
+    ("NearestFirst", "가까운 순서", "最近优先"),
+    ("AutoMobbing", "자동 사냥", "自动打怪"),
+    // Auto mobbing targets
+    ("Model", "모델", "模型"),
+    ("MinimapDots", "미니맵 점", "小地图点"),
+    // Actions
+    ("Move", "이동", "移动"),
+    ("Key", "키", "按键"),
+    ("EveryMillis", "밀리초마다", "每隔毫秒"),
+    ("Percentage", "퍼센트", "百分比"),
+    ("Any", "항상", "任意"),
+    (
+        "ErdaShowerOffCooldown",
+        "에르다 샤워 사용 가능",
+        "艾尔达斯之雨冷却完毕",
+    ),
+    ("Linked", "연결", "链接"),
+    ("AnyOf", "하나라도", "任一"),
+    ("AllOf", "모두", "全部"),
+    ("MpAbovePercent", "MP 퍼센트 이상", "MP高于百分比"),
+    ("BuffActive", "버프 활성", "增益生效"),
+    ("BuffMissing", "버프 없음", "增益缺失"),
+    ("Stationary", "정지 상태", "静止"),
+    ("DoubleJump", "더블 점프", "二段跳"),
+    ("Left", "왼쪽", "左"),
+    ("Right", "오른쪽", "右"),
+    ("Before", "이전", "之前"),
+    ("AtTheSame", "동시에", "同时"),
+    ("After", "이후", "之后"),
+    ("Along", "함께", "伴随"),
+    // Key modifiers
+    ("None", "없음", "无"),
+];
+
+/// Localizes `text` to `language`
+///
+/// `text` is expected to be the `Display` string of a public enum or a state name. If there
+/// is no translation, `text` is returned as is.
+pub fn localize(language: Language, text: &str) -> String {
+    let translation = TRANSLATIONS
+        .iter()
+        .find(|(english, _, _)| *english == text)
+        .map(|(english, korean, chinese)| match language {
+            Language::English => *english,
+            Language::Korean => *korean,
+            Language::Chinese => *chinese,
+        });
+    translation.unwrap_or(text).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn localize_known_and_unknown() {
+        assert_eq!(localize(Language::English, "Adjusting"), "Adjusting");
+        assert_eq!(localize(Language::Korean, "Adjusting"), "위치 조정");
+        assert_eq!(localize(Language::Chinese, "Adjusting"), "调整位置");
+        assert_eq!(localize(Language::Korean, "Unknown"), "Unknown");
+    }
+}
@@ -50,3 +50,9 @@ impl MatTraitConst for OwnedMat {
         self.mat.as_raw_Mat()
     }
 }
+
+/// The size of `mat` data in bytes
+#[inline]
+pub fn mat_bytes(mat: &impl MatTraitConst) -> usize {
+    mat.total() * mat.elem_size().unwrap_or_default()
+}
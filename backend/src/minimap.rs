@@ -1,4 +1,8 @@
-use std::fmt;
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Result, anyhow};
 use log::debug;
@@ -7,29 +11,66 @@ use opencv::core::{MatTraitConst, Point, Rect, Vec4b};
 use crate::{
     array::Array,
     context::{Context, Contextual, ControlFlow},
-    database::Minimap as MinimapData,
-    detect::{Detector, OtherPlayerKind},
+    database::{Minimap as MinimapData, Portal, PortalUsage},
+    detect::{Detector, MAX_OTHER_PLAYERS, OtherPlayerKind},
     network::NotificationKind,
     pathing::{
         MAX_PLATFORMS_COUNT, Platform, PlatformWithNeighbors, find_neighbors, find_platforms_bound,
     },
-    player::{DOUBLE_JUMP_THRESHOLD, GRAPPLING_MAX_THRESHOLD, JUMP_THRESHOLD, Player},
+    player::{PathingThresholds, Player},
     task::{Task, Update, update_detection_task},
 };
 
 const MINIMAP_BORDER_WHITENESS_THRESHOLD: u8 = 160;
 
+/// The candidate thresholds to try when calibrating the minimap border threshold
+///
+/// The default threshold comes first so that it is preferred when stability ties.
+const BORDER_THRESHOLD_CANDIDATES: [u8; 5] = [160, 140, 180, 120, 200];
+
+/// The number of frames to sample for each candidate threshold when calibrating
+const BORDER_THRESHOLD_CALIBRATION_FRAMES: u32 = 4;
+
+/// The number of consecutive detections at the same position before a rune is considered
+/// appeared
+const RUNE_STABLE_COUNT: u32 = 3;
+
+/// The maximum distance in pixels between detections to be considered the same position
+const RUNE_STABLE_DISTANCE: i32 = 2;
+
+/// The duration rune detection is repeated quicker after [`MinimapState::expedite_rune_detection`]
+const RUNE_EXPEDITE_DURATION: Duration = Duration::from_secs(30);
+
+/// The maximum distance in pixels an other player dot can move between two detections to be
+/// considered the same player
+const OTHER_PLAYER_TRACK_DISTANCE: i32 = 25;
+
+/// The number of consecutive detections an other player dot can be missing from before it is
+/// no longer tracked
+const OTHER_PLAYER_TRACK_MAX_MISSES: u32 = 2;
+
+type OtherPlayers = Array<Point, MAX_OTHER_PLAYERS>;
+
 #[derive(Debug, Default)]
 pub struct MinimapState {
     data: Option<MinimapData>,
-    minimap_task: Option<Task<Result<(Anchors, Rect)>>>,
+    minimap_task: Option<Task<Result<(Anchors, Rect, Option<u64>)>>>,
     rune_task: Option<Task<Result<Point>>>,
     portals_task: Option<Task<Result<Vec<Rect>>>>,
     has_elite_boss_task: Option<Task<Result<bool>>>,
-    has_guildie_player_task: Option<Task<Result<bool>>>,
-    has_stranger_player_task: Option<Task<Result<bool>>>,
-    has_friend_player_task: Option<Task<Result<bool>>>,
+    guildie_player_task: Option<Task<Result<OtherPlayers>>>,
+    stranger_player_task: Option<Task<Result<OtherPlayers>>>,
+    friend_player_task: Option<Task<Result<OtherPlayers>>>,
+    guildie_tracker: OtherPlayerTracker,
+    stranger_tracker: OtherPlayerTracker,
+    friend_tracker: OtherPlayerTracker,
+    stranger_grace: StrangerGrace,
     update_platforms: bool,
+    border_threshold: BorderThreshold,
+    detected_map_id: Option<i64>,
+    platforms_repairs: Vec<String>,
+    unreachable_actions: Vec<String>,
+    rune_expedited_at: Option<Instant>,
 }
 
 impl MinimapState {
@@ -41,6 +82,236 @@ impl MinimapState {
         self.data = Some(data);
         self.update_platforms = true;
     }
+
+    /// The id of the saved map identified from the currently detected minimap
+    #[inline]
+    pub fn detected_map_id(&self) -> Option<i64> {
+        self.detected_map_id
+    }
+
+    #[inline]
+    pub fn set_detected_map_id(&mut self, id: Option<i64>) {
+        self.detected_map_id = id;
+    }
+
+    /// The changes made to the platforms of the current data when it was loaded
+    #[inline]
+    pub fn platforms_repairs(&self) -> &[String] {
+        &self.platforms_repairs
+    }
+
+    #[inline]
+    pub fn set_platforms_repairs(&mut self, repairs: Vec<String>) {
+        self.platforms_repairs = repairs;
+    }
+
+    /// The actions of the current preset that can never be reached by moving between platforms
+    #[inline]
+    pub fn unreachable_actions(&self) -> &[String] {
+        &self.unreachable_actions
+    }
+
+    #[inline]
+    pub fn set_unreachable_actions(&mut self, actions: Vec<String>) {
+        self.unreachable_actions = actions;
+    }
+
+    /// Repeats rune detection quicker for a while when there is a hint that a rune spawned
+    #[inline]
+    pub fn expedite_rune_detection(&mut self) {
+        self.rune_expedited_at = Some(Instant::now());
+    }
+
+    /// Requires `count` stranger detections within `window_millis` before reacting to a stranger
+    pub fn set_stranger_grace(&mut self, count: u32, window_millis: u64) {
+        self.stranger_grace.count = count;
+        self.stranger_grace.window = Duration::from_millis(window_millis);
+    }
+
+    /// The number of stranger episodes reacted to in this session
+    #[inline]
+    pub fn stranger_episodes(&self) -> u32 {
+        self.stranger_grace.episodes
+    }
+
+    /// The number of distinct strangers currently on the minimap
+    #[inline]
+    pub fn stranger_count(&self) -> u32 {
+        self.stranger_tracker.tracks.len() as u32
+    }
+
+    /// The number of distinct strangers seen on the minimap in this session
+    #[inline]
+    pub fn distinct_strangers(&self) -> u32 {
+        self.stranger_tracker.distinct
+    }
+}
+
+/// Tracks other player dots across detections by nearest neighbor
+///
+/// A dot is considered the same player as the nearest tracked dot within
+/// [`OTHER_PLAYER_TRACK_DISTANCE`] so that a player moving around is only counted once.
+#[derive(Debug, Default)]
+struct OtherPlayerTracker {
+    /// The tracked dots and the number of consecutive detections each is missing from
+    tracks: Vec<(Point, u32)>,
+    /// The number of distinct players tracked
+    distinct: u32,
+}
+
+impl OtherPlayerTracker {
+    /// Updates the tracked dots with the `dots` from a completed detection
+    ///
+    /// Returns the number of newly tracked players.
+    fn update(&mut self, dots: &[Point]) -> u32 {
+        let mut matched = vec![false; self.tracks.len()];
+        let mut new_tracks = vec![];
+        for dot in dots {
+            let nearest = self
+                .tracks
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !matched[*i])
+                .map(|(i, (track, _))| (i, (track.x - dot.x).pow(2) + (track.y - dot.y).pow(2)))
+                .filter(|(_, distance)| *distance <= OTHER_PLAYER_TRACK_DISTANCE.pow(2))
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(i, _)| i);
+            match nearest {
+                Some(i) => {
+                    matched[i] = true;
+                    self.tracks[i] = (*dot, 0);
+                }
+                None => new_tracks.push((*dot, 0)),
+            }
+        }
+
+        for (track, matched) in self.tracks.iter_mut().zip(matched) {
+            if !matched {
+                track.1 += 1;
+            }
+        }
+        self.tracks
+            .retain(|(_, misses)| *misses <= OTHER_PLAYER_TRACK_MAX_MISSES);
+
+        let count = new_tracks.len() as u32;
+        self.tracks.extend(new_tracks);
+        self.distinct += count;
+        count
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.tracks.clear();
+    }
+}
+
+/// Filters out strangers passing by before reacting to them
+///
+/// A stranger episode starts when there are [`Self::count`] detections within [`Self::window`]
+/// and ends when the stranger is no longer on the minimap.
+#[derive(Debug, Default)]
+struct StrangerGrace {
+    /// The number of detections required with `0` treated as `1`
+    count: u32,
+    window: Duration,
+    /// The instants of detections within [`Self::window`] in the current episode
+    detections: VecDeque<Instant>,
+    /// Whether the current episode has been reacted to
+    triggered: bool,
+    /// The number of episodes reacted to
+    episodes: u32,
+}
+
+impl StrangerGrace {
+    /// Updates with whether a stranger is `detected` in this tick and is still `present`
+    ///
+    /// Returns `true` only once when a new episode starts.
+    fn update(&mut self, detected: bool, present: bool, now: Instant) -> bool {
+        if !present {
+            self.detections.clear();
+            self.triggered = false;
+            return false;
+        }
+        if !detected || self.triggered {
+            return false;
+        }
+
+        self.detections.push_back(now);
+        while let Some(instant) = self.detections.front()
+            && now.duration_since(*instant) > self.window
+        {
+            self.detections.pop_front();
+        }
+        if self.detections.len() < self.count.max(1) as usize {
+            return false;
+        }
+        self.detections.clear();
+        self.triggered = true;
+        self.episodes += 1;
+        true
+    }
+}
+
+/// The minimap border threshold that is auto-calibrated when detection fails
+#[derive(Debug)]
+struct BorderThreshold {
+    value: u8,
+    calibration: Option<BorderThresholdCalibration>,
+    calibration_task: Option<Task<Result<[Option<Rect>; BORDER_THRESHOLD_CANDIDATES.len()]>>>,
+}
+
+impl Default for BorderThreshold {
+    fn default() -> Self {
+        Self {
+            value: MINIMAP_BORDER_WHITENESS_THRESHOLD,
+            calibration: None,
+            calibration_task: None,
+        }
+    }
+}
+
+impl BorderThreshold {
+    fn start_calibration(&mut self) {
+        debug!(target: "minimap", "calibrating border threshold");
+        self.calibration = Some(BorderThresholdCalibration::default());
+    }
+}
+
+#[derive(Debug, Default)]
+struct BorderThresholdCalibration {
+    frames: u32,
+    /// The last detected bbox and the number of frames it stays the same for each candidate
+    candidates: [(Option<Rect>, u32); BORDER_THRESHOLD_CANDIDATES.len()],
+}
+
+impl BorderThresholdCalibration {
+    fn sample(&mut self, bboxes: [Option<Rect>; BORDER_THRESHOLD_CANDIDATES.len()]) {
+        self.frames += 1;
+        for ((last, count), bbox) in self.candidates.iter_mut().zip(bboxes) {
+            if bbox.is_some() && bbox == *last {
+                *count += 1;
+            }
+            *last = bbox;
+        }
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.frames >= BORDER_THRESHOLD_CALIBRATION_FRAMES
+    }
+
+    /// Returns the threshold with the most stable bbox across the sampled frames
+    fn most_stable(&self) -> Option<u8> {
+        let mut best = None;
+        let mut best_count = 0;
+        for (threshold, (_, count)) in BORDER_THRESHOLD_CANDIDATES.iter().zip(self.candidates) {
+            if count > best_count {
+                best = Some(*threshold);
+                best_count = count;
+            }
+        }
+        best
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -77,6 +348,10 @@ pub struct MinimapIdle {
     anchors: Anchors,
     /// The bounding box of the minimap.
     pub bbox: Rect,
+    /// The fingerprint of the minimap content when it was detected
+    ///
+    /// Used together with [`Self::bbox`] size to identify the saved map of the detected minimap.
+    pub fingerprint: Option<u64>,
     /// Whether the UI is being partially overlapped
     ///
     /// It is partially overlapped by other UIs if one of the anchor mismatches.
@@ -87,27 +362,71 @@ pub struct MinimapIdle {
     ///
     /// If fail count reaches a threshold, rune is considered no longer on the minimap
     rune_fail_count: u32,
+    /// The detected rune position and number of consecutive detections at that position
+    ///
+    /// Transient false positives are filtered by waiting for [`RUNE_STABLE_COUNT`] detections
+    /// before setting [`Self::rune`].
+    rune_candidate: Option<(Point, u32)>,
     /// Whether there is an elite boss
     ///
     /// This does not belong to minimap though...
     pub has_elite_boss: bool,
-    /// Whether there is a guildie
-    has_guildie_player: Threshold<bool>,
-    /// Whether there is a stranger
-    has_stranger_player: Threshold<bool>,
-    /// Whether there is a friend
-    has_friend_player: Threshold<bool>,
+    /// The guildie positions if there are any
+    guildie_player: Threshold<OtherPlayers>,
+    /// The stranger positions if there are any
+    stranger_player: Threshold<OtherPlayers>,
+    /// The friend positions if there are any
+    friend_player: Threshold<OtherPlayers>,
     /// The portal positions
     ///
     /// Praying each night that there won't be more than 16 portals...
     /// Initially, it is only 8 until it crashes at Henesys with 10 portals smh
     pub portals: Array<Rect, 16>,
+    /// The user saved portals with their usages
+    pub saved_portals: Array<Portal, 16>,
     /// The user provided platforms
     pub platforms: Array<PlatformWithNeighbors, MAX_PLATFORMS_COUNT>,
     /// The largest rectangle containing all the platforms
     pub platforms_bound: Option<Rect>,
 }
 
+impl MinimapIdle {
+    /// The first friend position relative to bottom-left of the minimap if there is one
+    #[inline]
+    pub fn friend_player(&self) -> Option<Point> {
+        self.friend_player
+            .value
+            .and_then(|players| players.iter().next().copied())
+    }
+
+    /// Whether the detected `portal` should be avoided
+    ///
+    /// A portal not saved by the user is always dangerous.
+    pub fn is_dangerous_portal(&self, portal: Rect) -> bool {
+        self.saved_portals
+            .iter()
+            .find(|saved| saved.is_same_position(portal.x, portal.y))
+            .is_none_or(|saved| saved.usage == PortalUsage::Dangerous)
+    }
+
+    /// The entry and exit points of each saved portal used as a shortcut
+    pub fn portal_shortcuts(&self) -> Vec<(Point, Point)> {
+        self.saved_portals
+            .iter()
+            .filter(|portal| portal.usage == PortalUsage::Shortcut)
+            .filter_map(|portal| {
+                let destination = portal
+                    .destination
+                    .filter(|index| *index < self.saved_portals.len())
+                    .map(|index| self.saved_portals[index])?;
+                let (entry_x, entry_y) = portal.entry_point();
+                let (exit_x, exit_y) = destination.entry_point();
+                Some((Point::new(entry_x, entry_y), Point::new(exit_x, exit_y)))
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 #[allow(clippy::large_enum_variant)] // There is only ever a single instance of Minimap
 pub enum Minimap {
@@ -134,18 +453,29 @@ fn update_context(contextual: Minimap, context: &Context, state: &mut MinimapSta
 }
 
 fn update_detecting_context(context: &Context, state: &mut MinimapState) -> Minimap {
-    let Update::Ok((anchors, bbox)) =
-        update_detection_task(context, 2000, &mut state.minimap_task, move |detector| {
-            let bbox = detector.detect_minimap(MINIMAP_BORDER_WHITENESS_THRESHOLD)?;
-            let size = bbox.width.min(bbox.height) as usize;
-            let tl = anchor_at(detector.mat(), bbox.tl(), size, 1)?;
-            let br = anchor_at(detector.mat(), bbox.br(), size, -1)?;
-            let anchors = Anchors { tl, br };
-            debug!(target: "minimap", "anchor points: {:?}", anchors);
-            Ok((anchors, bbox))
-        })
-    else {
+    if state.border_threshold.calibration.is_some() {
+        update_border_threshold_calibration(context, &mut state.border_threshold);
         return Minimap::Detecting;
+    }
+
+    let border_threshold = state.border_threshold.value;
+    let update = update_detection_task(context, 2000, &mut state.minimap_task, move |detector| {
+        let bbox = detector.detect_minimap(border_threshold)?;
+        let size = bbox.width.min(bbox.height) as usize;
+        let tl = anchor_at(detector.mat(), bbox.tl(), size, 1)?;
+        let br = anchor_at(detector.mat(), bbox.br(), size, -1)?;
+        let anchors = Anchors { tl, br };
+        debug!(target: "minimap", "anchor points: {:?}", anchors);
+        let fingerprint = detector.detect_minimap_fingerprint(bbox).ok();
+        Ok((anchors, bbox, fingerprint))
+    });
+    let (anchors, bbox, fingerprint) = match update {
+        Update::Ok(value) => value,
+        Update::Err(_) => {
+            state.border_threshold.start_calibration();
+            return Minimap::Detecting;
+        }
+        Update::Pending => return Minimap::Detecting,
     };
 
     let (platforms, platforms_bound) = state
@@ -153,26 +483,63 @@ fn update_detecting_context(context: &Context, state: &mut MinimapState) -> Mini
         .as_ref()
         .map(|data| platforms_from_data(bbox, data))
         .unwrap_or_default();
+    let saved_portals = state
+        .data
+        .as_ref()
+        .map(portals_from_data)
+        .unwrap_or_default();
     state.update_platforms = false;
     state.rune_task = None;
     state.has_elite_boss_task = None;
+    state.guildie_tracker.clear();
+    state.stranger_tracker.clear();
+    state.friend_tracker.clear();
 
     Minimap::Idle(MinimapIdle {
         anchors,
         bbox,
+        fingerprint,
         partially_overlapping: false,
         rune: None,
         rune_fail_count: 0,
+        rune_candidate: None,
         has_elite_boss: false,
-        has_guildie_player: Threshold::new(2),
-        has_stranger_player: Threshold::new(2),
-        has_friend_player: Threshold::new(2),
+        guildie_player: Threshold::new(2),
+        stranger_player: Threshold::new(2),
+        friend_player: Threshold::new(2),
         portals: Array::new(),
+        saved_portals,
         platforms,
         platforms_bound,
     })
 }
 
+/// Samples the minimap bbox for each candidate threshold and picks the most stable one
+///
+/// Keeps the current threshold if none of the candidates yields a stable bbox.
+fn update_border_threshold_calibration(context: &Context, threshold: &mut BorderThreshold) {
+    let Update::Ok(bboxes) =
+        update_detection_task(context, 500, &mut threshold.calibration_task, |detector| {
+            Ok(
+                BORDER_THRESHOLD_CANDIDATES
+                    .map(|threshold| detector.detect_minimap(threshold).ok()),
+            )
+        })
+    else {
+        return;
+    };
+    let calibration = threshold.calibration.as_mut().unwrap();
+    calibration.sample(bboxes);
+    if !calibration.is_done() {
+        return;
+    }
+    if let Some(value) = calibration.most_stable() {
+        debug!(target: "minimap", "border threshold calibrated to {value}");
+        threshold.value = value;
+    }
+    threshold.calibration = None;
+}
+
 fn update_idle_context(
     context: &Context,
     state: &mut MinimapState,
@@ -187,11 +554,13 @@ fn update_idle_context(
         bbox,
         rune,
         rune_fail_count,
+        rune_candidate,
         has_elite_boss,
-        has_guildie_player,
-        has_stranger_player,
-        has_friend_player,
+        guildie_player,
+        stranger_player,
+        friend_player,
         portals,
+        mut saved_portals,
         mut platforms,
         mut platforms_bound,
         ..
@@ -211,30 +580,46 @@ fn update_idle_context(
     }
 
     let partially_overlapping = (tl_match && !br_match) || (!tl_match && br_match);
-    let (rune, rune_fail_count) =
-        update_rune_task(context, &mut state.rune_task, bbox, rune, rune_fail_count);
+    let rune_expedited = state
+        .rune_expedited_at
+        .is_some_and(|at| at.elapsed() < RUNE_EXPEDITE_DURATION);
+    let (rune, rune_fail_count, rune_candidate) = update_rune_task(
+        context,
+        &mut state.rune_task,
+        bbox,
+        rune,
+        rune_fail_count,
+        rune_candidate,
+        rune_expedited,
+    );
     let has_elite_boss =
         update_elite_boss_task(context, &mut state.has_elite_boss_task, has_elite_boss);
-    let has_guildie_player = update_other_player_task(
+    let guildie_player = update_other_player_task(
         context,
-        &mut state.has_guildie_player_task,
+        &mut state.guildie_player_task,
         bbox,
-        has_guildie_player,
+        guildie_player,
         OtherPlayerKind::Guildie,
+        &mut state.guildie_tracker,
+        None,
     );
-    let has_stranger_player = update_other_player_task(
+    let stranger_player = update_other_player_task(
         context,
-        &mut state.has_stranger_player_task,
+        &mut state.stranger_player_task,
         bbox,
-        has_stranger_player,
+        stranger_player,
         OtherPlayerKind::Stranger,
+        &mut state.stranger_tracker,
+        Some(&mut state.stranger_grace),
     );
-    let has_friend_player = update_other_player_task(
+    let friend_player = update_other_player_task(
         context,
-        &mut state.has_friend_player_task,
+        &mut state.friend_player_task,
         bbox,
-        has_friend_player,
+        friend_player,
         OtherPlayerKind::Friend,
+        &mut state.friend_tracker,
+        None,
     );
     let portals = update_portals_task(context, &mut state.portals_task, portals, bbox);
 
@@ -244,18 +629,21 @@ fn update_idle_context(
             platforms_from_data(bbox, state.data.as_mut().unwrap());
         state.update_platforms = false;
         platforms = updated_platforms;
-        platforms_bound = updated_bound
+        platforms_bound = updated_bound;
+        saved_portals = portals_from_data(state.data.as_ref().unwrap());
     }
 
     Some(Minimap::Idle(MinimapIdle {
         partially_overlapping,
         rune,
         rune_fail_count,
+        rune_candidate,
         has_elite_boss,
-        has_guildie_player,
-        has_stranger_player,
-        has_friend_player,
+        guildie_player,
+        stranger_player,
+        friend_player,
         portals,
+        saved_portals,
         platforms,
         platforms_bound,
         ..idle
@@ -280,40 +668,64 @@ fn update_rune_task(
     minimap: Rect,
     rune: Option<Point>,
     rune_fail_count: u32,
-) -> (Option<Point>, u32) {
+    rune_candidate: Option<(Point, u32)>,
+    expedited: bool,
+) -> (Option<Point>, u32, Option<(Point, u32)>) {
     const MAX_RUNE_FAIL_COUNT: u32 = 3;
 
     let was_none = rune.is_none();
+    // Confirms the candidate or looks for the hinted rune quicker than the normal detection
+    // interval
+    let repeat_delay = if rune_candidate.is_some() || (expedited && was_none) {
+        1000
+    } else {
+        10000
+    };
     let update = if matches!(context.player, Player::SolvingRune(_)) && rune.is_some() {
         Update::Pending
     } else {
-        update_detection_task(context, 10000, task, move |detector| {
+        update_detection_task(context, repeat_delay, task, move |detector| {
             detector
                 .detect_minimap_rune(minimap)
                 .map(|rune| center_of_bbox(rune, minimap))
         })
     };
     match update {
-        Update::Ok(rune) => {
-            if was_none && !context.halting {
+        Update::Ok(detected) => {
+            if !was_none {
+                return (Some(detected), 0, None);
+            }
+            let count = match rune_candidate {
+                Some((candidate, count))
+                    if (candidate.x - detected.x).abs() <= RUNE_STABLE_DISTANCE
+                        && (candidate.y - detected.y).abs() <= RUNE_STABLE_DISTANCE =>
+                {
+                    count + 1
+                }
+                _ => 1,
+            };
+            if count < RUNE_STABLE_COUNT {
+                return (None, 0, Some((detected, count)));
+            }
+            if !context.halting {
                 let _ = context
                     .notification
                     .schedule_notification(NotificationKind::RuneAppear);
             }
-            (Some(rune), 0)
+            (Some(detected), 0, None)
         }
         Update::Err(_) => {
             if !was_none {
                 if rune_fail_count >= MAX_RUNE_FAIL_COUNT {
-                    (None, 0)
+                    (None, 0, None)
                 } else {
-                    (rune, rune_fail_count + 1)
+                    (rune, rune_fail_count + 1, None)
                 }
             } else {
-                (rune, rune_fail_count)
+                (rune, rune_fail_count, None)
             }
         }
-        Update::Pending => (rune, rune_fail_count),
+        Update::Pending => (rune, rune_fail_count, rune_candidate),
     }
 }
 
@@ -343,16 +755,40 @@ fn update_elite_boss_task(
 #[inline]
 fn update_other_player_task(
     context: &Context,
-    task: &mut Option<Task<Result<bool>>>,
+    task: &mut Option<Task<Result<OtherPlayers>>>,
     minimap: Rect,
-    threshold: Threshold<bool>,
+    threshold: Threshold<OtherPlayers>,
     kind: OtherPlayerKind,
-) -> Threshold<bool> {
-    let has_player = threshold.value.unwrap_or_default();
-    let threshold = update_threshold_detection(context, 5000, threshold, task, move |detector| {
-        Ok(detector.detect_player_kind(minimap, kind))
-    });
-    if !context.halting && !has_player && threshold.value.unwrap_or_default() {
+    tracker: &mut OtherPlayerTracker,
+    grace: Option<&mut StrangerGrace>,
+) -> Threshold<OtherPlayers> {
+    let (threshold, completed) =
+        update_threshold_detection(context, 5000, threshold, task, move |detector| {
+            detector.detect_player_kind(minimap, kind).map(|players| {
+                players
+                    .into_iter()
+                    .take(MAX_OTHER_PLAYERS)
+                    .map(|player| center_of_bbox(player, minimap))
+                    .collect()
+            })
+        });
+    let new_players = match completed {
+        Some(true) => {
+            let dots = threshold.value.unwrap().into_iter().collect::<Vec<_>>();
+            tracker.update(&dots)
+        }
+        Some(false) => tracker.update(&[]),
+        None => 0,
+    };
+    let appeared = match grace {
+        Some(grace) => grace.update(
+            completed == Some(true),
+            threshold.value.is_some(),
+            Instant::now(),
+        ),
+        None => new_players > 0,
+    };
+    if !context.halting && appeared {
         let notification = match kind {
             OtherPlayerKind::Guildie => NotificationKind::PlayerGuildieAppear,
             OtherPlayerKind::Stranger => NotificationKind::PlayerStrangerAppear,
@@ -388,10 +824,16 @@ fn update_portals_task(
     }
 }
 
+#[inline]
+fn portals_from_data(minimap: &MinimapData) -> Array<Portal, 16> {
+    Array::from_iter(minimap.portals.iter().copied().take(16))
+}
+
 fn platforms_from_data(
     bbox: Rect,
     minimap: &MinimapData,
 ) -> (Array<PlatformWithNeighbors, 24>, Option<Rect>) {
+    let thresholds = PathingThresholds::from(minimap.pathing_profile);
     let platforms = Array::from_iter(find_neighbors(
         &minimap
             .platforms
@@ -399,14 +841,18 @@ fn platforms_from_data(
             .copied()
             .map(Platform::from)
             .collect::<Vec<_>>(),
-        DOUBLE_JUMP_THRESHOLD,
-        JUMP_THRESHOLD,
-        GRAPPLING_MAX_THRESHOLD,
+        thresholds.double_jump,
+        thresholds.jump,
+        thresholds.grappling_max,
     ));
     let bound = find_platforms_bound(bbox, &platforms);
     (platforms, bound)
 }
 
+/// Updates `threshold` with the detection task result
+///
+/// Returns the updated threshold and whether the detection succeeded if it completed in this
+/// tick.
 #[inline]
 fn update_threshold_detection<T, F>(
     context: &Context,
@@ -414,7 +860,7 @@ fn update_threshold_detection<T, F>(
     mut threshold: Threshold<T>,
     threshold_task: &mut Option<Task<Result<T>>>,
     threshold_task_fn: F,
-) -> Threshold<T>
+) -> (Threshold<T>, Option<bool>)
 where
     T: fmt::Debug + Send + 'static,
     F: FnOnce(Box<dyn Detector>) -> Result<T> + Send + 'static,
@@ -426,6 +872,11 @@ where
         threshold_task_fn,
     );
 
+    let completed = match update {
+        Update::Ok(_) => Some(true),
+        Update::Err(_) => Some(false),
+        Update::Pending => None,
+    };
     match update {
         Update::Ok(value) => {
             threshold.value = Some(value);
@@ -443,7 +894,7 @@ where
         Update::Pending => (),
     }
 
-    threshold
+    (threshold, completed)
 }
 
 #[inline]
@@ -530,6 +981,9 @@ mod tests {
             .expect_detect_minimap()
             .with(eq(MINIMAP_BORDER_WHITENESS_THRESHOLD))
             .returning(move |_| Ok(bbox));
+        detector
+            .expect_detect_minimap_fingerprint()
+            .returning(|_| Ok(0));
         detector.expect_mat().return_const(mat.into());
         (detector, bbox, anchors, rune_bbox)
     }
@@ -575,6 +1029,34 @@ mod tests {
         }
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn minimap_border_threshold_calibration() {
+        fn detector() -> MockDetector {
+            let mut mock = MockDetector::new();
+            mock.expect_clone().returning(detector);
+            mock.expect_detect_minimap().returning(|threshold| {
+                if threshold == 140 {
+                    Ok(Rect::new(0, 0, 100, 100))
+                } else {
+                    Err(anyhow!("minimap not found"))
+                }
+            });
+            mock
+        }
+
+        let mut state = MinimapState::default();
+        let context = Context::new(None, Some(detector()));
+        let mut minimap = Minimap::Detecting;
+
+        state.border_threshold.start_calibration();
+        while state.border_threshold.calibration.is_some() {
+            minimap = update_context(minimap, &context, &mut state);
+            time::advance(Duration::from_millis(500)).await;
+        }
+        assert_matches!(minimap, Minimap::Detecting);
+        assert_eq!(state.border_threshold.value, 140);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn minimap_idle_rune_detection() {
         let mut state = MinimapState::default();
@@ -583,25 +1065,98 @@ mod tests {
         let idle = MinimapIdle {
             anchors,
             bbox,
+            fingerprint: None,
             partially_overlapping: false,
             rune: None,
             rune_fail_count: 0,
+            rune_candidate: None,
             has_elite_boss: false,
-            has_guildie_player: Threshold::default(),
-            has_stranger_player: Threshold::default(),
-            has_friend_player: Threshold::default(),
+            guildie_player: Threshold::default(),
+            stranger_player: Threshold::default(),
+            friend_player: Threshold::default(),
             portals: Array::new(),
+            saved_portals: Array::new(),
             platforms: Array::new(),
             platforms_bound: None,
         };
 
-        let minimap = advance_task(Minimap::Idle(idle), detector, &mut state).await;
+        let rune = center_of_bbox(rune_bbox, bbox);
+        let mut minimap = advance_task(Minimap::Idle(idle), detector, &mut state).await;
+        assert_matches!(
+            minimap,
+            Minimap::Idle(MinimapIdle {
+                rune: None,
+                rune_candidate: Some((candidate, 1)),
+                ..
+            }) if candidate == rune
+        );
+
+        for _ in 1..RUNE_STABLE_COUNT {
+            minimap = advance_task(minimap, create_mock_detector().0, &mut state).await;
+        }
         assert_matches!(minimap, Minimap::Idle(_));
         match minimap {
             Minimap::Idle(idle) => {
-                assert_eq!(idle.rune, Some(center_of_bbox(rune_bbox, bbox)));
+                assert_eq!(idle.rune, Some(rune));
+                assert_eq!(idle.rune_candidate, None);
             }
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn stranger_grace_episode() {
+        let mut grace = StrangerGrace {
+            count: 3,
+            window: Duration::from_secs(10),
+            ..StrangerGrace::default()
+        };
+        let now = Instant::now();
+
+        assert!(!grace.update(true, true, now));
+        // Detections outside of the window are dropped
+        assert!(!grace.update(true, true, now + Duration::from_secs(11)));
+        assert!(!grace.update(false, true, now + Duration::from_secs(12)));
+        assert!(!grace.update(true, true, now + Duration::from_secs(15)));
+        assert!(grace.update(true, true, now + Duration::from_secs(20)));
+        assert_eq!(grace.episodes, 1);
+
+        // Same episode until the stranger is gone
+        assert!(!grace.update(true, true, now + Duration::from_secs(21)));
+        assert!(!grace.update(false, false, now + Duration::from_secs(22)));
+        for secs in 23..26 {
+            grace.update(true, true, now + Duration::from_secs(secs));
+        }
+        assert_eq!(grace.episodes, 2);
+    }
+
+    #[test]
+    fn other_player_tracker_nearest_neighbor() {
+        let mut tracker = OtherPlayerTracker::default();
+
+        assert_eq!(
+            tracker.update(&[Point::new(10, 10), Point::new(100, 10)]),
+            2
+        );
+        // Both players moving around are not tracked again
+        assert_eq!(tracker.update(&[Point::new(95, 12), Point::new(20, 10)]), 0);
+        assert_eq!(tracker.tracks[0].0, Point::new(20, 10));
+        assert_eq!(tracker.tracks[1].0, Point::new(95, 12));
+
+        // A player missing from a few detections is still the same player
+        assert_eq!(tracker.update(&[Point::new(20, 10)]), 0);
+        assert_eq!(tracker.update(&[Point::new(20, 10), Point::new(90, 12)]), 0);
+
+        // A new player far away from the tracked ones
+        assert_eq!(tracker.update(&[Point::new(20, 10), Point::new(50, 60)]), 1);
+        assert_eq!(tracker.tracks.len(), 3);
+        assert_eq!(tracker.distinct, 3);
+
+        // Players gone after missing too many detections
+        for _ in 0..=OTHER_PLAYER_TRACK_MAX_MISSES {
+            tracker.update(&[]);
+        }
+        assert!(tracker.tracks.is_empty());
+        assert_eq!(tracker.distinct, 3);
+    }
 }
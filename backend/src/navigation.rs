@@ -0,0 +1,278 @@
+use log::{debug, info};
+
+use crate::{
+    context::{Context, MS_PER_TICK},
+    database::{Minimap as MinimapData, NavigationStep},
+    minimap::Minimap,
+};
+
+/// The maximum number of ticks to wait for the minimap to be detected before giving up
+const WAIT_MINIMAP_MAX_TICKS: u32 = 30_000 / MS_PER_TICK as u32;
+
+/// The maximum number of ticks to wait for the farm map after the macro is played
+const VERIFY_MAX_TICKS: u32 = 10_000 / MS_PER_TICK as u32;
+
+/// The maximum number of times the macro is played before giving up
+const MAX_ATTEMPTS: u32 = 3;
+
+/// The result of updating [`Navigation`]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NavigationUpdate {
+    /// Still navigating or not navigating
+    Pending,
+    /// The farm map is detected again and actions can be resumed
+    Resumed,
+    /// The farm map could not be reached
+    Failed,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+enum NavigationState {
+    #[default]
+    Inactive,
+    /// Waits for the minimap to be detected for the given ticks
+    WaitingMinimap(u32),
+    /// Plays the step at the index after the remaining ticks
+    Playing(usize, u32),
+    /// Waits for the minimap to match the farm map for the given ticks
+    Verifying(u32),
+}
+
+/// Navigates back to the farm map by playing back [`MinimapData::return_macro`]
+///
+/// Navigation is started when the minimap changed unexpectedly (e.g. player died and
+/// respawned in town) or after the player respawned. The current map is verified against the
+/// identity of the farm map. If it does not match, the macro is played and the map is verified
+/// again. There is no dedicated town detection, any map other than the farm map is navigated
+/// from. The macro only presses keys so NPCs must be interacted with using keys (e.g. the
+/// interact key) instead of mouse clicks.
+#[derive(Debug, Default)]
+pub struct Navigation {
+    state: NavigationState,
+    attempts: u32,
+}
+
+impl Navigation {
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.state != NavigationState::Inactive
+    }
+
+    pub fn start(&mut self) {
+        self.state = NavigationState::WaitingMinimap(0);
+        self.attempts = 0;
+    }
+
+    pub fn abort(&mut self) {
+        self.state = NavigationState::Inactive;
+    }
+
+    pub fn update(&mut self, context: &Context, data: &MinimapData) -> NavigationUpdate {
+        let (next, update) = match self.state {
+            NavigationState::Inactive => (NavigationState::Inactive, NavigationUpdate::Pending),
+            NavigationState::WaitingMinimap(ticks) => match context.minimap {
+                Minimap::Idle(_) if is_farm_map(context, data) => {
+                    (NavigationState::Inactive, NavigationUpdate::Resumed)
+                }
+                Minimap::Idle(_) => {
+                    info!(target: "navigation", "not in farm map, playing return macro");
+                    self.attempts += 1;
+                    (NavigationState::Playing(0, 0), NavigationUpdate::Pending)
+                }
+                Minimap::Detecting if ticks >= WAIT_MINIMAP_MAX_TICKS => {
+                    (NavigationState::Inactive, NavigationUpdate::Failed)
+                }
+                Minimap::Detecting => (
+                    NavigationState::WaitingMinimap(ticks + 1),
+                    NavigationUpdate::Pending,
+                ),
+            },
+            NavigationState::Playing(index, ticks) if ticks > 0 => (
+                NavigationState::Playing(index, ticks - 1),
+                NavigationUpdate::Pending,
+            ),
+            NavigationState::Playing(index, _) => match data.return_macro.get(index) {
+                Some(NavigationStep {
+                    key,
+                    wait_after_millis,
+                }) => {
+                    debug!(target: "navigation", "playing step {index} {key}");
+                    let _ = context.keys.send((*key).into());
+                    let ticks = (*wait_after_millis / MS_PER_TICK) as u32;
+                    (
+                        NavigationState::Playing(index + 1, ticks),
+                        NavigationUpdate::Pending,
+                    )
+                }
+                None => (NavigationState::Verifying(0), NavigationUpdate::Pending),
+            },
+            NavigationState::Verifying(_) if is_farm_map(context, data) => {
+                info!(target: "navigation", "returned to farm map");
+                (NavigationState::Inactive, NavigationUpdate::Resumed)
+            }
+            NavigationState::Verifying(ticks) if ticks < VERIFY_MAX_TICKS => (
+                NavigationState::Verifying(ticks + 1),
+                NavigationUpdate::Pending,
+            ),
+            NavigationState::Verifying(_) if self.attempts < MAX_ATTEMPTS => (
+                NavigationState::WaitingMinimap(0),
+                NavigationUpdate::Pending,
+            ),
+            NavigationState::Verifying(_) => (NavigationState::Inactive, NavigationUpdate::Failed),
+        };
+        self.state = next;
+        update
+    }
+}
+
+/// Whether the detected minimap has the identity of the farm map `data`
+///
+/// Only the size is compared when either the farm map or the detected minimap has no
+/// fingerprint (e.g. maps saved before identities were recorded).
+#[inline]
+fn is_farm_map(context: &Context, data: &MinimapData) -> bool {
+    let Minimap::Idle(idle) = context.minimap else {
+        return false;
+    };
+    match (data.fingerprint, idle.fingerprint) {
+        (Some(_), Some(fingerprint)) => data
+            .identity_distance(idle.bbox.width, idle.bbox.height, fingerprint)
+            .is_some(),
+        _ => idle.bbox.width == data.width && idle.bbox.height == data.height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::Rect;
+    use platforms::windows::KeyKind;
+
+    use super::*;
+    use crate::{KeyBinding, bridge::MockKeySender, minimap::MinimapIdle};
+
+    fn minimap_idle(width: i32, height: i32) -> Minimap {
+        let mut idle = MinimapIdle::default();
+        idle.bbox = Rect::new(0, 0, width, height);
+        Minimap::Idle(idle)
+    }
+
+    fn minimap_idle_with_fingerprint(width: i32, height: i32, fingerprint: u64) -> Minimap {
+        let mut idle = MinimapIdle::default();
+        idle.bbox = Rect::new(0, 0, width, height);
+        idle.fingerprint = Some(fingerprint);
+        Minimap::Idle(idle)
+    }
+
+    #[test]
+    fn navigation_resume_if_farm_map() {
+        let mut context = Context::new(None, None);
+        context.minimap = minimap_idle(100, 50);
+        let data = MinimapData {
+            width: 100,
+            height: 50,
+            ..Default::default()
+        };
+        let mut navigation = Navigation::default();
+
+        navigation.start();
+        assert_eq!(
+            navigation.update(&context, &data),
+            NavigationUpdate::Resumed
+        );
+        assert!(!navigation.is_active());
+    }
+
+    #[test]
+    fn navigation_identity_distinguishes_same_size_map() {
+        let mut context = Context::new(None, None);
+        let data = MinimapData {
+            width: 100,
+            height: 50,
+            fingerprint: Some(0),
+            ..Default::default()
+        };
+        let mut navigation = Navigation::default();
+
+        context.minimap = minimap_idle_with_fingerprint(100, 50, u64::MAX);
+        navigation.start();
+        assert_eq!(
+            navigation.update(&context, &data),
+            NavigationUpdate::Pending
+        );
+        assert!(navigation.is_active());
+
+        context.minimap = minimap_idle_with_fingerprint(101, 50, 1);
+        navigation.start();
+        assert_eq!(
+            navigation.update(&context, &data),
+            NavigationUpdate::Resumed
+        );
+    }
+
+    #[test]
+    fn navigation_play_macro_then_resume() {
+        let mut keys = MockKeySender::new();
+        keys.expect_send()
+            .once()
+            .withf(|key| matches!(key, KeyKind::Up))
+            .returning(|_| Ok(()));
+        let mut context = Context::new(Some(keys), None);
+        context.minimap = minimap_idle(200, 50);
+        let data = MinimapData {
+            width: 100,
+            height: 50,
+            return_macro: vec![NavigationStep {
+                key: KeyBinding::Up,
+                wait_after_millis: 0,
+            }],
+            ..Default::default()
+        };
+        let mut navigation = Navigation::default();
+
+        navigation.start();
+        // in town, starts playing
+        assert_eq!(
+            navigation.update(&context, &data),
+            NavigationUpdate::Pending
+        );
+        // plays the single step
+        assert_eq!(
+            navigation.update(&context, &data),
+            NavigationUpdate::Pending
+        );
+        // macro completes, starts verifying
+        assert_eq!(
+            navigation.update(&context, &data),
+            NavigationUpdate::Pending
+        );
+
+        context.minimap = minimap_idle(100, 50);
+        assert_eq!(
+            navigation.update(&context, &data),
+            NavigationUpdate::Resumed
+        );
+    }
+
+    #[test]
+    fn navigation_fail_after_max_attempts() {
+        let mut context = Context::new(None, None);
+        context.minimap = minimap_idle(200, 50);
+        let data = MinimapData {
+            width: 100,
+            height: 50,
+            ..Default::default()
+        };
+        let mut navigation = Navigation::default();
+
+        navigation.start();
+        let mut update = NavigationUpdate::Pending;
+        for _ in 0..MAX_ATTEMPTS * (VERIFY_MAX_TICKS + 4) {
+            update = navigation.update(&context, &data);
+            if update != NavigationUpdate::Pending {
+                break;
+            }
+        }
+        assert_eq!(update, NavigationUpdate::Failed);
+        assert!(!navigation.is_active());
+    }
+}
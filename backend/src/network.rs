@@ -4,28 +4,30 @@ use std::{
     ops::{Index, Not},
     rc::Rc,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Error, Ok, bail};
 use bit_vec::BitVec;
 use log::{debug, error};
+use platforms::windows;
 use reqwest::{
     Client, Url,
     multipart::{Form, Part},
 };
 use serde::Serialize;
+use strum::EnumCount;
 use tokio::{
     spawn,
     time::{Instant, sleep},
 };
 
-use crate::Settings;
+use crate::{Notifications, Settings};
 
 static TRUE: bool = true;
 static FALSE: bool = false;
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, EnumCount)]
 #[repr(usize)]
 pub enum NotificationKind {
     FailOrMapChange,
@@ -35,6 +37,34 @@ pub enum NotificationKind {
     PlayerStrangerAppear,
     PlayerFriendAppear,
     PlayerIsDead,
+    Halted,
+    Restarted,
+    RestartLimitReached,
+    Frozen,
+    QuotaReached,
+    /// Rune arrows are detected with low confidence and awaiting the arrows from the user
+    RuneLowConfidence,
+    /// A notification sent on request to verify the notification settings
+    Test,
+    /// The summary of notifications batched during quiet hours
+    QuietHoursSummary,
+}
+
+impl NotificationKind {
+    /// Whether this notification needs immediate attention and is sent even during quiet hours
+    #[inline]
+    fn is_critical(self) -> bool {
+        matches!(
+            self,
+            NotificationKind::FailOrMapChange
+                | NotificationKind::PlayerIsDead
+                | NotificationKind::RestartLimitReached
+                | NotificationKind::Frozen
+                | NotificationKind::RuneLowConfidence
+                | NotificationKind::Test
+                | NotificationKind::QuietHoursSummary
+        )
+    }
 }
 
 impl From<NotificationKind> for usize {
@@ -80,6 +110,9 @@ pub struct DiscordNotification {
     ///
     /// There can only be one unique [`NotificationKind`] scheduled at a time.
     pending: Arc<Mutex<BitVec>>,
+    /// The non-critical notifications suppressed during quiet hours and the number of times
+    /// each was suppressed
+    quiet_batched: RefCell<Vec<(NotificationKind, u32)>>,
 }
 
 impl DiscordNotification {
@@ -89,14 +122,26 @@ impl DiscordNotification {
             settings,
             scheduled: Arc::new(Mutex::new(vec![])),
             pending: Arc::new(Mutex::new(BitVec::from_elem(
-                mem::variant_count::<NotificationKind>(),
+                NotificationKind::COUNT,
                 false,
             ))),
+            quiet_batched: RefCell::new(vec![]),
         }
     }
 
     pub fn schedule_notification(&self, kind: NotificationKind) -> Result<(), Error> {
-        let settings = self.settings.borrow();
+        self.schedule_notification_with(kind, &self.settings.borrow())
+    }
+
+    /// Same as [`Self::schedule_notification`] but with the provided `settings`
+    ///
+    /// Used when the shared [`Settings`] is already mutably borrowed (e.g. while handling a
+    /// request).
+    pub fn schedule_notification_with(
+        &self,
+        kind: NotificationKind,
+        settings: &Settings,
+    ) -> Result<(), Error> {
         let is_enabled = match kind {
             NotificationKind::FailOrMapChange => {
                 settings.notifications.notify_on_fail_or_change_map
@@ -113,10 +158,38 @@ impl DiscordNotification {
             NotificationKind::PlayerFriendAppear => {
                 settings.notifications.notify_on_player_friend_appear
             }
+            NotificationKind::Halted => settings.notifications.notify_on_halt,
+            NotificationKind::Restarted | NotificationKind::RestartLimitReached => {
+                settings.notifications.notify_on_restart
+            }
+            NotificationKind::Frozen => settings.notifications.notify_on_freeze,
+            NotificationKind::QuotaReached => settings.notifications.notify_on_quota_reached,
+            NotificationKind::RuneLowConfidence => {
+                settings.notifications.notify_on_rune_low_confidence
+            }
+            NotificationKind::Test | NotificationKind::QuietHoursSummary => true,
         };
         if !is_enabled {
             bail!("notification not enabled");
         }
+        if !kind.is_critical()
+            && settings
+                .notifications
+                .quiet_hours
+                .contains(local_minute_of_day())
+        {
+            if settings.notifications.quiet_hours.batch {
+                let mut batched = self.quiet_batched.borrow_mut();
+                match batched.iter_mut().find(|(batched, _)| *batched == kind) {
+                    Some((_, count)) => *count += 1,
+                    None => batched.push((kind, 1)),
+                }
+            }
+            bail!("notification suppressed during quiet hours");
+        }
+        if settings.notifications.text_to_speech {
+            let _ = speak_notification(kind);
+        }
         if settings.notifications.discord_webhook_url.is_empty() {
             bail!("webhook url not provided");
         }
@@ -140,7 +213,7 @@ impl DiscordNotification {
             .unwrap_or_default();
         let content = match kind {
             NotificationKind::FailOrMapChange => {
-                if self.settings.borrow().stop_on_fail_or_change_map {
+                if settings.stop_on_fail_or_change_map {
                     format!(
                         "{user_id}Bot stopped because it has failed to detect or the map has changed"
                     )
@@ -166,6 +239,37 @@ impl DiscordNotification {
             NotificationKind::PlayerFriendAppear => {
                 format!("{user_id}Bot has detected friend player(s)")
             }
+            NotificationKind::Halted => {
+                format!("{user_id}Bot has halted")
+            }
+            NotificationKind::Restarted => {
+                format!("{user_id}Bot has restarted after an unexpected error")
+            }
+            NotificationKind::RestartLimitReached => {
+                format!("{user_id}Bot has halted after restarting too many times")
+            }
+            NotificationKind::Frozen => {
+                format!("{user_id}Bot has detected game freeze and stopped sending keys")
+            }
+            NotificationKind::QuotaReached => {
+                format!("{user_id}Bot has halted because the run time quota is reached")
+            }
+            NotificationKind::RuneLowConfidence => {
+                format!("{user_id}Bot is unsure about the rune arrows, please submit them")
+            }
+            NotificationKind::Test => {
+                format!("{user_id}This is a test notification")
+            }
+            NotificationKind::QuietHoursSummary => {
+                let summary = self
+                    .quiet_batched
+                    .borrow()
+                    .iter()
+                    .map(|(kind, count)| format!("- {} (x{count})", notification_text(*kind)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("{user_id}Notifications during quiet hours:\n{summary}")
+            }
         };
         let body = DiscordWebhookBody {
             content,
@@ -174,20 +278,36 @@ impl DiscordNotification {
         };
         let frames = match kind {
             NotificationKind::FailOrMapChange => vec![(None, 2), (None, 4)],
+            // Captures immediately while the rune arrows are still on screen
+            NotificationKind::RuneLowConfidence => vec![(None, 0)],
             NotificationKind::EliteBossAppear
             | NotificationKind::PlayerIsDead
             | NotificationKind::PlayerGuildieAppear
             | NotificationKind::PlayerStrangerAppear
             | NotificationKind::PlayerFriendAppear
+            | NotificationKind::Halted
+            | NotificationKind::Restarted
+            | NotificationKind::RestartLimitReached
+            | NotificationKind::Frozen
+            | NotificationKind::QuotaReached
+            | NotificationKind::Test
             | NotificationKind::RuneAppear => vec![(None, 2)],
+            NotificationKind::QuietHoursSummary => vec![],
         };
         let delay = match kind {
             NotificationKind::FailOrMapChange => 5,
+            NotificationKind::RuneLowConfidence | NotificationKind::QuietHoursSummary => 1,
             NotificationKind::EliteBossAppear
             | NotificationKind::PlayerIsDead
             | NotificationKind::PlayerGuildieAppear
             | NotificationKind::PlayerStrangerAppear
             | NotificationKind::PlayerFriendAppear
+            | NotificationKind::Halted
+            | NotificationKind::Restarted
+            | NotificationKind::RestartLimitReached
+            | NotificationKind::Frozen
+            | NotificationKind::QuotaReached
+            | NotificationKind::Test
             | NotificationKind::RuneAppear => 3,
         };
 
@@ -235,6 +355,44 @@ impl DiscordNotification {
         Ok(())
     }
 
+    /// The number of notifications scheduled but not yet sent
+    #[inline]
+    pub fn scheduled_count(&self) -> usize {
+        self.scheduled.lock().unwrap().len()
+    }
+
+    /// Applies the updated `notifications` to the scheduled but not yet sent notifications
+    ///
+    /// New notifications always use the current shared [`Settings`]. But scheduled notifications
+    /// are still pending to capture frames and would otherwise be posted to the old webhook.
+    pub fn refresh(&self, notifications: &Notifications) {
+        let url = &notifications.discord_webhook_url;
+        for item in self.scheduled.lock().unwrap().iter_mut() {
+            if item.url != *url {
+                debug!(target: "notification", "refreshing scheduled {:?} webhook url", item.kind);
+                item.url = url.clone();
+            }
+        }
+    }
+
+    /// Sends the notifications batched during quiet hours as a single summary once quiet hours
+    /// end
+    pub fn update_quiet_hours(&self) {
+        if self.quiet_batched.borrow().is_empty() {
+            return;
+        }
+        let settings = self.settings.borrow();
+        if settings
+            .notifications
+            .quiet_hours
+            .contains(local_minute_of_day())
+        {
+            return;
+        }
+        let _ = self.schedule_notification_with(NotificationKind::QuietHoursSummary, &settings);
+        mem::take(&mut *self.quiet_batched.borrow_mut());
+    }
+
     pub fn update_scheduled_frames(&self, frame: impl Fn() -> Option<Vec<u8>>) {
         for item in self.scheduled.lock().unwrap().iter_mut() {
             let elapsed_secs = item.instant.elapsed().as_secs() as u32;
@@ -250,6 +408,46 @@ impl DiscordNotification {
     }
 }
 
+/// Announces `kind` using text-to-speech
+///
+/// Unlike Discord notification, this is announced immediately without frames.
+fn speak_notification(kind: NotificationKind) -> Result<(), Error> {
+    windows::speak(notification_text(kind))?;
+    Ok(())
+}
+
+/// The short description of `kind` for announcing and summarizing
+fn notification_text(kind: NotificationKind) -> &'static str {
+    match kind {
+        NotificationKind::FailOrMapChange => "Failed to detect or map changed",
+        NotificationKind::RuneAppear => "Rune appeared",
+        NotificationKind::EliteBossAppear => "Elite boss spawned",
+        NotificationKind::PlayerGuildieAppear => "Guildie in map",
+        NotificationKind::PlayerStrangerAppear => "Stranger in map",
+        NotificationKind::PlayerFriendAppear => "Friend in map",
+        NotificationKind::PlayerIsDead => "Player is dead",
+        NotificationKind::Halted => "Bot halted",
+        NotificationKind::Restarted => "Bot restarted",
+        NotificationKind::RestartLimitReached => "Bot restarted too many times",
+        NotificationKind::Frozen => "Game frozen",
+        NotificationKind::QuotaReached => "Run time quota reached",
+        NotificationKind::RuneLowConfidence => "Rune arrows needed",
+        NotificationKind::Test => "Test notification",
+        NotificationKind::QuietHoursSummary => "Quiet hours ended",
+    }
+}
+
+/// The current minute of the day in local time
+#[inline]
+pub fn local_minute_of_day() -> u32 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+        + windows::local_utc_offset_minutes() as i64 * 60;
+    (secs.rem_euclid(24 * 60 * 60) / 60) as u32
+}
+
 async fn post_notification(
     client: Client,
     mut notification: ScheduledNotification,
@@ -1,12 +1,12 @@
-use core::range::Range;
 use std::{
     cmp::{Reverse, max, min},
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, HashSet},
+    fmt,
 };
 
 use opencv::core::{Point, Rect};
 
-use crate::array::Array;
+use crate::{array::Array, range::Range};
 
 pub const MAX_PLATFORMS_COUNT: usize = 24;
 
@@ -18,6 +18,26 @@ pub enum MovementHint {
     Infer,
     /// Performs a walk and then jump
     WalkAndJump,
+    /// Enters the portal at the current point
+    EnterPortal,
+}
+
+/// The reason a point can never be reached by moving between platforms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unreachable {
+    /// The point is not within any platform x range or too far from the platform y
+    OutsidePlatforms,
+    /// The platform of the point cannot be moved to from the lowest platforms
+    TooHigh,
+}
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unreachable::OutsidePlatforms => write!(f, "is outside of all platforms"),
+            Unreachable::TooHigh => write!(f, "is too high to reach from the lowest platforms"),
+        }
+    }
 }
 
 /// A platform where player can stand on
@@ -52,6 +72,14 @@ impl PlatformWithNeighbors {
     }
 }
 
+/// A portal connecting two platforms
+struct PortalEdge {
+    entry_platform: Platform,
+    entry_x: i32,
+    exit_platform: Platform,
+    exit_x: i32,
+}
+
 /// The platform being visited during path finding
 #[derive(PartialEq, Eq)]
 struct VisitingPlatform {
@@ -78,13 +106,65 @@ pub fn find_platforms_bound(
     minimap: Rect,
     platforms: &Array<PlatformWithNeighbors, MAX_PLATFORMS_COUNT>,
 ) -> Option<Rect> {
+    bound_of(minimap, platforms.iter().map(|platform| platform.inner))
+}
+
+/// Finds the smallest bounding rectangle of each group of platforms reachable from each other.
+///
+/// Two platforms are in the same group if either one can reach the other. The groups are sorted
+/// by area from largest to smallest.
+pub fn find_platforms_groups_bound(
+    minimap: Rect,
+    platforms: &Array<PlatformWithNeighbors, MAX_PLATFORMS_COUNT>,
+) -> Vec<Rect> {
+    let platforms = platforms.iter().copied().collect::<Vec<_>>();
+    let mut group_ids = vec![None::<usize>; platforms.len()];
+    let mut groups = Vec::new();
+    for i in 0..platforms.len() {
+        if group_ids[i].is_some() {
+            continue;
+        }
+        let group_id = groups.len();
+        let mut group = vec![];
+        let mut stack = vec![i];
+        group_ids[i] = Some(group_id);
+        while let Some(current) = stack.pop() {
+            group.push(platforms[current].inner);
+            for j in 0..platforms.len() {
+                if group_ids[j].is_none()
+                    && (platforms[current]
+                        .neighbors
+                        .iter()
+                        .any(|platform| *platform == platforms[j].inner)
+                        || platforms[j]
+                            .neighbors
+                            .iter()
+                            .any(|platform| *platform == platforms[current].inner))
+                {
+                    group_ids[j] = Some(group_id);
+                    stack.push(j);
+                }
+            }
+        }
+        groups.push(group);
+    }
+
+    let mut bounds = groups
+        .into_iter()
+        .filter_map(|group| bound_of(minimap, group.into_iter()))
+        .collect::<Vec<_>>();
+    bounds.sort_by_key(|bound| Reverse(bound.area()));
+    bounds
+}
+
+#[inline]
+fn bound_of(minimap: Rect, platforms: impl Iterator<Item = Platform>) -> Option<Rect> {
     platforms
-        .iter()
         .map(|platform| {
             Rect::new(
-                platform.inner.xs.start,
-                minimap.height - platform.inner.y,
-                platform.inner.xs.end - platform.inner.xs.start,
+                platform.xs.start,
+                minimap.height - platform.y,
+                platform.xs.end - platform.xs.start,
                 1,
             )
         })
@@ -131,14 +211,64 @@ pub fn find_neighbors(
     vec
 }
 
+/// Finds the `points` that can never be reached by moving between `platforms`
+///
+/// The lowest platforms are assumed to be reachable by falling down from any other platform. A
+/// point is reachable if its platform, the nearest platform within `jump_threshold` of its y,
+/// can be moved to from the lowest platforms. Returns the indices of the unreachable points.
+pub fn find_unreachable_points(
+    platforms: &[PlatformWithNeighbors],
+    points: &[Point],
+    jump_threshold: i32,
+) -> Vec<(usize, Unreachable)> {
+    let Some(lowest_y) = platforms.iter().map(|platform| platform.inner.y).min() else {
+        return vec![];
+    };
+    let platforms = platforms
+        .iter()
+        .map(|platform| (platform.inner, *platform))
+        .collect::<HashMap<_, _>>();
+    let mut reachable = HashSet::new();
+    let mut stack = platforms
+        .keys()
+        .filter(|platform| platform.y == lowest_y)
+        .copied()
+        .collect::<Vec<_>>();
+    while let Some(platform) = stack.pop() {
+        if !reachable.insert(platform) {
+            continue;
+        }
+        if let Some(current) = platforms.get(&platform) {
+            stack.extend(current.neighbors.iter().copied());
+        }
+    }
+
+    points
+        .iter()
+        .enumerate()
+        .filter_map(
+            |(i, point)| match find_platform(&platforms, *point, Some(jump_threshold)) {
+                Some(platform) if reachable.contains(&platform) => None,
+                Some(_) => Some((i, Unreachable::TooHigh)),
+                None => Some((i, Unreachable::OutsidePlatforms)),
+            },
+        )
+        .collect()
+}
+
 /// Finds a sequence of points representing a path from `from` to `to`, using the given
 /// platform map.
 ///
 /// `vertical_threshold` represents maximum y distance between two connected platforms to perform
 /// a grappling. This is used as weight score to help prioritize vertical movement over
 /// horizontal movement. If `enable_hint` is true, provides movement hints like `WalkAndJump`.
+///
+/// `portals` are pairs of entry and exit points that connect the platforms under them. A portal
+/// is always preferred over other movements between the same two platforms.
+#[allow(clippy::too_many_arguments)]
 pub fn find_points_with(
     platforms: &Array<PlatformWithNeighbors, MAX_PLATFORMS_COUNT>,
+    portals: &[(Point, Point)],
     from: Point,
     to: Point,
     enable_hint: bool,
@@ -152,6 +282,19 @@ pub fn find_points_with(
         .collect::<HashMap<_, _>>();
     let from_platform = find_platform(&platforms, from, None)?; // Clamp `from` to nearest platform
     let to_platform = find_platform(&platforms, to, Some(jump_threshold))?;
+    let portals = portals
+        .iter()
+        .filter_map(|(entry, exit)| {
+            let entry_platform = find_platform(&platforms, *entry, Some(jump_threshold))?;
+            let exit_platform = find_platform(&platforms, *exit, Some(jump_threshold))?;
+            (entry_platform != exit_platform).then_some(PortalEdge {
+                entry_platform,
+                entry_x: entry.x,
+                exit_platform,
+                exit_x: exit.x,
+            })
+        })
+        .collect::<Vec<_>>();
     let mut came_from = HashMap::<Platform, Platform>::new();
     let mut visiting = BinaryHeap::new();
     let mut score = HashMap::<Platform, u32>::new();
@@ -168,6 +311,7 @@ pub fn find_points_with(
         if current.platform == to_platform {
             return points_from(
                 &came_from,
+                &portals,
                 from,
                 from_platform,
                 to_platform,
@@ -178,13 +322,23 @@ pub fn find_points_with(
             );
         }
 
-        let neighbors = platforms[&current.platform].neighbors;
-        for neighbor in neighbors {
-            let tentative_score = current_score.saturating_add(weight_score(
-                current.platform,
-                neighbor,
-                vertical_threshold,
-            ));
+        let neighbors = platforms[&current.platform]
+            .neighbors
+            .into_iter()
+            .map(|neighbor| (neighbor, false))
+            .chain(
+                portals
+                    .iter()
+                    .filter(|portal| portal.entry_platform == current.platform)
+                    .map(|portal| (portal.exit_platform, true)),
+            );
+        for (neighbor, is_portal) in neighbors {
+            let weight = if is_portal {
+                0
+            } else {
+                weight_score(current.platform, neighbor, vertical_threshold)
+            };
+            let tentative_score = current_score.saturating_add(weight);
             let neighbor_score = score.get(&neighbor).copied().unwrap_or(u32::MAX);
             if tentative_score < neighbor_score {
                 came_from.insert(neighbor, current.platform);
@@ -211,6 +365,7 @@ pub fn find_points_with(
 #[allow(clippy::too_many_arguments)]
 fn points_from(
     came_from: &HashMap<Platform, Platform>,
+    portals: &[PortalEdge],
     from: Point,
     from_platform: Platform,
     to_platform: Platform,
@@ -244,6 +399,20 @@ fn points_from(
         let next = went_to[&current];
         let start_max = max(next.xs.start, current.xs.start);
         let end_min = min(next.xs.end, current.xs.end);
+        let portal = portals
+            .iter()
+            .find(|portal| portal.entry_platform == current && portal.exit_platform == next);
+
+        if let Some(portal) = portal {
+            // Walks to the portal and enters it, continuing from where the portal exits
+            points.push((
+                Point::new(portal.entry_x, current.y),
+                MovementHint::EnterPortal,
+            ));
+            last_point = Point::new(portal.exit_x, next.y);
+            current = next;
+            continue;
+        }
 
         // Check if the current platform overlap with the next platform
         if ranges_overlap(next.xs, current.xs) {
@@ -365,10 +534,11 @@ fn ranges_overlap<R: Into<Range<i32>>>(first: R, second: R) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use opencv::core::Point;
+    use opencv::core::{Point, Rect};
 
     use super::{
-        MAX_PLATFORMS_COUNT, MovementHint, Platform, PlatformWithNeighbors, find_neighbors,
+        MAX_PLATFORMS_COUNT, MovementHint, Platform, PlatformWithNeighbors, Unreachable,
+        find_neighbors, find_platforms_bound, find_platforms_groups_bound, find_unreachable_points,
     };
     use crate::{
         array::Array,
@@ -397,6 +567,54 @@ mod tests {
         assert!(!ranges_overlap(5..10, 0..5));
     }
 
+    #[test]
+    fn find_platforms_groups_bound_separates_unreachable() {
+        let minimap = Rect::new(0, 0, 300, 100);
+        let platforms = [
+            Platform::new(0..100, 10),
+            Platform::new(0..100, 15),
+            Platform::new(200..250, 80),
+        ];
+        let platforms = make_platforms_with_neighbors(&platforms);
+
+        let groups = find_platforms_groups_bound(minimap, &platforms);
+
+        assert_eq!(
+            groups,
+            vec![Rect::new(0, 82, 100, 9), Rect::new(200, 17, 50, 4)]
+        );
+        assert_eq!(
+            find_platforms_bound(minimap, &platforms),
+            Some(groups[0] | groups[1])
+        );
+    }
+
+    #[test]
+    fn find_unreachable_points_flags_outside_and_too_high() {
+        let platforms = [
+            Platform::new(0..100, 10),
+            Platform::new(0..100, 40),
+            Platform::new(150..200, 100),
+        ];
+        let platforms = find_neighbors(&platforms, 25, 7, 41);
+        let points = [
+            Point::new(50, 40),
+            Point::new(120, 10),
+            Point::new(170, 100),
+            Point::new(50, 10),
+        ];
+
+        let unreachable = find_unreachable_points(&platforms, &points, 7);
+
+        assert_eq!(
+            unreachable,
+            vec![
+                (1, Unreachable::OutsidePlatforms),
+                (2, Unreachable::TooHigh)
+            ]
+        );
+    }
+
     #[test]
     fn find_points_with_direct_overlap() {
         let platforms = [
@@ -408,7 +626,7 @@ mod tests {
         let from = Point::new(10, 50);
         let to = Point::new(20, 60);
 
-        let points = find_points_with(&platforms, from, to, true, 25, 7, 41).unwrap();
+        let points = find_points_with(&platforms, &[], from, to, true, 25, 7, 41).unwrap();
 
         let expected = vec![
             (Point::new(10, 60), MovementHint::Infer),
@@ -429,7 +647,7 @@ mod tests {
         let from = Point::new(25, 50);
         let to = Point::new(65, 55);
 
-        let points = find_points_with(&platforms, from, to, true, 25, 7, 41).unwrap();
+        let points = find_points_with(&platforms, &[], from, to, true, 25, 7, 41).unwrap();
 
         assert_eq!(points.first().unwrap().0.y, 50);
         assert_eq!(points.last().unwrap().0.y, 55);
@@ -448,7 +666,7 @@ mod tests {
         let from = Point::new(10, 50);
         let to = Point::new(20, 132);
 
-        let points = find_points_with(&platforms, from, to, true, 25, 7, 41).unwrap();
+        let points = find_points_with(&platforms, &[], from, to, true, 25, 7, 41).unwrap();
 
         // Check that y-values ascend (multi-hop upward movement)
         let ys: Vec<_> = points.iter().map(|(p, _)| p.y).collect();
@@ -461,6 +679,28 @@ mod tests {
         assert_eq!(points.last().unwrap().0.y, 132);
     }
 
+    #[test]
+    fn find_points_with_portal_shortcut() {
+        let platforms = [
+            Platform::new(0..50, 50),
+            Platform::new(100..150, 55), // Too far without the portal
+        ];
+        let platforms = make_platforms_with_neighbors(&platforms);
+        let portals = [(Point::new(20, 51), Point::new(120, 56))];
+
+        let from = Point::new(10, 50);
+        let to = Point::new(140, 55);
+
+        let points = find_points_with(&platforms, &portals, from, to, true, 25, 7, 41).unwrap();
+
+        let expected = vec![
+            (Point::new(20, 50), MovementHint::EnterPortal),
+            (Point::new(140, 55), MovementHint::Infer),
+        ];
+
+        assert_eq!(points, expected);
+    }
+
     #[test]
     fn find_points_with_no_path() {
         let platforms = [
@@ -472,7 +712,7 @@ mod tests {
         let from = Point::new(25, 50);
         let to = Point::new(125, 55);
 
-        let points = find_points_with(&platforms, from, to, true, 25, 7, 41);
+        let points = find_points_with(&platforms, &[], from, to, true, 25, 7, 41);
         assert!(points.is_none());
     }
 
@@ -487,7 +727,7 @@ mod tests {
         let from = Point::new(45, 50); // Near right edge of first platform
         let to = Point::new(60, 52); // Near left edge of second platform
 
-        let points = find_points_with(&platforms, from, to, true, 25, 7, 41).unwrap();
+        let points = find_points_with(&platforms, &[], from, to, true, 25, 7, 41).unwrap();
 
         let has_walk_and_jump = points
             .iter()
@@ -6,7 +6,7 @@ use super::{Player, PlayerState, use_key::UseKey};
 use crate::{
     Action, ActionKey, ActionKeyDirection, ActionKeyWith, ActionMove, KeyBinding, Position,
     context::{Context, MS_PER_TICK},
-    database::LinkKeyBinding,
+    database::{KeyModifier, LinkKeyBinding},
 };
 
 /// The minimum x distance required to transition to [`Player::UseKey`] in auto mob action
@@ -21,7 +21,9 @@ const AUTO_MOB_USE_KEY_Y_THRESHOLD: i32 = 8;
 #[derive(Clone, Copy, Debug)]
 pub struct PlayerActionKey {
     pub key: KeyBinding,
+    pub modifier: KeyModifier,
     pub link_key: Option<LinkKeyBinding>,
+    pub link_key_modifier: KeyModifier,
     pub count: u32,
     pub position: Option<Position>,
     pub direction: ActionKeyDirection,
@@ -30,13 +32,17 @@ pub struct PlayerActionKey {
     pub wait_before_use_ticks_random_range: u32,
     pub wait_after_use_ticks: u32,
     pub wait_after_use_ticks_random_range: u32,
+    pub hold_ticks: u32,
+    pub heavy: bool,
 }
 
 impl From<ActionKey> for PlayerActionKey {
     fn from(
         ActionKey {
             key,
+            modifier,
             link_key,
+            link_key_modifier,
             count,
             position,
             direction,
@@ -45,12 +51,16 @@ impl From<ActionKey> for PlayerActionKey {
             wait_before_use_millis_random_range,
             wait_after_use_millis,
             wait_after_use_millis_random_range,
+            hold_millis,
+            heavy,
             ..
         }: ActionKey,
     ) -> Self {
         Self {
             key,
+            modifier,
             link_key,
+            link_key_modifier,
             count: count.max(1),
             position,
             direction,
@@ -61,6 +71,8 @@ impl From<ActionKey> for PlayerActionKey {
             wait_after_use_ticks: (wait_after_use_millis / MS_PER_TICK) as u32,
             wait_after_use_ticks_random_range: (wait_after_use_millis_random_range / MS_PER_TICK)
                 as u32,
+            hold_ticks: (hold_millis / MS_PER_TICK) as u32,
+            heavy,
         }
     }
 }
@@ -93,6 +105,8 @@ impl From<ActionMove> for PlayerActionMove {
 #[cfg_attr(test, derive(Default))]
 pub struct PlayerActionAutoMob {
     pub key: KeyBinding,
+    /// The key to use instead of [`Self::key`] when transitioning to [`Player::UseKey`] mid-air
+    pub airborne_key: Option<KeyBinding>,
     pub count: u32,
     pub wait_before_ticks: u32,
     pub wait_after_ticks: u32,
@@ -129,7 +143,8 @@ impl From<Action> for PlayerAction {
 
 /// Checks proximity in [`PlayerAction::AutoMob`] for transitioning to [`Player::UseKey`]
 ///
-/// This is common logics shared with other contextual states when there is auto mob action
+/// This is common logics shared with other contextual states when there is auto mob action.
+/// When `airborne` is true, [`PlayerActionAutoMob::airborne_key`] is used if provided.
 #[inline]
 pub fn on_auto_mob_use_key_action(
     context: &Context,
@@ -137,8 +152,16 @@ pub fn on_auto_mob_use_key_action(
     cur_pos: Point,
     x_distance: i32,
     y_distance: i32,
+    airborne: bool,
 ) -> Option<(Player, bool)> {
     if x_distance <= AUTO_MOB_USE_KEY_X_THRESHOLD && y_distance <= AUTO_MOB_USE_KEY_Y_THRESHOLD {
+        let action = match action {
+            PlayerAction::AutoMob(mob) if airborne => PlayerAction::AutoMob(PlayerActionAutoMob {
+                key: mob.airborne_key.unwrap_or(mob.key),
+                ..mob
+            }),
+            _ => action,
+        };
         let _ = context.keys.send_up(KeyKind::Down);
         let _ = context.keys.send_up(KeyKind::Up);
         let _ = context.keys.send_up(KeyKind::Left);
@@ -1,11 +1,13 @@
 use std::cmp::Ordering;
 
+use opencv::core::Point;
 use platforms::windows::KeyKind;
 
 use super::{PlayerAction, PlayerActionKey, PlayerState, moving::Moving, use_key::UseKey};
 use crate::{
     ActionKeyDirection, ActionKeyWith,
     context::Context,
+    minimap::Minimap,
     player::{
         Player,
         actions::{on_action_state, on_auto_mob_use_key_action},
@@ -27,10 +29,14 @@ const ADJUSTING_SHORT_TIMEOUT: u32 = 3;
 /// Minimium y distance required to perform a fall and then walk
 const FALLING_THRESHOLD: i32 = 8;
 
+/// Maximum y distance from a platform for the player to be considered standing on it
+const PLATFORM_Y_THRESHOLD: i32 = 1;
+
 /// Updates the [`Player::Adjusting`] contextual state
 ///
 /// This state just walks towards the destination. If [`Moving::exact`] is true,
-/// then it will perform small movement to ensure the `x` is as close as possible.
+/// then it will perform small movement to ensure the `x` is as close as possible. The action
+/// arrival tolerance, if any, decides how close is close enough instead.
 pub fn update_adjusting_context(
     context: &Context,
     state: &mut PlayerState,
@@ -41,7 +47,7 @@ pub fn update_adjusting_context(
     let (x_distance, x_direction) = moving.x_distance_direction_from(true, cur_pos);
     let (y_distance, y_direction) = moving.y_distance_direction_from(true, cur_pos);
     let is_intermediate = moving.is_destination_intermediate();
-    if x_distance >= state.double_jump_threshold(is_intermediate) {
+    if x_distance >= state.double_jump_threshold(is_intermediate, x_direction) {
         state.use_immediate_control_flow = true;
         return Player::Moving(moving.dest, moving.exact, moving.intermediates);
     }
@@ -59,6 +65,11 @@ pub fn update_adjusting_context(
         state.last_movement = Some(LastMovement::Adjusting);
     }
 
+    // Walks towards the clamped x instead so that the player stops at the platform edge
+    let walk_x = clamp_walk_x_to_platform(context, cur_pos, moving.dest);
+    let walk_x_direction = walk_x - cur_pos.x;
+    let walk_x_distance = walk_x_direction.abs();
+
     update_moving_axis_context(
         moving,
         cur_pos,
@@ -70,9 +81,18 @@ pub fn update_adjusting_context(
         }),
         |mut moving| {
             if !moving.completed {
-                let should_adjust_medium = x_distance >= ADJUSTING_MEDIUM_THRESHOLD;
-                let should_adjust_short = moving.exact && x_distance >= ADJUSTING_SHORT_THRESHOLD;
-                let direction = match x_direction.cmp(&0) {
+                // Releases keys slightly before the destination when the player is predicted to
+                // reach it by momentum except for exact moves that must not complete early
+                let predicted_distance = if moving.exact {
+                    walk_x_distance
+                } else {
+                    state.predicted_x_distance(walk_x_distance, walk_x_direction)
+                };
+                let adjusting_threshold = state.adjusting_threshold(moving.exact, is_intermediate);
+                let should_adjust_medium = predicted_distance >= ADJUSTING_MEDIUM_THRESHOLD
+                    && predicted_distance >= adjusting_threshold;
+                let should_adjust_short = predicted_distance >= adjusting_threshold;
+                let direction = match walk_x_direction.cmp(&0) {
                     Ordering::Greater => {
                         Some((KeyKind::Right, KeyKind::Left, ActionKeyDirection::Right))
                     }
@@ -101,7 +121,11 @@ pub fn update_adjusting_context(
                     _ => {
                         let _ = context.keys.send_up(KeyKind::Left);
                         let _ = context.keys.send_up(KeyKind::Right);
-                        moving = moving.completed(true);
+                        // Stopping at the platform edge does not complete the move so that it
+                        // times out and is retried instead of completing short of the destination
+                        if walk_x == moving.dest.x || x_distance < adjusting_threshold {
+                            moving = moving.completed(true);
+                        }
                     }
                 }
             }
@@ -122,6 +146,28 @@ pub fn update_adjusting_context(
     )
 }
 
+/// Clamps the destination `x` to the edges of the platform the player is standing on
+///
+/// Walking off an edge is only allowed when the destination is below the player since that is
+/// how the player gets down. Otherwise, the player may fall into a gap it can't recover from.
+fn clamp_walk_x_to_platform(context: &Context, cur_pos: Point, dest: Point) -> i32 {
+    let Minimap::Idle(idle) = context.minimap else {
+        return dest.x;
+    };
+    if dest.y < cur_pos.y {
+        return dest.x;
+    }
+    idle.platforms
+        .iter()
+        .find(|platform| {
+            let xs = platform.xs();
+            (platform.y() - cur_pos.y).abs() <= PLATFORM_Y_THRESHOLD
+                && (xs.start..=xs.end).contains(&cur_pos.x)
+        })
+        .map(|platform| dest.x.clamp(platform.xs().start, platform.xs().end))
+        .unwrap_or(dest.x)
+}
+
 fn on_player_action(
     context: &Context,
     state: &PlayerState,
@@ -169,7 +215,7 @@ fn on_player_action(
             }
         }
         PlayerAction::AutoMob(_) => {
-            on_auto_mob_use_key_action(context, action, moving.pos, x_distance, y_distance)
+            on_auto_mob_use_key_action(context, action, moving.pos, x_distance, y_distance, false)
         }
         PlayerAction::Key(PlayerActionKey {
             with: ActionKeyWith::Stationary,
@@ -179,3 +225,59 @@ fn on_player_action(
         | PlayerAction::Move(_) => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::Point;
+
+    use super::clamp_walk_x_to_platform;
+    use crate::{
+        array::Array,
+        context::Context,
+        minimap::{Minimap, MinimapIdle},
+        pathing::{Platform, find_neighbors},
+    };
+
+    fn context_with_platforms(platforms: &[Platform]) -> Context {
+        let mut idle = MinimapIdle::default();
+        idle.platforms = Array::from_iter(find_neighbors(platforms, 25, 7, 41));
+        let mut context = Context::new(None, None);
+        context.minimap = Minimap::Idle(idle);
+        context
+    }
+
+    #[test]
+    fn clamp_walk_x_to_platform_edge() {
+        let context = context_with_platforms(&[Platform::new(10..50, 20)]);
+        let pos = Point::new(45, 20);
+
+        assert_eq!(
+            clamp_walk_x_to_platform(&context, pos, Point::new(60, 20)),
+            50
+        );
+        assert_eq!(
+            clamp_walk_x_to_platform(&context, pos, Point::new(0, 25)),
+            10
+        );
+        assert_eq!(
+            clamp_walk_x_to_platform(&context, pos, Point::new(30, 20)),
+            30
+        );
+    }
+
+    #[test]
+    fn clamp_walk_x_to_platform_ignored() {
+        let context = context_with_platforms(&[Platform::new(10..50, 20)]);
+
+        // Destination below the player
+        assert_eq!(
+            clamp_walk_x_to_platform(&context, Point::new(45, 20), Point::new(60, 10)),
+            60
+        );
+        // Not standing on any platform
+        assert_eq!(
+            clamp_walk_x_to_platform(&context, Point::new(45, 30), Point::new(60, 30)),
+            60
+        );
+    }
+}
@@ -1,12 +1,15 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, ops::RangeInclusive};
 
 use log::debug;
 use opencv::core::Point;
 use platforms::windows::KeyKind;
 
 use super::{
-    Player, PlayerAction, PlayerActionKey, PlayerState, actions::on_auto_mob_use_key_action,
-    moving::Moving, use_key::UseKey,
+    Player, PlayerAction, PlayerActionKey, PlayerState,
+    actions::on_auto_mob_use_key_action,
+    adjust::ADJUSTING_MEDIUM_THRESHOLD,
+    moving::{Moving, MovingIntermediates},
+    use_key::UseKey,
 };
 use crate::{
     ActionKeyDirection, ActionKeyWith,
@@ -25,6 +28,16 @@ pub const DOUBLE_JUMP_THRESHOLD: i32 = 25;
 /// Minimum x distance from the destination required to perform a double jump in auto mobbing
 pub const DOUBLE_JUMP_AUTO_MOB_THRESHOLD: i32 = 15;
 
+/// The minimum double jump threshold after applying the configured direction offsets
+///
+/// Stays above [`ADJUSTING_MEDIUM_THRESHOLD`] so that adjusting still covers the distances
+/// not double jumped.
+pub const DOUBLE_JUMP_MIN_THRESHOLD: i32 = ADJUSTING_MEDIUM_THRESHOLD + 1;
+
+/// The range of the configured double jump direction offsets
+pub const DOUBLE_JUMP_OFFSET_RANGE: RangeInclusive<i32> =
+    DOUBLE_JUMP_MIN_THRESHOLD - DOUBLE_JUMP_AUTO_MOB_THRESHOLD..=DOUBLE_JUMP_THRESHOLD;
+
 /// Minimum x distance from the destination required to transition to [`Player::UseKey`]
 const USE_KEY_X_THRESHOLD: i32 = DOUBLE_JUMP_THRESHOLD;
 
@@ -66,6 +79,11 @@ impl DoubleJumping {
     fn moving(self, moving: Moving) -> DoubleJumping {
         DoubleJumping { moving, ..self }
     }
+
+    #[inline]
+    pub fn intermediates(&self) -> Option<MovingIntermediates> {
+        self.moving.intermediates
+    }
 }
 
 /// Updates the [`Player::DoubleJumping`] contextual state
@@ -143,7 +161,8 @@ pub fn update_double_jumping_context(
                 }
 
                 let can_continue = !double_jumping.forced
-                    && x_distance >= state.double_jump_threshold(is_intermediate);
+                    && state.predicted_x_distance(x_distance, x_direction)
+                        >= state.double_jump_threshold(is_intermediate, x_direction);
                 let can_press = double_jumping.forced && x_changed <= FORCE_THRESHOLD;
                 if can_continue || can_press {
                     let _ = context
@@ -204,7 +223,7 @@ fn on_player_action(
         // ignore proximity check when it is forced to double jumped
         // this indicates the player is already near the destination
         PlayerAction::AutoMob(_) => {
-            on_auto_mob_use_key_action(context, action, moving.pos, x_distance, y_distance)
+            on_auto_mob_use_key_action(context, action, moving.pos, x_distance, y_distance, true)
         }
         PlayerAction::Key(PlayerActionKey {
             with: ActionKeyWith::DoubleJump | ActionKeyWith::Any,
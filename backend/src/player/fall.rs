@@ -8,6 +8,7 @@ use crate::{
     player::{
         MOVE_TIMEOUT, PlayerAction,
         actions::{on_action, on_auto_mob_use_key_action},
+        adjust::ADJUSTING_MEDIUM_THRESHOLD,
         state::LastMovement,
         timeout::{ChangeAxis, update_moving_axis_context},
     },
@@ -29,6 +30,9 @@ const TELEPORT_FALL_THRESHOLD: i32 = 14;
 /// position is below `anchor`. If `timeout_on_complete` is provided, it will timeout when the
 /// action is complete and return to [`Player::Moving`]. Timing out early is currently used by
 /// [`Player::DoubleJumping`] to perform a composite action `drop down and then double jump`.
+///
+/// While in the air, the direction toward the destination is held so the player lands on the
+/// destination platform instead of needing another horizontal movement after landing.
 pub fn update_falling_context(
     context: &Context,
     state: &mut PlayerState,
@@ -52,6 +56,7 @@ pub fn update_falling_context(
     let y_changed = cur_pos.y - anchor.y;
     let jump_key = state.config.jump_key;
     let teleport_key = state.config.teleport_key;
+    let prev_soft_landing_key = soft_landing_key(moving);
 
     update_moving_axis_context(
         moving,
@@ -70,11 +75,28 @@ pub fn update_falling_context(
         },
         Some(|| {
             let _ = context.keys.send_up(KeyKind::Down);
+            if let Some(key) = prev_soft_landing_key {
+                let _ = context.keys.send_up(key);
+            }
         }),
         |mut moving| {
             if moving.timeout.total == STOP_DOWN_KEY_TICK {
                 let _ = context.keys.send_up(KeyKind::Down);
             }
+            let soft_landing_key = soft_landing_key(moving);
+            if soft_landing_key != prev_soft_landing_key {
+                if let Some(key) = prev_soft_landing_key {
+                    let _ = context.keys.send_up(key);
+                }
+                if let Some(key) = soft_landing_key {
+                    let _ = context.keys.send_down(key);
+                }
+            }
+            let release_soft_landing_key = || {
+                if let Some(key) = soft_landing_key {
+                    let _ = context.keys.send_up(key);
+                }
+            };
             if !moving.completed && y_changed < 0 {
                 moving = moving.completed(true);
             } else if moving.completed && timeout_on_complete {
@@ -91,6 +113,7 @@ pub fn update_falling_context(
                             && y_direction >= 0
                         {
                             let _ = context.keys.send_up(KeyKind::Down);
+                            release_soft_landing_key();
                             return Some((
                                 Player::Moving(moving.dest, moving.exact, moving.intermediates),
                                 false,
@@ -98,7 +121,9 @@ pub fn update_falling_context(
                         }
                         let (x_distance, _) = moving.x_distance_direction_from(false, cur_pos);
                         let (y_distance, _) = moving.y_distance_direction_from(false, cur_pos);
-                        on_auto_mob_use_key_action(context, action, cur_pos, x_distance, y_distance)
+                        on_auto_mob_use_key_action(
+                            context, action, cur_pos, x_distance, y_distance, true,
+                        )
                     }
                     PlayerAction::Key(PlayerActionKey {
                         with: ActionKeyWith::Any,
@@ -107,6 +132,7 @@ pub fn update_falling_context(
                         if !moving.completed || y_direction < 0 {
                             return None;
                         }
+                        release_soft_landing_key();
                         Some((Player::UseKey(UseKey::from_action(action)), false))
                     }
                     PlayerAction::Key(PlayerActionKey {
@@ -123,6 +149,27 @@ pub fn update_falling_context(
     )
 }
 
+/// The direction key to hold toward the destination of `moving` while falling
+///
+/// The key is only held after [`KeyKind::Down`] is released to not interrupt the drop down and
+/// while the player is still above and not close enough horizontally to the destination.
+#[inline]
+fn soft_landing_key(moving: Moving) -> Option<KeyKind> {
+    if !moving.timeout.started || moving.timeout.total < STOP_DOWN_KEY_TICK {
+        return None;
+    }
+    let (x_distance, x_direction) = moving.x_distance_direction_from(true, moving.pos);
+    let (_, y_direction) = moving.y_distance_direction_from(true, moving.pos);
+    if y_direction >= 0 || x_distance <= ADJUSTING_MEDIUM_THRESHOLD {
+        return None;
+    }
+    Some(if x_direction > 0 {
+        KeyKind::Right
+    } else {
+        KeyKind::Left
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::assert_matches::assert_matches;
@@ -213,4 +260,49 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn falling_soft_landing() {
+        let mut keys = MockKeySender::new();
+        keys.expect_send_up()
+            .withf(|key| matches!(key, KeyKind::Down))
+            .once()
+            .returning(|_| Ok(()));
+        keys.expect_send_down()
+            .withf(|key| matches!(key, KeyKind::Right))
+            .once()
+            .returning(|_| Ok(()));
+        let context = Context::new(Some(keys), None);
+        let dest = Point::new(20, 2);
+        let mut state = PlayerState::default();
+        state.last_known_pos = Some(Point::new(5, 10));
+        let moving = Moving {
+            pos: Point::new(5, 11),
+            dest,
+            completed: true,
+            timeout: Timeout {
+                started: true,
+                total: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Hold right toward the destination after releasing down key
+        let player = update_falling_context(&context, &mut state, moving, Point::default(), false);
+        let Player::Falling(moving, _, _) = player else {
+            panic!("unexpected {player:?}");
+        };
+
+        // Release right once close enough to the destination
+        let mut keys = MockKeySender::new();
+        keys.expect_send_up()
+            .withf(|key| matches!(key, KeyKind::Right))
+            .once()
+            .returning(|_| Ok(()));
+        keys.expect_send_down().never();
+        let context = Context::new(Some(keys), None);
+        state.last_known_pos = Some(Point::new(18, 6));
+        update_falling_context(&context, &mut state, moving, Point::default(), false);
+    }
 }
@@ -79,7 +79,9 @@ pub fn update_grappling_context(
                         }
                         let (x_distance, _) = moving.x_distance_direction_from(false, cur_pos);
                         let (y_distance, _) = moving.y_distance_direction_from(false, cur_pos);
-                        on_auto_mob_use_key_action(context, action, cur_pos, x_distance, y_distance)
+                        on_auto_mob_use_key_action(
+                            context, action, cur_pos, x_distance, y_distance, false,
+                        )
                     }
                     PlayerAction::Key(_) | PlayerAction::Move(_) | PlayerAction::SolveRune => None,
                 },
@@ -39,16 +39,35 @@ fn on_player_action(
     let cur_pos = state.last_known_pos.unwrap();
     match action {
         PlayerAction::AutoMob(PlayerActionAutoMob { position, .. }) => {
+            if let Some(mut intermediates) = state.take_interrupted_normal_intermediates() {
+                debug!(
+                    target: "player",
+                    "resuming interrupted auto mob at intermediate {}",
+                    intermediates.current
+                );
+                state.last_destinations = Some(
+                    intermediates
+                        .inner
+                        .into_iter()
+                        .skip(intermediates.current)
+                        .map(|(point, _, _)| point)
+                        .collect(),
+                );
+                let (point, exact) = intermediates.next().unwrap();
+                return Some((Player::Moving(point, exact, Some(intermediates)), false));
+            }
             let point = Point::new(position.x, position.y);
             let intermediates = if state.config.auto_mob_platforms_pathing {
                 match context.minimap {
                     Minimap::Idle(idle) => find_intermediate_points(
                         &idle.platforms,
+                        &idle.portal_shortcuts(),
                         state.last_known_pos.unwrap(),
                         point,
                         position.allow_adjusting,
                         state.config.auto_mob_platforms_pathing_up_jump_only,
                         false,
+                        state.config.pathing_thresholds,
                     ),
                     _ => unreachable!(),
                 }
@@ -128,11 +147,13 @@ fn on_player_action(
                     }
                     let intermediates = find_intermediate_points(
                         &idle.platforms,
+                        &idle.portal_shortcuts(),
                         cur_pos,
                         rune,
                         true,
                         state.config.rune_platforms_pathing_up_jump_only,
                         true,
+                        state.config.pathing_thresholds,
                     );
                     if let Some(mut intermediates) = intermediates {
                         state.last_destinations = Some(
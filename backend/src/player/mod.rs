@@ -16,7 +16,7 @@ use strum::Display;
 use timeout::{Timeout, update_with_timeout};
 use unstuck::update_unstucking_context;
 use up_jump::update_up_jumping_context;
-use use_key::{UseKey, update_use_key_context};
+use use_key::{UseKey, UseKeyStage, update_use_key_context};
 
 use crate::{
     context::{Context, Contextual, ControlFlow},
@@ -43,8 +43,9 @@ mod use_key;
 
 pub use {
     actions::PlayerAction, actions::PlayerActionAutoMob, actions::PlayerActionKey,
-    actions::PlayerActionMove, double_jump::DOUBLE_JUMP_THRESHOLD,
-    grapple::GRAPPLING_MAX_THRESHOLD, grapple::GRAPPLING_THRESHOLD, state::PlayerState,
+    actions::PlayerActionMove, double_jump::DOUBLE_JUMP_OFFSET_RANGE,
+    double_jump::DOUBLE_JUMP_THRESHOLD, grapple::GRAPPLING_MAX_THRESHOLD,
+    grapple::GRAPPLING_THRESHOLD, moving::PathingThresholds, state::PlayerState,
 };
 
 /// Minimum y distance from the destination required to perform a jump
@@ -105,6 +106,33 @@ impl Player {
             | Player::Stalling(_, _) => false,
         }
     }
+
+    /// The intermediate points of the current movement if any
+    #[inline]
+    fn moving_intermediates(&self) -> Option<MovingIntermediates> {
+        match self {
+            Player::Moving(_, _, intermediates) => *intermediates,
+            Player::DoubleJumping(double_jumping) => double_jumping.intermediates(),
+            Player::Adjusting(moving)
+            | Player::Grappling(moving)
+            | Player::Jumping(moving)
+            | Player::UpJumping(moving)
+            | Player::Falling(moving, _, _) => moving.intermediates,
+            Player::Detecting
+            | Player::Idle
+            | Player::UseKey(_)
+            | Player::Unstucking(_, _, _)
+            | Player::Stalling(_, _)
+            | Player::SolvingRune(_)
+            | Player::CashShopThenExit(_, _) => None,
+        }
+    }
+
+    /// Whether [`Player::SolvingRune`] is waiting for the rune arrows submitted by the user
+    #[inline]
+    pub fn is_awaiting_rune_keys(&self) -> bool {
+        matches!(self, Player::SolvingRune(solving_rune) if solving_rune.awaiting_keys)
+    }
 }
 
 impl Contextual for Player {
@@ -112,6 +140,18 @@ impl Contextual for Player {
 
     // TODO: Detect if a point is reachable after number of retries?
     fn update(self, context: &Context, state: &mut PlayerState) -> ControlFlow<Self> {
+        let is_holding_key = matches!(
+            self,
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Holding(_),
+                ..
+            })
+        );
+        if !is_holding_key || state.reset_to_idle_next_update {
+            // Guarantees the key is released when holding is aborted
+            state.release_held_key(context);
+        }
+
         if state.rune_cash_shop {
             let _ = context.keys.send_up(KeyKind::Up);
             let _ = context.keys.send_up(KeyKind::Down);
@@ -163,6 +203,10 @@ impl Contextual for Player {
         };
 
         let contextual = if state.reset_to_idle_next_update {
+            if state.priority_interrupted_normal {
+                state.priority_interrupted_normal = false;
+                state.save_interrupted_normal_intermediates(self.moving_intermediates());
+            }
             Player::Idle
         } else {
             self
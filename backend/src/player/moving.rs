@@ -10,22 +10,53 @@ use super::{
     timeout::Timeout,
 };
 use crate::{
-    ActionKeyDirection, ActionKeyWith, MAX_PLATFORMS_COUNT,
+    ActionKeyDirection, ActionKeyWith, MAX_PLATFORMS_COUNT, PathingProfile,
     array::Array,
     context::Context,
     pathing::{MovementHint, PlatformWithNeighbors, find_points_with},
-    player::{
-        adjust::{ADJUSTING_MEDIUM_THRESHOLD, ADJUSTING_SHORT_THRESHOLD},
-        grapple::GRAPPLING_THRESHOLD,
-        on_action,
-        solve_rune::SolvingRune,
-        use_key::UseKey,
-    },
+    player::{grapple::GRAPPLING_THRESHOLD, on_action, solve_rune::SolvingRune, use_key::UseKey},
 };
 
 /// Maximum amount of ticks a change in x or y direction must be detected
 pub const MOVE_TIMEOUT: u32 = 5;
 
+/// The amount of ticks to wait for the player to appear at the portal exit after entering
+const ENTER_PORTAL_TICKS: u32 = 30;
+
+/// The platforms pathing thresholds resolved from a map [`PathingProfile`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PathingThresholds {
+    pub double_jump: i32,
+    pub jump: i32,
+    pub grappling: i32,
+    pub grappling_max: i32,
+}
+
+impl Default for PathingThresholds {
+    fn default() -> Self {
+        Self {
+            double_jump: DOUBLE_JUMP_THRESHOLD,
+            jump: JUMP_THRESHOLD,
+            grappling: GRAPPLING_THRESHOLD,
+            grappling_max: GRAPPLING_MAX_THRESHOLD,
+        }
+    }
+}
+
+impl From<PathingProfile> for PathingThresholds {
+    fn from(profile: PathingProfile) -> Self {
+        let default = PathingThresholds::default();
+        Self {
+            double_jump: profile.double_jump_threshold.unwrap_or(default.double_jump),
+            jump: profile.jump_threshold.unwrap_or(default.jump),
+            grappling: profile.grappling_threshold.unwrap_or(default.grappling),
+            grappling_max: profile
+                .grappling_max_threshold
+                .unwrap_or(default.grappling_max),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct MovingIntermediates {
     pub current: usize,
@@ -189,8 +220,8 @@ impl Moving {
                     && y_direction >= 0)
                     || (matches!(state.last_movement, Some(LastMovement::UpJumping))
                         && y_direction <= 0)
-                    || y_distance.abs() < JUMP_THRESHOLD;
-                x_distance < DOUBLE_JUMP_THRESHOLD && y_skippable
+                    || y_distance.abs() < state.config.pathing_thresholds.jump;
+                x_distance < state.config.pathing_thresholds.double_jump && y_skippable
             })
     }
 }
@@ -227,29 +258,30 @@ pub fn update_moving_context(
     }
 
     let cur_pos = state.last_known_pos.unwrap();
+    state.track_velocity_destination(dest);
     let moving = Moving::new(cur_pos, dest, exact, intermediates);
-    let (x_distance, _) = moving.x_distance_direction_from(true, cur_pos);
+    let (x_distance, x_direction) = moving.x_distance_direction_from(true, cur_pos);
     let (y_distance, y_direction) = moving.y_distance_direction_from(true, cur_pos);
     let skip_destination = moving.auto_mob_can_skip_current_destination(state);
     let is_intermediate = moving.is_destination_intermediate();
 
     match (skip_destination, x_distance, y_direction, y_distance) {
-        (false, d, _, _) if d >= state.double_jump_threshold(is_intermediate) => {
+        (false, d, _, _) if d >= state.double_jump_threshold(is_intermediate, x_direction) => {
             abort_action_on_state_repeat(
                 Player::DoubleJumping(DoubleJumping::new(moving, false, false)),
                 context,
                 state,
             )
         }
-        (false, d, _, _)
-            if d >= ADJUSTING_MEDIUM_THRESHOLD || (exact && d >= ADJUSTING_SHORT_THRESHOLD) =>
-        {
+        (false, d, _, _) if d >= state.adjusting_threshold(exact, is_intermediate) => {
             abort_action_on_state_repeat(Player::Adjusting(moving), context, state)
         }
         // y > 0: cur_pos is below dest
         // y < 0: cur_pos is above of dest
         (false, _, y, d)
-            if y > 0 && d >= GRAPPLING_THRESHOLD && !state.should_disable_grappling() =>
+            if y > 0
+                && d >= state.config.pathing_thresholds.grappling
+                && !state.should_disable_grappling() =>
         {
             abort_action_on_state_repeat(Player::Grappling(moving), context, state)
         }
@@ -260,7 +292,7 @@ pub fn update_moving_context(
                 && state.config.auto_mob_platforms_pathing
                 && state.config.auto_mob_platforms_pathing_up_jump_only
                 && intermediates.is_none()
-                && d >= GRAPPLING_THRESHOLD
+                && d >= state.config.pathing_thresholds.grappling
             {
                 debug!(target: "player", "auto mob aborted because distance for up jump only is too big");
                 state.clear_action_completed();
@@ -268,7 +300,7 @@ pub fn update_moving_context(
             }
             abort_action_on_state_repeat(Player::UpJumping(moving), context, state)
         }
-        (false, _, y, d) if y > 0 && d >= JUMP_THRESHOLD => {
+        (false, _, y, d) if y > 0 && d >= state.config.pathing_thresholds.jump => {
             abort_action_on_state_repeat(Player::Jumping(moving), context, state)
         }
         // this probably won't work if the platforms are far apart,
@@ -304,6 +336,12 @@ pub fn update_moving_context(
                     let _ = context.keys.send_down(key);
                     return Player::Stalling(Timeout::default(), 3);
                 }
+                if matches!(moving.intermediate_hint(), Some(MovementHint::EnterPortal)) {
+                    state.stalling_timeout_state =
+                        Some(Player::Moving(dest, exact, Some(intermediates)));
+                    let _ = context.keys.send(KeyKind::Up);
+                    return Player::Stalling(Timeout::default(), ENTER_PORTAL_TICKS);
+                }
 
                 return Player::Moving(dest, exact, Some(intermediates));
             }
@@ -384,32 +422,39 @@ fn on_player_action(
 #[inline]
 pub fn find_intermediate_points(
     platforms: &Array<PlatformWithNeighbors, MAX_PLATFORMS_COUNT>,
+    portals: &[(Point, Point)],
     cur_pos: Point,
     dest: Point,
     exact: bool,
     up_jump_only: bool,
     enable_hint: bool,
+    thresholds: PathingThresholds,
 ) -> Option<MovingIntermediates> {
     let vertical_threshold = if up_jump_only {
-        GRAPPLING_THRESHOLD
+        thresholds.grappling
     } else {
-        GRAPPLING_MAX_THRESHOLD
+        thresholds.grappling_max
     };
     let vec = find_points_with(
         platforms,
+        portals,
         cur_pos,
         dest,
         enable_hint,
-        DOUBLE_JUMP_THRESHOLD,
-        JUMP_THRESHOLD,
+        thresholds.double_jump,
+        thresholds.jump,
         vertical_threshold,
     )?;
     let len = vec.len();
-    let array = Array::from_iter(
-        vec.into_iter()
-            .enumerate()
-            .map(|(i, (point, hint))| (point, hint, if i == len - 1 { exact } else { false })),
-    );
+    let array = Array::from_iter(vec.into_iter().enumerate().map(|(i, (point, hint))| {
+        // Portals can only be entered when the player is within their narrow x range
+        let exact = if i == len - 1 {
+            exact
+        } else {
+            matches!(hint, MovementHint::EnterPortal)
+        };
+        (point, hint, exact)
+    }));
     Some(MovingIntermediates {
         current: 0,
         inner: array,
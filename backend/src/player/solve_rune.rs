@@ -5,6 +5,7 @@ use super::{Player, PlayerState, actions::PlayerAction};
 use crate::{
     context::Context,
     detect::{ArrowsCalibrating, ArrowsState},
+    network::NotificationKind,
     player::{
         on_action_state_mut,
         state::MAX_RUNE_FAILED_COUNT,
@@ -18,12 +19,19 @@ const SOLVE_START_TICK: u32 = 30;
 
 const PRESS_KEY_INTERVAL: u32 = 8;
 
+/// The maximum ticks to wait for the rune arrows submitted by the user
+const AWAITING_KEYS_TIMEOUT: u32 = 600;
+
 #[derive(Clone, Copy, Default, Debug)]
 pub struct SolvingRune {
     timeout: Timeout,
     keys: Option<[KeyKind; 4]>,
     key_index: usize,
     calibrating: ArrowsCalibrating,
+    /// Whether the arrows were detected with low confidence during calibration
+    low_confidence: bool,
+    /// Whether it is waiting for the rune arrows submitted by the user
+    pub(super) awaiting_keys: bool,
 }
 
 /// Updates the [`Player::SolvingRune`] contextual state
@@ -33,6 +41,8 @@ pub struct SolvingRune {
 /// - On timeout start, sends the interact key
 /// - On timeout update, detects the rune and sends the keys
 /// - On timeout end or rune is solved before timing out, transitions to `Player::Idle`
+/// - On timeout end with low confidence arrows and manual fallback enabled, notifies and
+///   awaits the arrows submitted by the user
 pub fn update_solving_rune_context(
     context: &Context,
     state: &mut PlayerState,
@@ -48,6 +58,11 @@ pub fn update_solving_rune_context(
             ..solving_rune
         })
     };
+    if solving_rune.awaiting_keys {
+        let next = update_awaiting_keys(state, solving_rune);
+        return on_solving_rune_action(state, solving_rune, next);
+    }
+
     let next = update_with_timeout(
         solving_rune.timeout,
         TIMEOUT,
@@ -56,6 +71,20 @@ pub fn update_solving_rune_context(
             update_timeout(timeout)
         },
         || {
+            if solving_rune.keys.is_none()
+                && solving_rune.low_confidence
+                && state.config.rune_manual_fallback
+            {
+                let _ = context
+                    .notification
+                    .schedule_notification(NotificationKind::RuneLowConfidence);
+                state.rune_submitted_keys = None;
+                return Player::SolvingRune(SolvingRune {
+                    timeout: Timeout::default(),
+                    awaiting_keys: true,
+                    ..solving_rune
+                });
+            }
             // likely a spinning rune if the bot can't detect and timeout
             Player::Idle
         },
@@ -92,6 +121,50 @@ pub fn update_solving_rune_context(
         },
     );
 
+    on_solving_rune_action(state, solving_rune, next)
+}
+
+/// Waits for the rune arrows submitted through [`PlayerState::submit_rune_keys`]
+///
+/// On receiving the arrows, resumes pressing the keys. Otherwise, transitions to `Player::Idle`
+/// on timeout end.
+fn update_awaiting_keys(state: &mut PlayerState, solving_rune: SolvingRune) -> Player {
+    update_with_timeout(
+        solving_rune.timeout,
+        AWAITING_KEYS_TIMEOUT,
+        |timeout| {
+            Player::SolvingRune(SolvingRune {
+                timeout,
+                ..solving_rune
+            })
+        },
+        || Player::Idle,
+        |timeout| match state.rune_submitted_keys.take() {
+            Some(keys) => Player::SolvingRune(SolvingRune {
+                // resumes after the solve start tick for pressing keys
+                timeout: Timeout {
+                    current: 1, // starts at 1 instead of 0 to avoid immediate key press
+                    total: SOLVE_START_TICK + 1,
+                    started: true,
+                },
+                keys: Some(keys),
+                awaiting_keys: false,
+                ..solving_rune
+            }),
+            None => Player::SolvingRune(SolvingRune {
+                timeout,
+                ..solving_rune
+            }),
+        },
+    )
+}
+
+#[inline]
+fn on_solving_rune_action(
+    state: &mut PlayerState,
+    solving_rune: SolvingRune,
+    next: Player,
+) -> Player {
     on_action_state_mut(
         state,
         |state, action| match action {
@@ -137,6 +210,11 @@ fn calibrate_rune_arrows(
             calibrating,
             ..solving_rune
         }),
+        ArrowsState::LowConfidence => Player::SolvingRune(SolvingRune {
+            timeout,
+            low_confidence: true,
+            ..solving_rune
+        }),
         ArrowsState::Complete(keys) => {
             Player::SolvingRune(SolvingRune {
                 // reset current timeout for pressing keys
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, range::Range};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+};
 
 use anyhow::Result;
 use log::debug;
@@ -7,17 +10,22 @@ use platforms::windows::KeyKind;
 use rand::seq::IteratorRandom;
 
 use super::{
-    DOUBLE_JUMP_THRESHOLD, JUMP_THRESHOLD, MOVE_TIMEOUT, Player, PlayerAction,
-    double_jump::DOUBLE_JUMP_AUTO_MOB_THRESHOLD, fall::FALLING_THRESHOLD, timeout::Timeout,
+    MOVE_TIMEOUT, Player, PlayerAction, PlayerActionAutoMob, PlayerActionKey, PlayerActionMove,
+    adjust::{ADJUSTING_MEDIUM_THRESHOLD, ADJUSTING_SHORT_THRESHOLD},
+    double_jump::{DOUBLE_JUMP_AUTO_MOB_THRESHOLD, DOUBLE_JUMP_MIN_THRESHOLD},
+    fall::FALLING_THRESHOLD,
+    moving::{MovingIntermediates, PathingThresholds},
+    timeout::Timeout,
 };
 use crate::{
-    ActionKeyDirection, Class,
+    ActionKeyDirection, AutoMobLearned, Class, TuningParameters,
     buff::{Buff, BuffKind},
-    context::Context,
+    context::{Context, MS_PER_TICK},
     detect::ArrowsState,
     minimap::Minimap,
     network::NotificationKind,
     player::timeout::update_with_timeout,
+    range::Range,
     task::{Task, Update, update_detection_task},
 };
 
@@ -35,6 +43,10 @@ const VERTICAL_MOVEMENT_REPEAT_COUNT: u32 = 8;
 /// always a y that has platform(s)
 const AUTO_MOB_REACHABLE_Y_SOLIDIFY_COUNT: u32 = 4;
 
+/// The minimum distance the player must move during a [`Player::Unstucking`] attempt for it
+/// to be considered successful
+const UNSTUCK_MOVED_THRESHOLD: i32 = 3;
+
 /// The number of times an auto-mob position has made the player aborted the auto-mob action
 ///
 /// If the count is reached, subsequent auto-mob position falling within the x range will be ignored
@@ -48,9 +60,6 @@ const AUTO_MOB_IGNORE_XS_RANGE: i32 = 3;
 /// The maximum of number points for auto mobbing to periodically move to
 const AUTO_MOB_MAX_PATHING_POINTS: usize = 3;
 
-/// The acceptable y range above and below the detected mob position when matched with a reachable y
-const AUTO_MOB_REACHABLE_Y_THRESHOLD: i32 = 10;
-
 /// The maximum number of times horizontal movement contextual state can be repeated in
 /// auto-mob before aborting.
 const AUTO_MOB_HORIZONTAL_MOVEMENT_REPEAT_COUNT: u32 = 4;
@@ -59,12 +68,14 @@ const AUTO_MOB_HORIZONTAL_MOVEMENT_REPEAT_COUNT: u32 = 4;
 /// auto-mob before aborting.
 const AUTO_MOB_VERTICAL_MOVEMENT_REPEAT_COUNT: u32 = 3;
 
-/// Maximum number of times [`Player::Moving`] state can be transitioned to
-/// without changing position
-const UNSTUCK_COUNT_THRESHOLD: u32 = 7;
+/// The number of recent positions used to estimate the player velocity
+const VELOCITY_SAMPLES: usize = 4;
 
-/// The number of times [`Player::Unstucking`] can be transitioned to before entering GAMBA MODE
-const UNSTUCK_GAMBA_MODE_COUNT: u32 = 3;
+/// The number of ticks the player is assumed to keep moving after releasing the direction keys
+const VELOCITY_PREDICTION_TICKS: f32 = 2.0;
+
+/// The minimum health drop ratio between two health updates to be considered a sudden damage
+const DAMAGE_HEALTH_DROP_RATIO: f32 = 0.1;
 
 /// The player previous movement-related contextual state
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
@@ -92,6 +103,8 @@ pub struct PlayerConfiguration {
     ///
     /// TODO: This shouldn't be here...
     pub auto_mob_platforms_bound: bool,
+    /// The platforms pathing thresholds of the current map
+    pub pathing_thresholds: PathingThresholds,
     /// The interact key
     pub interact_key: KeyKind,
     /// The RopeLift key
@@ -111,7 +124,24 @@ pub struct PlayerConfiguration {
     /// Uses potion when health is below a percentage
     pub use_potion_below_percent: Option<f32>,
     /// Milliseconds interval to update current health
+    ///
+    /// Also used as the interval to update current mana.
     pub update_health_millis: Option<u64>,
+    /// Detects the player mana for MP conditions
+    pub update_mana: bool,
+    /// Milliseconds to delay using key after taking a sudden damage with `0` indicating disabled
+    pub damage_grace_millis: u64,
+    /// Double jump threshold offset when moving left
+    pub double_jump_left_offset: i32,
+    /// Double jump threshold offset when moving right
+    pub double_jump_right_offset: i32,
+    /// Milliseconds of Erda Shower cooldown when its icon is not visible with `0` indicating
+    /// disabled
+    pub erda_shower_cooldown_millis: u64,
+    /// Runtime parameters tuned from [`crate::Settings::tuning`]
+    pub tuning: TuningParameters,
+    /// Waits for the rune arrows submitted by the user when detection confidence is low
+    pub rune_manual_fallback: bool,
 }
 
 /// The player persistent states
@@ -140,6 +170,12 @@ pub struct PlayerState {
     health_bar: Option<Rect>,
     /// The task for the health bar
     health_bar_task: Option<Task<Result<Rect>>>,
+    /// The player current mana and max mana
+    pub mana: Option<(u32, u32)>,
+    /// The task to update mana
+    mana_task: Option<Task<Result<(u32, u32)>>>,
+    /// The remaining ticks to delay using key after taking a sudden damage
+    pub(super) damage_grace_ticks: u32,
     /// Track if the player moved within a specified ticks to determine if the player is stationary
     is_stationary_timeout: Timeout,
     /// Whether the player is stationary
@@ -156,6 +192,10 @@ pub struct PlayerState {
     pub last_destinations: Option<Vec<Point>>,
     /// Last known position after each detection used for unstucking, also for displaying to UI
     pub last_known_pos: Option<Point>,
+    /// The most recent positions from oldest to newest for estimating the player velocity
+    recent_positions: VecDeque<Point>,
+    /// The destination [`Self::recent_positions`] are tracked for
+    recent_positions_dest: Option<Point>,
     /// Indicates whether to use [`ControlFlow::Immediate`] on this update
     pub(super) use_immediate_control_flow: bool,
     /// Indicates whether to ignore update_pos and use last_known_pos on next update
@@ -202,14 +242,22 @@ pub struct PlayerState {
     unstuck_count: u32,
     /// The number of times player transtioned to [`Player::Unstucking`]
     ///
-    /// Resets when threshold reached or position changed
+    /// Resets when threshold reached or an unstucking attempt moved the player
     unstuck_transitioned_count: u32,
+    /// The position before the current [`Player::Unstucking`] attempt and whether it moves right
+    pub(super) unstuck_attempt: Option<(Point, bool)>,
+    /// Whether the last [`Player::Unstucking`] attempt that did not move the player moved right
+    ///
+    /// The next attempt moves in the opposite direction.
+    pub(super) unstuck_failed_to_right: Option<bool>,
     /// Unstuck task for detecting settings when mis-pressing ESC key
     pub(super) unstuck_task: Option<Task<Result<bool>>>,
     /// Rune solving task
     pub(super) rune_task: Option<Task<Result<ArrowsState>>>,
     /// The number of times [`Player::SolvingRune`] failed
     pub(super) rune_failed_count: u32,
+    /// The rune arrows submitted by the user while [`Player::SolvingRune`] is awaiting keys
+    pub(super) rune_submitted_keys: Option<[KeyKind; 4]>,
     /// Indicates the state will be transitioned to [`Player::CashShopThenExit`] in the next tick
     pub(super) rune_cash_shop: bool,
     /// [`Timeout`] for validating whether the rune is solved
@@ -221,6 +269,19 @@ pub struct PlayerState {
     ///
     /// Resets when [`Player::Stalling`] timed out or in [`Player::Idle`]
     pub(super) stalling_timeout_state: Option<Player>,
+    /// The key and its modifier being held down by [`Player::UseKey`] for charge skills
+    ///
+    /// Released when holding completes or the player is no longer holding
+    pub(super) held_key: Option<(KeyKind, Option<KeyKind>)>,
+    /// Whether the current priority action interrupted an executing normal action
+    ///
+    /// Consumed on the next reset to [`Player::Idle`] to save the normal action progress.
+    pub(super) priority_interrupted_normal: bool,
+    /// The intermediate points of the normal action interrupted by a priority action keyed by
+    /// the normal action id
+    ///
+    /// Resumed once the priority action completes instead of moving from scratch.
+    interrupted_normal_intermediates: Option<(u32, MovingIntermediates)>,
 }
 
 impl PlayerState {
@@ -232,10 +293,30 @@ impl PlayerState {
         *self = PlayerState {
             config: self.config,
             reset_to_idle_next_update: true,
+            // Kept so that it is still released on next update
+            held_key: self.held_key,
             ..PlayerState::default()
         };
     }
 
+    /// Same as [`Self::reset`] but keeps the learned auto mobbing data
+    pub fn reset_keep_auto_mob_learned(&mut self) {
+        let learned = self.auto_mob_learned();
+        self.reset();
+        self.set_auto_mob_learned(&learned);
+    }
+
+    /// Releases the key and its modifier held down by [`Player::UseKey`] if there is one
+    #[inline]
+    pub(super) fn release_held_key(&mut self, context: &Context) {
+        if let Some((key, modifier)) = self.held_key.take() {
+            let _ = context.keys.send_up(key);
+            if let Some(modifier) = modifier {
+                let _ = context.keys.send_up(modifier);
+            }
+        }
+    }
+
     /// The normal action name for displaying to UI
     #[inline]
     pub fn normal_action_name(&self) -> Option<String> {
@@ -258,6 +339,7 @@ impl PlayerState {
     #[inline]
     pub fn set_normal_action(&mut self, id: u32, action: PlayerAction) {
         self.reset_to_idle_next_update = true;
+        self.interrupted_normal_intermediates = None;
         self.normal_action_id = id;
         self.normal_action = Some(action);
     }
@@ -266,6 +348,7 @@ impl PlayerState {
     #[inline]
     pub fn reset_normal_action(&mut self) {
         self.reset_to_idle_next_update = true;
+        self.interrupted_normal_intermediates = None;
         self.normal_action = None;
     }
 
@@ -310,6 +393,9 @@ impl PlayerState {
     pub fn replace_priority_action(&mut self, id: u32, action: PlayerAction) -> Option<u32> {
         let prev_id = self.priority_action_id;
         self.reset_to_idle_next_update = true;
+        if self.priority_action.is_none() && self.normal_action.is_some() {
+            self.priority_interrupted_normal = true;
+        }
         self.priority_action_id = id;
         self.priority_action
             .replace(action)
@@ -323,12 +409,36 @@ impl PlayerState {
         self.rune_validate_timeout.is_some()
     }
 
+    /// The number of times [`Player::SolvingRune`] failed
+    #[inline]
+    pub fn rune_failed_count(&self) -> u32 {
+        self.rune_failed_count
+    }
+
+    /// Submits the rune arrows typed by the user for [`Player::SolvingRune`] awaiting keys
+    #[inline]
+    pub fn submit_rune_keys(&mut self, keys: [KeyKind; 4]) {
+        self.rune_submitted_keys = Some(keys);
+    }
+
+    /// The number of times the player is detected stuck before unstucking
+    #[inline]
+    pub fn unstuck_count(&self) -> u32 {
+        self.unstuck_count
+    }
+
     /// Whether there is a priority rune action
     #[inline]
     pub fn has_rune_action(&self) -> bool {
         matches!(self.priority_action, Some(PlayerAction::SolveRune))
     }
 
+    /// Whether the player is recovering from a sudden damage and using key should be delayed
+    #[inline]
+    pub(super) fn is_recovering_from_damage(&self) -> bool {
+        self.damage_grace_ticks > 0
+    }
+
     /// Whether there is only auto mob action
     #[inline]
     pub(super) fn has_auto_mob_action_only(&self) -> bool {
@@ -339,6 +449,7 @@ impl PlayerState {
     #[inline]
     pub fn clear_actions_aborted(&mut self) {
         self.reset_to_idle_next_update = true;
+        self.interrupted_normal_intermediates = None;
         self.priority_action = None;
         self.normal_action = None;
     }
@@ -351,10 +462,41 @@ impl PlayerState {
             self.priority_action = None;
         } else {
             self.auto_mob_reachable_y = None;
+            self.interrupted_normal_intermediates = None;
             self.normal_action = None;
         }
     }
 
+    /// Saves the remaining `intermediates` of the normal action interrupted by a priority action
+    #[inline]
+    pub(super) fn save_interrupted_normal_intermediates(
+        &mut self,
+        intermediates: Option<MovingIntermediates>,
+    ) {
+        self.interrupted_normal_intermediates = intermediates
+            .filter(|intermediates| intermediates.current > 0)
+            .map(|intermediates| (self.normal_action_id, intermediates));
+    }
+
+    /// Takes the saved intermediates of the current normal action to resume from
+    ///
+    /// The returned intermediates continue from the point being moved to when interrupted.
+    #[inline]
+    pub(super) fn take_interrupted_normal_intermediates(&mut self) -> Option<MovingIntermediates> {
+        if self.has_priority_action() {
+            return None;
+        }
+        let (id, mut intermediates) = self.interrupted_normal_intermediates.take()?;
+        if self
+            .normal_action_id()
+            .is_none_or(|action_id| action_id != id)
+        {
+            return None;
+        }
+        intermediates.current -= 1;
+        Some(intermediates)
+    }
+
     /// Clears the last movement tracking for either normal or priority action
     #[inline]
     pub(super) fn clear_last_movement(&mut self) {
@@ -389,7 +531,7 @@ impl PlayerState {
     #[inline]
     pub(super) fn track_unstucking_transitioned(&mut self) -> bool {
         self.unstuck_transitioned_count += 1;
-        if self.unstuck_transitioned_count >= UNSTUCK_GAMBA_MODE_COUNT {
+        if self.unstuck_transitioned_count >= self.config.tuning.unstuck_gamba_mode_count {
             self.unstuck_transitioned_count = 0;
             true
         } else {
@@ -397,13 +539,33 @@ impl PlayerState {
         }
     }
 
+    /// Verifies whether the current [`Player::Unstucking`] attempt moved the player
+    ///
+    /// A moved player resets the transitioned counter so that GAMBA MODE is only entered after
+    /// consecutive failed attempts.
+    pub(super) fn verify_unstucking_attempt(&mut self) {
+        let Some((start, to_right)) = self.unstuck_attempt.take() else {
+            return;
+        };
+        let moved = self.last_known_pos.is_some_and(|pos| {
+            (pos.x - start.x).abs() >= UNSTUCK_MOVED_THRESHOLD
+                || (pos.y - start.y).abs() >= UNSTUCK_MOVED_THRESHOLD
+        });
+        if moved {
+            self.unstuck_transitioned_count = 0;
+            self.unstuck_failed_to_right = None;
+        } else {
+            self.unstuck_failed_to_right = Some(to_right);
+        }
+    }
+
     /// Increments the unstucking counter
     ///
     /// Returns `true` when the player should transition to [`Player::Unstucking`]
     #[inline]
     pub(super) fn track_unstucking(&mut self) -> bool {
         self.unstuck_count += 1;
-        if self.unstuck_count >= UNSTUCK_COUNT_THRESHOLD {
+        if self.unstuck_count >= self.config.tuning.unstuck_count_threshold {
             self.unstuck_count = 0;
             true
         } else {
@@ -460,7 +622,7 @@ impl PlayerState {
     #[inline]
     pub(super) fn falling_threshold(&self, is_intermediate: bool) -> i32 {
         if self.has_auto_mob_action_only() || is_intermediate {
-            JUMP_THRESHOLD
+            self.config.pathing_thresholds.jump
         } else {
             FALLING_THRESHOLD
         }
@@ -469,16 +631,87 @@ impl PlayerState {
     /// Gets the double jump minimum `x` distance threshold
     ///
     /// In auto mob and final destination, the threshold is relaxed for more
-    /// fluid movement.
+    /// fluid movement. The configured per-direction offset is then applied based on
+    /// `x_direction` to compensate for classes that drift further in one direction but never
+    /// below [`DOUBLE_JUMP_MIN_THRESHOLD`].
     #[inline]
-    pub(super) fn double_jump_threshold(&self, is_intermediate: bool) -> i32 {
-        if self.has_auto_mob_action_only() && !is_intermediate {
+    pub(super) fn double_jump_threshold(&self, is_intermediate: bool, x_direction: i32) -> i32 {
+        let threshold = if self.has_auto_mob_action_only() && !is_intermediate {
             DOUBLE_JUMP_AUTO_MOB_THRESHOLD
         } else {
-            DOUBLE_JUMP_THRESHOLD
+            self.config.pathing_thresholds.double_jump
+        };
+        let offset = match x_direction.cmp(&0) {
+            Ordering::Greater => self.config.double_jump_right_offset,
+            Ordering::Less => self.config.double_jump_left_offset,
+            Ordering::Equal => 0,
+        };
+        (threshold + offset).max(DOUBLE_JUMP_MIN_THRESHOLD)
+    }
+
+    /// The minimum x distance from the destination required to adjust
+    ///
+    /// For the final destination, the current action [`Position::arrival_tolerance`] takes
+    /// precedence over `exact`.
+    ///
+    /// [`Position::arrival_tolerance`]: crate::Position::arrival_tolerance
+    #[inline]
+    pub(super) fn adjusting_threshold(&self, exact: bool, is_intermediate: bool) -> i32 {
+        let position = match self.priority_action.or(self.normal_action) {
+            Some(PlayerAction::Move(PlayerActionMove { position, .. }))
+            | Some(PlayerAction::AutoMob(PlayerActionAutoMob { position, .. }))
+            | Some(PlayerAction::Key(PlayerActionKey {
+                position: Some(position),
+                ..
+            })) => Some(position),
+            _ => None,
+        };
+        match position.and_then(|position| position.arrival_tolerance) {
+            Some(tolerance) if !is_intermediate => tolerance as i32 + 1,
+            _ if exact => ADJUSTING_SHORT_THRESHOLD,
+            _ => ADJUSTING_MEDIUM_THRESHOLD,
+        }
+    }
+
+    /// Clears the recent positions when moving to a destination different from the last one
+    ///
+    /// The velocity of the previous move would otherwise carry over to the new move.
+    pub(super) fn track_velocity_destination(&mut self, dest: Point) {
+        if self.recent_positions_dest != Some(dest) {
+            self.recent_positions_dest = Some(dest);
+            self.recent_positions.clear();
         }
     }
 
+    /// Estimates the player velocity in pixels per tick from the recent positions
+    pub(super) fn velocity(&self) -> (f32, f32) {
+        let (Some(first), Some(last)) =
+            (self.recent_positions.front(), self.recent_positions.back())
+        else {
+            return (0.0, 0.0);
+        };
+        let ticks = self.recent_positions.len().saturating_sub(1).max(1) as f32;
+        (
+            (last.x - first.x) as f32 / ticks,
+            (last.y - first.y) as f32 / ticks,
+        )
+    }
+
+    /// Predicts the remaining x distance after the player keeps moving at the current velocity
+    ///
+    /// `x_distance` and `x_direction` are the current distance and direction to the destination.
+    /// The distance is only reduced when moving towards the destination and is `0` if the player
+    /// is predicted to reach or overshoot it. Releasing keys based on this distance reduces the
+    /// overshoot and correct oscillation around exact destinations.
+    pub(super) fn predicted_x_distance(&self, x_distance: i32, x_direction: i32) -> i32 {
+        let (x_velocity, _) = self.velocity();
+        if x_velocity == 0.0 || x_velocity.is_sign_positive() != x_direction.is_positive() {
+            return x_distance;
+        }
+        let travel = (x_velocity.abs() * VELOCITY_PREDICTION_TICKS).round() as i32;
+        (x_distance - travel).max(0)
+    }
+
     #[inline]
     pub(super) fn should_disable_grappling(&self) -> bool {
         // FIXME: ....
@@ -526,6 +759,51 @@ impl PlayerState {
         })
     }
 
+    /// The auto mobbing data learned so far for persisting
+    pub fn auto_mob_learned(&self) -> AutoMobLearned {
+        AutoMobLearned {
+            reachable_ys: self.auto_mob_reachable_y_map.clone(),
+            ignore_xs: self
+                .auto_mob_ignore_xs_map
+                .iter()
+                .map(|(y, ranges)| {
+                    let ranges = ranges
+                        .iter()
+                        .map(|(range, count)| (range.start, range.end, *count))
+                        .collect();
+                    (*y, ranges)
+                })
+                .collect(),
+            pathing_points: self
+                .auto_mob_pathing_points
+                .iter()
+                .map(|point| (point.x, point.y))
+                .collect(),
+        }
+    }
+
+    /// Restores the auto mobbing data previously learned from [`Self::auto_mob_learned`]
+    pub fn set_auto_mob_learned(&mut self, learned: &AutoMobLearned) {
+        self.auto_mob_reachable_y_map = learned.reachable_ys.clone();
+        self.auto_mob_reachable_y = None;
+        self.auto_mob_ignore_xs_map = learned
+            .ignore_xs
+            .iter()
+            .map(|(y, ranges)| {
+                let ranges = ranges
+                    .iter()
+                    .map(|(start, end, count)| ((*start..*end).into(), *count))
+                    .collect();
+                (*y, ranges)
+            })
+            .collect();
+        self.auto_mob_pathing_points = learned
+            .pathing_points
+            .iter()
+            .map(|(x, y)| Point::new(*x, *y))
+            .collect();
+    }
+
     /// Populates pathing points for an auto mob action
     ///
     /// After using key state is fully complete, it will try to populate a pathing point to be used
@@ -585,11 +863,12 @@ impl PlayerState {
         }
         debug_assert!(!self.auto_mob_reachable_y_map.is_empty());
 
+        let threshold = self.config.tuning.auto_mob_reachable_y_threshold;
         let ys = self
             .auto_mob_reachable_y_map
             .keys()
             .copied()
-            .filter(|y| (mob_pos.y - y).abs() <= AUTO_MOB_REACHABLE_Y_THRESHOLD);
+            .filter(|y| (mob_pos.y - y).abs() <= threshold);
         let y = ys.choose(&mut rand::rng());
 
         // Checking whether y is solidified yet is not needed because y will only be added
@@ -623,10 +902,9 @@ impl PlayerState {
             }
             _ => unreachable!(),
         }
-        let _ = self.auto_mob_reachable_y_map.try_insert(
-            self.last_known_pos.unwrap().y,
-            AUTO_MOB_REACHABLE_Y_SOLIDIFY_COUNT - 1,
-        );
+        self.auto_mob_reachable_y_map
+            .entry(self.last_known_pos.unwrap().y)
+            .or_insert(AUTO_MOB_REACHABLE_Y_SOLIDIFY_COUNT - 1);
         debug!(target: "player", "auto mob initial reachable y map {:?}", self.auto_mob_reachable_y_map);
     }
 
@@ -688,13 +966,14 @@ impl PlayerState {
 
         if is_aborted
             && vec.len() >= 2
-            && vec.iter().array_chunks::<2>().any(
-                |[(first_range, first_count), (second_range, second_count)]| {
-                    second_range.start < first_range.end
-                        && (*first_count >= AUTO_MOB_IGNORE_XS_SOLIDIFY_COUNT
-                            || *second_count >= AUTO_MOB_IGNORE_XS_SOLIDIFY_COUNT)
-                },
-            )
+            && vec.chunks_exact(2).any(|chunk| {
+                let [(first_range, first_count), (second_range, second_count)] = chunk else {
+                    unreachable!()
+                };
+                second_range.start < first_range.end
+                    && (*first_count >= AUTO_MOB_IGNORE_XS_SOLIDIFY_COUNT
+                        || *second_count >= AUTO_MOB_IGNORE_XS_SOLIDIFY_COUNT)
+            })
         {
             // Merge overlapping adjacent ranges with the same y
             let mut merged = Vec::<(Range<i32>, u32)>::new();
@@ -833,7 +1112,6 @@ impl PlayerState {
         let last_known_pos = self.last_known_pos.unwrap_or(pos);
         if last_known_pos != pos {
             self.unstuck_count = 0;
-            self.unstuck_transitioned_count = 0;
             self.is_stationary_timeout = Timeout::default();
         }
 
@@ -847,6 +1125,10 @@ impl PlayerState {
         self.is_stationary = is_stationary;
         self.is_stationary_timeout = is_stationary_timeout;
         self.last_known_pos = Some(pos);
+        if self.recent_positions.len() >= VELOCITY_SAMPLES {
+            self.recent_positions.pop_front();
+        }
+        self.recent_positions.push_back(pos);
         true
     }
 
@@ -879,7 +1161,7 @@ impl PlayerState {
         });
     }
 
-    /// Updates the player current health
+    /// Updates the player current health and mana
     ///
     /// The detection first detects the HP bar and caches the result. The HP bar is then used
     /// to crop into the game image and detects the current health bar and max health bar. These
@@ -887,16 +1169,19 @@ impl PlayerState {
     // TODO: This should be a PlayerAction?
     #[inline]
     fn update_health_state(&mut self, context: &Context) {
+        self.damage_grace_ticks = self.damage_grace_ticks.saturating_sub(1);
         if let Player::SolvingRune(_) = context.player {
             return;
         }
-        if self.config.use_potion_below_percent.is_none() {
+        if self.config.use_potion_below_percent.is_none() && !self.config.update_mana {
             {
                 let this = &mut *self;
                 this.health = None;
                 this.health_task = None;
                 this.health_bar = None;
                 this.health_bar_task = None;
+                this.mana = None;
+                this.mana_task = None;
             };
             return;
         }
@@ -911,6 +1196,12 @@ impl PlayerState {
             }
             return;
         };
+        self.update_mana_state(context, health_bar);
+        if self.config.use_potion_below_percent.is_none() {
+            self.health = None;
+            self.health_task = None;
+            return;
+        }
 
         let Update::Ok(health) = update_detection_task(
             context,
@@ -931,12 +1222,50 @@ impl PlayerState {
         let (current, max) = health;
         let ratio = current as f32 / max as f32;
 
+        if self.config.damage_grace_millis > 0
+            && let Some((prev_current, _)) = self.health
+            && prev_current > current
+            && (prev_current - current) as f32 / max as f32 >= DAMAGE_HEALTH_DROP_RATIO
+        {
+            debug!(target: "player", "sudden damage taken, delaying using key");
+            self.damage_grace_ticks = (self.config.damage_grace_millis / MS_PER_TICK) as u32;
+        }
         self.health = Some(health);
         if ratio <= percentage {
             let _ = context.keys.send(self.config.potion_key);
         }
     }
 
+    /// Updates the player current mana
+    ///
+    /// The MP bar is located from the cached HP bar and the current mana and max mana are
+    /// extracted the same way as health.
+    #[inline]
+    fn update_mana_state(&mut self, context: &Context, health_bar: Rect) {
+        if !self.config.update_mana {
+            self.mana = None;
+            self.mana_task = None;
+            return;
+        }
+
+        let update = update_detection_task(
+            context,
+            self.config.update_health_millis.unwrap_or(1000),
+            &mut self.mana_task,
+            move |detector| {
+                let mana_bar = detector.detect_player_mana_bar(health_bar)?;
+                let (current_bar, max_bar) =
+                    detector.detect_player_current_max_health_bars(mana_bar)?;
+                let mana = detector.detect_player_health(current_bar, max_bar)?;
+                debug!(target: "player", "mana updated {:?}", mana);
+                Ok(mana)
+            },
+        );
+        if let Update::Ok(mana) = update {
+            self.mana = Some(mana);
+        }
+    }
+
     /// Updates whether the player is dead
     ///
     /// Upon being dead, a notification will be scheduled to notify the user.
@@ -977,10 +1306,103 @@ mod tests {
         array::Array,
         context::Context,
         minimap::{Minimap, MinimapIdle},
-        pathing::{Platform, find_neighbors},
-        player::{PlayerAction, PlayerActionAutoMob, PlayerState},
+        pathing::{MovementHint, Platform, find_neighbors},
+        player::{
+            PlayerAction, PlayerActionAutoMob, PlayerActionMove, PlayerState,
+            double_jump::{DOUBLE_JUMP_MIN_THRESHOLD, DOUBLE_JUMP_THRESHOLD},
+            moving::MovingIntermediates,
+        },
     };
 
+    #[test]
+    fn adjusting_threshold_arrival_tolerance() {
+        let mut state = PlayerState::default();
+        assert_eq!(state.adjusting_threshold(true, false), 1);
+        assert_eq!(state.adjusting_threshold(false, false), 3);
+
+        state.normal_action = Some(PlayerAction::Move(PlayerActionMove {
+            position: Position {
+                arrival_tolerance: Some(5),
+                ..Default::default()
+            },
+            wait_after_move_ticks: 0,
+        }));
+        assert_eq!(state.adjusting_threshold(true, false), 6);
+        assert_eq!(state.adjusting_threshold(false, false), 6);
+        // Tolerance does not apply to intermediate points
+        assert_eq!(state.adjusting_threshold(true, true), 1);
+    }
+
+    #[test]
+    fn double_jump_threshold_direction_offset() {
+        let mut state = PlayerState::default();
+        state.config.double_jump_left_offset = 4;
+        state.config.double_jump_right_offset = -3;
+
+        assert_eq!(
+            state.double_jump_threshold(false, -1),
+            DOUBLE_JUMP_THRESHOLD + 4
+        );
+        assert_eq!(
+            state.double_jump_threshold(false, 1),
+            DOUBLE_JUMP_THRESHOLD - 3
+        );
+        assert_eq!(state.double_jump_threshold(false, 0), DOUBLE_JUMP_THRESHOLD);
+
+        state.config.double_jump_right_offset = -DOUBLE_JUMP_THRESHOLD;
+        assert_eq!(
+            state.double_jump_threshold(false, 1),
+            DOUBLE_JUMP_MIN_THRESHOLD
+        );
+    }
+
+    #[test]
+    fn double_jump_threshold_uses_pathing_thresholds() {
+        let mut state = PlayerState::default();
+        state.config.pathing_thresholds.double_jump = DOUBLE_JUMP_THRESHOLD + 10;
+        state.config.double_jump_right_offset = 2;
+
+        assert_eq!(
+            state.double_jump_threshold(false, 0),
+            DOUBLE_JUMP_THRESHOLD + 10
+        );
+        assert_eq!(
+            state.double_jump_threshold(false, 1),
+            DOUBLE_JUMP_THRESHOLD + 12
+        );
+    }
+
+    #[test]
+    fn predicted_x_distance_towards_destination_only() {
+        let state = PlayerState {
+            recent_positions: [0, 1, 2, 3].map(|x| Point::new(x, 0)).into(),
+            ..Default::default()
+        };
+
+        assert_eq!(state.velocity(), (1.0, 0.0));
+        assert_eq!(state.predicted_x_distance(5, 5), 3);
+        assert_eq!(state.predicted_x_distance(1, 1), 0);
+        // Moving away from the destination
+        assert_eq!(state.predicted_x_distance(5, -5), 5);
+        // Stationary
+        assert_eq!(PlayerState::default().predicted_x_distance(5, 5), 5);
+    }
+
+    #[test]
+    fn track_velocity_destination_clears_on_new_destination() {
+        let mut state = PlayerState {
+            recent_positions: [0, 1, 2, 3].map(|x| Point::new(x, 0)).into(),
+            ..Default::default()
+        };
+
+        state.track_velocity_destination(Point::new(10, 0));
+        assert_eq!(state.velocity(), (0.0, 0.0));
+
+        state.recent_positions = [0, 1, 2, 3].map(|x| Point::new(x, 0)).into();
+        state.track_velocity_destination(Point::new(10, 0));
+        assert_eq!(state.velocity(), (1.0, 0.0));
+    }
+
     #[test]
     fn auto_mob_pick_reachable_y_should_ignore_solidified_x_range() {
         let context = Context::new(None, None);
@@ -1109,6 +1531,81 @@ mod tests {
         assert_eq!(ranges, &vec![((55..65).into(), 2), ((63..75).into(), 1)])
     }
 
+    #[test]
+    fn resume_interrupted_normal_intermediates() {
+        let mut state = PlayerState::default();
+        let mut intermediates = MovingIntermediates {
+            current: 0,
+            inner: Array::from_iter([
+                (Point::new(10, 10), MovementHint::Infer, false),
+                (Point::new(20, 10), MovementHint::Infer, false),
+                (Point::new(30, 10), MovementHint::Infer, true),
+            ]),
+        };
+        intermediates.next();
+        intermediates.next();
+        state.set_normal_action(1, PlayerAction::AutoMob(PlayerActionAutoMob::default()));
+        state.set_priority_action(2, PlayerAction::SolveRune);
+        assert!(state.priority_interrupted_normal);
+
+        state.save_interrupted_normal_intermediates(Some(intermediates));
+        assert!(state.take_interrupted_normal_intermediates().is_none());
+
+        state.take_priority_action();
+        let mut resumed = state.take_interrupted_normal_intermediates().unwrap();
+        assert_eq!(resumed.next(), Some((Point::new(20, 10), false)));
+    }
+
+    #[test]
+    fn verify_unstucking_attempt() {
+        let mut state = PlayerState {
+            last_known_pos: Some(Point::new(50, 50)),
+            unstuck_transitioned_count: 1,
+            unstuck_attempt: Some((Point::new(49, 50), true)),
+            ..Default::default()
+        };
+
+        // Jittering in place is not moving
+        state.verify_unstucking_attempt();
+        assert_eq!(state.unstuck_attempt, None);
+        assert_eq!(state.unstuck_failed_to_right, Some(true));
+        assert_eq!(state.unstuck_transitioned_count, 1);
+
+        state.unstuck_attempt = Some((Point::new(40, 50), false));
+        state.verify_unstucking_attempt();
+        assert_eq!(state.unstuck_failed_to_right, None);
+        assert_eq!(state.unstuck_transitioned_count, 0);
+    }
+
+    #[test]
+    fn auto_mob_learned_round_trip() {
+        let player = PlayerState {
+            auto_mob_reachable_y_map: HashMap::from([(50, 4), (80, 1)]),
+            auto_mob_ignore_xs_map: HashMap::from([(50, vec![((53..58).into(), 3)])]),
+            auto_mob_pathing_points: vec![Point::new(10, 50)],
+            ..Default::default()
+        };
+        let learned = player.auto_mob_learned();
+        assert_eq!(learned.ignore_xs.get(&50), Some(&vec![(53, 58, 3)]));
+        assert_eq!(learned.pathing_points, vec![(10, 50)]);
+
+        let mut restored = PlayerState::default();
+        restored.set_auto_mob_learned(&learned);
+        assert_eq!(
+            restored.auto_mob_reachable_y_map,
+            player.auto_mob_reachable_y_map
+        );
+        assert_eq!(
+            restored.auto_mob_ignore_xs_map,
+            player.auto_mob_ignore_xs_map
+        );
+        assert_eq!(
+            restored.auto_mob_pathing_points,
+            player.auto_mob_pathing_points
+        );
+        assert_eq!(restored.auto_mob_learned(), learned);
+    }
+
     #[test]
     fn auto_mob_populate_ignore_xs_detects_gaps_correctly() {
         let platforms = vec![
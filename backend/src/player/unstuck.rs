@@ -26,6 +26,9 @@ const Y_IGNORE_THRESHOLD: i32 = 18;
 /// Each initial transition to [`Player::Unstucking`] increases
 /// the [`PlayerState::unstuck_consecutive_counter`] by one. If the threshold is reached, this
 /// state will enter GAMBA mode. And by definition, it means `random bullsh*t go`.
+///
+/// The position is verified after each attempt. The counter only resets when the player
+/// actually moved and the next attempt after a failed one moves in the opposite direction.
 pub fn update_unstucking_context(
     context: &Context,
     state: &mut PlayerState,
@@ -53,6 +56,24 @@ pub fn update_unstucking_context(
         .map(|pos| Point::new(pos.x, idle.bbox.height - pos.y));
     let gamba_mode = gamba_mode || pos.is_none();
 
+    let jump_key = state.config.jump_key;
+    let to_right = if timeout.started {
+        None
+    } else {
+        match (gamba_mode, pos, state.unstuck_failed_to_right) {
+            (true, _, _) => Some(rand::random_bool(0.5)),
+            (_, Some(Point { y, .. }), _) if y <= Y_IGNORE_THRESHOLD => None,
+            (_, Some(_), Some(failed_to_right)) => Some(!failed_to_right),
+            (_, Some(Point { x, .. }), None) => Some(x <= idle.bbox.width / 2),
+            (_, None, _) => unreachable!(),
+        }
+    };
+    if let Some(to_right) = to_right {
+        state.unstuck_attempt = state
+            .last_known_pos
+            .map(|last_known_pos| (last_known_pos, to_right));
+    }
+
     update_with_timeout(
         timeout,
         MOVE_TIMEOUT,
@@ -60,13 +81,8 @@ pub fn update_unstucking_context(
             if has_settings.unwrap_or_default() || gamba_mode {
                 let _ = context.keys.send(KeyKind::Esc);
             }
-            let to_right = match (gamba_mode, pos) {
-                (true, _) => rand::random_bool(0.5),
-                (_, Some(Point { y, .. })) if y <= Y_IGNORE_THRESHOLD => {
-                    return Player::Unstucking(timeout, has_settings, gamba_mode);
-                }
-                (_, Some(Point { x, .. })) => x <= idle.bbox.width / 2,
-                (_, None) => unreachable!(),
+            let Some(to_right) = to_right else {
+                return Player::Unstucking(timeout, has_settings, gamba_mode);
             };
             if to_right {
                 let _ = context.keys.send_down(KeyKind::Right);
@@ -78,6 +94,7 @@ pub fn update_unstucking_context(
         || {
             let _ = context.keys.send_up(KeyKind::Right);
             let _ = context.keys.send_up(KeyKind::Left);
+            state.verify_unstucking_attempt();
             Player::Detecting
         },
         |timeout| {
@@ -87,7 +104,7 @@ pub fn update_unstucking_context(
                 _ => false,
             };
             if send_space {
-                let _ = context.keys.send(state.config.jump_key);
+                let _ = context.keys.send(jump_key);
             }
             Player::Unstucking(timeout, has_settings, gamba_mode)
         },
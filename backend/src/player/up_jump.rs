@@ -25,7 +25,7 @@ const TELEPORT_UP_JUMP_THRESHOLD: i32 = 14;
 /// This state can only be transitioned via [`Player::Moving`] when the
 /// player has reached the destination x-wise. Before performing an up jump, it will check for
 /// stationary state and whether the player is currently near a portal. If the player is near
-/// a portal, this action is aborted. The up jump action is made to be adapted for various classes
+/// a dangerous portal, this action is aborted. The up jump action is made to be adapted for various classes
 /// that has different up jump key combination.
 pub fn update_up_jumping_context(
     context: &Context,
@@ -40,7 +40,8 @@ pub fn update_up_jumping_context(
     if !moving.timeout.started {
         if let Minimap::Idle(idle) = context.minimap {
             for portal in idle.portals {
-                if portal.x <= cur_pos.x
+                if idle.is_dangerous_portal(portal)
+                    && portal.x <= cur_pos.x
                     && cur_pos.x < portal.x + portal.width
                     && portal.y >= cur_pos.y
                     && portal.y - portal.height < cur_pos.y
@@ -136,7 +137,9 @@ pub fn update_up_jumping_context(
                         }
                         let (x_distance, _) = moving.x_distance_direction_from(false, cur_pos);
                         let (y_distance, _) = moving.y_distance_direction_from(false, cur_pos);
-                        on_auto_mob_use_key_action(context, action, cur_pos, x_distance, y_distance)
+                        on_auto_mob_use_key_action(
+                            context, action, cur_pos, x_distance, y_distance, true,
+                        )
                     }
                     PlayerAction::Key(PlayerActionKey {
                         with: ActionKeyWith::Any,
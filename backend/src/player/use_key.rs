@@ -9,8 +9,8 @@ use super::{
     double_jump::DoubleJumping,
 };
 use crate::{
-    ActionKeyDirection, ActionKeyWith, Class, KeyBinding, LinkKeyBinding,
-    context::Context,
+    ActionKeyDirection, ActionKeyWith, Class, KeyBinding, KeyModifier, LinkKeyBinding,
+    context::{Context, MS_PER_TICK},
     player::{
         LastMovement, MOVE_TIMEOUT, Moving, Player, on_action_state_mut, update_with_timeout,
     },
@@ -27,6 +27,8 @@ const LINK_ALONG_PRESS_TICK: u32 = 2;
 pub enum UseKeyStage {
     /// Checks whether [`ActionKeyWith`] and [`ActionKeyDirection`] are satisfied and stalls
     /// for [`UseKey::wait_before_use_ticks`]
+    ///
+    /// Waits in this stage while the player is recovering from a sudden damage
     Precondition,
     /// Changes direction to match [`ActionKeyDirection`]
     ///
@@ -39,7 +41,15 @@ pub enum UseKeyStage {
     EnsuringUseWith,
     /// Uses the actual key with optional [`LinkKeyBinding`] and stalls
     /// for [`UseKey::wait_after_use_ticks`]
+    ///
+    /// Transfers to [`UseKeyStage::Holding`] instead of stalling if [`UseKey::hold_ticks`] is
+    /// non-zero and the actual key is the last key used.
     Using(Timeout, bool),
+    /// Holds down the actual key for [`UseKey::hold_ticks`] before releasing and stalls
+    /// for [`UseKey::wait_after_use_ticks`]
+    ///
+    /// The key is also released by [`PlayerState::release_held_key`] when aborted.
+    Holding(Timeout),
     /// Ensures all [`UseKey::count`] times executed
     Postcondition,
 }
@@ -47,13 +57,18 @@ pub enum UseKeyStage {
 #[derive(Clone, Copy, Debug)]
 pub struct UseKey {
     key: KeyBinding,
+    modifier: KeyModifier,
     link_key: Option<LinkKeyBinding>,
+    link_key_modifier: KeyModifier,
     count: u32,
     current_count: u32,
     direction: ActionKeyDirection,
     with: ActionKeyWith,
     wait_before_use_ticks: u32,
     wait_after_use_ticks: u32,
+    hold_ticks: u32,
+    /// Whether to wait at least [`heavy_skill_cast_lock_ticks`] after use
+    heavy: bool,
     stage: UseKeyStage,
 }
 
@@ -67,7 +82,9 @@ impl UseKey {
         match action {
             PlayerAction::Key(PlayerActionKey {
                 key,
+                modifier,
                 link_key,
+                link_key_modifier,
                 count,
                 direction,
                 with,
@@ -75,6 +92,8 @@ impl UseKey {
                 wait_before_use_ticks_random_range,
                 wait_after_use_ticks,
                 wait_after_use_ticks_random_range,
+                hold_ticks,
+                heavy,
                 ..
             }) => {
                 let wait_before_min =
@@ -91,19 +110,25 @@ impl UseKey {
 
                 Self {
                     key,
+                    modifier,
                     link_key,
+                    link_key_modifier,
                     count,
                     current_count: 0,
                     direction,
                     with,
                     wait_before_use_ticks: wait_before,
                     wait_after_use_ticks: wait_after,
+                    hold_ticks,
+                    heavy,
                     stage: UseKeyStage::Precondition,
                 }
             }
             PlayerAction::AutoMob(mob) => Self {
                 key: mob.key,
+                modifier: KeyModifier::None,
                 link_key: None,
+                link_key_modifier: KeyModifier::None,
                 count: mob.count,
                 current_count: 0,
                 direction: match pos {
@@ -117,6 +142,8 @@ impl UseKey {
                 with: ActionKeyWith::Any,
                 wait_before_use_ticks: mob.wait_before_ticks,
                 wait_after_use_ticks: mob.wait_after_ticks,
+                hold_ticks: 0,
+                heavy: false,
                 stage: UseKeyStage::Precondition,
             },
             PlayerAction::SolveRune | PlayerAction::Move { .. } => {
@@ -142,6 +169,9 @@ pub fn update_use_key_context(
     let next = match use_key.stage {
         UseKeyStage::Precondition => {
             debug_assert!(use_key.current_count < use_key.count);
+            if state.is_recovering_from_damage() {
+                return Player::UseKey(use_key);
+            }
             if !ensure_direction(state, use_key.direction) {
                 return Player::UseKey(UseKey {
                     stage: UseKeyStage::ChangingDirection(Timeout::default()),
@@ -233,7 +263,7 @@ pub fn update_use_key_context(
             match use_key.link_key {
                 Some(LinkKeyBinding::After(_)) => {
                     if !timeout.started {
-                        let _ = context.keys.send(use_key.key.into());
+                        send_chord(context, use_key.modifier, use_key.key);
                     }
                     if !completed {
                         return update_link_key(
@@ -247,8 +277,11 @@ pub fn update_use_key_context(
                     }
                 }
                 Some(LinkKeyBinding::AtTheSame(key)) => {
-                    let _ = context.keys.send(key.into());
-                    let _ = context.keys.send(use_key.key.into());
+                    send_chord(context, use_key.link_key_modifier, key);
+                    if use_key.hold_ticks > 0 {
+                        return hold_chord(context, state, use_key);
+                    }
+                    send_chord(context, use_key.modifier, use_key.key);
                 }
                 Some(LinkKeyBinding::Along(_)) => {
                     if !completed {
@@ -274,20 +307,34 @@ pub fn update_use_key_context(
                         );
                     }
                     debug_assert!(use_key.link_key.is_none() || completed);
-                    let _ = context.keys.send(use_key.key.into());
+                    if use_key.hold_ticks > 0 {
+                        return hold_chord(context, state, use_key);
+                    }
+                    send_chord(context, use_key.modifier, use_key.key);
                 }
             }
-            let next = Player::UseKey(UseKey {
-                stage: UseKeyStage::Postcondition,
-                ..use_key
-            });
-            if use_key.wait_after_use_ticks > 0 {
-                state.stalling_timeout_state = Some(next);
-                Player::Stalling(Timeout::default(), use_key.wait_after_use_ticks)
-            } else {
-                next
-            }
+            stall_after_use(state, use_key)
         }
+        UseKeyStage::Holding(timeout) => update_with_timeout(
+            timeout,
+            use_key.hold_ticks,
+            |timeout| {
+                Player::UseKey(UseKey {
+                    stage: UseKeyStage::Holding(timeout),
+                    ..use_key
+                })
+            },
+            || {
+                state.release_held_key(context);
+                stall_after_use(state, use_key)
+            },
+            |timeout| {
+                Player::UseKey(UseKey {
+                    stage: UseKeyStage::Holding(timeout),
+                    ..use_key
+                })
+            },
+        ),
         UseKeyStage::Postcondition => {
             debug_assert!(state.stalling_timeout_state.is_none());
             if use_key.current_count + 1 < use_key.count {
@@ -370,8 +417,11 @@ fn update_link_key(
         link_key_timeout,
         |timeout| {
             if let LinkKeyBinding::Before(key) = link_key {
-                let _ = context.keys.send(key.into());
+                send_chord(context, use_key.link_key_modifier, key);
             } else if let LinkKeyBinding::Along(key) = link_key {
+                if let Some(modifier) = Option::<KeyKind>::from(use_key.link_key_modifier) {
+                    let _ = context.keys.send_down(modifier);
+                }
                 let _ = context.keys.send_down(key.into());
             }
             Player::UseKey(UseKey {
@@ -381,12 +431,15 @@ fn update_link_key(
         },
         || {
             if let LinkKeyBinding::After(key) = link_key {
-                let _ = context.keys.send(key.into());
+                send_chord(context, use_key.link_key_modifier, key);
                 if matches!(class, Class::Blaster) && KeyKind::from(key) != jump_key {
                     let _ = context.keys.send(jump_key);
                 }
             } else if let LinkKeyBinding::Along(key) = link_key {
                 let _ = context.keys.send_up(key.into());
+                if let Some(modifier) = Option::<KeyKind>::from(use_key.link_key_modifier) {
+                    let _ = context.keys.send_up(modifier);
+                }
             }
             Player::UseKey(UseKey {
                 stage: UseKeyStage::Using(timeout, true),
@@ -397,7 +450,7 @@ fn update_link_key(
             if matches!(link_key, LinkKeyBinding::Along(_))
                 && timeout.total == LINK_ALONG_PRESS_TICK
             {
-                let _ = context.keys.send(use_key.key.into());
+                send_chord(context, use_key.modifier, use_key.key);
             }
             Player::UseKey(UseKey {
                 stage: UseKeyStage::Using(timeout, completed),
@@ -407,35 +460,151 @@ fn update_link_key(
     )
 }
 
+/// Stalls for [`UseKey::wait_after_use_ticks`] before [`UseKeyStage::Postcondition`]
+///
+/// If [`UseKey::heavy`] is true, stalls for at least the class cast lock duration.
+#[inline]
+fn stall_after_use(state: &mut PlayerState, use_key: UseKey) -> Player {
+    let next = Player::UseKey(UseKey {
+        stage: UseKeyStage::Postcondition,
+        ..use_key
+    });
+    let wait_after_use_ticks = if use_key.heavy {
+        use_key
+            .wait_after_use_ticks
+            .max(heavy_skill_cast_lock_ticks(state.config.class))
+    } else {
+        use_key.wait_after_use_ticks
+    };
+    if wait_after_use_ticks > 0 {
+        state.stalling_timeout_state = Some(next);
+        Player::Stalling(Timeout::default(), wait_after_use_ticks)
+    } else {
+        next
+    }
+}
+
+/// The approximate ticks a heavy skill of `class` locks the player in its cast animation
+#[inline]
+fn heavy_skill_cast_lock_ticks(class: Class) -> u32 {
+    let millis = match class {
+        Class::Cadena => 450,
+        Class::Blaster => 600,
+        Class::Ark => 750,
+        Class::Generic => 900,
+    };
+    (millis / MS_PER_TICK) as u32
+}
+
+/// Holds down the actual key with its modifier and transfers to [`UseKeyStage::Holding`]
+#[inline]
+fn hold_chord(context: &Context, state: &mut PlayerState, use_key: UseKey) -> Player {
+    let modifier: Option<KeyKind> = use_key.modifier.into();
+    let key = use_key.key.into();
+    if let Some(modifier) = modifier {
+        let _ = context.keys.send_down(modifier);
+    }
+    let _ = context.keys.send_down(key);
+    state.held_key = Some((key, modifier));
+    Player::UseKey(UseKey {
+        stage: UseKeyStage::Holding(Timeout::default()),
+        ..use_key
+    })
+}
+
+/// Sends `key` while holding down `modifier` if there is one
+#[inline]
+fn send_chord(context: &Context, modifier: KeyModifier, key: KeyBinding) {
+    let modifier: Option<KeyKind> = modifier.into();
+    if let Some(modifier) = modifier {
+        let _ = context.keys.send_down(modifier);
+    }
+    let _ = context.keys.send(key.into());
+    if let Some(modifier) = modifier {
+        let _ = context.keys.send_up(modifier);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::assert_matches::assert_matches;
 
+    use mockall::Sequence;
     use platforms::windows::KeyKind;
 
     use crate::{
-        ActionKeyDirection, ActionKeyWith, KeyBinding, LinkKeyBinding,
+        ActionKeyDirection, ActionKeyWith, Class, KeyBinding, KeyModifier, LinkKeyBinding,
         bridge::MockKeySender,
         context::Context,
         player::{
             Player, PlayerState, Timeout, update_non_positional_context,
-            use_key::{UseKey, UseKeyStage, update_use_key_context},
+            use_key::{
+                UseKey, UseKeyStage, heavy_skill_cast_lock_ticks, stall_after_use,
+                update_use_key_context,
+            },
         },
     };
 
+    #[test]
+    fn use_key_delay_when_recovering_from_damage() {
+        let mut state = PlayerState::default();
+        state.damage_grace_ticks = 1;
+        let mut keys = MockKeySender::new();
+        keys.expect_send().never();
+        let context = Context::new(Some(keys), None);
+        let use_key = UseKey {
+            key: KeyBinding::A,
+            modifier: KeyModifier::None,
+            link_key: None,
+            link_key_modifier: KeyModifier::None,
+            count: 1,
+            current_count: 0,
+            direction: ActionKeyDirection::Any,
+            with: ActionKeyWith::Any,
+            wait_before_use_ticks: 0,
+            wait_after_use_ticks: 0,
+            hold_ticks: 0,
+            heavy: false,
+            stage: UseKeyStage::Precondition,
+        };
+
+        let player = update_use_key_context(&context, &mut state, use_key);
+        assert_matches!(
+            player,
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Precondition,
+                ..
+            })
+        );
+
+        state.damage_grace_ticks = 0;
+        let player = update_use_key_context(&context, &mut state, use_key);
+        assert_matches!(
+            player,
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Using(_, false),
+                ..
+            })
+        );
+    }
+
     #[test]
     fn use_key_ensure_use_with() {
         let mut state = PlayerState::default();
         let context = Context::new(None, None);
         let use_key = UseKey {
             key: KeyBinding::A,
+            modifier: KeyModifier::None,
             link_key: None,
+            link_key_modifier: KeyModifier::None,
             count: 1,
             current_count: 0,
             direction: ActionKeyDirection::Any,
             with: ActionKeyWith::Stationary,
             wait_before_use_ticks: 0,
             wait_after_use_ticks: 0,
+            hold_ticks: 0,
+            heavy: false,
             stage: UseKeyStage::Precondition,
         };
 
@@ -475,13 +644,17 @@ mod tests {
         let context = Context::new(Some(keys), None);
         let use_key = UseKey {
             key: KeyBinding::A,
+            modifier: KeyModifier::None,
             link_key: None,
+            link_key_modifier: KeyModifier::None,
             count: 1,
             current_count: 0,
             direction: ActionKeyDirection::Left,
             with: ActionKeyWith::Any,
             wait_before_use_ticks: 0,
             wait_after_use_ticks: 0,
+            hold_ticks: 0,
+            heavy: false,
             stage: UseKeyStage::Precondition,
         };
 
@@ -539,13 +712,17 @@ mod tests {
         let context = Context::new(Some(keys), None);
         let use_key = UseKey {
             key: KeyBinding::A,
+            modifier: KeyModifier::None,
             link_key: None,
+            link_key_modifier: KeyModifier::None,
             count: 100,
             current_count: 0,
             direction: ActionKeyDirection::Any,
             with: ActionKeyWith::Any,
             wait_before_use_ticks: 0,
             wait_after_use_ticks: 0,
+            hold_ticks: 0,
+            heavy: false,
             stage: UseKeyStage::Precondition,
         };
 
@@ -582,6 +759,123 @@ mod tests {
         }
     }
 
+    #[test]
+    fn use_key_chord() {
+        let mut keys = MockKeySender::new();
+        let mut sequence = Sequence::new();
+        keys.expect_send_down()
+            .once()
+            .in_sequence(&mut sequence)
+            .withf(|key| matches!(key, KeyKind::Shift))
+            .returning(|_| Ok(()));
+        keys.expect_send()
+            .once()
+            .in_sequence(&mut sequence)
+            .withf(|key| matches!(key, KeyKind::F1))
+            .returning(|_| Ok(()));
+        keys.expect_send_up()
+            .once()
+            .in_sequence(&mut sequence)
+            .withf(|key| matches!(key, KeyKind::Shift))
+            .returning(|_| Ok(()));
+        let mut state = PlayerState::default();
+        let context = Context::new(Some(keys), None);
+        let use_key = UseKey {
+            key: KeyBinding::F1,
+            modifier: KeyModifier::Shift,
+            link_key: None,
+            link_key_modifier: KeyModifier::None,
+            count: 1,
+            current_count: 0,
+            direction: ActionKeyDirection::Any,
+            with: ActionKeyWith::Any,
+            wait_before_use_ticks: 0,
+            wait_after_use_ticks: 0,
+            hold_ticks: 0,
+            heavy: false,
+            stage: UseKeyStage::Using(Timeout::default(), false),
+        };
+
+        assert_matches!(
+            update_use_key_context(&context, &mut state, use_key),
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Postcondition,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn use_key_hold() {
+        let mut keys = MockKeySender::new();
+        let mut sequence = Sequence::new();
+        keys.expect_send().never();
+        keys.expect_send_down()
+            .once()
+            .in_sequence(&mut sequence)
+            .withf(|key| matches!(key, KeyKind::A))
+            .returning(|_| Ok(()));
+        keys.expect_send_up()
+            .once()
+            .in_sequence(&mut sequence)
+            .withf(|key| matches!(key, KeyKind::A))
+            .returning(|_| Ok(()));
+        let mut state = PlayerState::default();
+        let context = Context::new(Some(keys), None);
+        let use_key = UseKey {
+            key: KeyBinding::A,
+            modifier: KeyModifier::None,
+            link_key: None,
+            link_key_modifier: KeyModifier::None,
+            count: 1,
+            current_count: 0,
+            direction: ActionKeyDirection::Any,
+            with: ActionKeyWith::Any,
+            wait_before_use_ticks: 0,
+            wait_after_use_ticks: 0,
+            hold_ticks: 2,
+            heavy: false,
+            stage: UseKeyStage::Using(Timeout::default(), false),
+        };
+
+        let mut player = update_use_key_context(&context, &mut state, use_key);
+        assert_matches!(
+            player,
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Holding(_),
+                ..
+            })
+        );
+        assert_eq!(state.held_key, Some((KeyKind::A, None)));
+
+        // started and two ticks of holding
+        for _ in 0..3 {
+            let Player::UseKey(use_key) = player else {
+                unreachable!()
+            };
+            player = update_use_key_context(&context, &mut state, use_key);
+            assert_matches!(
+                player,
+                Player::UseKey(UseKey {
+                    stage: UseKeyStage::Holding(_),
+                    ..
+                })
+            );
+        }
+
+        let Player::UseKey(use_key) = player else {
+            unreachable!()
+        };
+        assert_matches!(
+            update_use_key_context(&context, &mut state, use_key),
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Postcondition,
+                ..
+            })
+        );
+        assert_eq!(state.held_key, None);
+    }
+
     #[test]
     fn use_key_stalling() {
         let mut keys = MockKeySender::new();
@@ -592,13 +886,17 @@ mod tests {
         let context = Context::new(Some(keys), None);
         let use_key = UseKey {
             key: KeyBinding::A,
+            modifier: KeyModifier::None,
             link_key: None,
+            link_key_modifier: KeyModifier::None,
             count: 1,
             current_count: 0,
             direction: ActionKeyDirection::Any,
             with: ActionKeyWith::Any,
             wait_before_use_ticks: 10,
             wait_after_use_ticks: 20,
+            hold_ticks: 0,
+            heavy: false,
             stage: UseKeyStage::Precondition,
         };
 
@@ -646,19 +944,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn use_key_heavy_stall_at_least_cast_lock() {
+        let mut state = PlayerState::default();
+        state.config.class = Class::Ark;
+        let cast_lock_ticks = heavy_skill_cast_lock_ticks(Class::Ark);
+        let use_key = UseKey {
+            key: KeyBinding::A,
+            modifier: KeyModifier::None,
+            link_key: None,
+            link_key_modifier: KeyModifier::None,
+            count: 1,
+            current_count: 0,
+            direction: ActionKeyDirection::Any,
+            with: ActionKeyWith::Any,
+            wait_before_use_ticks: 0,
+            wait_after_use_ticks: 2,
+            hold_ticks: 0,
+            heavy: true,
+            stage: UseKeyStage::Using(Timeout::default(), true),
+        };
+
+        assert_matches!(
+            stall_after_use(&mut state, use_key),
+            Player::Stalling(_, ticks) if ticks == cast_lock_ticks
+        );
+        assert_matches!(
+            stall_after_use(
+                &mut state,
+                UseKey {
+                    wait_after_use_ticks: cast_lock_ticks + 5,
+                    ..use_key
+                }
+            ),
+            Player::Stalling(_, ticks) if ticks == cast_lock_ticks + 5
+        );
+        assert_matches!(
+            stall_after_use(
+                &mut state,
+                UseKey {
+                    heavy: false,
+                    ..use_key
+                }
+            ),
+            Player::Stalling(_, 2)
+        );
+    }
+
     #[test]
     fn use_key_link_along() {
         let mut state = PlayerState::default();
         let mut context = Context::new(None, None);
         let mut use_key = UseKey {
             key: KeyBinding::A,
+            modifier: KeyModifier::None,
             link_key: Some(LinkKeyBinding::Along(KeyBinding::Alt)),
+            link_key_modifier: KeyModifier::None,
             count: 1,
             current_count: 0,
             direction: ActionKeyDirection::Any,
             with: ActionKeyWith::Any,
             wait_before_use_ticks: 0,
             wait_after_use_ticks: 0,
+            hold_ticks: 0,
+            heavy: false,
             stage: UseKeyStage::Using(Timeout::default(), false),
         };
 
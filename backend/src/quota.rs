@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::error;
+
+use crate::database::{add_run_time_secs, query_run_time_secs};
+
+/// The interval between each run time persisted to the database while rotating
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Tracks the cumulative time spent rotating actions per day and week
+///
+/// Days and weeks are in UTC with weeks starting on Monday.
+#[derive(Debug)]
+pub struct RunTimeQuota {
+    /// The current day since Unix epoch
+    day: u64,
+    /// The run time in seconds of the current day persisted to the database
+    daily_secs: u64,
+    /// The run time in seconds of the current week persisted to the database
+    weekly_secs: u64,
+    /// The run time not yet persisted to the database
+    unsaved: Duration,
+    last_tick: Option<Instant>,
+    last_saved: Instant,
+}
+
+impl Default for RunTimeQuota {
+    fn default() -> Self {
+        let mut quota = Self {
+            day: current_day(),
+            daily_secs: 0,
+            weekly_secs: 0,
+            unsaved: Duration::ZERO,
+            last_tick: None,
+            last_saved: Instant::now(),
+        };
+        quota.load();
+        quota
+    }
+}
+
+impl RunTimeQuota {
+    /// Accumulates the run time since the last tick if `rotating`
+    ///
+    /// Returns `true` if rotating and either the daily or weekly quota in minutes is reached. A
+    /// quota of `0` is unlimited.
+    pub fn update(&mut self, rotating: bool, daily_minutes: u32, weekly_minutes: u32) -> bool {
+        let now = Instant::now();
+        let day = current_day();
+        if day != self.day {
+            self.save();
+            self.day = day;
+            self.load();
+        }
+
+        if !rotating {
+            self.last_tick = None;
+            if !self.unsaved.is_zero() {
+                self.save();
+            }
+            return false;
+        }
+        if let Some(last_tick) = self.last_tick.replace(now) {
+            self.unsaved += now.duration_since(last_tick);
+        }
+        if now.duration_since(self.last_saved) >= SAVE_INTERVAL {
+            self.save();
+        }
+
+        self.is_reached(daily_minutes, weekly_minutes)
+    }
+
+    fn is_reached(&self, daily_minutes: u32, weekly_minutes: u32) -> bool {
+        let unsaved_secs = self.unsaved.as_secs();
+        let daily_reached =
+            daily_minutes > 0 && self.daily_secs + unsaved_secs >= daily_minutes as u64 * 60;
+        let weekly_reached =
+            weekly_minutes > 0 && self.weekly_secs + unsaved_secs >= weekly_minutes as u64 * 60;
+        daily_reached || weekly_reached
+    }
+
+    fn load(&mut self) {
+        self.daily_secs = query_run_time_secs(self.day, self.day).unwrap_or_default();
+        self.weekly_secs =
+            query_run_time_secs(week_start_day(self.day), self.day).unwrap_or_default();
+    }
+
+    /// Persists the whole seconds of unsaved run time and keeps the remainder
+    fn save(&mut self) {
+        self.last_saved = Instant::now();
+        let secs = self.unsaved.as_secs();
+        if secs == 0 {
+            return;
+        }
+        if let Err(err) = add_run_time_secs(self.day, secs) {
+            error!(target: "quota", "failed to persist run time {err}");
+            return;
+        }
+        self.unsaved -= Duration::from_secs(secs);
+        self.daily_secs += secs;
+        self.weekly_secs += secs;
+    }
+}
+
+#[inline]
+fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / SECS_PER_DAY
+}
+
+/// Returns the Monday of the week containing `day`
+///
+/// Unix epoch is on a Thursday.
+#[inline]
+fn week_start_day(day: u64) -> u64 {
+    day.saturating_sub((day + 3) % 7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(daily_secs: u64, weekly_secs: u64, unsaved: Duration) -> RunTimeQuota {
+        RunTimeQuota {
+            day: 0,
+            daily_secs,
+            weekly_secs,
+            unsaved,
+            last_tick: None,
+            last_saved: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn week_start_day_is_monday() {
+        // 1970-01-05 is a Monday
+        assert_eq!(week_start_day(4), 4);
+        assert_eq!(week_start_day(10), 4);
+        assert_eq!(week_start_day(11), 11);
+        // 2025-01-01 (20089) is a Wednesday
+        assert_eq!(week_start_day(20089), 20087);
+    }
+
+    #[test]
+    fn is_reached_daily_or_weekly() {
+        assert!(!quota(3_000, 3_000, Duration::ZERO).is_reached(0, 0));
+        assert!(!quota(3_000, 3_000, Duration::from_secs(599)).is_reached(60, 0));
+        assert!(quota(3_000, 3_000, Duration::from_secs(600)).is_reached(60, 0));
+        assert!(quota(0, 6_000, Duration::ZERO).is_reached(60, 100));
+        assert!(!quota(0, 5_999, Duration::ZERO).is_reached(60, 100));
+    }
+}
@@ -0,0 +1,38 @@
+//! The [`Copy`] half-open range used for platform and ignored x ranges
+//!
+//! This is `core::range::Range` on nightly. With the `stable` feature, a minimal stand-in
+//! providing only what this crate uses is compiled instead.
+
+#[cfg(not(feature = "stable"))]
+pub use core::range::Range;
+
+#[cfg(feature = "stable")]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct Range<Idx> {
+    pub start: Idx,
+    pub end: Idx,
+}
+
+#[cfg(feature = "stable")]
+impl<Idx: Ord> Range<Idx> {
+    #[inline]
+    pub fn contains(&self, item: &Idx) -> bool {
+        self.start <= *item && *item < self.end
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+}
+
+#[cfg(feature = "stable")]
+impl<Idx> From<std::ops::Range<Idx>> for Range<Idx> {
+    #[inline]
+    fn from(value: std::ops::Range<Idx>) -> Self {
+        Self {
+            start: value.start,
+            end: value.end,
+        }
+    }
+}
@@ -1,22 +1,32 @@
+use std::collections::HashMap;
 #[cfg(debug_assertions)]
 use std::sync::LazyLock;
 #[cfg(debug_assertions)]
+use std::thread;
+#[cfg(debug_assertions)]
 use std::time::Instant;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use anyhow::{Result, anyhow, bail};
 #[cfg(debug_assertions)]
 use include_dir::{Dir, include_dir};
-use log::debug;
-use opencv::core::{MatTraitConst, MatTraitConstManual, Vec4b};
+use log::{debug, warn};
+use opencv::core::{MatTraitConst, MatTraitConstManual, Point, Vec4b};
 #[cfg(debug_assertions)]
 use opencv::{
     core::{Mat, ModifyInplace, Vector},
     imgcodecs::{IMREAD_COLOR, imdecode},
     imgproc::{COLOR_BGR2BGRA, cvt_color_def},
 };
-use platforms::windows::{Handle, KeyInputKind, KeyKind, KeyReceiver, query_capture_handles};
+use platforms::windows::{
+    Handle, KeyInputKind, KeyKind, KeyReceiver, ScheduledKey, query_capture_handles,
+};
 #[cfg(debug_assertions)]
 use rand::distr::{Alphanumeric, SampleString};
-use tokio::sync::broadcast;
+use tokio::sync::{
+    broadcast::{self, error::TryRecvError},
+    mpsc,
+};
 
 #[cfg(debug_assertions)]
 use crate::debug::{
@@ -25,22 +35,52 @@ use crate::debug::{
 #[cfg(debug_assertions)]
 use crate::detect::{ArrowsCalibrating, ArrowsState, CachedDetector, Detector};
 #[cfg(debug_assertions)]
+use crate::frame_history::FrameHistory;
+#[cfg(debug_assertions)]
 use crate::mat::OwnedMat;
 use crate::{
-    Action, ActionCondition, ActionKey, Bound, Configuration, GameState, KeyBinding,
-    KeyBindingConfiguration, Minimap as MinimapData, PotionMode, RequestHandler, Settings,
+    Action, ActionCondition, ActionKey, ActionMove, AutoMobLearned, Bound, CaptureHandle,
+    Configuration, GameState, GameStateDelta, GlobalAction, GlobalActionSlot, HaltEvent,
+    HaltReason, KeyBinding, KeyBindingConfiguration, KeyEvent, ManualControl,
+    Minimap as MinimapData, PlatformsBounds, Portal, Position, PotionMode, RequestHandler,
+    RequestItem, RotationMode, RotatorSchedule, Settings, TuningParameter,
     bridge::{ImageCapture, ImageCaptureKind, KeySenderMethod},
-    buff::{BuffKind, BuffState},
-    context::Context,
-    database::InputMethod,
+    buff::{Buff, BuffKind, BuffState},
+    comparison::AbComparison,
+    console::ConsoleCommand,
+    context::{Context, GAME_WINDOW_CLASS, preserve_state, preserved_state, to_png},
+    database::{
+        InputMethod, invalid_rows, query_auto_mob_learned, query_map_by_identity, query_maps,
+        repair_platforms, upsert_auto_mob_learned, upsert_map, upsert_settings,
+    },
+    detect::{
+        ResolutionPreset, is_minimap_model_available, is_mob_model_available,
+        is_rune_model_available,
+    },
+    key_log,
+    localization::localize,
     minimap::{Minimap, MinimapState},
-    player::PlayerState,
+    network::NotificationKind,
+    pathing::{self, find_neighbors, find_platforms_groups_bound, find_unreachable_points},
+    player::{PathingThresholds, PlayerAction, PlayerActionMove, PlayerState},
     poll_request,
-    rotator::Rotator,
-    skill::SkillKind,
+    rotator::{Rotator, resolve_anchored_positions, snap_erda_actions_to_platform_midpoints},
+    scheduler::Scheduler,
+    selected_client,
+    skill::{Skill, SkillKind},
+    state_stream::{GameStateSnapshot, GameStateStream},
+    transition_log::{self, PlayerTransition},
 };
 
+/// The number of ticks to hold a direction key for a [`ManualControl`] command
+const MANUAL_CONTROL_HOLD_TICKS: u32 = 10;
+
+/// The priority action id of [`ConsoleCommand::Goto`] that is not used by [`Rotator`]
+const CONSOLE_GOTO_ACTION_ID: u32 = u32::MAX - 1;
+
 pub struct DefaultRequestHandler<'a> {
+    /// The id of the client this handler belongs to
+    pub client: usize,
     pub context: &'a mut Context,
     pub config: &'a mut Configuration,
     pub settings: &'a mut Settings,
@@ -55,21 +95,124 @@ pub struct DefaultRequestHandler<'a> {
     pub image_capture: &'a mut ImageCapture,
     pub capture_handles: &'a mut Vec<(String, Handle)>,
     pub selected_capture_handle: &'a mut Option<Handle>,
+    pub ab_comparison: &'a mut AbComparison,
+    pub state_stream: &'a mut GameStateStream,
+    /// Receives the ids of the maps replaced by an import from the sync folder
+    pub map_imports: &'a mut broadcast::Receiver<i64>,
+    pub scheduler: &'a mut Scheduler,
     #[cfg(debug_assertions)]
     pub recording_images_id: &'a mut Option<String>,
+    /// Stores the frames before recording starts bounded by [`Settings::frame_history_budget_mb`]
+    #[cfg(debug_assertions)]
+    pub frame_history: &'a mut FrameHistory,
     #[cfg(debug_assertions)]
     pub infering_rune: &'a mut Option<(ArrowsCalibrating, Instant)>,
 }
 
 impl DefaultRequestHandler<'_> {
-    pub fn poll_request(&mut self) {
-        poll_request(self);
+    /// Handles a pending request from `requests`
+    ///
+    /// Returns `false` if the client is removed.
+    pub fn poll_request(&mut self, requests: &mut mpsc::Receiver<RequestItem>) -> bool {
+        poll_request(self, requests)
     }
 
     pub fn poll_key(&mut self) {
         poll_key(self);
     }
 
+    /// Switches to the other compared preset once the current preset has looped enough times
+    pub fn poll_ab_comparison(&mut self) {
+        let Some(preset) = self.ab_comparison.take_switch() else {
+            return;
+        };
+        let Some(minimap) = self.minimap.data() else {
+            return;
+        };
+        debug!(target: "handler", "switching compared preset to {preset}");
+        *self.actions = minimap.actions.get(&preset).cloned().unwrap_or_default();
+        self.rotator
+            .set_start_position(minimap.presets_start_position.get(&preset).copied());
+        // The current normal action belongs to the previous preset
+        self.player.reset_normal_action();
+        self.update_rotator_actions();
+    }
+
+    /// Pushes the streamed [`GameState`] fields changed in this tick to the UI
+    pub fn poll_state_stream(&mut self) {
+        if !self.state_stream.has_receivers() {
+            return;
+        }
+        self.state_stream.update(GameStateSnapshot {
+            position: self.player.last_known_pos.map(|pos| (pos.x, pos.y)),
+            state: localize(self.settings.language, &self.context.player.to_string()),
+            normal_action: self.player.normal_action_name(),
+            priority_action: self.player.priority_action_name(),
+            destinations: self.destinations(),
+        });
+    }
+
+    /// Reloads the current map from the database if it is replaced by an import
+    ///
+    /// The current preset is kept if the imported map still has it.
+    pub fn poll_map_imports(&mut self) {
+        let id = self.minimap.data().and_then(|data| data.id);
+        let mut imported = false;
+        loop {
+            match self.map_imports.try_recv() {
+                Ok(imported_id) => imported |= id == Some(imported_id),
+                // Reloads anyway since the skipped ids may include the current map
+                Err(TryRecvError::Lagged(_)) => imported = true,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+        let Some(id) = id.filter(|_| imported) else {
+            return;
+        };
+        let minimap = match query_maps() {
+            Ok(maps) => maps.into_iter().find(|map| map.id == Some(id)),
+            Err(err) => {
+                warn!(target: "handler", "failed to reload imported map {err}");
+                return;
+            }
+        };
+        let Some(minimap) = minimap else {
+            return;
+        };
+        let preset = preserved_state()
+            .minimap
+            .and_then(|(preset, _)| preset)
+            .filter(|preset| minimap.actions.contains_key(preset))
+            .or_else(|| minimap.actions.keys().next().cloned());
+        debug!(target: "handler", "reloading map {} replaced by import", minimap.name);
+        self.on_update_minimap(preset, minimap);
+    }
+
+    /// Looks up the saved map with the identity of the minimap when it is newly detected
+    ///
+    /// The found map is reloaded by the UI through [`GameState::detected_map_id`].
+    pub fn update_detected_map(&mut self, was_detecting: bool) {
+        let Minimap::Idle(idle) = self.context.minimap else {
+            self.minimap.set_detected_map_id(None);
+            return;
+        };
+        if !was_detecting {
+            return;
+        }
+        let Some(fingerprint) = idle.fingerprint else {
+            return;
+        };
+        match query_map_by_identity(idle.bbox.width, idle.bbox.height, fingerprint) {
+            Ok(map) => {
+                if let Some(map) = map.as_ref() {
+                    debug!(target: "handler", "detected minimap identified as map {}", map.name);
+                }
+                self.minimap.set_detected_map_id(map.and_then(|map| map.id));
+            }
+            Err(err) => warn!(target: "handler", "failed to query map by identity {err}"),
+        }
+    }
+
     #[cfg(debug_assertions)]
     pub fn poll_debug(&mut self) {
         if let Some((calibrating, instant)) = self.infering_rune.as_ref().copied() {
@@ -90,6 +233,10 @@ impl DefaultRequestHandler<'_> {
                     Ok(ArrowsState::Calibrating(calibrating)) => {
                         *self.infering_rune = Some((calibrating, instant));
                     }
+                    Ok(ArrowsState::LowConfidence) => {
+                        debug!(target: "debug", "infer rune result has low confidence");
+                        *self.infering_rune = None;
+                    }
                     Err(err) => {
                         debug!(target: "debug", "infer rune failed {err}");
                         *self.infering_rune = None;
@@ -105,9 +252,52 @@ impl DefaultRequestHandler<'_> {
                 false,
                 false,
             );
+        } else if self.settings.frame_history_budget_mb > 0
+            && let Ok(frame) = self.context.detector_unwrap().mat().try_clone()
+        {
+            self.frame_history.push(frame);
+        }
+    }
+
+    /// Halts actions and records `reason` as the [`HaltEvent`]
+    pub fn halt_with_reason(&mut self, reason: HaltReason) {
+        self.on_rotate_actions(true);
+        if self.context.halting {
+            debug!(target: "handler", "halted actions because {reason}");
+            self.context.halt_event = Some(HaltEvent {
+                reason,
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64,
+            });
         }
     }
 
+    /// The memory used by the current frame and frame history in bytes
+    fn memory_usage(&self) -> usize {
+        let frame = self
+            .context
+            .detector
+            .as_ref()
+            .map(|detector| detector.memory_usage())
+            .unwrap_or_default();
+        #[cfg(debug_assertions)]
+        let history = self.frame_history.memory_usage();
+        #[cfg(not(debug_assertions))]
+        let history = 0;
+        frame + history
+    }
+
+    /// The last pathing destinations of the player in minimap coordinates
+    fn destinations(&self) -> Vec<(i32, i32)> {
+        self.player
+            .last_destinations
+            .as_ref()
+            .map(|points| points.iter().map(|point| (point.x, point.y)).collect())
+            .unwrap_or_default()
+    }
+
     fn update_rotator_actions(&mut self) {
         let mode = self
             .minimap
@@ -120,17 +310,50 @@ impl DefaultRequestHandler<'_> {
             .data()
             .map(|minimap| minimap.actions_any_reset_on_erda_condition)
             .unwrap_or_default();
+        // Anchors are indices into the preset so they are resolved before merging
+        let mut preset_actions = self.actions.clone();
+        resolve_anchored_positions(&mut preset_actions);
+        let (mut actions, stable_ids) = merge_global_actions(
+            config_actions(self.config),
+            &preset_actions,
+            &self.settings.global_actions,
+        );
+        if let Some(minimap) = self.minimap.data()
+            && minimap.actions_erda_snap_to_platform_midpoint
+        {
+            snap_erda_actions_to_platform_midpoints(&mut actions, &minimap.platforms);
+        }
 
+        let (warm_up, warm_up_position) = self
+            .minimap
+            .data()
+            .map(|minimap| (minimap.actions_warm_up, minimap.actions_warm_up_position))
+            .unwrap_or_default();
+        self.rotator.set_warm_up(warm_up, warm_up_position);
+        self.rotator.set_safe_mode(
+            (self.settings.safe_mode_seconds > 0)
+                .then(|| Duration::from_secs(self.settings.safe_mode_seconds as u64)),
+        );
+        self.rotator.set_auto_mob_polygon(
+            self.minimap
+                .data()
+                .map(|minimap| {
+                    minimap
+                        .auto_mob_bound_polygon
+                        .iter()
+                        .copied()
+                        .map(Point::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        );
         self.rotator.build_actions(
             mode,
-            config_actions(self.config)
-                .into_iter()
-                .chain(self.actions.iter().copied())
-                .collect::<Vec<_>>()
-                .as_slice(),
+            actions.as_slice(),
+            &stable_ids,
             self.buffs,
             self.config.potion_key.key,
-            self.settings.enable_rune_solving,
+            self.settings.enable_rune_solving && is_rune_model_available(),
             reset_on_erda,
         );
     }
@@ -140,6 +363,7 @@ impl RequestHandler for DefaultRequestHandler<'_> {
     fn on_rotate_actions(&mut self, halting: bool) {
         if self.minimap.data().is_some() {
             self.context.halting = halting;
+            self.context.halt_event = None;
             if halting {
                 self.rotator.reset_queue();
                 self.player.clear_actions_aborted();
@@ -147,8 +371,32 @@ impl RequestHandler for DefaultRequestHandler<'_> {
         }
     }
 
-    fn on_rotate_actions_halting(&self) -> bool {
-        self.context.halting
+    fn on_rotate_actions_halting(&self) -> (bool, Option<HaltEvent>) {
+        (self.context.halting, self.context.halt_event)
+    }
+
+    fn on_toggle_auto_mob(&mut self) -> bool {
+        let paused = !self.rotator.auto_mob_paused();
+        self.rotator.set_auto_mob_paused(paused);
+        // Auto mobbing actions are the only normal actions with this id
+        if paused && self.player.normal_action_id() == Some(u32::MAX) {
+            self.player.reset_normal_action();
+        }
+        paused
+    }
+
+    fn on_clear_auto_mob_learned(&mut self) {
+        let learned = AutoMobLearned::default();
+        self.player.set_auto_mob_learned(&learned);
+        if let Some(id) = self.minimap.data().and_then(|minimap| minimap.id)
+            && let Err(err) = upsert_auto_mob_learned(id, &learned)
+        {
+            warn!(target: "handler", "failed to clear learned auto mobbing data {err}");
+        }
+    }
+
+    fn on_cancel_scheduled_start(&mut self) {
+        self.scheduler.cancel_pending_start();
     }
 
     fn on_create_minimap(&self, name: String) -> Option<MinimapData> {
@@ -157,6 +405,7 @@ impl RequestHandler for DefaultRequestHandler<'_> {
                 name,
                 width: idle.bbox.width,
                 height: idle.bbox.height,
+                fingerprint: idle.fingerprint,
                 ..MinimapData::default()
             })
         } else {
@@ -164,28 +413,115 @@ impl RequestHandler for DefaultRequestHandler<'_> {
         }
     }
 
-    fn on_update_minimap(&mut self, preset: Option<String>, minimap: MinimapData) {
+    fn on_update_minimap(&mut self, preset: Option<String>, mut minimap: MinimapData) {
+        // The learned data of the same map is more up-to-date than the saved one
+        let learned = match self.minimap.data() {
+            Some(current) if current.id.is_some() && current.id == minimap.id => {
+                self.player.auto_mob_learned()
+            }
+            _ => minimap
+                .id
+                .map(|id| {
+                    query_auto_mob_learned(id).unwrap_or_else(|err| {
+                        warn!(target: "handler", "failed to load learned auto mobbing data {err}");
+                        AutoMobLearned::default()
+                    })
+                })
+                .unwrap_or_default(),
+        };
+        let mut changed = false;
+        // Maps saved before identities were recorded take the identity of the detected minimap
+        // when selected while it is detected
+        if minimap.fingerprint.is_none()
+            && let Minimap::Idle(idle) = self.context.minimap
+            && idle.fingerprint.is_some()
+            && (idle.bbox.width, idle.bbox.height) == (minimap.width, minimap.height)
+        {
+            minimap.fingerprint = idle.fingerprint;
+            changed = true;
+        }
+        // Malformed manually entered platforms produce nonsense paths
+        let (platforms, repairs) =
+            repair_platforms(&minimap.platforms, minimap.width, minimap.height);
+        if !repairs.is_empty() {
+            for repair in repairs.iter() {
+                warn!(target: "handler", "map {} platforms {repair}", minimap.name);
+            }
+            minimap.platforms = platforms;
+            changed = true;
+        }
+        self.minimap
+            .set_platforms_repairs(repairs.iter().map(ToString::to_string).collect());
+        if changed
+            && minimap.id.is_some()
+            && let Err(err) = upsert_map(&mut minimap.clone())
+        {
+            warn!(target: "handler", "failed to persist repaired map {err}");
+        }
+        preserve_state(|state| state.minimap = Some((preset.clone(), minimap.clone())));
         self.minimap.set_data(minimap);
 
         let minimap = self.minimap.data().unwrap();
+        // Pathing without platforms produces degenerate paths so direct movement is used instead
+        let has_platforms = !minimap.platforms.is_empty();
+        if is_platforms_pathing_unavailable(minimap) {
+            warn!(
+                target: "handler",
+                "platforms pathing is enabled but map {} has no platforms, using direct movement",
+                minimap.name
+            );
+        }
         self.player.reset();
-        self.player.config.rune_platforms_pathing = minimap.rune_platforms_pathing;
+        self.player.set_auto_mob_learned(&learned);
+        self.player.config.rune_platforms_pathing = minimap.rune_platforms_pathing && has_platforms;
         self.player.config.rune_platforms_pathing_up_jump_only =
             minimap.rune_platforms_pathing_up_jump_only;
-        self.player.config.auto_mob_platforms_pathing = minimap.auto_mob_platforms_pathing;
+        self.player.config.auto_mob_platforms_pathing =
+            minimap.auto_mob_platforms_pathing && has_platforms;
         self.player.config.auto_mob_platforms_pathing_up_jump_only =
             minimap.auto_mob_platforms_pathing_up_jump_only;
         self.player.config.auto_mob_platforms_bound = minimap.auto_mob_platforms_bound;
+        self.player.config.pathing_thresholds = PathingThresholds::from(minimap.pathing_profile);
+        match (&preset, &minimap.ab_comparison_preset) {
+            (Some(preset_a), Some(preset_b))
+                if preset_a != preset_b
+                    && minimap.ab_comparison_loops > 0
+                    && minimap.actions.contains_key(preset_b) =>
+            {
+                self.ab_comparison.start(
+                    preset_a.clone(),
+                    preset_b.clone(),
+                    minimap.ab_comparison_loops,
+                );
+            }
+            _ => self.ab_comparison.stop(),
+        }
+        // Keeps rotating the compared preset that is currently active
+        let preset = self
+            .ab_comparison
+            .current_preset()
+            .map(str::to_string)
+            .or(preset);
         *self.actions = preset
-            .and_then(|preset| minimap.actions.get(&preset).cloned())
+            .as_ref()
+            .and_then(|preset| minimap.actions.get(preset).cloned())
             .unwrap_or_default();
+        let unreachable_actions = find_unreachable_actions(minimap, self.actions);
+        self.rotator.set_start_position(
+            preset.and_then(|preset| minimap.presets_start_position.get(&preset).copied()),
+        );
+        for unreachable in unreachable_actions.iter() {
+            warn!(target: "handler", "{unreachable}");
+        }
+        self.minimap.set_unreachable_actions(unreachable_actions);
         self.update_rotator_actions();
     }
 
     fn on_update_configuration(&mut self, config: Configuration) {
+        preserve_state(|state| state.config = Some(config.clone()));
         *self.config = config;
         *self.buffs = config_buffs(self.config);
-        self.player.reset();
+        self.player.reset_keep_auto_mob_learned();
         self.player.config.class = self.config.class;
         self.player.config.interact_key = self.config.interact_key.key.into();
         self.player.config.grappling_key = self.config.ropelift_key.key.into();
@@ -200,6 +536,18 @@ impl RequestHandler for DefaultRequestHandler<'_> {
                 (_, PotionMode::Percentage(percent)) => Some(percent / 100.0),
             };
         self.player.config.update_health_millis = Some(self.config.health_update_millis);
+        self.player.config.damage_grace_millis = self.config.damage_grace_millis;
+        self.player.config.update_mana = self.config.mana_detection;
+        self.player.config.double_jump_left_offset = self.config.double_jump_left_offset;
+        self.player.config.double_jump_right_offset = self.config.double_jump_right_offset;
+        self.player.config.erda_shower_cooldown_millis = self.config.erda_shower_cooldown_millis;
+        self.context.keys.set_min_repeat_intervals(
+            self.config
+                .key_repeat_intervals
+                .iter()
+                .map(|interval| (interval.key.into(), interval.millis))
+                .collect(),
+        );
         self.buff_states.iter_mut().for_each(|state| {
             state.update_enabled_state(self.config, self.settings);
         });
@@ -207,6 +555,7 @@ impl RequestHandler for DefaultRequestHandler<'_> {
     }
 
     fn on_update_settings(&mut self, settings: Settings) {
+        preserve_state(|state| state.settings = Some(settings.clone()));
         let handle_or_default = self.selected_capture_handle.unwrap_or(self.context.handle);
 
         if settings.capture_mode != self.settings.capture_mode {
@@ -235,7 +584,33 @@ impl RequestHandler for DefaultRequestHandler<'_> {
                 settings.input_method_rpc_server_url.clone(),
             ));
         }
+        if settings.foreground_interlock != self.settings.foreground_interlock {
+            self.context.keys.set_foreground_interlock(
+                (!settings.foreground_interlock.is_empty())
+                    .then(|| settings.foreground_interlock.clone()),
+            );
+        }
+        if settings.max_keys_per_second != self.settings.max_keys_per_second {
+            self.context
+                .keys
+                .set_max_keys_per_second(settings.max_keys_per_second);
+        }
+
+        #[cfg(debug_assertions)]
+        self.frame_history
+            .set_budget(settings.frame_history_budget_mb as usize * 1024 * 1024);
 
+        if settings.notifications != self.settings.notifications {
+            self.context.notification.refresh(&settings.notifications);
+        }
+
+        self.player.config.tuning = settings.tuning;
+        self.player.config.rune_manual_fallback =
+            settings.notifications.notify_on_rune_low_confidence;
+        self.minimap.set_stranger_grace(
+            settings.stranger_grace_count,
+            settings.stranger_grace_window_millis,
+        );
         *self.settings = settings;
         self.buff_states.iter_mut().for_each(|state| {
             state.update_enabled_state(self.config, self.settings);
@@ -250,24 +625,94 @@ impl RequestHandler for DefaultRequestHandler<'_> {
 
     #[inline]
     fn on_game_state(&self) -> GameState {
+        let resolution = self
+            .context
+            .detector
+            .as_ref()
+            .and_then(|detector| detector.mat().size().ok());
         GameState {
             position: self.player.last_known_pos.map(|pos| (pos.x, pos.y)),
             health: self.player.health,
-            state: self.context.player.to_string(),
+            mana: self.player.mana,
+            detected_map_id: self.minimap.detected_map_id(),
+            detected_map_identity: match self.context.minimap {
+                Minimap::Idle(idle) => idle
+                    .fingerprint
+                    .map(|fingerprint| (idle.bbox.width, idle.bbox.height, fingerprint)),
+                _ => None,
+            },
+            platforms_repairs: self.minimap.platforms_repairs().to_vec(),
+            unreachable_actions: self.minimap.unreachable_actions().to_vec(),
+            invalid_database_rows: invalid_rows(),
+            state: localize(self.settings.language, &self.context.player.to_string()),
             normal_action: self.player.normal_action_name(),
             priority_action: self.player.priority_action_name(),
-            erda_shower_state: self.context.skills[SkillKind::ErdaShower].to_string(),
-            destinations: self
-                .player
-                .last_destinations
-                .clone()
-                .map(|points| {
-                    points
-                        .into_iter()
-                        .map(|point| (point.x, point.y))
-                        .collect::<Vec<_>>()
+            erda_shower_state: localize(
+                self.settings.language,
+                &self.context.skills[SkillKind::ErdaShower].to_string(),
+            ),
+            erda_shower_cooldown: matches!(
+                self.context.skills[SkillKind::ErdaShower],
+                Skill::Cooldown
+            ),
+            buffs: self
+                .buffs
+                .iter()
+                .map(|(kind, _)| {
+                    (
+                        localize(self.settings.language, &kind.to_string()),
+                        matches!(self.context.buffs[*kind], Buff::HasBuff),
+                    )
+                })
+                .collect(),
+            has_rune: matches!(self.context.minimap, Minimap::Idle(idle) if idle.rune.is_some()),
+            rune_failed_count: self.player.rune_failed_count(),
+            rune_awaiting_keys: self.context.player.is_awaiting_rune_keys(),
+            stranger_episodes: self.minimap.stranger_episodes(),
+            auto_mob_paused: self.rotator.auto_mob_paused(),
+            stranger_count: self.minimap.stranger_count(),
+            distinct_strangers: self.minimap.distinct_strangers(),
+            unstuck_count: self.player.unstuck_count(),
+            auto_mob_bound: self.minimap.data().and_then(|minimap| {
+                if let RotationMode::AutoMobbing(auto_mobbing) = minimap.rotation_mode {
+                    Some(auto_mobbing.bound)
+                } else {
+                    None
+                }
+            }),
+            auto_mob_bound_polygon: self
+                .minimap
+                .data()
+                .filter(|minimap| {
+                    matches!(minimap.rotation_mode, RotationMode::AutoMobbing(_))
+                        && minimap.auto_mob_bound_polygon.len() >= 3
+                })
+                .map(|minimap| {
+                    minimap
+                        .auto_mob_bound_polygon
+                        .iter()
+                        .map(|vertex| (vertex.x, vertex.y))
+                        .collect()
                 })
                 .unwrap_or_default(),
+            destinations: self.destinations(),
+            memory_usage: self.memory_usage(),
+            resolution: resolution.map(|size| (size.width, size.height)),
+            resolution_supported: resolution
+                .is_some_and(|size| ResolutionPreset::from_supported(size).is_some()),
+            capture_health: self.image_capture.health(),
+            platforms_pathing_unavailable: self
+                .minimap
+                .data()
+                .is_some_and(is_platforms_pathing_unavailable),
+            mob_model_available: is_mob_model_available(),
+            minimap_model_available: is_minimap_model_available(),
+            rune_model_available: is_rune_model_available(),
+            halt_event: self.context.halt_event,
+            scheduled_start_pending: self.scheduler.has_pending_start(),
+            portals: detected_portals(self.context, self.minimap.data()),
+            ab_comparison_preset: self.ab_comparison.current_preset().map(str::to_string),
+            ab_comparison: self.ab_comparison.stats().to_vec(),
         }
     }
 
@@ -280,6 +725,15 @@ impl RequestHandler for DefaultRequestHandler<'_> {
             .and_then(|mat| extract_minimap(self.context, mat))
     }
 
+    fn on_screenshot(&self) -> Option<Vec<u8>> {
+        to_png(
+            self.context
+                .detector
+                .as_ref()
+                .map(|detector| detector.mat()),
+        )
+    }
+
     fn on_minimap_platforms_bound(&self) -> Option<Bound> {
         if let Minimap::Idle(idle) = self.context.minimap {
             idle.platforms_bound.map(|bound| bound.into())
@@ -288,19 +742,44 @@ impl RequestHandler for DefaultRequestHandler<'_> {
         }
     }
 
+    fn on_minimap_platforms_bounds(&self) -> PlatformsBounds {
+        if let Minimap::Idle(idle) = self.context.minimap {
+            PlatformsBounds {
+                groups: find_platforms_groups_bound(idle.bbox, &idle.platforms)
+                    .into_iter()
+                    .map(Bound::from)
+                    .collect(),
+                union: idle.platforms_bound.map(|bound| bound.into()),
+            }
+        } else {
+            PlatformsBounds::default()
+        }
+    }
+
+    fn on_rotator_schedule(&self) -> RotatorSchedule {
+        self.rotator.schedule()
+    }
+
     #[inline]
     fn on_key_receiver(&self) -> broadcast::Receiver<KeyBinding> {
         self.key_sender.subscribe()
     }
 
-    fn on_query_capture_handles(&mut self) -> (Vec<String>, Option<usize>) {
+    fn on_game_state_receiver(&self) -> broadcast::Receiver<GameStateDelta> {
+        self.state_stream.subscribe()
+    }
+
+    fn on_query_capture_handles(&mut self) -> (Vec<CaptureHandle>, Option<usize>) {
         *self.capture_handles = query_capture_handles();
 
-        let names = self
+        let handles = self
             .capture_handles
             .iter()
-            .map(|(name, _)| name)
-            .cloned()
+            .map(|(title, handle)| CaptureHandle {
+                title: title.clone(),
+                process_id: handle.process_id(),
+                is_game: handle.is_class(GAME_WINDOW_CLASS),
+            })
             .collect::<Vec<_>>();
         let selected = if let Some(selected_handle) = self.selected_capture_handle {
             self.capture_handles
@@ -311,7 +790,7 @@ impl RequestHandler for DefaultRequestHandler<'_> {
         } else {
             None
         };
-        (names, selected)
+        (handles, selected)
     }
 
     fn on_select_capture_handle(&mut self, index: Option<usize>) {
@@ -332,6 +811,174 @@ impl RequestHandler for DefaultRequestHandler<'_> {
         }
     }
 
+    fn on_query_client_state(
+        &self,
+    ) -> (Option<Configuration>, Option<(Option<String>, MinimapData)>) {
+        let state = preserved_state();
+        (state.config, state.minimap)
+    }
+
+    fn on_tune_parameter(&mut self, parameter: TuningParameter, value: u64) -> Option<Settings> {
+        let mut settings = self.settings.clone();
+        if !settings.tuning.set(parameter, value) {
+            debug!(target: "handler", "rejected tuning {parameter} to {value}");
+            return None;
+        }
+        if let Err(err) = upsert_settings(&mut settings) {
+            debug!(target: "handler", "failed to persist tuning {parameter} {err}");
+            return None;
+        }
+        debug!(target: "handler", "tuned {parameter} to {value}");
+        preserve_state(|state| state.settings = Some(settings.clone()));
+        self.player.config.tuning = settings.tuning;
+        *self.settings = settings.clone();
+        Some(settings)
+    }
+
+    fn on_key_events(&self) -> Vec<KeyEvent> {
+        key_log::events()
+    }
+
+    fn on_export_key_events(&self) -> Option<String> {
+        key_log::export_events(self.client)
+            .inspect_err(|err| debug!(target: "handler", "failed to export key events {err}"))
+            .ok()
+            .map(|path| path.to_string_lossy().to_string())
+    }
+
+    fn on_player_transitions(&self, count: usize) -> Vec<PlayerTransition> {
+        transition_log::transitions(count)
+    }
+
+    fn on_test_notification(&self) -> Result<()> {
+        self.context
+            .notification
+            .schedule_notification_with(NotificationKind::Test, self.settings)
+    }
+
+    fn on_calibrate_buffs_region(&mut self) -> Option<Settings> {
+        let offset = self
+            .context
+            .detector
+            .as_ref()?
+            .detect_buffs_region_offset()
+            .inspect_err(|err| debug!(target: "handler", "failed to calibrate buffs region {err}"))
+            .ok()?;
+        let mut settings = self.settings.clone();
+        settings.buffs_region_offset_x = offset.x;
+        settings.buffs_region_offset_y = offset.y;
+        if let Err(err) = upsert_settings(&mut settings) {
+            debug!(target: "handler", "failed to persist buffs region offset {err}");
+            return None;
+        }
+        debug!(target: "handler", "calibrated buffs region offset to {offset:?}");
+        preserve_state(|state| state.settings = Some(settings.clone()));
+        *self.settings = settings.clone();
+        Some(settings)
+    }
+
+    fn on_manual_control(&mut self, control: ManualControl) -> bool {
+        if !self.context.halting {
+            return false;
+        }
+        let key = match control {
+            ManualControl::Left => KeyKind::Left,
+            ManualControl::Right => KeyKind::Right,
+            ManualControl::Up => KeyKind::Up,
+            ManualControl::Down => KeyKind::Down,
+            ManualControl::Jump => {
+                return self.context.keys.send(self.player.config.jump_key).is_ok();
+            }
+        };
+        let sequence = [
+            ScheduledKey {
+                tick_offset: 0,
+                kind: key,
+                is_down: true,
+            },
+            ScheduledKey {
+                tick_offset: MANUAL_CONTROL_HOLD_TICKS,
+                kind: key,
+                is_down: false,
+            },
+        ];
+        // RPC input method does not support scheduling so fallbacks to a single press
+        self.context
+            .keys
+            .schedule(&sequence)
+            .or_else(|_| self.context.keys.send(key))
+            .is_ok()
+    }
+
+    fn on_submit_rune_keys(&mut self, keys: [KeyBinding; 4]) -> bool {
+        if !self.context.player.is_awaiting_rune_keys() {
+            return false;
+        }
+        self.player.submit_rune_keys(keys.map(KeyKind::from));
+        true
+    }
+
+    fn on_current_player_position(&self) -> Option<(i32, i32)> {
+        self.player.last_known_pos.map(|pos| (pos.x, pos.y))
+    }
+
+    fn on_append_action_to_preset(&self, preset: String, action: Action) -> Option<MinimapData> {
+        let mut minimap = self.minimap.data()?.clone();
+        minimap.actions.get_mut(&preset)?.push(action);
+        Some(minimap)
+    }
+
+    fn on_console_command(&mut self, command: ConsoleCommand) -> Result<String> {
+        if !self.settings.enable_console {
+            bail!("console is disabled");
+        }
+        debug!(target: "handler", "console command {command:?}");
+        match command {
+            ConsoleCommand::Halt | ConsoleCommand::Start => {
+                if self.minimap.data().is_none() {
+                    bail!("no minimap is selected");
+                }
+                let halting = matches!(command, ConsoleCommand::Halt);
+                self.on_rotate_actions(halting);
+                Ok(if halting { "halted" } else { "started" }.to_string())
+            }
+            ConsoleCommand::Goto(x, y) => {
+                // Moving while rotating would fight the rotator for the priority action
+                if !self.context.halting {
+                    bail!("actions must be halted to move");
+                }
+                let action = PlayerAction::Move(PlayerActionMove {
+                    position: Position {
+                        x,
+                        x_random_range: 0,
+                        y,
+                        allow_adjusting: true,
+                        arrival_tolerance: None,
+                    },
+                    wait_after_move_ticks: 0,
+                });
+                self.player
+                    .set_priority_action(CONSOLE_GOTO_ACTION_ID, action);
+                Ok(format!("moving to ({x}, {y})"))
+            }
+            ConsoleCommand::UseKey(key) => {
+                if !self.context.halting {
+                    bail!("actions must be halted to use key");
+                }
+                self.context
+                    .keys
+                    .send(key.into())
+                    .map_err(|err| anyhow!("failed to send key {key} {err}"))?;
+                Ok(format!("sent key {key}"))
+            }
+            ConsoleCommand::DumpState => Ok(format!(
+                "player: {}\n{:#?}",
+                self.context.player,
+                self.on_game_state()
+            )),
+        }
+    }
+
     #[cfg(debug_assertions)]
     fn on_capture_image(&self, is_grayscale: bool) {
         if let Some(ref detector) = self.context.detector {
@@ -361,6 +1008,15 @@ impl RequestHandler for DefaultRequestHandler<'_> {
         } else {
             None
         };
+        if let Some(id) = self.recording_images_id.clone() {
+            // Frames kept before the recording started are saved first
+            let frames = self.frame_history.take();
+            thread::spawn(move || {
+                for frame in frames {
+                    save_image_for_training_to(&frame, Some(id.clone()), false, false);
+                }
+            });
+        }
     }
 
     #[cfg(debug_assertions)]
@@ -395,6 +1051,10 @@ impl RequestHandler for DefaultRequestHandler<'_> {
                 Ok(ArrowsState::Calibrating(new_calibrating)) => {
                     calibrating = new_calibrating;
                 }
+                Ok(ArrowsState::LowConfidence) => {
+                    debug!(target: "test", "spin test low confidence");
+                    break;
+                }
                 Err(err) => {
                     debug!(target: "test", "spin test error {err}");
                     break;
@@ -407,7 +1067,8 @@ impl RequestHandler for DefaultRequestHandler<'_> {
 // TODO: should only handle a single matched key binding
 #[inline]
 fn poll_key(handler: &mut DefaultRequestHandler) {
-    let Some(received_key) = handler.key_receiver.try_recv() else {
+    let global_key = global_key(handler.settings).filter(|_| handler.client == selected_client());
+    let Some(received_key) = handler.key_receiver.try_recv(global_key.as_slice()) else {
         return;
     };
     debug!(target: "handler", "received key {received_key:?}");
@@ -416,9 +1077,49 @@ fn poll_key(handler: &mut DefaultRequestHandler) {
     {
         handler.on_rotate_actions(!handler.context.halting);
     }
+    if let KeyBindingConfiguration { key, enabled: true } = handler.settings.toggle_auto_mob_key
+        && KeyKind::from(key) == received_key
+    {
+        handler.on_toggle_auto_mob();
+    }
     let _ = handler.key_sender.send(received_key.into());
 }
 
+/// The toggle actions key if it is enabled as a global hotkey in `settings`
+///
+/// The hotkey only applies to the selected client so that pressing it does not toggle the
+/// actions of every client.
+fn global_key(settings: &Settings) -> Option<KeyKind> {
+    let KeyBindingConfiguration { key, enabled } = settings.toggle_actions_key;
+    (enabled && settings.toggle_actions_key_global).then(|| KeyKind::from(key))
+}
+
+/// The currently detected portals with usages from matching portals saved in `minimap`
+fn detected_portals(context: &Context, minimap: Option<&MinimapData>) -> Vec<Portal> {
+    let Minimap::Idle(idle) = context.minimap else {
+        return vec![];
+    };
+    idle.portals
+        .into_iter()
+        .map(|portal| {
+            let saved = minimap.and_then(|minimap| {
+                minimap
+                    .portals
+                    .iter()
+                    .find(|saved| saved.is_same_position(portal.x, portal.y))
+            });
+            Portal {
+                x: portal.x,
+                y: portal.y,
+                width: portal.width,
+                height: portal.height,
+                usage: saved.map(|saved| saved.usage).unwrap_or_default(),
+                destination: None,
+            }
+        })
+        .collect()
+}
+
 #[inline]
 fn extract_minimap(context: &Context, mat: &impl MatTraitConst) -> Option<(Vec<u8>, usize, usize)> {
     if let Minimap::Idle(idle) = context.minimap {
@@ -466,6 +1167,103 @@ pub fn config_buffs(config: &Configuration) -> Vec<(BuffKind, KeyBinding)> {
     buffs
 }
 
+/// Whether rune or auto mob platforms pathing is enabled for `minimap` without any platform
+#[inline]
+fn is_platforms_pathing_unavailable(minimap: &MinimapData) -> bool {
+    (minimap.rune_platforms_pathing || minimap.auto_mob_platforms_pathing)
+        && minimap.platforms.is_empty()
+}
+
+/// Finds the `actions` whose positions can never be reached by moving between the platforms of
+/// `minimap`
+///
+/// Returns the descriptions of the unreachable actions or empty if `minimap` has no platforms.
+fn find_unreachable_actions(minimap: &MinimapData, actions: &[Action]) -> Vec<String> {
+    let thresholds = PathingThresholds::from(minimap.pathing_profile);
+    let platforms = find_neighbors(
+        &minimap
+            .platforms
+            .iter()
+            .copied()
+            .map(pathing::Platform::from)
+            .collect::<Vec<_>>(),
+        thresholds.double_jump,
+        thresholds.jump,
+        thresholds.grappling_max,
+    );
+    let mut actions = actions.to_vec();
+    resolve_anchored_positions(&mut actions);
+    let (indices, points) = actions
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, action)| match action {
+            Action::Move(ActionMove { position, .. })
+            | Action::Key(ActionKey {
+                position: Some(position),
+                ..
+            }) => Some((i, Point::new(position.x, position.y))),
+            Action::Key(_) => None,
+        })
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+
+    find_unreachable_points(&platforms, &points, thresholds.jump)
+        .into_iter()
+        .map(|(i, unreachable)| {
+            let point = points[i];
+            format!(
+                "action #{} at ({}, {}) {unreachable}",
+                indices[i] + 1,
+                point.x,
+                point.y
+            )
+        })
+        .collect()
+}
+
+/// Merges the enabled `global_actions` into their slots around `preset_actions`
+///
+/// `config_actions` are always placed first. Returns the merged actions and the
+/// [`GlobalAction::id`]s keyed by the merged indices.
+///
+/// A global action with [`ActionCondition::Linked`] is skipped unless it follows another
+/// merged global action of the same slot so that it is never linked to a config or preset
+/// action.
+fn merge_global_actions(
+    config_actions: Vec<Action>,
+    preset_actions: &[Action],
+    global_actions: &[GlobalAction],
+) -> (Vec<Action>, HashMap<usize, u32>) {
+    let mut actions = config_actions;
+    let mut stable_ids = HashMap::new();
+    let mut extend_slot = |actions: &mut Vec<Action>, slot: GlobalActionSlot| {
+        let mut can_link = false;
+        for global in global_actions.iter().filter(|global| global.slot == slot) {
+            let is_linked = matches!(
+                global.action,
+                Action::Move(ActionMove {
+                    condition: ActionCondition::Linked,
+                    ..
+                }) | Action::Key(ActionKey {
+                    condition: ActionCondition::Linked,
+                    ..
+                })
+            );
+            if !global.enabled || (is_linked && !can_link) {
+                can_link = false;
+                continue;
+            }
+            stable_ids.insert(actions.len(), global.id);
+            actions.push(global.action);
+            can_link = true;
+        }
+    };
+
+    extend_slot(&mut actions, GlobalActionSlot::Before);
+    actions.extend_from_slice(preset_actions);
+    extend_slot(&mut actions, GlobalActionSlot::After);
+    (actions, stable_ids)
+}
+
 fn config_actions(config: &Configuration) -> Vec<Action> {
     let mut vec = Vec::new();
     if let KeyBindingConfiguration { key, enabled: true } = config.feed_pet_key {
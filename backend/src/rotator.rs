@@ -1,32 +1,50 @@
 use std::{
-    assert_matches::debug_assert_matches,
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
+    mem,
+    ops::Range,
     sync::atomic::{AtomicU32, Ordering},
-    time::Instant,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
 use log::debug;
-use opencv::core::Point;
+use opencv::core::{Point, Rect};
 use ordered_hash_map::OrderedHashMap;
-use rand::seq::IteratorRandom;
+use rand::seq::IndexedRandom;
 
 use crate::{
-    ActionKeyDirection, ActionKeyWith, AutoMobbing, KeyBinding, Position, RotationMode,
+    ActionKeyDirection, ActionKeyWith, AutoMobbing, AutoMobbingTarget, KeyBinding, KeyModifier,
+    Platform, Position, RotationMode, RotatorSchedule, ScheduledAction, YBand,
+    array::Array,
     buff::{Buff, BuffKind},
     context::{Context, MS_PER_TICK},
-    database::{Action, ActionCondition, ActionKey, ActionMove},
+    database::{Action, ActionCondition, ActionConditionOperand, ActionKey, ActionMove},
     minimap::Minimap,
+    pathing::{MAX_PLATFORMS_COUNT, PlatformWithNeighbors},
     player::{
-        GRAPPLING_THRESHOLD, Player, PlayerAction, PlayerActionAutoMob, PlayerActionKey,
-        PlayerState,
+        Player, PlayerAction, PlayerActionAutoMob, PlayerActionKey, PlayerActionMove, PlayerState,
     },
     skill::{Skill, SkillKind},
     task::{Task, Update, update_detection_task},
 };
 
 const COOLDOWN_BETWEEN_QUEUE_MILLIS: u128 = 20_000;
-const COOLDOWN_BETWEEN_POTION_QUEUE_MILLIS: u128 = 2_000;
+
+/// The time after a buff action is executed to verify the buff is applied
+const BUFF_VERIFY_MILLIS: u128 = 3_000;
+
+/// The range of ticks to wait after each move while resting from auto mobbing
+const AUTO_MOB_REST_WAIT_TICKS: Range<u32> = 60..240;
+
+/// The maximum y distance from a platform for an Erda action position to be considered on it
+const ERDA_PLATFORM_SNAP_Y_TOLERANCE: i32 = 5;
+
+/// The maximum y distance from a platform for a point to be considered on it when balancing
+/// auto mobbing time across platforms
+const AUTO_MOB_PLATFORM_Y_TOLERANCE: i32 = 5;
+
+/// The maximum time to wait for Erda Shower to be detected during warm up
+const WARM_UP_ERDA_SHOWER_TIMEOUT_MILLIS: u128 = 5_000;
 
 type ConditionFn = Box<dyn Fn(&Context, &mut PlayerState, Option<Instant>) -> bool>;
 
@@ -70,6 +88,29 @@ struct PriorityAction {
     ignoring: bool,
     /// The last [`Instant`] when this action was queued
     last_queued_time: Option<Instant>,
+    /// The buff expected to be applied after this action is executed
+    buff: Option<BuffKind>,
+    /// The verification state of [`Self::buff`]
+    buff_verification: BuffVerification,
+    /// The key to persist [`Self::last_queued_time`] across rebuilds and app restarts
+    ///
+    /// Only set for user-provided actions with [`ActionCondition::EveryMillis`] condition.
+    persist_key: Option<String>,
+}
+
+/// The verification state of a buff [`PriorityAction`]
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum BuffVerification {
+    None,
+    /// The action was queued and the buff will be verified once the action is executed
+    Pending,
+    /// The action was executed at the [`Instant`] and the buff will be verified after
+    /// [`BUFF_VERIFY_MILLIS`]
+    Executed(Instant),
+    /// The buff was not applied (e.g. still on cooldown) after the action was executed
+    ///
+    /// The action is retried once [`Rotator::normal_loops`] changes from the stored value.
+    Deferred(u32),
 }
 
 /// The action that will be passed to the player
@@ -91,12 +132,44 @@ struct LinkedAction {
     next: Option<Box<LinkedAction>>,
 }
 
+/// The farming and resting duty cycle of [`RotatorMode::AutoMobbing`]
+#[derive(Clone, Copy, Debug)]
+struct AutoMobRestCycle {
+    resting: bool,
+    /// The [`Instant`] the current farming or resting phase started
+    started: Instant,
+}
+
+/// The stage of the warm up executed once when the rotation starts
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum WarmUp {
+    /// Queues all buffs regardless of their conditions
+    Buffs,
+    /// Waits for the queued buffs to be executed
+    Casting,
+    /// Waits for the player to arrive at the start point
+    Moving,
+    /// Waits for Erda Shower cooldown state to be detected since the [`Instant`]
+    ErdaShower(Instant),
+}
+
+/// The stages of the safe mode window after the rotation starts
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SafeMode {
+    /// Waits for the rotation to start
+    Pending,
+    /// Holds back user priority actions with keys since the [`Instant`]
+    Active(Instant),
+}
+
 /// The rotator's rotation mode
 #[derive(Default, Debug)]
 pub enum RotatorMode {
     StartToEnd,
     #[default]
     StartToEndThenReverse,
+    /// Picks the nearest action to the player that has not been executed in the current loop
+    NearestFirst,
     AutoMobbing(AutoMobbing),
 }
 
@@ -105,6 +178,7 @@ impl From<RotationMode> for RotatorMode {
         match mode {
             RotationMode::StartToEnd => RotatorMode::StartToEnd,
             RotationMode::StartToEndThenReverse => RotatorMode::StartToEndThenReverse,
+            RotationMode::NearestFirst => RotatorMode::NearestFirst,
             RotationMode::AutoMobbing(auto_mobbing) => RotatorMode::AutoMobbing(auto_mobbing),
         }
     }
@@ -115,14 +189,41 @@ pub struct Rotator {
     // This is literally free postfix increment!
     id_counter: AtomicU32,
     normal_actions: Vec<(u32, RotatorAction)>,
+    /// The player y bands of normal actions keyed by action id
+    ///
+    /// A normal action whose band does not contain the player is skipped for the current loop.
+    normal_actions_y_band: HashMap<u32, YBand>,
     normal_queuing_linked_action: Option<(u32, Box<LinkedAction>)>,
     normal_index: usize,
     /// Whether [`Self::normal_actions`] is being accessed from the end
     normal_actions_backward: bool,
+    /// The ids of normal actions executed in the current loop for [`RotatorMode::NearestFirst`]
+    ///
+    /// An executed action is on cooldown until all other actions are executed so that far
+    /// away actions are not starved.
+    normal_executed_ids: Vec<u32>,
+    /// The number of times the normal actions have looped
+    ///
+    /// For [`RotatorMode::AutoMobbing`], each auto mobbing action is counted as a loop.
+    normal_loops: u32,
     normal_actions_reset_on_erda: bool,
     normal_rotate_mode: RotatorMode,
     /// The [`Task`] used when [`Self::normal_rotate_mode`] is [`RotatorMode::AutoMobbing`]
     auto_mob_task: Option<Task<Result<Vec<Point>>>>,
+    /// The number of mobs detected by [`Self::auto_mob_task`] since last taken
+    auto_mob_detected_count: u32,
+    /// The current duty cycle phase when [`AutoMobbing::farm_minutes`] and
+    /// [`AutoMobbing::rest_minutes`] are set
+    auto_mob_rest_cycle: Option<AutoMobRestCycle>,
+    /// The number of ticks the player spent on each platform keyed by its index in
+    /// [`crate::minimap::MinimapIdle::platforms`]
+    ///
+    /// Only tracked when [`AutoMobbing::balance_platforms`] is enabled.
+    auto_mob_platform_ticks: HashMap<usize, u32>,
+    /// The polygon bound replacing [`AutoMobbing::bound`] if it has at least 3 vertices
+    auto_mob_polygon: Vec<Point>,
+    /// Whether auto mobbing is paused while priority actions keep rotating
+    auto_mob_paused: bool,
     priority_actions: OrderedHashMap<u32, PriorityAction>,
     /// The currently executing [`RotatorAction::Linked`] action
     priority_queuing_linked_action: Option<(u32, Box<LinkedAction>)>,
@@ -130,13 +231,42 @@ pub struct Rotator {
     ///
     /// Populates from [`Self::priority_actions`] when its predicate for queuing is true
     priority_actions_queue: VecDeque<u32>,
+    /// The last queued wall-clock time in milliseconds of priority actions keyed by
+    /// [`PriorityAction::persist_key`]
+    ///
+    /// Restores [`PriorityAction::last_queued_time`] when the actions are rebuilt so that
+    /// long interval actions are not queued early every time the rotation changes.
+    priority_actions_fired: HashMap<String, u64>,
+    /// The entries of [`Self::priority_actions_fired`] not yet persisted
+    priority_actions_fired_unsaved: Vec<(String, u64)>,
+    /// Whether to warm up when the rotation starts
+    warm_up_enabled: bool,
+    /// The start point to walk to during warm up
+    warm_up_position: Option<Position>,
+    /// The current warm up stage or [`None`] if not warming up
+    warm_up: Option<WarmUp>,
+    /// The preset start point to move to first whenever the rotation starts or resumes
+    ///
+    /// Takes precedence over [`Self::warm_up_position`] and is moved to during the warm up even
+    /// when the warm up is disabled.
+    start_position: Option<Position>,
+    /// The duration of the safe mode window or [`None`] if disabled
+    safe_mode_duration: Option<Duration>,
+    /// The current safe mode stage or [`None`] if not in safe mode
+    safe_mode: Option<SafeMode>,
 }
 
 impl Rotator {
+    /// Builds the normal and priority actions from `actions`
+    ///
+    /// `stable_ids` maps the indices of `actions` to ids that stay the same across edits (e.g.
+    /// [`crate::GlobalAction::id`]). The last queued times of these actions are persisted with
+    /// the ids instead of the actions themselves.
     pub fn build_actions(
         &mut self,
         mode: RotatorMode,
         actions: &[Action],
+        stable_ids: &HashMap<usize, u32>,
         buffs: &[(BuffKind, KeyBinding)],
         potion_key: KeyBinding,
         enable_rune_solving: bool,
@@ -145,10 +275,13 @@ impl Rotator {
         debug!(target: "rotator", "preparing actions {actions:?} {buffs:?}");
         self.reset_queue();
         self.normal_actions.clear();
+        self.normal_actions_y_band.clear();
         self.normal_rotate_mode = mode;
         self.normal_actions_reset_on_erda = reset_normal_actions_on_erda;
         self.priority_actions.clear();
+        self.auto_mob_platform_ticks.clear();
 
+        let mut persist_keys = HashMap::<String, usize>::new();
         let mut i = 0;
         while i < actions.len() {
             let action = actions[i];
@@ -160,24 +293,49 @@ impl Rotator {
                 Action::Move(_) => false,
                 Action::Key(ActionKey { queue_to_front, .. }) => queue_to_front.unwrap_or_default(),
             };
+            let y_band = match action {
+                Action::Move(ActionMove { y_band, .. }) | Action::Key(ActionKey { y_band, .. }) => {
+                    y_band
+                }
+            };
             let (action, offset) = rotator_action(action, i, actions);
             debug_assert!(i != 0 || !matches!(condition, ActionCondition::Linked));
             // Should not move i below the match because it could cause
             // infinite loop due to auto mobbing ignoring Any condition
             i += offset;
             match condition {
-                ActionCondition::EveryMillis(_) | ActionCondition::ErdaShowerOffCooldown => {
+                ActionCondition::EveryMillis(_)
+                | ActionCondition::ErdaShowerOffCooldown
+                | ActionCondition::AnyOf(_, _)
+                | ActionCondition::AllOf(_, _)
+                | ActionCondition::MpAbovePercent(_) => {
+                    let mut priority_action =
+                        priority_action(action, condition, queue_to_front, y_band);
+                    if has_every_millis_condition(condition) {
+                        let key = match stable_ids.get(&(i - offset)) {
+                            Some(id) => format!("stable#{id}"),
+                            None => persist_key(actions[i - offset], &mut persist_keys),
+                        };
+                        priority_action.last_queued_time = self
+                            .priority_actions_fired
+                            .get(&key)
+                            .and_then(|millis| instant_from_epoch_millis(*millis));
+                        priority_action.persist_key = Some(key);
+                    }
                     self.priority_actions.insert(
                         self.id_counter.fetch_add(1, Ordering::Relaxed),
-                        priority_action(action, condition, queue_to_front),
+                        priority_action,
                     );
                 }
                 ActionCondition::Any => {
                     if matches!(self.normal_rotate_mode, RotatorMode::AutoMobbing(_)) {
                         continue;
                     }
-                    self.normal_actions
-                        .push((self.id_counter.fetch_add(1, Ordering::Relaxed), action))
+                    let id = self.id_counter.fetch_add(1, Ordering::Relaxed);
+                    if let Some(y_band) = y_band {
+                        self.normal_actions_y_band.insert(id, y_band);
+                    }
+                    self.normal_actions.push((id, action))
                 }
                 ActionCondition::Linked => unreachable!(),
             }
@@ -201,9 +359,140 @@ impl Rotator {
         }
     }
 
+    /// Sets whether to warm up with an optional start point `position` when the rotation starts
+    ///
+    /// Must be called before [`Self::build_actions`] or [`Self::reset_queue`] to take effect.
+    pub fn set_warm_up(&mut self, enabled: bool, position: Option<Position>) {
+        self.warm_up_enabled = enabled;
+        self.warm_up_position = position;
+    }
+
+    /// Sets the `duration` after the rotation starts during which only movement and buffs are
+    /// allowed
+    ///
+    /// While in safe mode, user priority actions (e.g. long cooldown skills) that press any key
+    /// are not queued. Normal actions, buffs and other built-in priority actions are not affected.
+    ///
+    /// Must be called before [`Self::build_actions`] or [`Self::reset_queue`] to take effect.
+    pub fn set_safe_mode(&mut self, duration: Option<Duration>) {
+        self.safe_mode_duration = duration;
+    }
+
+    /// Whether the rotation is currently in the safe mode window
+    #[inline]
+    pub fn in_safe_mode(&self) -> bool {
+        matches!(self.safe_mode, Some(SafeMode::Active(_)))
+    }
+
+    /// Sets the polygon bound replacing [`AutoMobbing::bound`] for auto mobbing
+    pub fn set_auto_mob_polygon(&mut self, polygon: Vec<Point>) {
+        self.auto_mob_polygon = polygon;
+    }
+
+    /// Sets the preset start point `position` to move to first when the rotation starts
+    ///
+    /// Must be called before [`Self::build_actions`] or [`Self::reset_queue`] to take effect.
+    pub fn set_start_position(&mut self, position: Option<Position>) {
+        self.start_position = position;
+    }
+
+    /// Resolves the currently built actions into a [`RotatorSchedule`]
+    ///
+    /// For [`RotatorMode::StartToEndThenReverse`], the normal actions are listed forward and
+    /// then backward as a full loop. For [`RotatorMode::AutoMobbing`], the normal actions
+    /// are replaced by a single auto mobbing action.
+    pub fn schedule(&self) -> RotatorSchedule {
+        let normal_actions = match self.normal_rotate_mode {
+            RotatorMode::StartToEnd | RotatorMode::NearestFirst => self
+                .normal_actions
+                .iter()
+                .map(|(id, action)| scheduled_action(*id, None, false, action))
+                .collect(),
+            RotatorMode::StartToEndThenReverse => self
+                .normal_actions
+                .iter()
+                .chain(self.normal_actions.iter().rev())
+                .map(|(id, action)| scheduled_action(*id, None, false, action))
+                .collect(),
+            RotatorMode::AutoMobbing(_) => vec![ScheduledAction {
+                id: u32::MAX,
+                condition: None,
+                queue_to_front: false,
+                actions: vec!["AutoMob".to_string()],
+            }],
+        };
+        let priority_actions = self
+            .priority_actions
+            .iter()
+            .map(|(id, action)| {
+                scheduled_action(
+                    *id,
+                    action.condition_kind,
+                    action.queue_to_front,
+                    &action.inner,
+                )
+            })
+            .collect();
+
+        RotatorSchedule {
+            normal_actions,
+            priority_actions,
+        }
+    }
+
+    /// Loads the persisted last queued wall-clock times of priority actions
+    ///
+    /// Must be called before [`Self::build_actions`] for the times to take effect.
+    pub fn load_fired_timestamps(&mut self, timestamps: HashMap<String, u64>) {
+        self.priority_actions_fired = timestamps;
+    }
+
+    /// Takes the last queued wall-clock times of priority actions not yet persisted
+    #[inline]
+    pub fn take_unsaved_fired_timestamps(&mut self) -> Vec<(String, u64)> {
+        mem::take(&mut self.priority_actions_fired_unsaved)
+    }
+
+    /// The number of priority actions waiting in the queue
+    #[inline]
+    pub fn queued_priority_actions_count(&self) -> usize {
+        self.priority_actions_queue.len()
+    }
+
+    /// Takes the number of mobs detected when auto mobbing since the last call
+    /// Pauses or resumes auto mobbing without affecting priority actions
+    #[inline]
+    pub fn set_auto_mob_paused(&mut self, paused: bool) {
+        self.auto_mob_paused = paused;
+    }
+
+    #[inline]
+    pub fn auto_mob_paused(&self) -> bool {
+        self.auto_mob_paused
+    }
+
+    /// The number of times the normal actions have looped
+    #[inline]
+    pub fn normal_loops(&self) -> u32 {
+        self.normal_loops
+    }
+
+    #[inline]
+    pub fn take_auto_mob_detected_count(&mut self) -> u32 {
+        mem::take(&mut self.auto_mob_detected_count)
+    }
+
     #[inline]
     pub fn reset_queue(&mut self) {
+        self.warm_up = if self.warm_up_enabled {
+            Some(WarmUp::Buffs)
+        } else {
+            // Only moves to the preset start point without casting buffs
+            self.start_position.map(|_| WarmUp::Casting)
+        };
+        self.safe_mode = self.safe_mode_duration.map(|_| SafeMode::Pending);
         self.normal_actions_backward = false;
+        self.auto_mob_rest_cycle = None;
         self.reset_normal_actions_queue();
         self.priority_actions_queue.clear();
         self.priority_queuing_linked_action = None;
@@ -212,6 +501,7 @@ impl Rotator {
     #[inline]
     fn reset_normal_actions_queue(&mut self) {
         self.normal_index = 0;
+        self.normal_executed_ids.clear();
         self.normal_queuing_linked_action = None;
     }
 
@@ -220,12 +510,26 @@ impl Rotator {
         if context.halting || matches!(context.player, Player::CashShopThenExit(_, _)) {
             return;
         }
+        self.update_safe_mode();
+        if self.warm_up.is_some() {
+            self.rotate_warm_up(context, player);
+            return;
+        }
+        if let RotatorMode::AutoMobbing(AutoMobbing {
+            balance_platforms: true,
+            ..
+        }) = self.normal_rotate_mode
+        {
+            self.update_auto_mob_platform_ticks(context, player);
+        }
         self.rotate_priority_actions(context, player);
         self.rotate_priority_actions_queue(context, player);
         if !player.has_priority_action() && !player.has_normal_action() {
             match self.normal_rotate_mode {
                 RotatorMode::StartToEnd => self.rotate_start_to_end(player),
                 RotatorMode::StartToEndThenReverse => self.rotate_start_to_end_then_reverse(player),
+                RotatorMode::NearestFirst => self.rotate_nearest_first(player),
+                RotatorMode::AutoMobbing(_) if self.auto_mob_paused => (),
                 RotatorMode::AutoMobbing(auto_mobbing) => {
                     self.rotate_auto_mobbing(context, player, auto_mobbing)
                 }
@@ -233,6 +537,102 @@ impl Rotator {
         }
     }
 
+    /// Starts the safe mode window on the first rotation and ends it once the duration passed
+    #[inline]
+    fn update_safe_mode(&mut self) {
+        match self.safe_mode {
+            Some(SafeMode::Pending) => {
+                debug!(target: "rotator", "entering safe mode");
+                self.safe_mode = Some(SafeMode::Active(Instant::now()));
+            }
+            Some(SafeMode::Active(started))
+                if self
+                    .safe_mode_duration
+                    .is_none_or(|duration| started.elapsed() >= duration) =>
+            {
+                debug!(target: "rotator", "leaving safe mode");
+                self.safe_mode = None;
+            }
+            Some(SafeMode::Active(_)) | None => (),
+        }
+    }
+
+    /// Rotates the warm up stages before entering the normal rotation
+    ///
+    /// The warm up casts all buffs regardless of their conditions, walks to
+    /// [`Self::warm_up_move_position`] if any and waits for Erda Shower cooldown state to be
+    /// detected if there is any [`ActionCondition::ErdaShowerOffCooldown`] action. Other
+    /// priority actions are not queued until the warm up completes. When the warm up is
+    /// disabled, only the walk to [`Self::start_position`] is done.
+    fn rotate_warm_up(&mut self, context: &Context, player: &mut PlayerState) {
+        match self.warm_up.unwrap() {
+            WarmUp::Buffs => {
+                debug!(target: "rotator", "warming up by queuing all buffs");
+                let ids = self.priority_actions.keys().copied().collect::<Vec<_>>();
+                for id in ids {
+                    let action = self.priority_actions.get_mut(&id).unwrap();
+                    if action.buff.is_none() || self.priority_actions_queue.contains(&id) {
+                        continue;
+                    }
+                    action.last_queued_time = Some(Instant::now());
+                    action.buff_verification = BuffVerification::Pending;
+                    self.priority_actions_queue.push_back(id);
+                }
+                self.warm_up = Some(WarmUp::Casting);
+            }
+            WarmUp::Casting => {
+                if !self.priority_actions_queue.is_empty() || player.has_priority_action() {
+                    self.rotate_priority_actions_queue(context, player);
+                    return;
+                }
+                self.warm_up = Some(if let Some(position) = self.warm_up_move_position() {
+                    debug!(target: "rotator", "warming up by moving to {position:?}");
+                    player.set_normal_action(
+                        self.id_counter.fetch_add(1, Ordering::Relaxed),
+                        PlayerAction::Move(PlayerActionMove {
+                            position,
+                            wait_after_move_ticks: 0,
+                        }),
+                    );
+                    WarmUp::Moving
+                } else {
+                    WarmUp::ErdaShower(Instant::now())
+                });
+            }
+            WarmUp::Moving => {
+                if !player.has_normal_action() {
+                    self.warm_up = self
+                        .warm_up_enabled
+                        .then(|| WarmUp::ErdaShower(Instant::now()));
+                }
+            }
+            WarmUp::ErdaShower(instant) => {
+                let has_erda_action = self.priority_actions.iter().any(|(_, action)| {
+                    action.condition_kind.is_some_and(has_erda_shower_condition)
+                });
+                let is_detecting =
+                    matches!(context.skills[SkillKind::ErdaShower], Skill::Detecting);
+                if !has_erda_action
+                    || !is_detecting
+                    || instant.elapsed().as_millis() >= WARM_UP_ERDA_SHOWER_TIMEOUT_MILLIS
+                {
+                    debug!(target: "rotator", "warm up completed");
+                    self.warm_up = None;
+                }
+            }
+        }
+    }
+
+    /// The position to walk to during the warm up
+    ///
+    /// The preset [`Self::start_position`] takes precedence over the map
+    /// [`Self::warm_up_position`].
+    #[inline]
+    fn warm_up_move_position(&self) -> Option<Position> {
+        self.start_position
+            .or(self.warm_up_position.filter(|_| self.warm_up_enabled))
+    }
+
     /// Checks if the provided `id` is a linked action in queue or executing
     #[inline]
     fn is_priority_linked_action_queuing_or_executing(
@@ -256,24 +656,24 @@ impl Rotator {
         })
     }
 
-    /// Checks if the player or the queue has a [`ActionCondition::ErdaShowerOffCooldown`] action
+    /// Checks if the player or the queue has an Erda Shower condition action
+    ///
+    /// See [`has_erda_shower_condition`] for what counts as an Erda Shower condition.
     #[inline]
     fn has_erda_action_queuing_or_executing(&self, player: &PlayerState) -> bool {
         if player.priority_action_id().is_some_and(|id| {
-            self.priority_actions.get(&id).is_some_and(|action| {
-                matches!(
-                    action.condition_kind,
-                    Some(ActionCondition::ErdaShowerOffCooldown)
-                )
-            })
+            self.priority_actions
+                .get(&id)
+                .is_some_and(|action| action.condition_kind.is_some_and(has_erda_shower_condition))
         }) {
             return true;
         }
         self.priority_actions_queue.iter().any(|id| {
-            matches!(
-                self.priority_actions.get(id).unwrap().condition_kind,
-                Some(ActionCondition::ErdaShowerOffCooldown)
-            )
+            self.priority_actions
+                .get(id)
+                .unwrap()
+                .condition_kind
+                .is_some_and(has_erda_shower_condition)
         })
     }
 
@@ -286,16 +686,19 @@ impl Rotator {
         let has_erda_action = self.has_erda_action_queuing_or_executing(player);
         let ids = self.priority_actions.keys().copied().collect::<Vec<_>>(); // why?
         let mut did_queue_erda_action = false;
+        let normal_loops = self.normal_loops;
+        let in_safe_mode = self.in_safe_mode();
         for id in ids {
             // Ignores for as long as the action is a linked action that is queuing
             // or executing
             let has_linked_action = self.is_priority_linked_action_queuing_or_executing(player, id);
             let action = self.priority_actions.get_mut(&id).unwrap();
             action.ignoring = match action.condition_kind {
-                Some(ActionCondition::ErdaShowerOffCooldown) => {
+                Some(ActionCondition::Any) => unreachable!(),
+                Some(condition) if has_erda_shower_condition(condition) => {
                     has_erda_action || has_linked_action
                 }
-                Some(ActionCondition::Linked) | Some(ActionCondition::EveryMillis(_)) | None => {
+                Some(_) | None => {
                     player // The player currently executing action
                         .priority_action_id()
                         .is_some_and(|action_id| action_id == id)
@@ -305,24 +708,48 @@ impl Rotator {
                             .any(|action_id| *action_id == id)
                         || has_linked_action
                 }
-                Some(ActionCondition::Any) => unreachable!(),
             };
             if action.ignoring {
                 action.last_queued_time = Some(Instant::now());
                 continue;
             }
-            if (action.condition.0)(context, player, action.last_queued_time) {
+            if let Some(buff) = action.buff {
+                action.buff_verification =
+                    verify_buff(context, buff, action.buff_verification, normal_loops);
+            }
+            let last_queued_time = match action.buff_verification {
+                BuffVerification::Deferred(loops) if loops == normal_loops => continue,
+                // Retries on the next loop without waiting for the cooldown between queue
+                BuffVerification::Deferred(_) => None,
+                BuffVerification::None
+                | BuffVerification::Pending
+                | BuffVerification::Executed(_) => action.last_queued_time,
+            };
+            // Only user actions have a condition kind
+            if in_safe_mode
+                && action.condition_kind.is_some()
+                && rotator_action_has_key(&action.inner)
+            {
+                continue;
+            }
+            if (action.condition.0)(context, player, last_queued_time) {
                 if action.queue_to_front {
                     self.priority_actions_queue.push_front(id);
                 } else {
                     self.priority_actions_queue.push_back(id);
                 }
                 action.last_queued_time = Some(Instant::now());
+                if let Some(key) = action.persist_key.clone() {
+                    let millis = epoch_millis_now();
+                    self.priority_actions_fired.insert(key.clone(), millis);
+                    self.priority_actions_fired_unsaved.push((key, millis));
+                }
+                if action.buff.is_some() {
+                    action.buff_verification = BuffVerification::Pending;
+                }
                 if !did_queue_erda_action {
-                    did_queue_erda_action = matches!(
-                        action.condition_kind,
-                        Some(ActionCondition::ErdaShowerOffCooldown)
-                    );
+                    did_queue_erda_action =
+                        action.condition_kind.is_some_and(has_erda_shower_condition);
                 }
             }
         }
@@ -440,30 +867,87 @@ impl Rotator {
         let AutoMobbing {
             bound,
             key,
+            airborne_key,
             key_count,
             key_wait_before_millis,
             key_wait_after_millis,
+            split_bound_by_friend,
+            target,
+            farm_minutes,
+            rest_minutes,
+            balance_platforms,
+            same_y_preference,
         } = auto_mobbing;
-        let bound = if player.config.auto_mob_platforms_bound {
-            idle.platforms_bound.unwrap_or(bound.into())
+        if self.update_auto_mob_rest_cycle(farm_minutes, rest_minutes) {
+            if let Some(point) = player.auto_mob_pathing_point(context) {
+                debug!(target: "rotator", "auto mob resting move to {point:?}");
+                player.set_normal_action(
+                    u32::MAX,
+                    PlayerAction::Move(PlayerActionMove {
+                        position: Position {
+                            x: point.x,
+                            x_random_range: 0,
+                            y: point.y,
+                            allow_adjusting: false,
+                            arrival_tolerance: None,
+                        },
+                        wait_after_move_ticks: rand::random_range(AUTO_MOB_REST_WAIT_TICKS),
+                    }),
+                );
+            }
+            return;
+        }
+        let polygon_bound = polygon_bounding_rect(&self.auto_mob_polygon);
+        let (bound, polygon) = if player.config.auto_mob_platforms_bound {
+            (idle.platforms_bound.unwrap_or(bound.into()), vec![])
+        } else if let Some(polygon_bound) = polygon_bound {
+            (polygon_bound, self.auto_mob_polygon.clone())
         } else {
-            bound.into()
+            (bound.into(), vec![])
         };
-        let Update::Ok(points) =
-            update_detection_task(context, 0, &mut self.auto_mob_task, move |detector| {
-                detector.detect_mobs(idle.bbox, bound, pos)
-            })
-        else {
+        let bound = if split_bound_by_friend && let Some(friend) = idle.friend_player() {
+            split_bound_away_from(bound, friend)
+        } else {
+            bound
+        };
+        let Update::Ok(points) = update_detection_task(
+            context,
+            0,
+            &mut self.auto_mob_task,
+            move |detector| match target {
+                AutoMobbingTarget::Model => detector.detect_mobs(idle.bbox, bound, &polygon, pos),
+                AutoMobbingTarget::MinimapDots => {
+                    detector.detect_minimap_mobs(idle.bbox, bound, &polygon)
+                }
+            },
+        ) else {
             return;
         };
-        let Some(point) = points
+        self.auto_mob_detected_count += points.len() as u32;
+        let points = points
             .iter()
+            .map(|point| Point::new(point.x, idle.bbox.height - point.y))
             .filter(|point| {
-                let y = idle.bbox.height - point.y;
-                y <= pos.y || (y - pos.y).abs() <= GRAPPLING_THRESHOLD
+                point.y <= pos.y
+                    || (point.y - pos.y).abs() <= player.config.pathing_thresholds.grappling
             })
-            .choose(&mut rand::rng())
-            .map(|point| Point::new(point.x, idle.bbox.height - point.y))
+            .collect::<Vec<_>>();
+        let point = if balance_platforms || same_y_preference > 0 {
+            let platform_weights = if balance_platforms {
+                auto_mob_platform_weights(&idle.platforms, &self.auto_mob_platform_ticks, &points)
+            } else {
+                vec![1; points.len()]
+            };
+            let y_weights = auto_mob_same_y_weights(pos, &points, same_y_preference);
+            (0..points.len())
+                .collect::<Vec<_>>()
+                .choose_weighted(&mut rand::rng(), |i| platform_weights[*i] * y_weights[*i])
+                .ok()
+                .map(|i| points[*i])
+        } else {
+            points.choose(&mut rand::rng()).copied()
+        };
+        let Some(point) = point
             .and_then(|point| {
                 debug!(target: "rotator", "auto mob raw position {point:?}");
                 player.auto_mob_pick_reachable_y_position(context, point)
@@ -476,10 +960,12 @@ impl Rotator {
         else {
             return;
         };
+        self.normal_loops = self.normal_loops.wrapping_add(1);
         player.set_normal_action(
             u32::MAX,
             PlayerAction::AutoMob(PlayerActionAutoMob {
                 key,
+                airborne_key,
                 count: key_count.max(1),
                 wait_before_ticks: (key_wait_before_millis / MS_PER_TICK) as u32,
                 wait_after_ticks: (key_wait_after_millis / MS_PER_TICK) as u32,
@@ -488,11 +974,51 @@ impl Rotator {
                     x_random_range: 0,
                     y: point.y,
                     allow_adjusting: false,
+                    arrival_tolerance: None,
                 },
             }),
         );
     }
 
+    /// Increments the ticks of the platform the player is currently on
+    fn update_auto_mob_platform_ticks(&mut self, context: &Context, player: &PlayerState) {
+        let Minimap::Idle(idle) = context.minimap else {
+            return;
+        };
+        let Some(index) = player
+            .last_known_pos
+            .and_then(|pos| auto_mob_platform_index(&idle.platforms, pos))
+        else {
+            return;
+        };
+        let ticks = self.auto_mob_platform_ticks.entry(index).or_default();
+        *ticks = ticks.saturating_add(1);
+    }
+
+    /// Updates [`Self::auto_mob_rest_cycle`] and returns whether the player should be resting
+    fn update_auto_mob_rest_cycle(&mut self, farm_minutes: u32, rest_minutes: u32) -> bool {
+        if farm_minutes == 0 || rest_minutes == 0 {
+            self.auto_mob_rest_cycle = None;
+            return false;
+        }
+        let now = Instant::now();
+        let cycle = self.auto_mob_rest_cycle.get_or_insert(AutoMobRestCycle {
+            resting: false,
+            started: now,
+        });
+        let minutes = if cycle.resting {
+            rest_minutes
+        } else {
+            farm_minutes
+        };
+        if now.duration_since(cycle.started) >= Duration::from_secs(minutes as u64 * 60) {
+            cycle.resting = !cycle.resting;
+            cycle.started = now;
+            debug!(target: "rotator", "auto mob resting {}", cycle.resting);
+        }
+        cycle.resting
+    }
+
     fn rotate_start_to_end(&mut self, player: &mut PlayerState) {
         debug_assert!(!player.has_normal_action() && !player.has_priority_action());
         if self.normal_actions.is_empty() {
@@ -504,6 +1030,12 @@ impl Rotator {
         debug_assert!(self.normal_index < self.normal_actions.len());
         let (id, action) = self.normal_actions[self.normal_index].clone();
         self.normal_index = (self.normal_index + 1) % self.normal_actions.len();
+        if self.normal_index == 0 {
+            self.normal_loops = self.normal_loops.wrapping_add(1);
+        }
+        if !self.is_normal_action_in_y_band(player, id) {
+            return;
+        }
         match action {
             RotatorAction::Single(action) => {
                 player.set_normal_action(id, action);
@@ -535,6 +1067,47 @@ impl Rotator {
         }
         let (id, action) = self.normal_actions[i].clone();
         self.normal_index = (self.normal_index + 1) % len;
+        if self.normal_index == 0 {
+            self.normal_loops = self.normal_loops.wrapping_add(1);
+        }
+        if !self.is_normal_action_in_y_band(player, id) {
+            return;
+        }
+        match action {
+            RotatorAction::Single(action) => {
+                player.set_normal_action(id, action);
+            }
+            RotatorAction::Linked(action) => {
+                self.normal_queuing_linked_action = Some((id, Box::new(action)));
+                self.rotate_queuing_linked_action(player, false);
+            }
+        }
+    }
+
+    fn rotate_nearest_first(&mut self, player: &mut PlayerState) {
+        debug_assert!(!player.has_normal_action() && !player.has_priority_action());
+        if self.normal_actions.is_empty() {
+            return;
+        }
+        if self.rotate_queuing_linked_action(player, false) {
+            return;
+        }
+        let pos = player.last_known_pos;
+        let (id, action) = self
+            .normal_actions
+            .iter()
+            .filter(|(id, _)| !self.normal_executed_ids.contains(id))
+            .min_by_key(|(_, action)| rotator_action_distance(action, pos))
+            .cloned()
+            .expect("has at least one pending action");
+        self.normal_executed_ids.push(id);
+        if self.normal_executed_ids.len() >= self.normal_actions.len() {
+            self.normal_executed_ids.clear();
+            self.normal_loops = self.normal_loops.wrapping_add(1);
+        }
+        if !self.is_normal_action_in_y_band(player, id) {
+            return;
+        }
         match action {
             RotatorAction::Single(action) => {
                 player.set_normal_action(id, action);
@@ -546,6 +1119,16 @@ impl Rotator {
         }
     }
 
+    /// Checks if the normal action `id` can be executed at the player current y position
+    ///
+    /// Returns `true` if the action has no y band or the player position is unknown.
+    #[inline]
+    fn is_normal_action_in_y_band(&self, player: &PlayerState, id: u32) -> bool {
+        self.normal_actions_y_band
+            .get(&id)
+            .is_none_or(|y_band| is_player_in_y_band(player, *y_band))
+    }
+
     #[inline]
     fn rotate_queuing_linked_action(
         &mut self,
@@ -571,6 +1154,24 @@ impl Rotator {
     }
 }
 
+/// Checks whether any action of `action` presses a key
+#[inline]
+fn rotator_action_has_key(action: &RotatorAction) -> bool {
+    match action {
+        RotatorAction::Single(action) => matches!(action, PlayerAction::Key(_)),
+        RotatorAction::Linked(linked) => {
+            let mut current = Some(linked);
+            while let Some(linked) = current {
+                if matches!(linked.inner, PlayerAction::Key(_)) {
+                    return true;
+                }
+                current = linked.next.as_deref();
+            }
+            false
+        }
+    }
+}
+
 /// Creates a [`RotatorAction`] with `start_action` as the initial action
 ///
 /// If `start_action` is linked, this function returns [`RotatorAction::Linked`] with [`usize`] as
@@ -630,20 +1231,35 @@ fn priority_action(
     action: RotatorAction,
     condition: ActionCondition,
     queue_to_front: bool,
+    y_band: Option<YBand>,
 ) -> PriorityAction {
-    debug_assert_matches!(
+    debug_assert!(matches!(
         condition,
-        ActionCondition::EveryMillis(_) | ActionCondition::ErdaShowerOffCooldown
-    );
+        ActionCondition::EveryMillis(_)
+            | ActionCondition::ErdaShowerOffCooldown
+            | ActionCondition::AnyOf(_, _)
+            | ActionCondition::AllOf(_, _)
+            | ActionCondition::MpAbovePercent(_)
+    ));
     PriorityAction {
         inner: action,
-        condition: Condition(Box::new(move |context, _, last_queued_time| {
-            should_queue_fixed_action(context, last_queued_time, condition)
+        condition: Condition(Box::new(move |context, player, last_queued_time| {
+            y_band.is_none_or(|y_band| is_player_in_y_band(player, y_band))
+                && should_queue_fixed_action(
+                    context,
+                    last_queued_time,
+                    player.config.erda_shower_cooldown_millis,
+                    player.mana,
+                    condition,
+                )
         })),
         condition_kind: Some(condition),
         queue_to_front,
         ignoring: false,
         last_queued_time: None,
+        buff: None,
+        buff_verification: BuffVerification::None,
+        persist_key: None,
     }
 }
 
@@ -651,9 +1267,9 @@ fn priority_action(
 #[inline]
 fn elite_boss_potion_spam_priority_action(key: KeyBinding) -> PriorityAction {
     PriorityAction {
-        condition: Condition(Box::new(|context, _, last_queued_time| {
-            if !at_least_millis_passed_since(last_queued_time, COOLDOWN_BETWEEN_POTION_QUEUE_MILLIS)
-            {
+        condition: Condition(Box::new(|context, player, last_queued_time| {
+            let interval = player.config.tuning.elite_boss_potion_interval_millis as u128;
+            if !at_least_millis_passed_since(last_queued_time, interval) {
                 return false;
             }
             if let Minimap::Idle(idle) = context.minimap {
@@ -664,7 +1280,9 @@ fn elite_boss_potion_spam_priority_action(key: KeyBinding) -> PriorityAction {
         condition_kind: None,
         inner: RotatorAction::Single(PlayerAction::Key(PlayerActionKey {
             key,
+            modifier: KeyModifier::None,
             link_key: None,
+            link_key_modifier: KeyModifier::None,
             count: 1,
             position: None,
             direction: ActionKeyDirection::Any,
@@ -673,10 +1291,15 @@ fn elite_boss_potion_spam_priority_action(key: KeyBinding) -> PriorityAction {
             wait_before_use_ticks_random_range: 0,
             wait_after_use_ticks: 0,
             wait_after_use_ticks_random_range: 0,
+            hold_ticks: 0,
+            heavy: false,
         })),
         queue_to_front: true,
         ignoring: false,
         last_queued_time: None,
+        buff: None,
+        buff_verification: BuffVerification::None,
+        persist_key: None,
     }
 }
 
@@ -708,6 +1331,9 @@ fn solve_rune_priority_action() -> PriorityAction {
         queue_to_front: true,
         ignoring: false,
         last_queued_time: None,
+        buff: None,
+        buff_verification: BuffVerification::None,
+        persist_key: None,
     }
 }
 
@@ -726,7 +1352,9 @@ fn buff_priority_action(buff: BuffKind, key: KeyBinding) -> PriorityAction {
         condition_kind: None,
         inner: RotatorAction::Single(PlayerAction::Key(PlayerActionKey {
             key,
+            modifier: KeyModifier::None,
             link_key: None,
+            link_key_modifier: KeyModifier::None,
             count: 1,
             position: None,
             direction: ActionKeyDirection::Any,
@@ -735,60 +1363,503 @@ fn buff_priority_action(buff: BuffKind, key: KeyBinding) -> PriorityAction {
             wait_before_use_ticks_random_range: 0,
             wait_after_use_ticks: 10,
             wait_after_use_ticks_random_range: 0,
+            hold_ticks: 0,
+            heavy: false,
         })),
         queue_to_front: true,
         ignoring: false,
         last_queued_time: None,
+        buff: Some(buff),
+        buff_verification: BuffVerification::None,
+        persist_key: None,
     }
 }
 
 #[inline]
-fn at_least_millis_passed_since(last_queued_time: Option<Instant>, millis: u128) -> bool {
-    last_queued_time
-        .map(|instant| Instant::now().duration_since(instant).as_millis() >= millis)
-        .unwrap_or(true)
+fn scheduled_action(
+    id: u32,
+    condition: Option<ActionCondition>,
+    queue_to_front: bool,
+    action: &RotatorAction,
+) -> ScheduledAction {
+    let actions = match action {
+        RotatorAction::Single(action) => vec![player_action_summary(*action)],
+        RotatorAction::Linked(linked) => {
+            let mut actions = vec![player_action_summary(linked.inner)];
+            let mut current = linked.next.as_ref();
+            while let Some(linked) = current {
+                actions.push(player_action_summary(linked.inner));
+                current = linked.next.as_ref();
+            }
+            actions
+        }
+    };
+    ScheduledAction {
+        id,
+        condition,
+        queue_to_front,
+        actions,
+    }
 }
 
 #[inline]
-fn should_queue_fixed_action(
-    context: &Context,
-    last_queued_time: Option<Instant>,
-    condition: ActionCondition,
-) -> bool {
-    let millis_should_passed = match condition {
-        ActionCondition::EveryMillis(millis) => millis as u128,
-        ActionCondition::ErdaShowerOffCooldown => COOLDOWN_BETWEEN_QUEUE_MILLIS,
-        ActionCondition::Linked | ActionCondition::Any => unreachable!(),
-    };
-    if !at_least_millis_passed_since(last_queued_time, millis_should_passed) {
-        return false;
-    }
-    if matches!(condition, ActionCondition::ErdaShowerOffCooldown)
-        && !matches!(context.skills[SkillKind::ErdaShower], Skill::Idle(_, _))
-    {
-        return false;
+fn player_action_summary(action: PlayerAction) -> String {
+    match action {
+        PlayerAction::Key(PlayerActionKey {
+            key,
+            modifier,
+            count,
+            ..
+        }) => format!("Key({} x{count})", modifier.chord(key)),
+        PlayerAction::Move(PlayerActionMove { position, .. }) => {
+            format!("Move({}, {})", position.x, position.y)
+        }
+        PlayerAction::SolveRune | PlayerAction::AutoMob(_) => action.to_string(),
     }
-    true
 }
 
-#[cfg(test)]
-mod tests {
-    use std::time::{Duration, Instant};
+/// Checks if the player last known y position is inside `y_band`
+///
+/// Returns `true` if the player position is unknown.
+#[inline]
+fn is_player_in_y_band(player: &PlayerState, y_band: YBand) -> bool {
+    player
+        .last_known_pos
+        .is_none_or(|pos| y_band.contains(pos.y))
+}
 
-    use opencv::core::{Point, Vec4b};
+/// Computes the squared distance from `pos` to the first position of `action`
+///
+/// Returns `0` if either `pos` or the action position is unknown so that actions not requiring
+/// movement are executed first.
+#[inline]
+fn rotator_action_distance(action: &RotatorAction, pos: Option<Point>) -> i32 {
+    let action = match action {
+        RotatorAction::Single(action) => action,
+        RotatorAction::Linked(linked) => &linked.inner,
+    };
+    let position = match action {
+        PlayerAction::Key(PlayerActionKey { position, .. }) => *position,
+        PlayerAction::Move(PlayerActionMove { position, .. }) => Some(*position),
+        PlayerAction::SolveRune | PlayerAction::AutoMob(_) => None,
+    };
+    match (position, pos) {
+        (Some(position), Some(pos)) => {
+            let x = position.x - pos.x;
+            let y = position.y - pos.y;
+            x * x + y * y
+        }
+        _ => 0,
+    }
+}
 
-    use super::*;
-    use crate::{Position, buff::BuffKind, minimap::MinimapIdle, skill::SkillKind};
+/// Shrinks `bound` to the half that is on the opposite side of `point` x-wise
+///
+/// Useful for not overlapping auto mobbing area with another player on the same map.
+#[inline]
+fn split_bound_away_from(bound: Rect, point: Point) -> Rect {
+    let mid = bound.x + bound.width / 2;
+    if point.x < mid {
+        Rect::new(mid, bound.y, bound.x + bound.width - mid, bound.height)
+    } else {
+        Rect::new(bound.x, bound.y, mid - bound.x, bound.height)
+    }
+}
 
-    const NORMAL_ACTION: Action = Action::Move(ActionMove {
-        position: Position {
-            x: 0,
-            x_random_range: 0,
-            y: 0,
-            allow_adjusting: false,
-        },
-        condition: ActionCondition::Any,
-        wait_after_move_millis: 0,
+/// Computes the bounding rectangle of `polygon` if it has at least 3 vertices
+#[inline]
+fn polygon_bounding_rect(polygon: &[Point]) -> Option<Rect> {
+    if polygon.len() < 3 {
+        return None;
+    }
+    let x_min = polygon.iter().map(|point| point.x).min().unwrap();
+    let x_max = polygon.iter().map(|point| point.x).max().unwrap();
+    let y_min = polygon.iter().map(|point| point.y).min().unwrap();
+    let y_max = polygon.iter().map(|point| point.y).max().unwrap();
+    Some(Rect::new(x_min, y_min, x_max - x_min, y_max - y_min))
+}
+
+/// Verifies whether `buff` is applied after the action is executed
+///
+/// Must only be called when the action is neither queued nor executing so that a
+/// [`BuffVerification::Pending`] action is known to have completed. Returns
+/// [`BuffVerification::Deferred`] if `buff` is still not applied after [`BUFF_VERIFY_MILLIS`]
+/// since the action completed.
+#[inline]
+fn verify_buff(
+    context: &Context,
+    buff: BuffKind,
+    verification: BuffVerification,
+    normal_loops: u32,
+) -> BuffVerification {
+    let has_buff = !matches!(context.buffs[buff], Buff::NoBuff);
+    match verification {
+        BuffVerification::Pending => BuffVerification::Executed(Instant::now()),
+        BuffVerification::Executed(instant)
+            if at_least_millis_passed_since(Some(instant), BUFF_VERIFY_MILLIS) =>
+        {
+            if has_buff {
+                BuffVerification::None
+            } else {
+                debug!(target: "rotator", "buff {buff:?} not applied, deferring to next loop");
+                BuffVerification::Deferred(normal_loops)
+            }
+        }
+        BuffVerification::Deferred(_) if has_buff => BuffVerification::None,
+        BuffVerification::None | BuffVerification::Executed(_) | BuffVerification::Deferred(_) => {
+            verification
+        }
+    }
+}
+
+/// Creates a key identifying `action` across rebuilds and app restarts
+///
+/// Identical actions are disambiguated by their occurrence order tracked in `keys`.
+#[inline]
+fn persist_key(action: Action, keys: &mut HashMap<String, usize>) -> String {
+    let action = serde_json::to_string(&action).unwrap_or_default();
+    let occurrence = keys.entry(action.clone()).or_default();
+    let key = format!("{action}#{occurrence}");
+    *occurrence += 1;
+    key
+}
+
+/// Snaps the positions of Erda Shower condition actions to the midpoint of the platforms they
+/// lie on
+///
+/// A position lies on a platform if its x is within the platform and its y is within
+/// [`ERDA_PLATFORM_SNAP_Y_TOLERANCE`] of the platform. The closest platform by y is picked when
+/// there are multiple. Positions not on any platform are left as is.
+pub(crate) fn snap_erda_actions_to_platform_midpoints(
+    actions: &mut [Action],
+    platforms: &[Platform],
+) {
+    for action in actions.iter_mut() {
+        let position = match action {
+            Action::Move(ActionMove {
+                position,
+                condition,
+                ..
+            })
+            | Action::Key(ActionKey {
+                position: Some(position),
+                condition,
+                ..
+            }) if has_erda_shower_condition(*condition) => position,
+            _ => continue,
+        };
+        let platform = platforms
+            .iter()
+            .filter(|platform| {
+                (platform.x_start..=platform.x_end).contains(&position.x)
+                    && (platform.y - position.y).abs() <= ERDA_PLATFORM_SNAP_Y_TOLERANCE
+            })
+            .min_by_key(|platform| (platform.y - position.y).abs());
+        if let Some(platform) = platform {
+            position.x = (platform.x_start + platform.x_end) / 2;
+            position.y = platform.y;
+        }
+    }
+}
+
+/// Resolves the positions of `actions` anchored to another action's position
+///
+/// An anchored action uses the x and y of the action at [`ActionMove::position_anchor`] or
+/// [`ActionKey::position_anchor`], following the chain if that action is also anchored. An
+/// anchor that is out of bound, has no position or is part of a cycle is ignored and the action
+/// keeps its own position.
+pub(crate) fn resolve_anchored_positions(actions: &mut [Action]) {
+    let resolved = (0..actions.len())
+        .map(|index| resolve_anchored_position(actions, index))
+        .collect::<Vec<_>>();
+    for (action, resolved) in actions.iter_mut().zip(resolved) {
+        let Some((x, y)) = resolved else {
+            continue;
+        };
+        if let Action::Move(ActionMove { position, .. })
+        | Action::Key(ActionKey {
+            position: Some(position),
+            ..
+        }) = action
+        {
+            position.x = x;
+            position.y = y;
+        }
+    }
+}
+
+/// Follows the anchors starting from the action at `index` and returns the anchored x and y
+#[inline]
+fn resolve_anchored_position(actions: &[Action], index: usize) -> Option<(i32, i32)> {
+    let mut visited = vec![index];
+    let mut current = index;
+    while let Some(anchor) = action_position_anchor(actions[current]) {
+        if anchor >= actions.len() {
+            debug!(target: "rotator", "action {current} anchors to missing action {anchor}");
+            return None;
+        }
+        if visited.contains(&anchor) {
+            debug!(target: "rotator", "action {index} has cyclic position anchors {visited:?}");
+            return None;
+        }
+        visited.push(anchor);
+        current = anchor;
+    }
+    if current == index {
+        return None;
+    }
+    match actions[current] {
+        Action::Move(ActionMove { position, .. })
+        | Action::Key(ActionKey {
+            position: Some(position),
+            ..
+        }) => Some((position.x, position.y)),
+        Action::Key(ActionKey { position: None, .. }) => None,
+    }
+}
+
+#[inline]
+fn action_position_anchor(action: Action) -> Option<usize> {
+    match action {
+        Action::Move(ActionMove {
+            position_anchor, ..
+        }) => position_anchor,
+        Action::Key(ActionKey {
+            position: Some(_),
+            position_anchor,
+            ..
+        }) => position_anchor,
+        Action::Key(ActionKey { position: None, .. }) => None,
+    }
+}
+
+/// Finds the index of the platform in `platforms` the `point` is on
+#[inline]
+fn auto_mob_platform_index(
+    platforms: &Array<PlatformWithNeighbors, MAX_PLATFORMS_COUNT>,
+    point: Point,
+) -> Option<usize> {
+    platforms
+        .iter()
+        .enumerate()
+        .filter(|(_, platform)| {
+            platform.xs().contains(&point.x)
+                && (platform.y() - point.y).abs() <= AUTO_MOB_PLATFORM_Y_TOLERANCE
+        })
+        .min_by_key(|(_, platform)| (platform.y() - point.y).abs())
+        .map(|(index, _)| index)
+}
+
+/// Computes the weights for choosing one of `points` as the auto mobbing target
+///
+/// A point on a platform with fewer ticks spent has a higher weight. A point not on any
+/// platform has the same weight as a point on the most visited platform.
+fn auto_mob_platform_weights(
+    platforms: &Array<PlatformWithNeighbors, MAX_PLATFORMS_COUNT>,
+    platform_ticks: &HashMap<usize, u32>,
+    points: &[Point],
+) -> Vec<u32> {
+    let max_ticks = platform_ticks.values().copied().max().unwrap_or_default();
+    points
+        .iter()
+        .map(|point| {
+            let ticks = match auto_mob_platform_index(platforms, *point) {
+                Some(index) => platform_ticks.get(&index).copied().unwrap_or_default(),
+                None => max_ticks,
+            };
+            (max_ticks - ticks).saturating_add(1)
+        })
+        .collect()
+}
+
+/// Computes the weights for choosing one of `points` as the auto mobbing target based on `pos`
+///
+/// A point on the same y band as `pos` has a weight of `100` while other points have a weight of
+/// `100 - preference`. All points have the same weight if none is on the same y band.
+fn auto_mob_same_y_weights(pos: Point, points: &[Point], preference: u32) -> Vec<u32> {
+    let preference = preference.min(100);
+    let is_same_y = |point: &Point| (point.y - pos.y).abs() <= AUTO_MOB_PLATFORM_Y_TOLERANCE;
+    if !points.iter().any(is_same_y) {
+        return vec![1; points.len()];
+    }
+    points
+        .iter()
+        .map(|point| {
+            if is_same_y(point) {
+                100
+            } else {
+                100 - preference
+            }
+        })
+        .collect()
+}
+
+/// Whether `condition` is [`ActionCondition::ErdaShowerOffCooldown`] or has it as an operand
+#[inline]
+fn has_erda_shower_condition(condition: ActionCondition) -> bool {
+    match condition {
+        ActionCondition::ErdaShowerOffCooldown => true,
+        ActionCondition::AnyOf(first, second) | ActionCondition::AllOf(first, second) => {
+            matches!(first, ActionConditionOperand::ErdaShowerOffCooldown)
+                || matches!(second, ActionConditionOperand::ErdaShowerOffCooldown)
+        }
+        ActionCondition::Any
+        | ActionCondition::Linked
+        | ActionCondition::EveryMillis(_)
+        | ActionCondition::MpAbovePercent(_) => false,
+    }
+}
+
+#[inline]
+fn has_every_millis_condition(condition: ActionCondition) -> bool {
+    match condition {
+        ActionCondition::EveryMillis(_) => true,
+        ActionCondition::AnyOf(first, second) | ActionCondition::AllOf(first, second) => {
+            matches!(first, ActionConditionOperand::EveryMillis(_))
+                || matches!(second, ActionConditionOperand::EveryMillis(_))
+        }
+        ActionCondition::Any
+        | ActionCondition::Linked
+        | ActionCondition::ErdaShowerOffCooldown
+        | ActionCondition::MpAbovePercent(_) => false,
+    }
+}
+
+#[inline]
+fn epoch_millis_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Converts a wall-clock time in milliseconds to [`Instant`]
+///
+/// Returns [`None`] if the time is too far in the past to be represented.
+#[inline]
+fn instant_from_epoch_millis(millis: u64) -> Option<Instant> {
+    let elapsed = epoch_millis_now().saturating_sub(millis);
+    Instant::now().checked_sub(Duration::from_millis(elapsed))
+}
+
+#[inline]
+fn at_least_millis_passed_since(last_queued_time: Option<Instant>, millis: u128) -> bool {
+    last_queued_time
+        .map(|instant| Instant::now().duration_since(instant).as_millis() >= millis)
+        .unwrap_or(true)
+}
+
+#[inline]
+fn should_queue_fixed_action(
+    context: &Context,
+    last_queued_time: Option<Instant>,
+    erda_cooldown_millis: u64,
+    mana: Option<(u32, u32)>,
+    condition: ActionCondition,
+) -> bool {
+    let should_queue = |operand| {
+        should_queue_fixed_action_operand(
+            context,
+            last_queued_time,
+            erda_cooldown_millis,
+            mana,
+            operand,
+        )
+    };
+    match condition {
+        ActionCondition::EveryMillis(millis) => {
+            should_queue(ActionConditionOperand::EveryMillis(millis))
+        }
+        ActionCondition::ErdaShowerOffCooldown => {
+            should_queue(ActionConditionOperand::ErdaShowerOffCooldown)
+        }
+        ActionCondition::AnyOf(first, second) => should_queue(first) || should_queue(second),
+        ActionCondition::AllOf(first, second) => should_queue(first) && should_queue(second),
+        ActionCondition::MpAbovePercent(percent) => {
+            at_least_millis_passed_since(last_queued_time, COOLDOWN_BETWEEN_QUEUE_MILLIS)
+                && should_queue(ActionConditionOperand::MpAbovePercent(percent))
+        }
+        ActionCondition::Linked | ActionCondition::Any => unreachable!(),
+    }
+}
+
+/// Checks whether an action with `operand` should be queued
+///
+/// When the Erda Shower icon is not visible (e.g. skill bar hidden or covered), the cooldown is
+/// estimated from `last_queued_time` and `erda_cooldown_millis` if it is not `0`. Because
+/// Erda Shower actions keep updating their last queued time while any of them is executing,
+/// `last_queued_time` is close to when the skill was last casted. The icon state takes over again
+/// once it is visible.
+///
+/// An MP operand is always satisfied when `mana` is not detected so that enabling the condition
+/// without mana detection does not block the action forever.
+#[inline]
+fn should_queue_fixed_action_operand(
+    context: &Context,
+    last_queued_time: Option<Instant>,
+    erda_cooldown_millis: u64,
+    mana: Option<(u32, u32)>,
+    operand: ActionConditionOperand,
+) -> bool {
+    let millis_should_passed = match operand {
+        ActionConditionOperand::EveryMillis(millis) => millis as u128,
+        ActionConditionOperand::ErdaShowerOffCooldown
+        | ActionConditionOperand::BuffActive(_)
+        | ActionConditionOperand::BuffMissing(_) => COOLDOWN_BETWEEN_QUEUE_MILLIS,
+        ActionConditionOperand::MpAbovePercent(_) => 0,
+    };
+    if !at_least_millis_passed_since(last_queued_time, millis_should_passed) {
+        return false;
+    }
+    if let ActionConditionOperand::MpAbovePercent(percent) = operand {
+        return mana
+            .is_none_or(|(current, max)| max > 0 && current as f32 / max as f32 * 100.0 > percent);
+    }
+    if let ActionConditionOperand::BuffActive(kind) | ActionConditionOperand::BuffMissing(kind) =
+        operand
+    {
+        let has_buff = !matches!(context.buffs[kind], Buff::NoBuff);
+        return has_buff == matches!(operand, ActionConditionOperand::BuffActive(_));
+    }
+    if matches!(operand, ActionConditionOperand::ErdaShowerOffCooldown) {
+        return match context.skills[SkillKind::ErdaShower] {
+            Skill::Idle(_, _) => true,
+            Skill::Detecting if erda_cooldown_millis > 0 => {
+                at_least_millis_passed_since(last_queued_time, erda_cooldown_millis as u128)
+            }
+            Skill::Detecting | Skill::Cooldown => false,
+        };
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use opencv::core::{Point, Vec4b};
+
+    use super::*;
+    use crate::{
+        Position,
+        buff::BuffKind,
+        minimap::MinimapIdle,
+        pathing::{self, find_neighbors},
+        skill::SkillKind,
+    };
+
+    const NORMAL_ACTION: Action = Action::Move(ActionMove {
+        position: Position {
+            x: 0,
+            x_random_range: 0,
+            y: 0,
+            allow_adjusting: false,
+            arrival_tolerance: None,
+        },
+        condition: ActionCondition::Any,
+        wait_after_move_millis: 0,
+        y_band: None,
+        position_anchor: None,
     });
     const PRIORITY_ACTION: Action = Action::Move(ActionMove {
         position: Position {
@@ -796,11 +1867,123 @@ mod tests {
             x_random_range: 0,
             y: 0,
             allow_adjusting: false,
+            arrival_tolerance: None,
         },
         condition: ActionCondition::ErdaShowerOffCooldown,
         wait_after_move_millis: 0,
+        y_band: None,
+        position_anchor: None,
     });
 
+    #[test]
+    fn snap_erda_actions_to_platform_midpoints_on_platform_only() {
+        let erda_at = |x, y| {
+            Action::Move(ActionMove {
+                position: Position {
+                    x,
+                    y,
+                    ..Position::default()
+                },
+                condition: ActionCondition::ErdaShowerOffCooldown,
+                wait_after_move_millis: 0,
+                y_band: None,
+                position_anchor: None,
+            })
+        };
+        let platforms = [
+            Platform {
+                x_start: 10,
+                x_end: 50,
+                y: 20,
+            },
+            Platform {
+                x_start: 0,
+                x_end: 100,
+                y: 24,
+            },
+        ];
+        let mut actions = [
+            erda_at(15, 21),
+            erda_at(70, 30),
+            NORMAL_ACTION,
+            erda_at(5, 21),
+        ];
+
+        snap_erda_actions_to_platform_midpoints(&mut actions, &platforms);
+
+        assert_eq!(actions[0], erda_at(30, 20));
+        assert_eq!(actions[1], erda_at(70, 30));
+        assert_eq!(actions[2], NORMAL_ACTION);
+        assert_eq!(actions[3], erda_at(50, 24));
+    }
+
+    #[test]
+    fn snap_erda_actions_to_platform_midpoints_composite_condition() {
+        let action_at = |x, y, condition| {
+            Action::Move(ActionMove {
+                position: Position {
+                    x,
+                    y,
+                    ..Position::default()
+                },
+                condition,
+                wait_after_move_millis: 0,
+                y_band: None,
+                position_anchor: None,
+            })
+        };
+        let all_of = ActionCondition::AllOf(
+            ActionConditionOperand::ErdaShowerOffCooldown,
+            ActionConditionOperand::BuffActive(BuffKind::SayramElixir),
+        );
+        let every_millis = ActionCondition::AnyOf(
+            ActionConditionOperand::EveryMillis(1000),
+            ActionConditionOperand::MpAbovePercent(50.0),
+        );
+        let platforms = [Platform {
+            x_start: 10,
+            x_end: 50,
+            y: 20,
+        }];
+        let mut actions = [action_at(15, 21, all_of), action_at(15, 21, every_millis)];
+
+        snap_erda_actions_to_platform_midpoints(&mut actions, &platforms);
+
+        assert_eq!(actions[0], action_at(30, 20, all_of));
+        assert_eq!(actions[1], action_at(15, 21, every_millis));
+    }
+
+    #[test]
+    fn resolve_anchored_positions_follows_chain_and_ignores_cycle() {
+        let move_at = |x, y, position_anchor| {
+            Action::Move(ActionMove {
+                position: Position {
+                    x,
+                    y,
+                    ..Position::default()
+                },
+                position_anchor,
+                ..ActionMove::default()
+            })
+        };
+        let mut actions = [
+            move_at(10, 20, None),
+            move_at(0, 0, Some(0)),
+            move_at(0, 0, Some(1)),
+            move_at(1, 1, Some(4)),
+            move_at(2, 2, Some(3)),
+            move_at(3, 3, Some(9)),
+        ];
+
+        resolve_anchored_positions(&mut actions);
+
+        assert_eq!(actions[1], move_at(10, 20, Some(0)));
+        assert_eq!(actions[2], move_at(10, 20, Some(1)));
+        assert_eq!(actions[3], move_at(1, 1, Some(4)));
+        assert_eq!(actions[4], move_at(2, 2, Some(3)));
+        assert_eq!(actions[5], move_at(3, 3, Some(9)));
+    }
+
     #[test]
     fn rotator_at_least_millis_passed_since() {
         let now = Instant::now();
@@ -815,6 +1998,41 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn rotator_split_bound_away_from() {
+        let bound = Rect::new(10, 5, 100, 50);
+
+        assert_eq!(
+            split_bound_away_from(bound, Point::new(20, 0)),
+            Rect::new(60, 5, 50, 50)
+        );
+        assert_eq!(
+            split_bound_away_from(bound, Point::new(90, 0)),
+            Rect::new(10, 5, 50, 50)
+        );
+    }
+
+    #[test]
+    fn rotator_polygon_bounding_rect() {
+        assert_eq!(
+            polygon_bounding_rect(&[Point::new(10, 5), Point::new(50, 5)]),
+            None
+        );
+        // L-shaped area
+        let polygon = [
+            Point::new(10, 5),
+            Point::new(50, 5),
+            Point::new(50, 30),
+            Point::new(100, 30),
+            Point::new(100, 60),
+            Point::new(10, 60),
+        ];
+        assert_eq!(
+            polygon_bounding_rect(&polygon),
+            Some(Rect::new(10, 5, 90, 55))
+        );
+    }
+
     #[test]
     fn rotator_should_queue_fixed_action_every_millis() {
         let context = Context::new(None, None);
@@ -823,11 +2041,15 @@ mod tests {
         assert!(should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(3000)),
+            0,
+            None,
             ActionCondition::EveryMillis(2000)
         ));
         assert!(!should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(1000)),
+            0,
+            None,
             ActionCondition::EveryMillis(2000)
         ));
     }
@@ -841,11 +2063,15 @@ mod tests {
         assert!(!should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64 - 1000)),
+            0,
+            None,
             ActionCondition::ErdaShowerOffCooldown
         ));
         assert!(should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64)),
+            0,
+            None,
             ActionCondition::ErdaShowerOffCooldown
         ));
 
@@ -853,10 +2079,187 @@ mod tests {
         assert!(!should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64)),
+            0,
+            None,
+            ActionCondition::ErdaShowerOffCooldown
+        ));
+
+        // estimates from last queued time when the icon is not visible
+        assert!(!should_queue_fixed_action(
+            &context,
+            Some(now - Duration::from_millis(30000)),
+            60000,
+            None,
+            ActionCondition::ErdaShowerOffCooldown
+        ));
+        assert!(should_queue_fixed_action(
+            &context,
+            Some(now - Duration::from_millis(60000)),
+            60000,
+            None,
+            ActionCondition::ErdaShowerOffCooldown
+        ));
+
+        // visible icon takes over the estimation
+        context.skills[SkillKind::ErdaShower] = Skill::Cooldown;
+        assert!(!should_queue_fixed_action(
+            &context,
+            Some(now - Duration::from_millis(60000)),
+            60000,
+            None,
             ActionCondition::ErdaShowerOffCooldown
         ));
     }
 
+    #[test]
+    fn rotator_should_queue_fixed_action_any_of_all_of() {
+        let mut context = Context::new(None, None);
+        let now = Instant::now();
+        let last_queued_time = Some(now - Duration::from_millis(3000));
+        let any_of = ActionCondition::AnyOf(
+            ActionConditionOperand::EveryMillis(2000),
+            ActionConditionOperand::ErdaShowerOffCooldown,
+        );
+        let all_of = ActionCondition::AllOf(
+            ActionConditionOperand::EveryMillis(2000),
+            ActionConditionOperand::ErdaShowerOffCooldown,
+        );
+
+        context.skills[SkillKind::ErdaShower] = Skill::Detecting;
+        assert!(should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            None,
+            any_of
+        ));
+        assert!(!should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            None,
+            all_of
+        ));
+
+        context.skills[SkillKind::ErdaShower] = Skill::Idle(Point::default(), Vec4b::default());
+        assert!(should_queue_fixed_action(
+            &context,
+            Some(now - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64)),
+            0,
+            None,
+            all_of
+        ));
+    }
+
+    #[test]
+    fn rotator_should_queue_fixed_action_buff_operands() {
+        let mut context = Context::new(None, None);
+        let last_queued_time =
+            Some(Instant::now() - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64));
+        let erda_and_buff = ActionCondition::AllOf(
+            ActionConditionOperand::ErdaShowerOffCooldown,
+            ActionConditionOperand::BuffActive(BuffKind::SayramElixir),
+        );
+        let missing = ActionCondition::AnyOf(
+            ActionConditionOperand::EveryMillis(u64::MAX),
+            ActionConditionOperand::BuffMissing(BuffKind::SayramElixir),
+        );
+
+        context.skills[SkillKind::ErdaShower] = Skill::Idle(Point::default(), Vec4b::default());
+        assert!(!should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            None,
+            erda_and_buff
+        ));
+        assert!(should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            None,
+            missing
+        ));
+
+        context.buffs[BuffKind::SayramElixir] = Buff::HasBuff;
+        assert!(should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            None,
+            erda_and_buff
+        ));
+        assert!(!should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            None,
+            missing
+        ));
+    }
+
+    #[test]
+    fn rotator_should_queue_fixed_action_mp_above_percent() {
+        let context = Context::new(None, None);
+        let last_queued_time = Some(Instant::now() - Duration::from_millis(3000));
+        let all_of = ActionCondition::AllOf(
+            ActionConditionOperand::EveryMillis(2000),
+            ActionConditionOperand::MpAbovePercent(50.0),
+        );
+
+        assert!(should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            Some((60, 100)),
+            all_of
+        ));
+        assert!(!should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            Some((40, 100)),
+            all_of
+        ));
+        // not blocked when mana is not detected
+        assert!(should_queue_fixed_action(
+            &context,
+            last_queued_time,
+            0,
+            None,
+            all_of
+        ));
+    }
+
+    #[test]
+    fn rotator_schedule_start_to_end_then_reverse() {
+        let mut rotator = Rotator::default();
+        let actions = vec![NORMAL_ACTION, PRIORITY_ACTION, NORMAL_ACTION];
+
+        rotator.build_actions(
+            RotatorMode::StartToEndThenReverse,
+            &actions,
+            &HashMap::new(),
+            &[],
+            KeyBinding::A,
+            false,
+            false,
+        );
+        let schedule = rotator.schedule();
+        let normal_ids = schedule
+            .normal_actions
+            .iter()
+            .map(|action| action.id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(normal_ids, vec![0, 2, 2, 0]);
+        assert_eq!(schedule.priority_actions.len(), 2);
+        assert_eq!(
+            schedule.priority_actions[0].condition,
+            Some(ActionCondition::ErdaShowerOffCooldown)
+        );
+    }
+
     #[test]
     fn rotator_build_actions() {
         let mut rotator = Rotator::default();
@@ -866,6 +2269,7 @@ mod tests {
         rotator.build_actions(
             RotatorMode::default(),
             &actions,
+            &HashMap::new(),
             &buffs,
             KeyBinding::A,
             true,
@@ -875,6 +2279,166 @@ mod tests {
         assert_eq!(rotator.normal_actions.len(), 2);
     }
 
+    #[test]
+    fn rotator_build_actions_restores_every_millis_last_queued_time() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let context = Context::new(None, None);
+        let action = Action::Move(ActionMove {
+            condition: ActionCondition::EveryMillis(60_000),
+            ..ActionMove::default()
+        });
+        let actions = vec![action, action];
+
+        rotator.build_actions(
+            RotatorMode::default(),
+            &actions,
+            &HashMap::new(),
+            &[],
+            KeyBinding::A,
+            false,
+            false,
+        );
+        rotator.rotate_priority_actions(&context, &mut player);
+        let fired = rotator.take_unsaved_fired_timestamps();
+        assert_eq!(fired.len(), 2);
+        assert_ne!(fired[0].0, fired[1].0);
+        assert!(rotator.take_unsaved_fired_timestamps().is_empty());
+
+        let mut rebuilt = Rotator::default();
+        rebuilt.load_fired_timestamps(fired.into_iter().collect());
+        rebuilt.build_actions(
+            RotatorMode::default(),
+            &actions,
+            &HashMap::new(),
+            &[],
+            KeyBinding::A,
+            false,
+            false,
+        );
+        rebuilt.rotate_priority_actions(&context, &mut player);
+        assert!(rebuilt.priority_actions_queue.is_empty());
+        let persisted = rebuilt
+            .priority_actions
+            .values()
+            .filter(|action| action.persist_key.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(persisted.len(), 2);
+        assert!(
+            persisted
+                .iter()
+                .all(|action| action.last_queued_time.is_some())
+        );
+    }
+
+    #[test]
+    fn rotator_build_actions_restores_stable_id_last_queued_time_after_edit() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let context = Context::new(None, None);
+        let action = |millis| {
+            Action::Move(ActionMove {
+                condition: ActionCondition::EveryMillis(millis),
+                ..ActionMove::default()
+            })
+        };
+        let stable_ids = HashMap::from([(0, 7)]);
+
+        rotator.build_actions(
+            RotatorMode::default(),
+            &[action(60_000)],
+            &stable_ids,
+            &[],
+            KeyBinding::A,
+            false,
+            false,
+        );
+        rotator.rotate_priority_actions(&context, &mut player);
+        let fired = rotator.take_unsaved_fired_timestamps();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].0, "stable#7");
+
+        let mut rebuilt = Rotator::default();
+        rebuilt.load_fired_timestamps(fired.into_iter().collect());
+        rebuilt.build_actions(
+            RotatorMode::default(),
+            &[action(120_000)],
+            &stable_ids,
+            &[],
+            KeyBinding::A,
+            false,
+            false,
+        );
+        let persisted = rebuilt
+            .priority_actions
+            .values()
+            .filter(|action| action.persist_key.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(persisted.len(), 1);
+        assert!(
+            persisted
+                .iter()
+                .all(|action| action.last_queued_time.is_some())
+        );
+    }
+
+    #[test]
+    fn rotator_auto_mob_platform_weights() {
+        let platforms = Array::from_iter(find_neighbors(
+            &[
+                pathing::Platform::new(0..10, 5),
+                pathing::Platform::new(20..30, 5),
+            ],
+            25,
+            7,
+            41,
+        ));
+        let ticks = HashMap::from([(0, 100), (1, 40)]);
+        let points = [Point::new(5, 5), Point::new(25, 6), Point::new(15, 5)];
+
+        assert_eq!(auto_mob_platform_index(&platforms, points[0]), Some(0));
+        assert_eq!(auto_mob_platform_index(&platforms, points[1]), Some(1));
+        assert_eq!(auto_mob_platform_index(&platforms, points[2]), None);
+        assert_eq!(
+            auto_mob_platform_weights(&platforms, &ticks, &points),
+            vec![1, 61, 1]
+        );
+    }
+
+    #[test]
+    fn rotator_auto_mob_same_y_weights() {
+        let pos = Point::new(10, 20);
+        let points = [Point::new(5, 22), Point::new(30, 40), Point::new(50, 0)];
+
+        assert_eq!(
+            auto_mob_same_y_weights(pos, &points, 0),
+            vec![100, 100, 100]
+        );
+        assert_eq!(auto_mob_same_y_weights(pos, &points, 75), vec![100, 25, 25]);
+        assert_eq!(auto_mob_same_y_weights(pos, &points, 150), vec![100, 0, 0]);
+        assert_eq!(auto_mob_same_y_weights(pos, &points[1..], 100), vec![1, 1]);
+    }
+
+    #[test]
+    fn rotator_update_auto_mob_rest_cycle() {
+        let mut rotator = Rotator::default();
+
+        assert!(!rotator.update_auto_mob_rest_cycle(0, 1));
+        assert!(rotator.auto_mob_rest_cycle.is_none());
+
+        assert!(!rotator.update_auto_mob_rest_cycle(2, 1));
+        let cycle = rotator.auto_mob_rest_cycle.as_mut().unwrap();
+        cycle.started = Instant::now() - Duration::from_secs(120);
+        assert!(rotator.update_auto_mob_rest_cycle(2, 1));
+
+        let cycle = rotator.auto_mob_rest_cycle.as_mut().unwrap();
+        cycle.started = Instant::now() - Duration::from_secs(30);
+        assert!(rotator.update_auto_mob_rest_cycle(2, 1));
+        let cycle = rotator.auto_mob_rest_cycle.as_mut().unwrap();
+        cycle.started = Instant::now() - Duration::from_secs(60);
+        assert!(!rotator.update_auto_mob_rest_cycle(2, 1));
+    }
+
     #[test]
     fn rotator_rotate_action_start_to_end_then_reverse() {
         let mut rotator = Rotator::default();
@@ -925,6 +2489,107 @@ mod tests {
         assert_eq!(rotator.normal_index, 0);
     }
 
+    #[test]
+    fn rotator_rotate_action_skips_normal_action_outside_y_band() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let context = Context::new(None, None);
+        rotator.normal_rotate_mode = RotatorMode::StartToEnd;
+        for i in 0..2 {
+            rotator
+                .normal_actions
+                .push((i, RotatorAction::Single(NORMAL_ACTION.into())));
+        }
+        rotator.normal_actions_y_band.insert(
+            0,
+            YBand {
+                y_min: 30,
+                y_max: 50,
+            },
+        );
+        player.last_known_pos = Some(Point::new(0, 10));
+
+        rotator.rotate_action(&context, &mut player);
+        assert!(!player.has_normal_action());
+        assert_eq!(rotator.normal_index, 1);
+
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(player.normal_action_id(), Some(1));
+
+        player.clear_actions_aborted();
+        player.last_known_pos = Some(Point::new(0, 40));
+
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(player.normal_action_id(), Some(0));
+    }
+
+    #[test]
+    fn rotator_priority_action_outside_y_band_not_queued() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let context = Context::new(None, None);
+        rotator.priority_actions.insert(
+            0,
+            priority_action(
+                RotatorAction::Single(NORMAL_ACTION.into()),
+                ActionCondition::EveryMillis(0),
+                false,
+                Some(YBand {
+                    y_min: 30,
+                    y_max: 50,
+                }),
+            ),
+        );
+        player.last_known_pos = Some(Point::new(0, 10));
+
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert!(rotator.priority_actions_queue.is_empty());
+
+        player.last_known_pos = Some(Point::new(0, 40));
+
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert_eq!(rotator.priority_actions_queue, [0]);
+    }
+
+    #[test]
+    fn rotator_rotate_action_nearest_first() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let context = Context::new(None, None);
+        rotator.normal_rotate_mode = RotatorMode::NearestFirst;
+        for (i, x) in [100, 10, 50].into_iter().enumerate() {
+            let action = Action::Move(ActionMove {
+                position: Position {
+                    x,
+                    ..Position::default()
+                },
+                ..ActionMove::default()
+            });
+            rotator
+                .normal_actions
+                .push((i as u32, RotatorAction::Single(action.into())));
+        }
+        player.last_known_pos = Some(Point::new(0, 0));
+
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(player.normal_action_id(), Some(1));
+        assert_eq!(rotator.normal_executed_ids, vec![1]);
+
+        player.clear_actions_aborted();
+        player.last_known_pos = Some(Point::new(10, 0));
+
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(player.normal_action_id(), Some(2));
+
+        player.clear_actions_aborted();
+        player.last_known_pos = Some(Point::new(0, 0));
+
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(player.normal_action_id(), Some(0));
+        assert!(rotator.normal_executed_ids.is_empty());
+        assert_eq!(rotator.normal_loops, 1);
+    }
+
     #[test]
     fn rotator_priority_action_queue() {
         let mut rotator = Rotator::default();
@@ -945,6 +2610,9 @@ mod tests {
                 queue_to_front: true,
                 ignoring: false,
                 last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
             },
         );
 
@@ -953,6 +2621,237 @@ mod tests {
         assert_eq!(player.priority_action_id(), Some(55));
     }
 
+    #[test]
+    fn rotator_auto_mob_paused_keeps_priority_actions() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        player.last_known_pos = Some(Point::default());
+        let mut context = Context::new(None, None);
+        context.minimap = Minimap::Idle(MinimapIdle::default());
+        rotator.normal_rotate_mode = RotatorMode::AutoMobbing(AutoMobbing::default());
+        rotator.set_auto_mob_paused(true);
+        rotator.priority_actions.insert(
+            55,
+            PriorityAction {
+                condition: Condition(Box::new(|_, _, _| true)),
+                condition_kind: None,
+                inner: RotatorAction::Single(PRIORITY_ACTION.into()),
+                queue_to_front: false,
+                ignoring: false,
+                last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
+            },
+        );
+
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(player.priority_action_id(), Some(55));
+
+        player.clear_actions_aborted();
+        rotator.rotate_action(&context, &mut player);
+        assert!(!player.has_normal_action());
+        assert!(rotator.auto_mob_task.is_none());
+    }
+
+    #[test]
+    fn rotator_warm_up() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let mut context = Context::new(None, None);
+        context.minimap = Minimap::Idle(MinimapIdle::default());
+        context.buffs[BuffKind::Rune] = Buff::HasBuff;
+        rotator
+            .priority_actions
+            .insert(1, buff_priority_action(BuffKind::Rune, KeyBinding::A));
+        rotator.set_warm_up(
+            true,
+            Some(Position {
+                x: 10,
+                ..Position::default()
+            }),
+        );
+        rotator.reset_queue();
+
+        // queues buff even when already applied
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(rotator.priority_actions_queue, [1]);
+        assert_eq!(rotator.warm_up, Some(WarmUp::Casting));
+
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(player.priority_action_id(), Some(1));
+        player.take_priority_action();
+
+        // moves to start point after buffs are executed
+        rotator.rotate_action(&context, &mut player);
+        assert!(player.has_normal_action());
+        assert_eq!(rotator.warm_up, Some(WarmUp::Moving));
+        player.reset_normal_action();
+
+        // completes without waiting for Erda Shower when there is no Erda action
+        rotator.rotate_action(&context, &mut player);
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(rotator.warm_up, None);
+    }
+
+    #[test]
+    fn rotator_start_point() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let mut context = Context::new(None, None);
+        context.minimap = Minimap::Idle(MinimapIdle::default());
+        rotator.set_start_position(Some(Position {
+            x: 10,
+            ..Position::default()
+        }));
+        rotator.reset_queue();
+        assert_eq!(rotator.warm_up, Some(WarmUp::Casting));
+
+        // moves to start point before any normal action
+        rotator.rotate_action(&context, &mut player);
+        assert!(player.has_normal_action());
+        assert_eq!(rotator.warm_up, Some(WarmUp::Moving));
+        player.reset_normal_action();
+
+        // completes without the other warm up stages
+        rotator.rotate_action(&context, &mut player);
+        assert_eq!(rotator.warm_up, None);
+
+        // moves again when rotation resumes from halt
+        rotator.reset_queue();
+        assert_eq!(rotator.warm_up, Some(WarmUp::Casting));
+    }
+
+    #[test]
+    fn rotator_start_point_takes_precedence_over_warm_up_position() {
+        let mut rotator = Rotator::default();
+        let warm_up_position = Position {
+            x: 5,
+            ..Position::default()
+        };
+        let start_position = Position {
+            x: 10,
+            ..Position::default()
+        };
+
+        rotator.set_warm_up(true, Some(warm_up_position));
+        assert_eq!(rotator.warm_up_move_position(), Some(warm_up_position));
+
+        rotator.set_start_position(Some(start_position));
+        assert_eq!(rotator.warm_up_move_position(), Some(start_position));
+
+        rotator.set_warm_up(false, Some(warm_up_position));
+        rotator.set_start_position(None);
+        assert_eq!(rotator.warm_up_move_position(), None);
+    }
+
+    #[test]
+    fn rotator_safe_mode_holds_key_priority_actions() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let context = Context::new(None, None);
+        let key_action = Action::Key(ActionKey {
+            condition: ActionCondition::EveryMillis(0),
+            ..ActionKey::default()
+        });
+        rotator.priority_actions.insert(
+            0,
+            priority_action(
+                RotatorAction::Single(key_action.into()),
+                ActionCondition::EveryMillis(0),
+                false,
+                None,
+            ),
+        );
+        rotator.priority_actions.insert(
+            1,
+            priority_action(
+                RotatorAction::Single(PRIORITY_ACTION.into()),
+                ActionCondition::EveryMillis(0),
+                false,
+                None,
+            ),
+        );
+        rotator.set_safe_mode(Some(Duration::from_secs(60)));
+        rotator.reset_queue();
+
+        // only the movement action is queued
+        rotator.update_safe_mode();
+        assert!(rotator.in_safe_mode());
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert_eq!(rotator.priority_actions_queue, [1]);
+
+        rotator.safe_mode = Some(SafeMode::Active(Instant::now() - Duration::from_secs(60)));
+        rotator.update_safe_mode();
+        assert!(!rotator.in_safe_mode());
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert_eq!(rotator.priority_actions_queue, [1, 0]);
+    }
+
+    #[test]
+    fn rotator_buff_action_deferred_to_next_loop() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let mut context = Context::new(None, None);
+        context.minimap = Minimap::Idle(MinimapIdle::default());
+        context.buffs[BuffKind::Rune] = Buff::NoBuff;
+        let mut action = buff_priority_action(BuffKind::Rune, KeyBinding::A);
+        action.buff_verification = BuffVerification::Executed(
+            Instant::now()
+                .checked_sub(Duration::from_millis(BUFF_VERIFY_MILLIS as u64 + 1000))
+                .unwrap(),
+        );
+        action.last_queued_time = Some(Instant::now());
+        rotator.priority_actions.insert(1, action);
+
+        // buff not applied after executed
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert!(rotator.priority_actions_queue.is_empty());
+        assert_eq!(
+            rotator.priority_actions.get(&1).unwrap().buff_verification,
+            BuffVerification::Deferred(0)
+        );
+
+        // retries on next loop without waiting for cooldown
+        rotator.normal_loops = 1;
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert_eq!(rotator.priority_actions_queue, [1]);
+        assert_eq!(
+            rotator.priority_actions.get(&1).unwrap().buff_verification,
+            BuffVerification::Pending
+        );
+    }
+
+    #[test]
+    fn rotator_buff_action_verified_after_executed() {
+        let mut rotator = Rotator::default();
+        let mut player = PlayerState::default();
+        let mut context = Context::new(None, None);
+        context.minimap = Minimap::Idle(MinimapIdle::default());
+        context.buffs[BuffKind::Rune] = Buff::NoBuff;
+        let mut action = buff_priority_action(BuffKind::Rune, KeyBinding::A);
+        action.buff_verification = BuffVerification::Pending;
+        action.last_queued_time =
+            Instant::now().checked_sub(Duration::from_millis(BUFF_VERIFY_MILLIS as u64 + 1000));
+        rotator.priority_actions.insert(1, action);
+        rotator.priority_actions_queue.push_back(1);
+
+        // still queued so not executed yet
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert_eq!(
+            rotator.priority_actions.get(&1).unwrap().buff_verification,
+            BuffVerification::Pending
+        );
+
+        // verification starts once executed regardless of when it was queued
+        rotator.priority_actions_queue.clear();
+        rotator.rotate_priority_actions(&context, &mut player);
+        assert!(matches!(
+            rotator.priority_actions.get(&1).unwrap().buff_verification,
+            BuffVerification::Executed(_)
+        ));
+    }
+
     #[test]
     fn rotator_priority_action_queue_to_front() {
         let mut rotator = Rotator::default();
@@ -968,6 +2867,9 @@ mod tests {
                 queue_to_front: false,
                 ignoring: false,
                 last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
             },
         );
         rotator.priority_actions.insert(
@@ -979,6 +2881,9 @@ mod tests {
                 queue_to_front: false,
                 ignoring: false,
                 last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
             },
         );
 
@@ -996,6 +2901,9 @@ mod tests {
                 queue_to_front: true,
                 ignoring: false,
                 last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
             },
         );
 
@@ -1017,6 +2925,9 @@ mod tests {
                 queue_to_front: true,
                 ignoring: false,
                 last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
             },
         );
 
@@ -1050,6 +2961,9 @@ mod tests {
                 queue_to_front: false,
                 ignoring: false,
                 last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
             },
         );
 
@@ -1069,6 +2983,9 @@ mod tests {
                 queue_to_front: true,
                 ignoring: false,
                 last_queued_time: None,
+                buff: None,
+                buff_verification: BuffVerification::None,
+                persist_key: None,
             },
         );
         rotator.rotate_action(&context, &mut player);
@@ -0,0 +1,284 @@
+use std::{collections::VecDeque, f32::consts::PI};
+
+use log::{debug, warn};
+use platforms::windows::AudioCapture;
+
+/// The number of samples of each analyzed frame
+const FRAME_SIZE: usize = 1024;
+
+/// The number of frequency bands of each analyzed frame
+const BANDS_COUNT: usize = 16;
+
+/// The lowest and highest center frequencies in Hz of the bands
+const BANDS_FREQUENCY_RANGE: (f32, f32) = (250.0, 8000.0);
+
+/// The seconds of frames kept to learn the chime from when a rune appears on the minimap
+///
+/// Covers the delay between the rune spawning and being confirmed on the minimap.
+const HISTORY_SECS: u32 = 16;
+
+/// The number of frames before a frame its onset strength is compared against
+const ONSET_WINDOW_FRAMES: usize = 8;
+
+/// The minimum ratio of a frame energy to the average energy of the frames before it to be
+/// considered an onset
+const ONSET_MIN_RATIO: f32 = 4.0;
+
+/// The minimum energy of a frame to be considered an onset so silence is ignored
+const ONSET_MIN_ENERGY: f32 = 1e-3;
+
+/// The minimum cosine similarity between the bands of an onset and the learned chime to match
+const CHIME_MIN_SIMILARITY: f32 = 0.9;
+
+/// The number of consistent learnings before the learned chime is used for matching
+const CHIME_MIN_CONFIDENCE: u32 = 2;
+
+/// The number of ticks after the chime is heard during which it is not heard again
+const CHIME_COOLDOWN_TICKS: u32 = 150;
+
+/// The band energies of an analyzed frame
+#[derive(Clone, Copy, Debug)]
+struct Spectrum {
+    bands: [f32; BANDS_COUNT],
+    energy: f32,
+    /// The ratio of [`Self::energy`] to the average energy of the frames before it
+    onset_ratio: f32,
+}
+
+/// Learns and matches the rune spawn chime from mono audio samples
+///
+/// The chime is not known beforehand. Each time a rune appears on the minimap, the strongest
+/// onset within the recent frames is taken as the chime candidate. Candidates agreeing with the
+/// learned chime increase its confidence while disagreeing candidates decrease it until the
+/// chime is replaced, so onsets that only coincide with a spawn once are not learned.
+#[derive(Debug)]
+struct RuneChime {
+    /// The Goertzel coefficient of each band
+    coefficients: [f32; BANDS_COUNT],
+    history_capacity: usize,
+    history: VecDeque<Spectrum>,
+    pending: Vec<f32>,
+    /// The learned chime bands normalized to unit length
+    chime: Option<[f32; BANDS_COUNT]>,
+    confidence: u32,
+}
+
+impl RuneChime {
+    fn new(sample_rate: u32) -> Self {
+        let (min, max) = BANDS_FREQUENCY_RANGE;
+        let nyquist = sample_rate as f32 / 2.0;
+        let coefficients = std::array::from_fn(|i| {
+            let t = i as f32 / (BANDS_COUNT - 1) as f32;
+            let frequency = (min * (max / min).powf(t)).min(nyquist);
+            2.0 * (2.0 * PI * frequency / sample_rate as f32).cos()
+        });
+        let history_capacity = (sample_rate * HISTORY_SECS) as usize / FRAME_SIZE;
+
+        Self {
+            coefficients,
+            history_capacity: history_capacity.max(ONSET_WINDOW_FRAMES + 1),
+            history: VecDeque::new(),
+            pending: Vec::with_capacity(FRAME_SIZE),
+            chime: None,
+            confidence: 0,
+        }
+    }
+
+    /// Analyzes `samples` and returns whether any complete frame matches the learned chime
+    fn push_samples(&mut self, samples: &[f32]) -> bool {
+        let mut matched = false;
+        for sample in samples {
+            self.pending.push(*sample);
+            if self.pending.len() < FRAME_SIZE {
+                continue;
+            }
+            let spectrum = self.analyze();
+            self.pending.clear();
+            matched |= self.matches(&spectrum);
+            if self.history.len() >= self.history_capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back(spectrum);
+        }
+        matched
+    }
+
+    /// Learns the strongest onset in the recent frames as the chime candidate
+    fn learn(&mut self) {
+        let Some(candidate) = self
+            .history
+            .iter()
+            .filter(|spectrum| is_onset(spectrum))
+            .max_by(|a, b| a.onset_ratio.total_cmp(&b.onset_ratio))
+            .map(|spectrum| normalized(spectrum.bands))
+        else {
+            return;
+        };
+        match self.chime {
+            Some(chime) if similarity(&chime, &candidate) >= CHIME_MIN_SIMILARITY => {
+                self.chime = Some(normalized(std::array::from_fn(|i| chime[i] + candidate[i])));
+                self.confidence += 1;
+            }
+            Some(_) if self.confidence > 1 => self.confidence -= 1,
+            Some(_) | None => {
+                self.chime = Some(candidate);
+                self.confidence = 1;
+            }
+        }
+        debug!(target: "rune_chime", "learned chime with confidence {}", self.confidence);
+    }
+
+    fn matches(&self, spectrum: &Spectrum) -> bool {
+        self.confidence >= CHIME_MIN_CONFIDENCE
+            && is_onset(spectrum)
+            && self.chime.is_some_and(|chime| {
+                similarity(&chime, &normalized(spectrum.bands)) >= CHIME_MIN_SIMILARITY
+            })
+    }
+
+    fn analyze(&self) -> Spectrum {
+        let bands = self.coefficients.map(|coefficient| {
+            let (mut s1, mut s2) = (0.0, 0.0);
+            for sample in self.pending.iter() {
+                let s0 = sample + coefficient * s1 - s2;
+                s2 = s1;
+                s1 = s0;
+            }
+            (s1 * s1 + s2 * s2 - coefficient * s1 * s2).max(0.0) / FRAME_SIZE as f32
+        });
+        let energy = bands.iter().sum::<f32>();
+        let window = self
+            .history
+            .iter()
+            .rev()
+            .take(ONSET_WINDOW_FRAMES)
+            .map(|spectrum| spectrum.energy)
+            .collect::<Vec<_>>();
+        // Frames without enough frames before them cannot be onsets
+        let onset_ratio = if window.len() < ONSET_WINDOW_FRAMES {
+            0.0
+        } else {
+            let average = window.iter().sum::<f32>() / window.len() as f32;
+            energy / average.max(f32::EPSILON)
+        };
+
+        Spectrum {
+            bands,
+            energy,
+            onset_ratio,
+        }
+    }
+}
+
+/// Listens to the game audio for the rune spawn chime as an auxiliary rune trigger
+///
+/// The audio capture is started only while enabled and is not retried after failing. The learned
+/// chime is discarded together with the capture when disabled.
+#[derive(Debug, Default)]
+pub struct RuneChimeListener {
+    capture: Option<(AudioCapture, RuneChime)>,
+    capture_failed: bool,
+    cooldown_ticks: u32,
+}
+
+impl RuneChimeListener {
+    /// Updates with the captured audio and whether a rune just appeared or is visible on the
+    /// minimap
+    ///
+    /// Returns `true` when the chime is heard while no rune is visible.
+    pub fn update(&mut self, enabled: bool, rune_appeared: bool, rune_visible: bool) -> bool {
+        if !enabled {
+            self.capture = None;
+            return false;
+        }
+        if self.capture.is_none() && !self.capture_failed {
+            match AudioCapture::new() {
+                Ok(capture) => {
+                    let chime = RuneChime::new(capture.sample_rate());
+                    self.capture = Some((capture, chime));
+                }
+                Err(err) => {
+                    warn!(target: "rune_chime", "failed to capture audio {err}");
+                    self.capture_failed = true;
+                }
+            }
+        }
+        let Some((capture, chime)) = self.capture.as_mut() else {
+            return false;
+        };
+
+        let matched = chime.push_samples(&capture.take_samples());
+        if rune_appeared {
+            chime.learn();
+        }
+        self.cooldown_ticks = self.cooldown_ticks.saturating_sub(1);
+        if !matched || rune_visible || self.cooldown_ticks > 0 {
+            return false;
+        }
+        self.cooldown_ticks = CHIME_COOLDOWN_TICKS;
+        true
+    }
+}
+
+#[inline]
+fn is_onset(spectrum: &Spectrum) -> bool {
+    spectrum.energy >= ONSET_MIN_ENERGY && spectrum.onset_ratio >= ONSET_MIN_RATIO
+}
+
+#[inline]
+fn normalized(bands: [f32; BANDS_COUNT]) -> [f32; BANDS_COUNT] {
+    let length = bands.iter().map(|band| band * band).sum::<f32>().sqrt();
+    bands.map(|band| band / length.max(f32::EPSILON))
+}
+
+#[inline]
+fn similarity(a: &[f32; BANDS_COUNT], b: &[f32; BANDS_COUNT]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    fn tone(frequency: f32, amplitude: f32, frames: usize) -> Vec<f32> {
+        (0..FRAME_SIZE * frames)
+            .map(|i| amplitude * (2.0 * PI * frequency * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn noise_then(chime: &[f32]) -> Vec<f32> {
+        let mut samples = tone(300.0, 0.01, ONSET_WINDOW_FRAMES);
+        samples.extend_from_slice(chime);
+        samples
+    }
+
+    #[test]
+    fn rune_chime_matches_after_consistent_learning() {
+        let mut rune_chime = RuneChime::new(SAMPLE_RATE);
+        let chime = tone(2000.0, 0.5, 1);
+
+        assert!(!rune_chime.push_samples(&noise_then(&chime)));
+        rune_chime.learn();
+        assert!(!rune_chime.push_samples(&noise_then(&chime)));
+        rune_chime.learn();
+
+        assert!(rune_chime.push_samples(&noise_then(&chime)));
+        assert!(!rune_chime.push_samples(&noise_then(&tone(500.0, 0.5, 1))));
+    }
+
+    #[test]
+    fn rune_chime_ignores_inconsistent_learning() {
+        let mut rune_chime = RuneChime::new(SAMPLE_RATE);
+
+        rune_chime.push_samples(&noise_then(&tone(2000.0, 0.5, 1)));
+        rune_chime.learn();
+        rune_chime.history.clear();
+        rune_chime.push_samples(&noise_then(&tone(500.0, 0.5, 1)));
+        rune_chime.learn();
+
+        assert_eq!(rune_chime.confidence, 1);
+        assert!(!rune_chime.push_samples(&noise_then(&tone(500.0, 0.5, 1))));
+    }
+}
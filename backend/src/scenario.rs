@@ -0,0 +1,766 @@
+//! Headless scenarios for writing end-to-end tests without capturing the game
+//!
+//! A [`Scenario`] runs the minimap, player and rotator contextual states on top of a mocked
+//! detector and key sender. The detector reports what the [`ScenarioBuilder`] describes instead
+//! of detecting from an image, so no OpenCV setup is needed to write a test.
+//!
+//! With [`ScenarioBuilder::virtual_map`], the player moves between platforms according to the
+//! keys sent instead of standing still. This allows running the rotator and player for thousands
+//! of ticks to catch the player stalling or looping between states.
+//!
+//! Requires the `test-support` feature and a paused Tokio clock since detection tasks are
+//! asynchronous:
+//!
+//! ```ignore
+//! #[tokio::test(start_paused = true)]
+//! async fn rune_appears() {
+//!     let mut scenario = scenario::rune_appears_while_auto_mobbing().build();
+//!     scenario.run(30).await;
+//!     assert!(scenario.has_rune());
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use opencv::core::{CV_8UC4, Mat, MatExprTraitConst, MatTrait, Point, Rect, Vec4b};
+use platforms::windows::KeyKind;
+use tokio::time;
+
+use crate::{
+    Action, ActionMove, AutoMobbing, Bound, KeyBinding, Platform, Position, RotationMode,
+    bridge::MockKeySender,
+    buff::BuffKind,
+    context::{Context, MS_PER_TICK, fold_context},
+    database::Minimap as MinimapData,
+    detect::{ArrowsState, MockDetector},
+    minimap::{Minimap, MinimapState},
+    player::PlayerState,
+    rotator::Rotator,
+};
+
+/// The jump key of the player in a [`Scenario`]
+const SCENARIO_JUMP_KEY: KeyKind = KeyKind::Space;
+
+/// The rope lift key of the player in a [`Scenario`]
+const SCENARIO_GRAPPLING_KEY: KeyKind = KeyKind::F;
+
+/// The upward velocity of a jump from a platform
+const JUMP_VELOCITY: i32 = 4;
+
+/// The upward velocity of an up jump in the air
+const UP_JUMP_VELOCITY: i32 = 6;
+
+/// The horizontal velocity of a double jump in the air
+const DOUBLE_JUMP_VELOCITY: i32 = 3;
+
+/// The maximum downward velocity when falling
+const MAX_FALL_VELOCITY: i32 = 3;
+
+/// The upward velocity of a rope lift
+const ROPE_LIFT_VELOCITY: i32 = 2;
+
+/// The maximum height a rope lift can reach a platform above
+const ROPE_LIFT_MAX_HEIGHT: i32 = 41;
+
+/// A key event sent by the bot during a [`Scenario`]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScenarioKey {
+    Press(KeyKind),
+    Down(KeyKind),
+    Up(KeyKind),
+}
+
+/// Describes what the mocked detector reports
+#[derive(Clone, Debug)]
+struct ScenarioDetector {
+    minimap: Rect,
+    player: Option<Point>,
+    rune: Option<Point>,
+    mobs: Vec<Point>,
+    /// The virtual map moving the player instead of [`Self::player`]
+    map: Option<Arc<Mutex<VirtualMap>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum VirtualMotion {
+    Standing,
+    Airborne { velocity: Point, air_jumped: bool },
+    RopeLifting { y: i32 },
+}
+
+/// A physics stub moving the player between platforms according to the keys sent
+///
+/// Only approximates the game closely enough for the movement states to reach their
+/// destinations by walking, jumping, double jumping, up jumping, rope lifting and falling
+/// through platforms. The bottom of the minimap is always standable.
+#[derive(Debug)]
+struct VirtualMap {
+    width: i32,
+    height: i32,
+    platforms: Vec<Platform>,
+    pos: Point,
+    motion: VirtualMotion,
+    held: Vec<KeyKind>,
+    /// The keys pressed or held down since the last step
+    pressed: Vec<KeyKind>,
+}
+
+impl VirtualMap {
+    fn new(minimap: Rect, platforms: Vec<Platform>, pos: Point) -> Self {
+        Self {
+            width: minimap.width,
+            height: minimap.height,
+            platforms,
+            pos,
+            motion: VirtualMotion::Standing,
+            held: vec![],
+            pressed: vec![],
+        }
+    }
+
+    fn on_key(&mut self, key: ScenarioKey) {
+        match key {
+            ScenarioKey::Press(key) => self.pressed.push(key),
+            ScenarioKey::Down(key) => {
+                if !self.held.contains(&key) {
+                    self.held.push(key);
+                }
+                self.pressed.push(key);
+            }
+            ScenarioKey::Up(key) => self.held.retain(|held| *held != key),
+        }
+    }
+
+    /// Moves the player by one tick with the keys sent since the last step
+    fn step(&mut self) {
+        let is_held = |key| self.held.contains(&key) || self.pressed.contains(&key);
+        let direction = match (is_held(KeyKind::Left), is_held(KeyKind::Right)) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        };
+        let up = self.held.contains(&KeyKind::Up);
+        let down = self.held.contains(&KeyKind::Down);
+        let jumped = self.pressed.contains(&SCENARIO_JUMP_KEY);
+        let rope_lifted = self.pressed.contains(&SCENARIO_GRAPPLING_KEY);
+        self.pressed.clear();
+
+        match self.motion {
+            VirtualMotion::Standing => {
+                if rope_lifted && let Some(y) = self.rope_lift_y() {
+                    self.motion = VirtualMotion::RopeLifting { y };
+                    return;
+                }
+                if jumped && down {
+                    // Drops through the platform but not the bottom of the minimap
+                    if self.pos.y > 0 {
+                        self.pos.y -= 1;
+                        self.motion = VirtualMotion::Airborne {
+                            velocity: Point::default(),
+                            air_jumped: true,
+                        };
+                    }
+                    return;
+                }
+                if !jumped {
+                    self.pos.x = (self.pos.x + direction).clamp(0, self.width - 1);
+                    if !self.is_standable(self.pos) {
+                        self.motion = VirtualMotion::Airborne {
+                            velocity: Point::default(),
+                            air_jumped: false,
+                        };
+                    }
+                    return;
+                }
+                self.motion = VirtualMotion::Airborne {
+                    velocity: Point::new(direction, JUMP_VELOCITY),
+                    air_jumped: false,
+                };
+            }
+            VirtualMotion::Airborne {
+                velocity,
+                air_jumped: false,
+            } if jumped => {
+                let velocity = if up {
+                    Point::new(velocity.x, UP_JUMP_VELOCITY)
+                } else if direction != 0 {
+                    Point::new(direction * DOUBLE_JUMP_VELOCITY, velocity.y.max(1))
+                } else {
+                    velocity
+                };
+                self.motion = VirtualMotion::Airborne {
+                    velocity,
+                    air_jumped: true,
+                };
+            }
+            VirtualMotion::Airborne { .. } => (),
+            VirtualMotion::RopeLifting { .. } if rope_lifted => {
+                self.motion = VirtualMotion::Airborne {
+                    velocity: Point::default(),
+                    air_jumped: true,
+                };
+            }
+            VirtualMotion::RopeLifting { y } => {
+                self.pos.y = (self.pos.y + ROPE_LIFT_VELOCITY).min(y);
+                if self.pos.y == y {
+                    self.motion = VirtualMotion::Standing;
+                }
+                return;
+            }
+        }
+
+        let VirtualMotion::Airborne {
+            velocity,
+            air_jumped,
+        } = self.motion
+        else {
+            unreachable!()
+        };
+        // Drifts toward the held direction without any horizontal momentum
+        let x_velocity = if velocity.x == 0 {
+            direction
+        } else {
+            velocity.x
+        };
+        let x = (self.pos.x + x_velocity).clamp(0, self.width - 1);
+        let y = self.pos.y + velocity.y;
+        let landing_y = if velocity.y < 0 {
+            self.landing_y(x, y, self.pos.y)
+        } else {
+            None
+        };
+        if let Some(y) = landing_y {
+            self.pos = Point::new(x, y);
+            self.motion = VirtualMotion::Standing;
+        } else {
+            self.pos = Point::new(x, y.min(self.height - 1));
+            self.motion = VirtualMotion::Airborne {
+                velocity: Point::new(velocity.x, (velocity.y - 1).max(-MAX_FALL_VELOCITY)),
+                air_jumped,
+            };
+        }
+    }
+
+    fn is_standable(&self, pos: Point) -> bool {
+        pos.y <= 0
+            || self.platforms.iter().any(|platform| {
+                platform.y == pos.y && (platform.x_start..platform.x_end).contains(&pos.x)
+            })
+    }
+
+    /// The highest platform y within `[to_y, from_y)` at `x` the player lands on when falling
+    fn landing_y(&self, x: i32, to_y: i32, from_y: i32) -> Option<i32> {
+        self.platforms
+            .iter()
+            .filter(|platform| {
+                (platform.x_start..platform.x_end).contains(&x)
+                    && platform.y >= to_y
+                    && platform.y < from_y
+            })
+            .map(|platform| platform.y)
+            .chain((to_y <= 0).then_some(0))
+            .max()
+    }
+
+    /// The lowest platform y above the player reachable by a rope lift
+    fn rope_lift_y(&self) -> Option<i32> {
+        self.platforms
+            .iter()
+            .filter(|platform| {
+                (platform.x_start..platform.x_end).contains(&self.pos.x)
+                    && platform.y > self.pos.y
+                    && platform.y - self.pos.y <= ROPE_LIFT_MAX_HEIGHT
+            })
+            .map(|platform| platform.y)
+            .min()
+    }
+}
+
+/// Builder for [`Scenario`]
+///
+/// All positions are in the player coordinate (bottom-left origin of the minimap).
+#[derive(Clone, Debug)]
+pub struct ScenarioBuilder {
+    detector: ScenarioDetector,
+    rotation_mode: RotationMode,
+    actions: Vec<Action>,
+    platforms: Option<Vec<Platform>>,
+    enable_rune_solving: bool,
+}
+
+impl Default for ScenarioBuilder {
+    fn default() -> Self {
+        Self {
+            detector: ScenarioDetector {
+                minimap: Rect::new(10, 10, 200, 100),
+                player: None,
+                rune: None,
+                mobs: vec![],
+                map: None,
+            },
+            rotation_mode: RotationMode::default(),
+            actions: vec![],
+            platforms: None,
+            enable_rune_solving: false,
+        }
+    }
+}
+
+impl ScenarioBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the minimap bounding box on the screen
+    pub fn minimap(mut self, bbox: Rect) -> Self {
+        self.detector.minimap = bbox;
+        self
+    }
+
+    /// Sets the player position on the minimap
+    pub fn player_at(mut self, pos: Point) -> Self {
+        self.detector.player = Some(pos);
+        self
+    }
+
+    /// Sets the rune position on the minimap
+    pub fn rune_at(mut self, pos: Point) -> Self {
+        self.detector.rune = Some(pos);
+        self
+    }
+
+    /// Sets the mobs detected when auto mobbing
+    pub fn mobs(mut self, mobs: Vec<Point>) -> Self {
+        self.detector.mobs = mobs;
+        self
+    }
+
+    pub fn rotation_mode(mut self, mode: RotationMode) -> Self {
+        self.rotation_mode = mode;
+        self
+    }
+
+    pub fn actions(mut self, actions: Vec<Action>) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    pub fn enable_rune_solving(mut self, enabled: bool) -> Self {
+        self.enable_rune_solving = enabled;
+        self
+    }
+
+    /// Moves the player on `platforms` according to the keys sent
+    ///
+    /// The player starts at [`Self::player_at`] instead of standing still there.
+    pub fn virtual_map(mut self, platforms: Vec<Platform>) -> Self {
+        self.platforms = Some(platforms);
+        self
+    }
+
+    pub fn build(self) -> Scenario {
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let map = self.platforms.clone().map(|platforms| {
+            Arc::new(Mutex::new(VirtualMap::new(
+                self.detector.minimap,
+                platforms,
+                self.detector.player.unwrap_or_default(),
+            )))
+        });
+        let context = Context::new(
+            Some(scenario_key_sender(keys.clone(), map.clone())),
+            Some(scenario_detector(ScenarioDetector {
+                map: map.clone(),
+                ..self.detector.clone()
+            })),
+        );
+
+        let mut minimap = MinimapState::default();
+        minimap.set_data(MinimapData {
+            width: self.detector.minimap.width,
+            height: self.detector.minimap.height,
+            rotation_mode: self.rotation_mode,
+            platforms: self.platforms.unwrap_or_default(),
+            ..MinimapData::default()
+        });
+
+        let mut player = PlayerState::default();
+        player.config.jump_key = SCENARIO_JUMP_KEY;
+        player.config.grappling_key = SCENARIO_GRAPPLING_KEY;
+
+        let mut rotator = Rotator::default();
+        rotator.build_actions(
+            self.rotation_mode.into(),
+            &self.actions,
+            &HashMap::new(),
+            &[],
+            KeyBinding::default(),
+            self.enable_rune_solving,
+            false,
+        );
+
+        Scenario {
+            context,
+            player,
+            minimap,
+            rotator,
+            keys,
+            map,
+        }
+    }
+}
+
+/// A headless bot running on mocked detector and key sender
+pub struct Scenario {
+    context: Context,
+    player: PlayerState,
+    minimap: MinimapState,
+    rotator: Rotator,
+    keys: Arc<Mutex<Vec<ScenarioKey>>>,
+    map: Option<Arc<Mutex<VirtualMap>>>,
+}
+
+impl Scenario {
+    /// Runs the bot for `ticks` ticks
+    ///
+    /// The Tokio clock must be paused so that detection tasks can complete between ticks.
+    pub async fn run(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.context.minimap =
+                fold_context(&self.context, self.context.minimap, &mut self.minimap);
+            self.context.player =
+                fold_context(&self.context, self.context.player, &mut self.player);
+            self.rotator.rotate_action(&self.context, &mut self.player);
+            if let Some(map) = self.map.as_ref() {
+                map.lock().unwrap().step();
+            }
+            time::advance(Duration::from_millis(MS_PER_TICK)).await;
+        }
+    }
+
+    /// The key events sent so far, oldest first
+    pub fn keys(&self) -> Vec<ScenarioKey> {
+        self.keys.lock().unwrap().clone()
+    }
+
+    /// The current player contextual state name
+    pub fn player_state(&self) -> String {
+        self.context.player.to_string()
+    }
+
+    /// The last known player position on the minimap
+    pub fn player_position(&self) -> Option<Point> {
+        self.player.last_known_pos
+    }
+
+    pub fn normal_action(&self) -> Option<String> {
+        self.player.normal_action_name()
+    }
+
+    pub fn priority_action(&self) -> Option<String> {
+        self.player.priority_action_name()
+    }
+
+    pub fn normal_action_id(&self) -> Option<u32> {
+        self.player.normal_action_id()
+    }
+
+    /// The number of times the rotator has gone through all the normal actions
+    pub fn normal_loops(&self) -> u32 {
+        self.rotator.normal_loops()
+    }
+
+    /// Whether the minimap is detected
+    pub fn is_minimap_detected(&self) -> bool {
+        matches!(self.context.minimap, Minimap::Idle(_))
+    }
+
+    /// Whether the rune is detected on the minimap
+    pub fn has_rune(&self) -> bool {
+        matches!(self.context.minimap, Minimap::Idle(idle) if idle.rune.is_some())
+    }
+
+    /// Whether the player is going to or solving the rune
+    pub fn has_rune_action(&self) -> bool {
+        self.player.has_rune_action()
+    }
+}
+
+/// A player standing on the minimap with no actions
+pub fn idle_player() -> ScenarioBuilder {
+    ScenarioBuilder::new().player_at(Point::new(100, 20))
+}
+
+/// A player auto mobbing with mobs on both sides then a rune appears
+pub fn rune_appears_while_auto_mobbing() -> ScenarioBuilder {
+    ScenarioBuilder::new()
+        .player_at(Point::new(100, 20))
+        .mobs(vec![Point::new(60, 80), Point::new(140, 80)])
+        .rune_at(Point::new(150, 20))
+        .enable_rune_solving(true)
+        .rotation_mode(RotationMode::AutoMobbing(AutoMobbing {
+            bound: Bound {
+                x: 0,
+                y: 0,
+                width: 200,
+                height: 100,
+            },
+            key: KeyBinding::A,
+            ..AutoMobbing::default()
+        }))
+}
+
+/// A player patrolling between platforms on a virtual map
+///
+/// Going through all the actions walks, double jumps across a gap, up jumps, rope lifts and
+/// falls, so running it long enough can catch the player stalling or looping between states.
+pub fn patrolling_platforms() -> ScenarioBuilder {
+    ScenarioBuilder::new()
+        .player_at(Point::new(100, 5))
+        .virtual_map(vec![
+            Platform {
+                x_start: 0,
+                x_end: 200,
+                y: 5,
+            },
+            Platform {
+                x_start: 20,
+                x_end: 90,
+                y: 20,
+            },
+            Platform {
+                x_start: 110,
+                x_end: 180,
+                y: 20,
+            },
+            Platform {
+                x_start: 60,
+                x_end: 140,
+                y: 50,
+            },
+        ])
+        .actions(vec![
+            move_action(40, 5),
+            move_action(50, 20),
+            move_action(150, 20),
+            move_action(120, 50),
+            move_action(80, 50),
+            move_action(30, 5),
+        ])
+}
+
+fn move_action(x: i32, y: i32) -> Action {
+    Action::Move(ActionMove {
+        position: Position {
+            x,
+            y,
+            ..Position::default()
+        },
+        ..ActionMove::default()
+    })
+}
+
+fn scenario_key_sender(
+    keys: Arc<Mutex<Vec<ScenarioKey>>>,
+    map: Option<Arc<Mutex<VirtualMap>>>,
+) -> MockKeySender {
+    let record = move |key: ScenarioKey| -> anyhow::Result<()> {
+        if let Some(map) = map.as_ref() {
+            map.lock().unwrap().on_key(key);
+        }
+        keys.lock().unwrap().push(key);
+        Ok(())
+    };
+    let mut sender = MockKeySender::new();
+    let press = record.clone();
+    sender
+        .expect_send()
+        .returning(move |key| press(ScenarioKey::Press(key)));
+    let down = record.clone();
+    sender
+        .expect_send_down()
+        .returning(move |key| down(ScenarioKey::Down(key)));
+    sender
+        .expect_send_up()
+        .returning(move |key| record(ScenarioKey::Up(key)));
+    sender.expect_send_click_to_focus().returning(|| Ok(()));
+    sender
+}
+
+fn scenario_detector(spec: ScenarioDetector) -> MockDetector {
+    let mut detector = MockDetector::new();
+    let bbox = spec.minimap;
+
+    let clone_spec = spec.clone();
+    detector
+        .expect_clone()
+        .returning(move || scenario_detector(clone_spec.clone()));
+    detector
+        .expect_mat()
+        .return_const(scenario_mat(bbox).into());
+    detector
+        .expect_detect_minimap()
+        .returning(move |_| Ok(bbox));
+    detector
+        .expect_detect_minimap_fingerprint()
+        .returning(|_| Ok(0));
+    detector
+        .expect_detect_minimap_portals()
+        .returning(|_| Ok(vec![]));
+    detector
+        .expect_detect_minimap_rune()
+        .returning(move |_| match spec.rune {
+            Some(rune) => Ok(Rect::new(rune.x - 1, bbox.height - rune.y - 1, 2, 2)),
+            None => Err(anyhow!("rune not found")),
+        });
+    detector.expect_detect_player().returning(move |_| {
+        let player = match spec.map.as_ref() {
+            Some(map) => Some(map.lock().unwrap().pos),
+            None => spec.player,
+        };
+        match player {
+            Some(pos) => Ok(Rect::new(pos.x - 1, bbox.height - pos.y - 2, 2, 2)),
+            None => Err(anyhow!("player not found")),
+        }
+    });
+    detector
+        .expect_detect_player_kind()
+        .returning(|_, _| Err(anyhow!("player not found")));
+    let mobs = spec.mobs.clone();
+    detector
+        .expect_detect_mobs()
+        .returning(move |_, _, _, _| Ok(mobs.clone()));
+    let mobs = spec.mobs;
+    detector
+        .expect_detect_minimap_mobs()
+        .returning(move |_, _, _| Ok(mobs.clone()));
+    detector.expect_detect_esc_settings().return_const(false);
+    detector.expect_detect_elite_boss_bar().return_const(false);
+    detector.expect_detect_player_is_dead().return_const(false);
+    detector
+        .expect_detect_player_in_cash_shop()
+        .return_const(false);
+    detector
+        .expect_detect_player_health_bar()
+        .returning(|| Err(anyhow!("health bar not found")));
+    detector
+        .expect_detect_player_current_max_health_bars()
+        .returning(|_| Err(anyhow!("health bar not found")));
+    detector
+        .expect_detect_player_health()
+        .returning(|_, _| Err(anyhow!("health not found")));
+    detector
+        .expect_detect_player_buff()
+        .returning(|kind| matches!(kind, BuffKind::Rune));
+    detector
+        .expect_detect_rune_arrows()
+        .returning(|calibrating| Ok(ArrowsState::Calibrating(calibrating)));
+    detector
+        .expect_detect_erda_shower()
+        .returning(|| Err(anyhow!("erda shower not found")));
+    detector
+}
+
+/// Creates an empty frame with white anchor pixels at the minimap corners
+fn scenario_mat(minimap: Rect) -> Mat {
+    let mut mat = Mat::zeros(
+        minimap.y + minimap.height + 1,
+        minimap.x + minimap.width + 1,
+        CV_8UC4,
+    )
+    .unwrap()
+    .to_mat()
+    .unwrap();
+    *mat.at_pt_mut::<Vec4b>(minimap.tl()).unwrap() = Vec4b::all(255);
+    *mat.at_pt_mut::<Vec4b>(minimap.br()).unwrap() = Vec4b::all(255);
+    mat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn scenario_idle_player_detected() {
+        let mut scenario = idle_player().build();
+
+        scenario.run(10).await;
+        assert!(scenario.is_minimap_detected());
+        assert_eq!(scenario.player_position(), Some(Point::new(100, 20)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scenario_rune_appears_while_auto_mobbing() {
+        let mut scenario = rune_appears_while_auto_mobbing().build();
+
+        let mut has_rune_action = false;
+        for _ in 0..150 {
+            scenario.run(1).await;
+            if scenario.has_rune_action() {
+                has_rune_action = true;
+                break;
+            }
+        }
+        assert!(scenario.has_rune());
+        assert!(has_rune_action);
+    }
+
+    #[test]
+    fn virtual_map_falls_through_platform() {
+        let mut map = VirtualMap::new(
+            Rect::new(0, 0, 200, 100),
+            vec![
+                Platform {
+                    x_start: 0,
+                    x_end: 200,
+                    y: 5,
+                },
+                Platform {
+                    x_start: 50,
+                    x_end: 150,
+                    y: 20,
+                },
+            ],
+            Point::new(100, 20),
+        );
+
+        map.on_key(ScenarioKey::Down(KeyKind::Down));
+        map.on_key(ScenarioKey::Press(SCENARIO_JUMP_KEY));
+        for _ in 0..10 {
+            map.step();
+        }
+        assert_eq!(map.pos, Point::new(100, 5));
+        assert_eq!(map.motion, VirtualMotion::Standing);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scenario_patrolling_platforms_without_stalling() {
+        // Not moving on to another normal action within 10 seconds is considered a stall
+        const STALL_TICKS: u32 = 300;
+
+        let mut scenario = patrolling_platforms().build();
+        let mut normal_action_id = None;
+        let mut unchanged_ticks = 0;
+        for tick in 0..5000 {
+            scenario.run(1).await;
+            if scenario.normal_action_id() == normal_action_id {
+                unchanged_ticks += 1;
+            } else {
+                normal_action_id = scenario.normal_action_id();
+                unchanged_ticks = 0;
+            }
+            assert!(
+                unchanged_ticks < STALL_TICKS,
+                "stalled at tick {tick} in {} at {:?}",
+                scenario.player_state(),
+                scenario.player_position()
+            );
+        }
+        assert!(scenario.normal_loops() >= 2);
+    }
+}
@@ -0,0 +1,260 @@
+use std::ops::RangeInclusive;
+
+use crate::{Schedule, context::MS_PER_TICK};
+
+/// The percentage the interval between breaks is randomized by in both directions
+const BREAK_INTERVAL_JITTER_PERCENT: u32 = 20;
+
+/// The action a [`Scheduler`] update requests the caller to take
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScheduleEvent {
+    /// Starts rotating actions at the scheduled time or after a break
+    Start,
+    /// Halts rotating actions for a break
+    Break,
+    /// Halts rotating actions because the scheduled run time has passed
+    Stop,
+}
+
+/// Starts, stops and inserts randomized breaks into rotating actions following a [`Schedule`]
+///
+/// A run starts when actions start rotating and ends when actions are halted for any reason
+/// other than a break. Breaks do not count toward the run time.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    /// The ticks of rotating actions in the current run
+    run_ticks: u32,
+    /// The ticks of rotating actions since the last break
+    since_break_ticks: u32,
+    /// The ticks of rotating actions between the last and the next break
+    break_interval_ticks: Option<u32>,
+    /// The ticks left of the current break
+    break_ticks_left: Option<u32>,
+    /// Whether the current minute is the scheduled start minute
+    in_start_minute: bool,
+    /// Whether a start is deferred until it can start
+    start_pending: bool,
+}
+
+impl Scheduler {
+    /// Updates the current run with whether actions are `rotating` at local `minute_of_day`
+    ///
+    /// Returns the [`ScheduleEvent`] to act on if any. A break is cancelled if actions are
+    /// resumed manually during the break. A start while `can_start` is false is deferred until
+    /// `can_start` becomes true.
+    pub fn update(
+        &mut self,
+        rotating: bool,
+        can_start: bool,
+        schedule: &Schedule,
+        minute_of_day: u32,
+    ) -> Option<ScheduleEvent> {
+        let in_start_minute =
+            schedule.start_enabled && minute_of_day == schedule.start_minute_of_day();
+        let start_minute_reached = in_start_minute && !self.in_start_minute;
+        self.in_start_minute = in_start_minute;
+
+        if self.start_pending {
+            if rotating {
+                self.start_pending = false;
+            } else {
+                return self.start(can_start);
+            }
+        }
+        if let Some(ticks_left) = self.break_ticks_left {
+            if rotating {
+                self.break_ticks_left = None;
+            } else if ticks_left == 0 {
+                self.break_ticks_left = None;
+                return self.start(can_start);
+            } else {
+                self.break_ticks_left = Some(ticks_left - 1);
+                return None;
+            }
+        }
+        if !rotating {
+            self.run_ticks = 0;
+            self.since_break_ticks = 0;
+            self.break_interval_ticks = None;
+            if start_minute_reached {
+                return self.start(can_start);
+            }
+            return None;
+        }
+
+        self.run_ticks = self.run_ticks.saturating_add(1);
+        self.since_break_ticks = self.since_break_ticks.saturating_add(1);
+        if schedule.stop_after_minutes > 0
+            && self.run_ticks >= minutes_to_ticks(schedule.stop_after_minutes)
+        {
+            return Some(ScheduleEvent::Stop);
+        }
+        if schedule.break_every_minutes > 0 {
+            let interval_ticks = *self
+                .break_interval_ticks
+                .get_or_insert_with(|| break_interval_ticks(schedule.break_every_minutes));
+            if self.since_break_ticks >= interval_ticks {
+                self.since_break_ticks = 0;
+                self.break_interval_ticks = None;
+                self.break_ticks_left = Some(break_ticks(
+                    schedule.break_min_minutes,
+                    schedule.break_max_minutes,
+                ));
+                return Some(ScheduleEvent::Break);
+            }
+        }
+        None
+    }
+
+    /// Whether actions will be started after a break or a deferred start
+    #[inline]
+    pub fn has_pending_start(&self) -> bool {
+        self.start_pending || self.break_ticks_left.is_some()
+    }
+
+    /// Cancels the pending start after a break or a deferred start
+    ///
+    /// Actions stay halted until started manually or at the next scheduled start minute.
+    pub fn cancel_pending_start(&mut self) {
+        self.start_pending = false;
+        self.break_ticks_left = None;
+    }
+
+    #[inline]
+    fn start(&mut self, can_start: bool) -> Option<ScheduleEvent> {
+        self.start_pending = !can_start;
+        can_start.then_some(ScheduleEvent::Start)
+    }
+}
+
+#[inline]
+fn minutes_to_ticks(minutes: u32) -> u32 {
+    (minutes as u64 * 60_000 / MS_PER_TICK).min(u32::MAX as u64) as u32
+}
+
+#[inline]
+fn break_interval_ticks(every_minutes: u32) -> u32 {
+    rand::random_range(break_interval_ticks_range(minutes_to_ticks(every_minutes)))
+}
+
+#[inline]
+fn break_interval_ticks_range(ticks: u32) -> RangeInclusive<u32> {
+    let jitter = (ticks as u64 * BREAK_INTERVAL_JITTER_PERCENT as u64 / 100) as u32;
+    ticks - jitter..=ticks.saturating_add(jitter)
+}
+
+#[inline]
+fn break_ticks(min_minutes: u32, max_minutes: u32) -> u32 {
+    let min_ticks = minutes_to_ticks(min_minutes.min(max_minutes));
+    let max_ticks = minutes_to_ticks(min_minutes.max(max_minutes));
+    rand::random_range(min_ticks..=max_ticks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE_TICKS: u32 = (60_000 / MS_PER_TICK) as u32;
+
+    #[test]
+    fn scheduler_starts_once_at_start_minute() {
+        let mut scheduler = Scheduler::default();
+        let schedule = Schedule {
+            start_enabled: true,
+            start_hour: 8,
+            start_minute: 30,
+            ..Schedule::default()
+        };
+
+        assert_eq!(scheduler.update(false, true, &schedule, 8 * 60 + 29), None);
+        assert_eq!(
+            scheduler.update(false, true, &schedule, 8 * 60 + 30),
+            Some(ScheduleEvent::Start)
+        );
+        assert_eq!(scheduler.update(false, true, &schedule, 8 * 60 + 30), None);
+        assert_eq!(scheduler.update(false, true, &schedule, 8 * 60 + 31), None);
+    }
+
+    #[test]
+    fn scheduler_stops_after_run_time() {
+        let mut scheduler = Scheduler::default();
+        let schedule = Schedule {
+            stop_after_minutes: 1,
+            ..Schedule::default()
+        };
+
+        for _ in 0..MINUTE_TICKS - 1 {
+            assert_eq!(scheduler.update(true, true, &schedule, 0), None);
+        }
+        assert_eq!(
+            scheduler.update(true, true, &schedule, 0),
+            Some(ScheduleEvent::Stop)
+        );
+        assert_eq!(scheduler.update(false, true, &schedule, 0), None);
+        assert_eq!(scheduler.update(true, true, &schedule, 0), None);
+    }
+
+    #[test]
+    fn scheduler_breaks_then_resumes() {
+        let mut scheduler = Scheduler::default();
+        let schedule = Schedule {
+            break_every_minutes: 10,
+            break_min_minutes: 1,
+            break_max_minutes: 1,
+            ..Schedule::default()
+        };
+        let interval = break_interval_ticks_range(10 * MINUTE_TICKS);
+
+        let mut ticks = 0;
+        while scheduler.update(true, true, &schedule, 0).is_none() {
+            ticks += 1;
+        }
+        assert!(interval.contains(&(ticks + 1)));
+        for _ in 0..MINUTE_TICKS {
+            assert_eq!(scheduler.update(false, true, &schedule, 0), None);
+        }
+        assert_eq!(
+            scheduler.update(false, true, &schedule, 0),
+            Some(ScheduleEvent::Start)
+        );
+        assert_eq!(scheduler.update(true, true, &schedule, 0), None);
+    }
+
+    #[test]
+    fn scheduler_defers_start_until_can_start() {
+        let mut scheduler = Scheduler::default();
+        let schedule = Schedule {
+            start_enabled: true,
+            ..Schedule::default()
+        };
+
+        assert_eq!(scheduler.update(false, false, &schedule, 0), None);
+        assert!(scheduler.has_pending_start());
+        assert_eq!(scheduler.update(false, false, &schedule, 1), None);
+        assert_eq!(
+            scheduler.update(false, true, &schedule, 1),
+            Some(ScheduleEvent::Start)
+        );
+        assert!(!scheduler.has_pending_start());
+    }
+
+    #[test]
+    fn scheduler_cancel_pending_start_after_break() {
+        let mut scheduler = Scheduler::default();
+        let schedule = Schedule {
+            break_every_minutes: 1,
+            break_min_minutes: 1,
+            break_max_minutes: 1,
+            ..Schedule::default()
+        };
+
+        while scheduler.update(true, true, &schedule, 0).is_none() {}
+        assert!(scheduler.has_pending_start());
+        scheduler.cancel_pending_start();
+
+        assert!(!scheduler.has_pending_start());
+        for _ in 0..=MINUTE_TICKS {
+            assert_eq!(scheduler.update(false, true, &schedule, 0), None);
+        }
+    }
+}
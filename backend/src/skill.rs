@@ -1,12 +1,9 @@
-use std::{
-    mem,
-    ops::{Index, IndexMut},
-};
+use std::ops::{Index, IndexMut};
 
 use anyhow::Result;
 use log::debug;
 use opencv::core::{MatTraitConst, Point, Rect, Vec4b};
-use strum::{Display, EnumIter};
+use strum::{Display, EnumCount, EnumIter};
 
 use crate::{
     context::{Context, Contextual, ControlFlow},
@@ -33,16 +30,12 @@ pub enum Skill {
     Cooldown,
 }
 
-#[derive(Clone, Copy, Debug, EnumIter)]
+#[derive(Clone, Copy, Debug, EnumIter, EnumCount)]
 pub enum SkillKind {
     ErdaShower,
     // TODO: Sol Janus?
 }
 
-impl SkillKind {
-    pub const COUNT: usize = mem::variant_count::<SkillKind>();
-}
-
 impl Index<SkillKind> for [Skill; SkillKind::COUNT] {
     type Output = Skill;
 
@@ -0,0 +1,138 @@
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use crate::GameState;
+
+/// The number of deltas buffered for each receiver before it starts lagging
+const STATE_STREAM_CAPACITY: usize = 32;
+
+/// A change of a [`GameState`] field pushed to the UI at tick rate
+///
+/// Only the frequently changing fields are streamed, the rest are still polled through
+/// [`crate::player_state`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameStateDelta {
+    Position(Option<(i32, i32)>),
+    State(String),
+    NormalAction(Option<String>),
+    PriorityAction(Option<String>),
+    Destinations(Vec<(i32, i32)>),
+}
+
+impl GameStateDelta {
+    /// Applies this delta to the polled `state`
+    pub fn apply(self, state: &mut GameState) {
+        match self {
+            GameStateDelta::Position(position) => state.position = position,
+            GameStateDelta::State(player_state) => state.state = player_state,
+            GameStateDelta::NormalAction(action) => state.normal_action = action,
+            GameStateDelta::PriorityAction(action) => state.priority_action = action,
+            GameStateDelta::Destinations(destinations) => state.destinations = destinations,
+        }
+    }
+}
+
+/// The streamed fields of [`GameState`] in a tick
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GameStateSnapshot {
+    pub position: Option<(i32, i32)>,
+    pub state: String,
+    pub normal_action: Option<String>,
+    pub priority_action: Option<String>,
+    pub destinations: Vec<(i32, i32)>,
+}
+
+/// Pushes [`GameStateDelta`] of the fields changed since the last tick
+#[derive(Debug)]
+pub struct GameStateStream {
+    sender: Sender<GameStateDelta>,
+    last: GameStateSnapshot,
+}
+
+impl Default for GameStateStream {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(STATE_STREAM_CAPACITY).0,
+            last: GameStateSnapshot::default(),
+        }
+    }
+}
+
+impl GameStateStream {
+    #[inline]
+    pub fn subscribe(&self) -> Receiver<GameStateDelta> {
+        self.sender.subscribe()
+    }
+
+    /// Whether there is any receiver so that the snapshot is only built when needed
+    #[inline]
+    pub fn has_receivers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Sends the deltas between `snapshot` and the last snapshot
+    pub fn update(&mut self, snapshot: GameStateSnapshot) {
+        if snapshot.position != self.last.position {
+            let _ = self
+                .sender
+                .send(GameStateDelta::Position(snapshot.position));
+        }
+        if snapshot.state != self.last.state {
+            let _ = self
+                .sender
+                .send(GameStateDelta::State(snapshot.state.clone()));
+        }
+        if snapshot.normal_action != self.last.normal_action {
+            let _ = self
+                .sender
+                .send(GameStateDelta::NormalAction(snapshot.normal_action.clone()));
+        }
+        if snapshot.priority_action != self.last.priority_action {
+            let _ = self.sender.send(GameStateDelta::PriorityAction(
+                snapshot.priority_action.clone(),
+            ));
+        }
+        if snapshot.destinations != self.last.destinations {
+            let _ = self
+                .sender
+                .send(GameStateDelta::Destinations(snapshot.destinations.clone()));
+        }
+        self.last = snapshot;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_sends_changed_fields_only() {
+        let mut stream = GameStateStream::default();
+        let mut receiver = stream.subscribe();
+        let snapshot = GameStateSnapshot {
+            position: Some((10, 20)),
+            state: "Idle".to_string(),
+            ..GameStateSnapshot::default()
+        };
+
+        stream.update(snapshot.clone());
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            GameStateDelta::Position(Some((10, 20)))
+        );
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            GameStateDelta::State("Idle".to_string())
+        );
+        assert!(receiver.try_recv().is_err());
+
+        stream.update(GameStateSnapshot {
+            normal_action: Some("Move".to_string()),
+            ..snapshot
+        });
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            GameStateDelta::NormalAction(Some("Move".to_string()))
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::sync::broadcast;
+
+use crate::database::{
+    Configuration, Identifiable, Minimap, SyncMetadata, delete_config_row, delete_map_row,
+    query_configs, query_maps, query_settings, unique_name, upsert_config_row, upsert_map_row,
+};
+
+const MAPS_FOLDER: &str = "maps";
+const CONFIGS_FOLDER: &str = "configurations";
+
+/// The interval between each import of the changes made by other machines
+const IMPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The interval between each check for exports that are ready to be written
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The duration a save waits for further saves of the same data before being exported
+const EXPORT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// The exports waiting for [`EXPORT_DEBOUNCE`] keyed by folder and [`SyncMetadata::id`]
+static PENDING_EXPORTS: LazyLock<Mutex<HashMap<(&'static str, String), PendingExport>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Broadcasts the ids of the maps replaced by an import so that the loaded copies are reloaded
+///
+/// A loaded copy that is not reloaded would overwrite the import on its next save since saving
+/// marks it as modified later.
+static MAP_IMPORTS: LazyLock<broadcast::Sender<i64>> = LazyLock::new(|| broadcast::channel(16).0);
+
+#[derive(Debug)]
+struct PendingExport {
+    queued_at: Instant,
+    contents: String,
+}
+
+/// A file in the sync folder named by [`SyncMetadata::id`]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SyncFile<T> {
+    /// Records the deletion so that other machines delete their older copy instead of
+    /// exporting it again
+    Deleted {
+        deleted: SyncMetadata,
+    },
+    Data(T),
+}
+
+/// Data that is exported to and imported from the sync folder
+pub(crate) trait Syncable: Serialize + DeserializeOwned + Identifiable {
+    const FOLDER: &'static str;
+
+    fn name(&self) -> &str;
+
+    fn set_name(&mut self, name: String);
+
+    fn sync(&self) -> &SyncMetadata;
+
+    fn sync_mut(&mut self) -> &mut SyncMetadata;
+
+    fn query_all() -> Result<Vec<Self>>;
+
+    /// Saves to the database without exporting
+    fn upsert_row(&mut self) -> Result<()>;
+
+    /// Deletes from the database without exporting
+    fn delete_row(&self) -> Result<()>;
+
+    /// Notifies that the saved data is replaced by an import
+    fn notify_imported(&self) {}
+}
+
+impl Syncable for Minimap {
+    const FOLDER: &'static str = MAPS_FOLDER;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    fn sync(&self) -> &SyncMetadata {
+        &self.sync
+    }
+
+    fn sync_mut(&mut self) -> &mut SyncMetadata {
+        &mut self.sync
+    }
+
+    fn query_all() -> Result<Vec<Self>> {
+        query_maps()
+    }
+
+    fn upsert_row(&mut self) -> Result<()> {
+        upsert_map_row(self)
+    }
+
+    fn delete_row(&self) -> Result<()> {
+        delete_map_row(self)
+    }
+
+    fn notify_imported(&self) {
+        if let Some(id) = self.id {
+            let _ = MAP_IMPORTS.send(id);
+        }
+    }
+}
+
+impl Syncable for Configuration {
+    const FOLDER: &'static str = CONFIGS_FOLDER;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    fn sync(&self) -> &SyncMetadata {
+        &self.sync
+    }
+
+    fn sync_mut(&mut self) -> &mut SyncMetadata {
+        &mut self.sync
+    }
+
+    fn query_all() -> Result<Vec<Self>> {
+        query_configs()
+    }
+
+    fn upsert_row(&mut self) -> Result<()> {
+        upsert_config_row(self)
+    }
+
+    fn delete_row(&self) -> Result<()> {
+        delete_config_row(self)
+    }
+}
+
+/// Receives the ids of the maps replaced by an import from the sync folder
+///
+/// The UI and clients holding a map with a received id should reload it from the database.
+pub fn map_import_receiver() -> broadcast::Receiver<i64> {
+    MAP_IMPORTS.subscribe()
+}
+
+/// Queues `data` to be exported to the sync folder once it is not saved again for
+/// [`EXPORT_DEBOUNCE`]
+pub(crate) fn queue_export<T: Syncable>(data: &T) {
+    queue(T::FOLDER, &data.sync().id, &SyncFile::Data(data));
+}
+
+/// Queues the deletion of `data` to be exported to the sync folder
+pub(crate) fn queue_deletion<T: Syncable>(data: &T) {
+    let mut sync = data.sync().clone();
+    sync.touch();
+    queue(
+        T::FOLDER,
+        &data.sync().id,
+        &SyncFile::<&T>::Deleted { deleted: sync },
+    );
+}
+
+fn queue<T: Serialize>(folder: &'static str, id: &str, file: &SyncFile<T>) {
+    if id.is_empty() {
+        return;
+    }
+    let contents = match serde_json::to_string_pretty(file) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!(target: "sync", "failed to serialize {id} for exporting {err}");
+            return;
+        }
+    };
+    PENDING_EXPORTS.lock().unwrap().insert(
+        (folder, id.to_string()),
+        PendingExport {
+            queued_at: Instant::now(),
+            contents,
+        },
+    );
+}
+
+/// Spawns a thread exporting the saved data and importing the changes made by other machines
+/// sharing the sync folder
+///
+/// The sync folder path is re-read on each import so changing it takes effect within
+/// [`IMPORT_INTERVAL`].
+pub fn spawn_sync_loop() {
+    thread::spawn(|| {
+        let mut folder = String::new();
+        let mut last_import = None::<Instant>;
+        loop {
+            if last_import.is_none_or(|instant| instant.elapsed() >= IMPORT_INTERVAL) {
+                folder = query_settings().sync_folder_path;
+                last_import = Some(Instant::now());
+                // Exports first so that the pending saves are not overwritten by older files
+                if let Err(err) = export_pending(&folder, true) {
+                    warn!(target: "sync", "failed to export to sync folder {err}");
+                }
+                if let Err(err) = import_from_sync_folder(&folder) {
+                    warn!(target: "sync", "failed to import from sync folder {err}");
+                }
+            } else if let Err(err) = export_pending(&folder, false) {
+                warn!(target: "sync", "failed to export to sync folder {err}");
+            }
+            thread::sleep(EXPORT_INTERVAL);
+        }
+    });
+}
+
+/// Writes the pending exports to `folder`
+///
+/// Only the exports not saved again for [`EXPORT_DEBOUNCE`] are written unless `all` is true.
+fn export_pending(folder: &str, all: bool) -> Result<()> {
+    let ready = {
+        let mut pending = PENDING_EXPORTS.lock().unwrap();
+        if folder.is_empty() {
+            pending.clear();
+            return Ok(());
+        }
+        let keys = pending
+            .iter()
+            .filter(|(_, export)| all || export.queued_at.elapsed() >= EXPORT_DEBOUNCE)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| pending.remove_entry(&key))
+            .collect::<Vec<_>>()
+    };
+    for ((subfolder, id), export) in ready {
+        let path = sync_file_path(folder, subfolder, &id);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, export.contents)?;
+    }
+    Ok(())
+}
+
+/// Imports maps and configurations from the sync folder `folder` if there is one
+///
+/// An export is matched to the local data by [`SyncMetadata::id`] and only replaces the local
+/// data when it was modified later. Local data that is newer or missing from the sync folder is
+/// exported instead.
+fn import_from_sync_folder(folder: &str) -> Result<()> {
+    if folder.is_empty() {
+        return Ok(());
+    }
+    let folder = Path::new(folder);
+    import::<Minimap>(folder)?;
+    import::<Configuration>(folder)?;
+    Ok(())
+}
+
+fn import<T: Syncable>(folder: &Path) -> Result<()> {
+    let mut locals = T::query_all()?;
+    let mut names = locals
+        .iter()
+        .map(|local| local.name().to_string())
+        .collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    let folder = folder.join(T::FOLDER);
+    let entries = if folder.is_dir() {
+        fs::read_dir(&folder)?.collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![]
+    };
+
+    for entry in entries {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Ok(file) = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| {
+                serde_json::from_str::<SyncFile<T>>(&json).map_err(anyhow::Error::from)
+            })
+            .inspect_err(|err| warn!(target: "sync", "skipping {path:?} {err}"))
+        else {
+            continue;
+        };
+        let sync = match &file {
+            SyncFile::Deleted { deleted } => deleted.clone(),
+            SyncFile::Data(data) => data.sync().clone(),
+        };
+        if sync.id.is_empty() {
+            debug!(target: "sync", "skipping {path:?} without sync id");
+            continue;
+        }
+        seen.insert(sync.id.clone());
+
+        let local = locals.iter().find(|local| local.sync().id == sync.id);
+        if let Some(local) = local
+            && local.sync().modified_at >= sync.modified_at
+        {
+            if local.sync().modified_at > sync.modified_at {
+                queue_export(local);
+            }
+            continue;
+        }
+        match file {
+            SyncFile::Deleted { .. } => {
+                if let Some(local) = local {
+                    debug!(target: "sync", "deleting {} removed by {path:?}", local.name());
+                    local.delete_row()?;
+                }
+            }
+            SyncFile::Data(mut data) => {
+                match local {
+                    Some(local) => data.set_id(local.id().unwrap()),
+                    None => {
+                        // Keeps names unique when another machine created data with the same name
+                        data.set_name(unique_name(data.name(), &names));
+                        names.push(data.name().to_string());
+                    }
+                }
+                debug!(target: "sync", "importing {path:?}");
+                data.upsert_row()?;
+                data.notify_imported();
+            }
+        }
+    }
+
+    for local in locals
+        .iter_mut()
+        .filter(|local| !seen.contains(&local.sync().id))
+    {
+        if local.sync().id.is_empty() {
+            local.sync_mut().touch();
+            local.upsert_row()?;
+        }
+        queue_export(&*local);
+    }
+    Ok(())
+}
+
+fn sync_file_path(folder: &str, subfolder: &str, id: &str) -> PathBuf {
+    Path::new(folder).join(subfolder).join(format!("{id}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_file_distinguishes_deletion_from_data() {
+        let mut map = Minimap {
+            name: "Map".to_string(),
+            ..Minimap::default()
+        };
+        map.sync.touch();
+        let deleted = serde_json::to_string(&SyncFile::<&Minimap>::Deleted {
+            deleted: map.sync.clone(),
+        })
+        .unwrap();
+        let data = serde_json::to_string(&SyncFile::Data(&map)).unwrap();
+
+        assert!(matches!(
+            serde_json::from_str::<SyncFile<Minimap>>(&deleted).unwrap(),
+            SyncFile::Deleted { deleted } if deleted == map.sync
+        ));
+        assert!(matches!(
+            serde_json::from_str::<SyncFile<Minimap>>(&data).unwrap(),
+            SyncFile::Data(data) if data.name == "Map" && data.sync == map.sync
+        ));
+    }
+}
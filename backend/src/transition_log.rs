@@ -0,0 +1,116 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    mem,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use opencv::core::Point;
+
+use crate::{key_log, player::Player};
+
+/// The maximum number of transitions kept before the oldest is dropped
+const MAX_TRANSITIONS: usize = 1_000;
+
+thread_local! {
+    /// The transitions between [`Player`] states across ticks of the client on this thread
+    static TRANSITION_LOG: RefCell<TransitionLog> = RefCell::default();
+}
+
+/// A transition between two [`Player`] states for diagnosing the player being stuck
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PlayerTransition {
+    /// The update loop tick the transition happened in
+    pub tick: u64,
+    /// The wall-clock time in milliseconds since Unix epoch
+    pub timestamp: u64,
+    pub from: String,
+    pub to: String,
+    /// The last known player position when transitioning
+    pub position: Option<(i32, i32)>,
+}
+
+/// A bounded log of [`PlayerTransition`]
+#[derive(Debug, Default)]
+struct TransitionLog {
+    transitions: VecDeque<PlayerTransition>,
+}
+
+impl TransitionLog {
+    fn record(&mut self, transition: PlayerTransition) {
+        if self.transitions.len() >= MAX_TRANSITIONS {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(transition);
+    }
+
+    fn last(&self, count: usize) -> Vec<PlayerTransition> {
+        self.transitions
+            .iter()
+            .skip(self.transitions.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Records the transition from `from` to `to` if they are different states
+///
+/// Only the state kinds are compared so updating the same state (e.g. timeout ticking) is not
+/// recorded.
+pub(crate) fn record(from: &Player, to: &Player, position: Option<Point>) {
+    if mem::discriminant(from) == mem::discriminant(to) {
+        return;
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let transition = PlayerTransition {
+        tick: key_log::tick(),
+        timestamp,
+        from: from.to_string(),
+        to: to.to_string(),
+        position: position.map(|pos| (pos.x, pos.y)),
+    };
+    TRANSITION_LOG.with_borrow_mut(|log| log.record(transition));
+}
+
+/// Clones the last `count` recorded transitions from oldest to newest
+pub(crate) fn transitions(count: usize) -> Vec<PlayerTransition> {
+    TRANSITION_LOG.with_borrow(|log| log.last(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition(tick: u64) -> PlayerTransition {
+        PlayerTransition {
+            tick,
+            timestamp: 0,
+            from: "Idle".to_string(),
+            to: "Moving".to_string(),
+            position: None,
+        }
+    }
+
+    #[test]
+    fn transition_log_record_bounded_and_last() {
+        let mut log = TransitionLog::default();
+
+        for tick in 0..=MAX_TRANSITIONS as u64 {
+            log.record(transition(tick));
+        }
+
+        assert_eq!(log.transitions.len(), MAX_TRANSITIONS);
+        assert_eq!(log.transitions.front(), Some(&transition(1)));
+        assert_eq!(
+            log.last(2),
+            vec![
+                transition(MAX_TRANSITIONS as u64 - 1),
+                transition(MAX_TRANSITIONS as u64)
+            ]
+        );
+        assert_eq!(log.last(MAX_TRANSITIONS * 2).len(), MAX_TRANSITIONS);
+    }
+}
@@ -1,5 +1,5 @@
-#![feature(str_from_raw_parts)]
-#![feature(let_chains)]
+#![cfg_attr(not(feature = "stable"), feature(str_from_raw_parts))]
+#![cfg_attr(not(feature = "stable"), feature(let_chains))]
 
 #[cfg(windows)]
 pub mod windows;
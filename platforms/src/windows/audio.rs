@@ -0,0 +1,169 @@
+use std::{
+    ptr, slice,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use windows::Win32::{
+    Media::Audio::{
+        AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK,
+        IAudioCaptureClient, IAudioClient, IMMDeviceEnumerator, MMDeviceEnumerator, eConsole,
+        eRender,
+    },
+    System::Com::{
+        CLSCTX_ALL, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx, CoTaskMemFree,
+    },
+};
+
+use super::error::Error;
+
+/// The buffer duration of the capture stream in 100-nanosecond units
+const BUFFER_DURATION: i64 = 10_000_000;
+
+/// The interval between each poll of the captured packets
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Captures the audio played to the default output device in loopback mode
+///
+/// The stream is created and polled only inside its own thread to not depend on the COM
+/// apartment of the caller. Captured samples are downmixed to mono and stop being captured when
+/// this struct is dropped.
+#[derive(Debug)]
+pub struct AudioCapture {
+    sample_rate: u32,
+    receiver: Receiver<Vec<f32>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl AudioCapture {
+    pub fn new() -> Result<Self, Error> {
+        let (tx, rx) = mpsc::channel::<Vec<f32>>();
+        let (init_tx, init_rx) = mpsc::channel::<Result<u32, Error>>();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        thread::spawn(move || {
+            let stream = unsafe { LoopbackStream::new() };
+            let stream = match stream {
+                Ok(stream) => {
+                    let _ = init_tx.send(Ok(stream.sample_rate));
+                    stream
+                }
+                Err(err) => {
+                    let _ = init_tx.send(Err(err));
+                    return;
+                }
+            };
+            while !thread_stopped.load(Ordering::Acquire) {
+                if unsafe { stream.poll(&tx) }.is_err() {
+                    break;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            let _ = unsafe { stream.client.Stop() };
+        });
+        let sample_rate = init_rx
+            .recv()
+            .map_err(|_| Error::AudioCaptureNotAvailable)??;
+
+        Ok(Self {
+            sample_rate,
+            receiver: rx,
+            stopped,
+        })
+    }
+
+    /// The number of mono samples per second
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Takes the mono samples captured since the last call
+    pub fn take_samples(&self) -> Vec<f32> {
+        self.receiver.try_iter().flatten().collect()
+    }
+}
+
+impl Drop for AudioCapture {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+}
+
+struct LoopbackStream {
+    client: IAudioClient,
+    capture: IAudioCaptureClient,
+    channels: usize,
+    sample_rate: u32,
+}
+
+impl LoopbackStream {
+    unsafe fn new() -> Result<Self, Error> {
+        unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let enumerator =
+                CoCreateInstance::<_, IMMDeviceEnumerator>(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+            let client = device.Activate::<IAudioClient>(CLSCTX_ALL, None)?;
+            let format_ptr = client.GetMixFormat()?;
+            let format = format_ptr.read_unaligned();
+            // The shared mode mix format is always 32-bit float in practice
+            let result = if format.wBitsPerSample == 32 {
+                client
+                    .Initialize(
+                        AUDCLNT_SHAREMODE_SHARED,
+                        AUDCLNT_STREAMFLAGS_LOOPBACK,
+                        BUFFER_DURATION,
+                        0,
+                        format_ptr,
+                        None,
+                    )
+                    .map_err(Error::from)
+            } else {
+                Err(Error::AudioCaptureNotAvailable)
+            };
+            CoTaskMemFree(Some(format_ptr as *const _));
+            result?;
+            let capture = client.GetService::<IAudioCaptureClient>()?;
+            client.Start()?;
+
+            Ok(Self {
+                client,
+                capture,
+                channels: format.nChannels.max(1) as usize,
+                sample_rate: format.nSamplesPerSec,
+            })
+        }
+    }
+
+    /// Sends all the available packets downmixed to mono to `sender`
+    unsafe fn poll(&self, sender: &Sender<Vec<f32>>) -> Result<(), Error> {
+        unsafe {
+            while self.capture.GetNextPacketSize()? > 0 {
+                let mut data = ptr::null_mut();
+                let mut frames = 0;
+                let mut flags = 0;
+                self.capture
+                    .GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+                let samples = if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 {
+                    vec![0.0; frames as usize]
+                } else {
+                    slice::from_raw_parts(data as *const f32, frames as usize * self.channels)
+                        .chunks_exact(self.channels)
+                        .map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+                        .collect()
+                };
+                self.capture.ReleaseBuffer(frames)?;
+                sender
+                    .send(samples)
+                    .map_err(|_| Error::AudioCaptureNotAvailable)?;
+            }
+        }
+        Ok(())
+    }
+}
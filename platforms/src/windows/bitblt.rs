@@ -24,6 +24,7 @@ use windows::core::PCWSTR;
 
 use super::Frame;
 use super::HandleCell;
+use super::dpi::with_per_monitor_dpi_awareness;
 use super::error::Error;
 use super::handle::Handle;
 
@@ -88,7 +89,7 @@ impl BitBltCapture {
 
     fn grab_inner(&mut self, mut offset: Option<(i32, i32)>) -> Result<Frame, Error> {
         let handle = self.handle.as_inner().ok_or(Error::WindowNotFound)?;
-        let rect = get_rect(handle)?;
+        let rect = with_per_monitor_dpi_awareness(|| get_rect(handle))?;
         let width = rect.right - rect.left;
         let height = rect.bottom - rect.top;
         if width == 0 || height == 0 {
@@ -96,7 +97,7 @@ impl BitBltCapture {
         }
 
         let handle_dc = if self.overlap {
-            get_device_context_from_monitor(handle, &mut offset)?
+            with_per_monitor_dpi_awareness(|| get_device_context_from_monitor(handle, &mut offset))?
         } else {
             get_device_context(handle)?
         };
@@ -0,0 +1,30 @@
+use windows::Win32::UI::HiDpi::{
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, SetProcessDpiAwarenessContext,
+    SetThreadDpiAwarenessContext,
+};
+
+/// Opts the process into per-monitor DPI awareness
+///
+/// Window and client coordinates are then in physical pixels of the monitor the window is on
+/// instead of being virtualized to the primary monitor DPI. This fails if the awareness is
+/// already set (e.g. by the manifest) which is fine.
+pub(crate) fn init() {
+    let _ = unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) };
+}
+
+/// Runs `f` with the current thread temporarily per-monitor DPI aware
+///
+/// Coordinates of a window on a scaled secondary monitor are only correct when the calling
+/// thread is per-monitor DPI aware. The process awareness can still be overridden by the host
+/// (e.g. the UI webview), so conversions between window, client and screen coordinates for
+/// capturing and mouse input should go through this.
+#[inline]
+pub(crate) fn with_per_monitor_dpi_awareness<T>(f: impl FnOnce() -> T) -> T {
+    let previous =
+        unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) };
+    let result = f();
+    if !previous.is_invalid() {
+        unsafe { SetThreadDpiAwarenessContext(previous) };
+    }
+    result
+}
@@ -10,8 +10,14 @@ pub enum Error {
     WindowNotFound,
     #[error("capture frame is not available")]
     FrameNotAvailable,
+    #[error("window is minimized or occluded")]
+    WindowOccluded,
     #[error("key not found")]
     KeyNotFound,
+    #[error("text-to-speech is not available")]
+    SpeechNotAvailable,
+    #[error("audio capture is not available")]
+    AudioCaptureNotAvailable,
     #[error("win32 API error {0}: {1}")]
     Win32(u32, String),
 }
@@ -1,14 +1,17 @@
 use std::{cell::Cell, ffi::OsString, os::windows::ffi::OsStringExt, ptr, str};
 
 use windows::Win32::{
-    Foundation::{BOOL, HWND, LPARAM},
+    Foundation::{BOOL, HWND, LPARAM, RECT},
     Graphics::Dwm::{DWMWA_CLOAKED, DwmGetWindowAttribute},
     UI::WindowsAndMessaging::{
-        EnumWindows, GWL_EXSTYLE, GWL_STYLE, GetClassNameW, GetWindowLongPtrW, GetWindowTextW,
-        IsWindowVisible, WS_DISABLED, WS_EX_TOOLWINDOW,
+        AdjustWindowRectEx, EnumWindows, GWL_EXSTYLE, GWL_STYLE, GetClassNameW, GetWindowLongPtrW,
+        GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindowVisible, SWP_NOACTIVATE,
+        SWP_NOZORDER, SetWindowPos, WINDOW_EX_STYLE, WINDOW_STYLE, WS_DISABLED, WS_EX_TOOLWINDOW,
     },
 };
 
+use super::{dpi::with_per_monitor_dpi_awareness, error::Error};
+
 #[derive(Clone, Debug)]
 pub(crate) struct HandleCell {
     handle: Handle,
@@ -66,6 +69,26 @@ impl Handle {
         }
     }
 
+    /// The id of the process owning the window or [`None`] if the window cannot be found
+    pub fn process_id(&self) -> Option<u32> {
+        let handle = self.query_handle()?;
+        let mut process_id = 0u32;
+        unsafe { GetWindowThreadProcessId(handle, Some(&raw mut process_id)) };
+        (process_id != 0).then_some(process_id)
+    }
+
+    /// Whether the window is minimized
+    pub fn is_minimized(&self) -> bool {
+        self.query_handle()
+            .is_some_and(|handle| unsafe { IsIconic(handle) }.as_bool())
+    }
+
+    /// Whether the window class starts with `class`
+    pub fn is_class(&self, class: &'static str) -> bool {
+        self.query_handle()
+            .is_some_and(|handle| is_class_matched(handle, class))
+    }
+
     fn query_handle(&self) -> Option<HWND> {
         match self.kind {
             HandleKind::Fixed(handle) => Some(handle),
@@ -140,6 +163,73 @@ pub fn query_capture_handles() -> Vec<(String, Handle)> {
     vec
 }
 
+/// Moves and resizes the window of `handle` so that its client area is at `x` and `y` in screen
+/// coordinates with `width` and `height`
+///
+/// The client area is what gets captured so the window rectangle is expanded to account for the
+/// borders and title bar.
+pub fn set_window_client_layout(
+    handle: Handle,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) -> Result<(), Error> {
+    if width <= 0 || height <= 0 {
+        return Err(Error::InvalidWindowSize);
+    }
+    let handle = handle.query_handle().ok_or(Error::WindowNotFound)?;
+    with_per_monitor_dpi_awareness(|| {
+        let style = unsafe { GetWindowLongPtrW(handle, GWL_STYLE) } as u32;
+        let ex_style = unsafe { GetWindowLongPtrW(handle, GWL_EXSTYLE) } as u32;
+        let mut rect = RECT {
+            left: x,
+            top: y,
+            right: x + width,
+            bottom: y + height,
+        };
+        unsafe {
+            AdjustWindowRectEx(
+                &raw mut rect,
+                WINDOW_STYLE(style),
+                false,
+                WINDOW_EX_STYLE(ex_style),
+            )
+        }?;
+        unsafe {
+            SetWindowPos(
+                handle,
+                None,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+        }?;
+        Ok(())
+    })
+}
+
+/// Whether the window title contains `text` or the window class starts with `text`
+#[inline]
+pub(crate) fn is_title_or_class_matched(handle: HWND, text: &str) -> bool {
+    let mut buf = [0u16; 256];
+    let count = unsafe { GetWindowTextW(handle, &mut buf) } as usize;
+    if count > 0
+        && OsString::from_wide(&buf[..count])
+            .to_str()
+            .is_some_and(|title| title.contains(text))
+    {
+        return true;
+    }
+    let count = unsafe { GetClassNameW(handle, &mut buf) as usize };
+    count > 0
+        && OsString::from_wide(&buf[..count])
+            .to_str()
+            .is_some_and(|class| class.starts_with(text))
+}
+
 #[inline]
 fn is_class_matched(handle: HWND, class: &'static str) -> bool {
     let mut buf = [0u16; 256];
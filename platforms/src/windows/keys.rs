@@ -1,5 +1,6 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    collections::VecDeque,
     mem::{self},
     sync::LazyLock,
 };
@@ -15,28 +16,34 @@ use windows::{
             Input::KeyboardAndMouse::{
                 INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT,
                 KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, MAPVK_VK_TO_VSC_EX, MOUSEEVENTF_ABSOLUTE,
-                MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MOVE, MOUSEINPUT,
-                MapVirtualKeyW, SendInput, VIRTUAL_KEY, VK_0, VK_1, VK_2, VK_3, VK_4, VK_5, VK_6,
-                VK_7, VK_8, VK_9, VK_A, VK_B, VK_C, VK_CONTROL, VK_D, VK_DELETE, VK_DOWN, VK_E,
-                VK_END, VK_ESCAPE, VK_F, VK_F1, VK_F2, VK_F3, VK_F4, VK_F5, VK_F6, VK_F7, VK_F8,
-                VK_F9, VK_F10, VK_F11, VK_F12, VK_G, VK_H, VK_HOME, VK_I, VK_INSERT, VK_J, VK_K,
-                VK_L, VK_LEFT, VK_M, VK_MENU, VK_N, VK_NEXT, VK_O, VK_OEM_1, VK_OEM_2, VK_OEM_3,
-                VK_OEM_7, VK_OEM_COMMA, VK_OEM_PERIOD, VK_P, VK_PRIOR, VK_Q, VK_R, VK_RETURN,
-                VK_RIGHT, VK_S, VK_SHIFT, VK_SPACE, VK_T, VK_U, VK_UP, VK_V, VK_W, VK_X, VK_Y,
-                VK_Z,
+                MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MOVE,
+                MOUSEEVENTF_VIRTUALDESK, MOUSEINPUT, MapVirtualKeyW, SendInput, VIRTUAL_KEY, VK_0,
+                VK_1, VK_2, VK_3, VK_4, VK_5, VK_6, VK_7, VK_8, VK_9, VK_A, VK_B, VK_C, VK_CONTROL,
+                VK_D, VK_DELETE, VK_DOWN, VK_E, VK_END, VK_ESCAPE, VK_F, VK_F1, VK_F2, VK_F3,
+                VK_F4, VK_F5, VK_F6, VK_F7, VK_F8, VK_F9, VK_F10, VK_F11, VK_F12, VK_G, VK_H,
+                VK_HOME, VK_I, VK_INSERT, VK_J, VK_K, VK_L, VK_LEFT, VK_M, VK_MENU, VK_N, VK_NEXT,
+                VK_O, VK_OEM_1, VK_OEM_2, VK_OEM_3, VK_OEM_7, VK_OEM_COMMA, VK_OEM_PERIOD, VK_P,
+                VK_PRIOR, VK_Q, VK_R, VK_RETURN, VK_RIGHT, VK_S, VK_SHIFT, VK_SPACE, VK_T, VK_U,
+                VK_UP, VK_V, VK_W, VK_X, VK_Y, VK_Z,
             },
             WindowsAndMessaging::{
                 CallNextHookEx, GetForegroundWindow, GetSystemMetrics, GetWindowRect,
                 GetWindowThreadProcessId, HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, LLKHF_INJECTED,
-                LLKHF_LOWER_IL_INJECTED, SM_CXSCREEN, SM_CYSCREEN, SetForegroundWindow,
-                SetWindowsHookExW, WH_KEYBOARD_LL, WM_KEYDOWN, WM_KEYUP,
+                LLKHF_LOWER_IL_INJECTED, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+                SM_YVIRTUALSCREEN, SetForegroundWindow, SetWindowsHookExW, WH_KEYBOARD_LL,
+                WM_KEYDOWN, WM_KEYUP,
             },
         },
     },
     core::Owned,
 };
 
-use super::{HandleCell, error::Error, handle::Handle};
+use super::{
+    HandleCell,
+    dpi::with_per_monitor_dpi_awareness,
+    error::Error,
+    handle::{Handle, is_title_or_class_matched},
+};
 
 static KEY_CHANNEL: LazyLock<Sender<KeyKind>> = LazyLock::new(|| broadcast::channel(1).0);
 static PROCESS_ID: LazyLock<u32> = LazyLock::new(|| unsafe { GetCurrentProcessId() });
@@ -85,11 +92,15 @@ impl KeyReceiver {
         }
     }
 
-    pub fn try_recv(&mut self) -> Option<KeyKind> {
+    /// Receives the next key pressed while this process or the captured window is in the
+    /// foreground
+    ///
+    /// Keys in `global_keys` are received regardless of the foreground window.
+    pub fn try_recv(&mut self, global_keys: &[KeyKind]) -> Option<KeyKind> {
         self.rx
             .try_recv()
             .ok()
-            .and_then(|key| self.can_process_key().then_some(key))
+            .and_then(|key| (global_keys.contains(&key) || self.can_process_key()).then_some(key))
     }
 
     // TODO: Is this good?
@@ -121,7 +132,25 @@ pub enum KeyInputKind {
 pub struct Keys {
     handle: HandleCell,
     key_input_kind: KeyInputKind,
+    /// The title or class the foreground window must match for inputs to be sent
+    ///
+    /// A safety interlock on top of [`KeyInputKind`] in case the wrong handle was selected.
+    foreground_interlock: Option<String>,
     key_down: RefCell<BitVec>,
+    /// The number of times [`Keys::update_scheduled`] has been called
+    tick: Cell<u64>,
+    /// The scheduled key inputs sorted by the tick they are due
+    scheduled: RefCell<VecDeque<(u64, ScheduledKey)>>,
+}
+
+/// A key input in a sequence passed to [`Keys::schedule`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledKey {
+    /// The number of ticks after scheduling to send this input
+    pub tick_offset: u32,
+    pub kind: KeyKind,
+    /// Whether to send key down or key up
+    pub is_down: bool,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
@@ -204,10 +233,57 @@ impl Keys {
         Self {
             handle: HandleCell::new(handle),
             key_input_kind: kind,
+            foreground_interlock: None,
             key_down: RefCell::new(BitVec::from_elem(256, false)),
+            tick: Cell::new(0),
+            scheduled: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// Sets the title or class the foreground window must match for inputs to be sent
+    ///
+    /// The window matches if its title contains or its class starts with `interlock`. [`None`]
+    /// disables the interlock.
+    pub fn set_foreground_interlock(&mut self, interlock: Option<String>) {
+        self.foreground_interlock = interlock;
+    }
+
+    /// Schedules a sequence of key inputs to be sent at exact tick offsets
+    ///
+    /// The inputs are sent by [`Keys::update_scheduled`] which must be called exactly once
+    /// per tick. Inputs due in the same tick are sent in the order they are scheduled.
+    pub fn schedule(&self, sequence: &[ScheduledKey]) {
+        let tick = self.tick.get();
+        let mut scheduled = self.scheduled.borrow_mut();
+        for key in sequence {
+            let due = tick + key.tick_offset as u64;
+            let index = scheduled.partition_point(|(key_due, _)| *key_due <= due);
+            scheduled.insert(index, (due, *key));
         }
     }
 
+    /// Sends the scheduled key inputs due in the current tick and advances the tick
+    ///
+    /// Returns the inputs that were sent successfully.
+    pub fn update_scheduled(&self) -> Vec<ScheduledKey> {
+        let tick = self.tick.get();
+        let mut sent = Vec::new();
+        loop {
+            let key = {
+                let mut scheduled = self.scheduled.borrow_mut();
+                match scheduled.front() {
+                    Some((due, _)) if *due <= tick => scheduled.pop_front().unwrap().1,
+                    _ => break,
+                }
+            };
+            if self.send_input(key.kind, key.is_down).is_ok() {
+                sent.push(key);
+            }
+        }
+        self.tick.set(tick + 1);
+        sent
+    }
+
     pub fn send(&self, kind: KeyKind) -> Result<(), Error> {
         self.send_down(kind)?;
         self.send_up(kind)?;
@@ -222,22 +298,37 @@ impl Keys {
     pub fn send_click_to_focus_inner(&self) -> Result<(), Error> {
         let mut handle = self.get_handle()?;
         match self.key_input_kind {
-            KeyInputKind::Fixed => unsafe { SetForegroundWindow(handle).ok()? },
+            KeyInputKind::Fixed => {
+                if !self.is_interlock_matched(handle) {
+                    return Err(Error::KeyNotSent);
+                }
+                unsafe { SetForegroundWindow(handle).ok()? }
+            }
             KeyInputKind::Foreground => {
                 if !is_foreground(handle, KeyInputKind::Foreground) {
                     return Err(Error::WindowNotFound);
                 }
                 handle = unsafe { GetForegroundWindow() };
+                if !self.is_interlock_matched(handle) {
+                    return Err(Error::KeyNotSent);
+                }
             }
         }
-        let x_metric = unsafe { GetSystemMetrics(SM_CXSCREEN) };
-        let y_metric = unsafe { GetSystemMetrics(SM_CYSCREEN) };
-        let mut rect = RECT::default();
-        unsafe { GetWindowRect(handle, &raw mut rect)? };
-        let dx = rect.left + (rect.right - rect.left) / 2;
-        let dx = (dx * 65536) / x_metric;
-        let dy = rect.top + (rect.bottom - rect.top) / 2;
-        let dy = (dy * 65536) / y_metric;
+        // Normalizes to the virtual desktop so that clicking works on any monitor
+        let (dx, dy) = with_per_monitor_dpi_awareness(|| -> Result<(i32, i32), Error> {
+            let x_virtual = unsafe { GetSystemMetrics(SM_XVIRTUALSCREEN) };
+            let y_virtual = unsafe { GetSystemMetrics(SM_YVIRTUALSCREEN) };
+            let width_virtual = unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) }.max(2);
+            let height_virtual = unsafe { GetSystemMetrics(SM_CYVIRTUALSCREEN) }.max(2);
+            let mut rect = RECT::default();
+            unsafe { GetWindowRect(handle, &raw mut rect)? };
+            let x = rect.left + (rect.right - rect.left) / 2 - x_virtual;
+            let y = rect.top + (rect.bottom - rect.top) / 2 - y_virtual;
+            Ok((
+                (x * 65535) / (width_virtual - 1),
+                (y * 65535) / (height_virtual - 1),
+            ))
+        })?;
         let input = [INPUT {
             r#type: INPUT_MOUSE,
             Anonymous: INPUT_0 {
@@ -245,6 +336,7 @@ impl Keys {
                     dx,
                     dy,
                     dwFlags: MOUSEEVENTF_ABSOLUTE
+                        | MOUSEEVENTF_VIRTUALDESK
                         | MOUSEEVENTF_MOVE
                         | MOUSEEVENTF_LEFTDOWN
                         | MOUSEEVENTF_LEFTUP,
@@ -266,7 +358,9 @@ impl Keys {
     #[inline]
     fn send_input(&self, kind: KeyKind, is_down: bool) -> Result<(), Error> {
         let handle = self.get_handle()?;
-        if !is_foreground(handle, self.key_input_kind) {
+        if !is_foreground(handle, self.key_input_kind)
+            || !self.is_interlock_matched(unsafe { GetForegroundWindow() })
+        {
             return Err(Error::KeyNotSent);
         }
         let key = kind.into();
@@ -284,6 +378,13 @@ impl Keys {
         send_input(to_input(key, scan_code, is_extended, is_down))
     }
 
+    #[inline]
+    fn is_interlock_matched(&self, handle: HWND) -> bool {
+        self.foreground_interlock
+            .as_deref()
+            .is_none_or(|interlock| is_title_or_class_matched(handle, interlock))
+    }
+
     #[inline]
     fn get_handle(&self) -> Result<HWND, Error> {
         self.handle.as_inner().ok_or(Error::WindowNotFound)
@@ -470,12 +571,14 @@ fn is_foreground(handle: HWND, kind: KeyInputKind) -> bool {
             let mut rect_fg = RECT::default();
             let mut rect_handle = RECT::default();
             let mut rect_intersect = RECT::default();
+            let has_rects = with_per_monitor_dpi_awareness(|| unsafe {
+                GetWindowRect(handle_fg, &mut rect_fg).is_ok()
+                    && GetWindowRect(handle, &mut rect_handle).is_ok()
+            });
+            if !has_rects {
+                return false;
+            }
             unsafe {
-                if GetWindowRect(handle_fg, &mut rect_fg).is_err()
-                    || GetWindowRect(handle, &mut rect_handle).is_err()
-                {
-                    return false;
-                }
                 IntersectRect(
                     &raw mut rect_intersect,
                     &raw const rect_fg,
@@ -10,14 +10,21 @@ use windows::Win32::UI::WindowsAndMessaging::{
     DispatchMessageW, GetMessageW, MSG, TranslateMessage,
 };
 
+#[cfg(feature = "audio-capture")]
+mod audio;
 mod bitblt;
+mod dpi;
 mod error;
 mod handle;
 mod keys;
+mod speech;
+mod time;
 mod wgc;
 mod window_box;
 
-pub use {bitblt::*, error::*, handle::*, keys::*, wgc::*, window_box::*};
+#[cfg(feature = "audio-capture")]
+pub use audio::*;
+pub use {bitblt::*, error::*, handle::*, keys::*, speech::*, time::*, wgc::*, window_box::*};
 
 #[derive(Clone, Debug)]
 pub struct Frame {
@@ -33,6 +40,7 @@ pub fn init() {
         .compare_exchange(false, true, Ordering::SeqCst, Ordering::Acquire)
         .is_ok()
     {
+        dpi::init();
         let barrier = Arc::new(Barrier::new(2));
         let keys_barrier = barrier.clone();
         thread::spawn(move || {
@@ -0,0 +1,53 @@
+use std::{
+    sync::{
+        LazyLock, Mutex,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use windows::{
+    Win32::{
+        Media::Speech::{ISpVoice, SPF_ASYNC, SPF_IS_NOT_XML, SpVoice},
+        System::Com::{CLSCTX_ALL, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx},
+    },
+    core::HSTRING,
+};
+
+use super::error::Error;
+
+/// The sender to the thread owning the SAPI voice
+///
+/// The voice is created and used only inside its own thread to not depend on the COM
+/// apartment of the caller.
+static SPEAKER: LazyLock<Mutex<Sender<String>>> = LazyLock::new(|| {
+    let (tx, rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        let voice = unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            CoCreateInstance::<_, ISpVoice>(&SpVoice, None, CLSCTX_ALL)
+        };
+        let Ok(voice) = voice else {
+            return;
+        };
+        for text in rx {
+            let _ = unsafe {
+                voice.Speak(
+                    &HSTRING::from(text),
+                    (SPF_ASYNC.0 | SPF_IS_NOT_XML.0) as u32,
+                    None,
+                )
+            };
+        }
+    });
+    Mutex::new(tx)
+});
+
+/// Speaks `text` asynchronously using the default Windows SAPI voice
+pub fn speak(text: impl Into<String>) -> Result<(), Error> {
+    SPEAKER
+        .lock()
+        .unwrap()
+        .send(text.into())
+        .map_err(|_| Error::SpeechNotAvailable)
+}
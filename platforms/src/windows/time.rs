@@ -0,0 +1,22 @@
+use windows::Win32::System::Time::{
+    GetTimeZoneInformation, TIME_ZONE_ID_DAYLIGHT, TIME_ZONE_ID_INVALID, TIME_ZONE_INFORMATION,
+};
+
+/// The offset in minutes of the current local time zone from UTC
+///
+/// Daylight saving time is included if it is currently in effect. Returns `0` if the time zone
+/// cannot be queried.
+pub fn local_utc_offset_minutes() -> i32 {
+    let mut info = TIME_ZONE_INFORMATION::default();
+    let id = unsafe { GetTimeZoneInformation(&raw mut info) };
+    if id == TIME_ZONE_ID_INVALID {
+        return 0;
+    }
+    // UTC = local time + bias
+    let bias = if id == TIME_ZONE_ID_DAYLIGHT {
+        info.Bias + info.DaylightBias
+    } else {
+        info.Bias + info.StandardBias
+    };
+    -bias
+}
@@ -27,7 +27,7 @@ use windows::{
                 D3D11_USAGE_STAGING, D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext,
                 ID3D11Texture2D,
             },
-            Dwm::{DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute},
+            Dwm::{DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute},
             Dxgi::{
                 Common::{DXGI_FORMAT, DXGI_SAMPLE_DESC},
                 IDXGIDevice,
@@ -39,15 +39,18 @@ use windows::{
             Graphics::Capture::IGraphicsCaptureItemInterop,
             RoGetActivationFactory,
         },
-        UI::WindowsAndMessaging::GetClientRect,
+        UI::WindowsAndMessaging::{GetClientRect, IsIconic},
     },
     core::{HSTRING, Interface, RuntimeName},
 };
 
-use super::{Error, Frame, Handle, HandleCell};
+use super::{Error, Frame, Handle, HandleCell, dpi::with_per_monitor_dpi_awareness};
 
 const MAX_FRAME_FAILURE: u32 = 3;
 
+/// The number of pixels to skip between sampled pixels when checking for a black frame
+const BLACK_FRAME_SAMPLE_STRIDE: usize = 97;
+
 #[derive(Debug)]
 struct WgcCaptureInner {
     handle: HWND,
@@ -69,6 +72,18 @@ struct WgcCaptureInner {
 
 impl WgcCaptureInner {
     fn grab_with_timeout(&mut self) -> Result<Frame, Error> {
+        if is_window_occluded(self.handle) {
+            // Drops the frames arrived while occluded so they are not returned later
+            while let Ok(message) = self.frame_rx.try_recv() {
+                if let Message::ItemClosed = message {
+                    return Err(Error::WindowNotFound);
+                }
+            }
+            // Frames are not expected while occluded so do not count as failures
+            self.consecutive_failure = 0;
+            return Err(Error::WindowOccluded);
+        }
+
         let message = self
             .frame_rx
             .recv_timeout(Duration::from_millis(self.frame_timeout))
@@ -100,7 +115,9 @@ impl WgcCaptureInner {
         let mut surface_desc = D3D11_TEXTURE2D_DESC::default();
         unsafe { surface_texture.GetDesc(&mut surface_desc) };
 
-        let texture_rect = get_client_rect(self.handle, surface_desc.Width, surface_desc.Height)?;
+        let texture_rect = with_per_monitor_dpi_awareness(|| {
+            get_client_rect(self.handle, surface_desc.Width, surface_desc.Height)
+        })?;
         let texture_width = texture_rect.right - texture_rect.left;
         let texture_height = texture_rect.bottom - texture_rect.top;
         if self.d3d11_texture.as_ref().is_none_or(|texture| {
@@ -166,6 +183,9 @@ impl WgcCaptureInner {
         unsafe {
             self.d3d11_context.Unmap(texture, 0);
         };
+        if is_black_frame(&vec) {
+            return Err(Error::WindowOccluded);
+        }
 
         if frame_content_size != self.frame_last_content_size {
             self.frame_format = DirectXPixelFormat(surface_desc.Format.0);
@@ -267,6 +287,7 @@ impl WgcCapture {
             ))?;
         session.StartCapture()?;
         let _ = session.SetIsBorderRequired(false);
+        let _ = session.SetIsCursorCaptureEnabled(false);
 
         self.inner = Some(WgcCaptureInner {
             handle,
@@ -289,6 +310,34 @@ impl WgcCapture {
     }
 }
 
+/// Whether the window is minimized or cloaked (e.g. on another virtual desktop)
+///
+/// The capture session keeps running but frames are either not arriving or black.
+#[inline]
+fn is_window_occluded(handle: HWND) -> bool {
+    if unsafe { IsIconic(handle) }.as_bool() {
+        return true;
+    }
+    let mut cloaked = 0u32;
+    let result = unsafe {
+        DwmGetWindowAttribute(
+            handle,
+            DWMWA_CLOAKED,
+            (&raw mut cloaked).cast(),
+            mem::size_of::<u32>() as u32,
+        )
+    };
+    result.is_ok() && cloaked != 0
+}
+
+/// Whether the BGRA `data` is completely black by sampling its pixels
+#[inline]
+fn is_black_frame(data: &[u8]) -> bool {
+    data.chunks_exact(4)
+        .step_by(BLACK_FRAME_SAMPLE_STRIDE)
+        .all(|pixel| pixel[0] == 0 && pixel[1] == 0 && pixel[2] == 0)
+}
+
 #[inline]
 fn get_client_rect(handle: HWND, width: u32, height: u32) -> Result<D3D11_BOX, Error> {
     let mut window_rect = RECT::default();
@@ -5,8 +5,10 @@ use std::{
 };
 
 use backend::{
-    Action, ActionCondition, ActionKey, ActionKeyDirection, ActionKeyWith, ActionMove,
-    IntoEnumIterator, LinkKeyBinding, Minimap, ParseError, Position, Settings,
+    Action, ActionCondition, ActionConditionOperand, ActionKey, ActionKeyDirection, ActionKeyWith,
+    ActionMove, GlobalAction, GlobalActionSlot, IntoEnumIterator, KeyModifier, LinkKeyBinding,
+    Minimap, ParseError, Position, PresetMetadata, Settings, YBand, append_action_to_preset,
+    current_player_position,
 };
 use dioxus::{document::eval, prelude::*};
 use futures_util::StreamExt;
@@ -14,10 +16,13 @@ use rand::distr::{Alphanumeric, SampleString};
 
 use crate::{
     AppMessage,
+    detection::Detection,
     icons::{PositionIcon, XIcon},
     input::{
-        Checkbox, KeyBindingInput, MillisInput, NumberInputI32, NumberInputU32, use_auto_numeric,
+        Checkbox, KeyBindingInput, LabeledInput, MillisInput, NumberInputI32, NumberInputU32,
+        PercentageInput, use_auto_numeric,
     },
+    navigation::Navigation,
     platform::Platforms,
     rotation::Rotations,
     select::{EnumSelect, TextSelect},
@@ -42,8 +47,11 @@ pub fn Actions(
     copy_position: ReadOnlySignal<Option<(i32, i32)>>,
 ) -> Element {
     const TAB_PRESET: &str = "Preset";
+    const TAB_GLOBAL: &str = "Global";
     const TAB_ROTATION_MODE: &str = "Rotation Mode";
     const TAB_PLATFORMS: &str = "Platforms";
+    const TAB_NAVIGATION: &str = "Navigation";
+    const TAB_DETECTION: &str = "Detection";
 
     let mut editing_action = use_signal::<Option<(Action, usize)>>(|| None);
     let value_action = use_signal(|| Action::Move(ActionMove::default()));
@@ -72,6 +80,37 @@ pub fn Actions(
             .map(|minimap| minimap.actions_any_reset_on_erda_condition)
             .unwrap_or_default()
     });
+    let erda_snap_to_platform_view = use_memo(move || {
+        minimap()
+            .map(|minimap| minimap.actions_erda_snap_to_platform_midpoint)
+            .unwrap_or_default()
+    });
+    let warm_up_view = use_memo(move || {
+        minimap()
+            .map(|minimap| minimap.actions_warm_up)
+            .unwrap_or_default()
+    });
+    let warm_up_position_view =
+        use_memo(move || minimap().and_then(|minimap| minimap.actions_warm_up_position));
+    let auto_mob_bound_polygon_view = use_memo(move || {
+        minimap()
+            .map(|minimap| minimap.auto_mob_bound_polygon)
+            .unwrap_or_default()
+    });
+    let presets_view = use_memo(move || {
+        minimap()
+            .map(|minimap| {
+                let mut presets = minimap.actions.into_keys().collect::<Vec<_>>();
+                presets.sort();
+                presets
+            })
+            .unwrap_or_default()
+    });
+    let ab_comparison_view = use_memo(move || {
+        minimap()
+            .map(|minimap| (minimap.ab_comparison_preset, minimap.ab_comparison_loops))
+            .unwrap_or_default()
+    });
 
     use_effect(move || {
         if preset().is_none() {
@@ -83,8 +122,11 @@ pub fn Actions(
         Tab {
             tabs: vec![
                 TAB_PRESET.to_string(),
+                TAB_GLOBAL.to_string(),
                 TAB_ROTATION_MODE.to_string(),
                 TAB_PLATFORMS.to_string(),
+                TAB_NAVIGATION.to_string(),
+                TAB_DETECTION.to_string(),
             ],
             div_class: "px-2 pt-2 pb-1 mb-2",
             class: "text-xs px-2 pb-2 focus:outline-none",
@@ -112,6 +154,15 @@ pub fn Actions(
                         },
                     }
                 },
+                TAB_GLOBAL => rsx! {
+                    ActionGlobalTab {
+                        settings,
+                        copy_position,
+                        update_settings: move |settings| {
+                            app_coroutine.send(AppMessage::UpdateSettings(settings));
+                        },
+                    }
+                },
                 TAB_ROTATION_MODE => rsx! {
                     Rotations {
                         disabled: minimap().is_none(),
@@ -127,8 +178,47 @@ pub fn Actions(
                                 coroutine.send(ActionsMessage::UpdateMinimap(minimap));
                             }
                         },
+                        on_erda_snap_to_platform: move |checked| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.actions_erda_snap_to_platform_midpoint = checked;
+                                coroutine.send(ActionsMessage::UpdateMinimap(minimap));
+                            }
+                        },
+                        on_warm_up: move |checked| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.actions_warm_up = checked;
+                                coroutine.send(ActionsMessage::UpdateMinimap(minimap));
+                            }
+                        },
+                        on_warm_up_position: move |position| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.actions_warm_up_position = position;
+                                coroutine.send(ActionsMessage::UpdateMinimap(minimap));
+                            }
+                        },
+                        on_auto_mob_bound_polygon: move |polygon| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.auto_mob_bound_polygon = polygon;
+                                coroutine.send(ActionsMessage::UpdateMinimap(minimap));
+                            }
+                        },
+                        on_ab_comparison: move |(compared, loops)| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.ab_comparison_preset = compared;
+                                minimap.ab_comparison_loops = loops;
+                                coroutine.send(ActionsMessage::UpdateMinimap(minimap));
+                            }
+                        },
                         rotation_mode: rotation_mode_view(),
                         reset_on_erda: reset_on_erda_view(),
+                        erda_snap_to_platform: erda_snap_to_platform_view(),
+                        warm_up: warm_up_view(),
+                        warm_up_position: warm_up_position_view(),
+                        auto_mob_bound_polygon: auto_mob_bound_polygon_view(),
+                        presets: presets_view(),
+                        preset: preset(),
+                        ab_comparison_preset: ab_comparison_view().0,
+                        ab_comparison_loops: ab_comparison_view().1,
                     }
                 },
                 TAB_PLATFORMS => rsx! {
@@ -141,6 +231,22 @@ pub fn Actions(
                         settings,
                     }
                 },
+                TAB_NAVIGATION => rsx! {
+                    Navigation {
+                        minimap,
+                        on_save: move |minimap| {
+                            coroutine.send(ActionsMessage::UpdateMinimap(minimap));
+                        },
+                    }
+                },
+                TAB_DETECTION => rsx! {
+                    Detection {
+                        minimap,
+                        on_save: move |minimap| {
+                            coroutine.send(ActionsMessage::UpdateMinimap(minimap));
+                        },
+                    }
+                },
                 _ => unreachable!(),
             }
         }
@@ -184,6 +290,17 @@ fn ActionPresetTab(
             .and_then(|(minimap, preset)| minimap.actions.get(&preset).cloned())
             .unwrap_or_default()
     });
+    let metadata = use_memo::<PresetMetadata>(move || {
+        minimap()
+            .zip(preset())
+            .and_then(|(minimap, preset)| minimap.presets_metadata.get(&preset).cloned())
+            .unwrap_or_default()
+    });
+    let start_position = use_memo::<Option<Position>>(move || {
+        minimap()
+            .zip(preset())
+            .and_then(|(minimap, preset)| minimap.presets_start_position.get(&preset).copied())
+    });
     let on_edit = use_callback(move |action| {
         value_action.set(action);
     });
@@ -198,6 +315,32 @@ fn ActionPresetTab(
             update_minimap(minimap);
         }
     });
+    let on_add_here = use_callback(move |_| {
+        let Some(preset) = preset() else {
+            return;
+        };
+        spawn(async move {
+            let Some((x, y)) = current_player_position().await else {
+                return;
+            };
+            let mut action = *value_action.peek();
+            match &mut action {
+                Action::Move(ActionMove { position, .. }) => {
+                    position.x = x;
+                    position.y = y;
+                }
+                Action::Key(ActionKey { position, .. }) => {
+                    let mut new_position = position.unwrap_or_default();
+                    new_position.x = x;
+                    new_position.y = y;
+                    *position = Some(new_position);
+                }
+            }
+            if let Some(minimap) = append_action_to_preset(preset, action).await {
+                update_minimap(minimap);
+            }
+        });
+    });
     let on_remove = use_callback(move |index| {
         if let Some((mut minimap, preset)) = minimap().zip(preset()) {
             let actions = minimap.actions.get_mut(&preset).unwrap();
@@ -322,6 +465,37 @@ fn ActionPresetTab(
                 options: presets(),
                 selected: preset(),
             }
+            if let Some(preset) = preset() {
+                ActionPresetMetadataInput {
+                    key: "{preset}",
+                    on_input: move |metadata| {
+                        if let Some(mut minimap) = minimap.peek().clone() {
+                            minimap.presets_metadata.insert(preset.clone(), metadata);
+                            update_minimap(minimap);
+                        }
+                    },
+                    value: metadata(),
+                }
+            }
+            if let Some(preset) = preset() {
+                ActionPresetStartPointInput {
+                    on_input: move |position: Option<Position>| {
+                        if let Some(mut minimap) = minimap.peek().clone() {
+                            let preset = preset.clone();
+                            match position {
+                                Some(position) => {
+                                    minimap.presets_start_position.insert(preset, position);
+                                }
+                                None => {
+                                    minimap.presets_start_position.remove(&preset);
+                                }
+                            }
+                            update_minimap(minimap);
+                        }
+                    },
+                    value: start_position(),
+                }
+            }
             div { class: "flex space-x-2 overflow-y-auto flex-1",
                 div { class: "w-1/2 overflow-y-auto scrollbar pr-2",
                     div { class: "flex flex-col space-y-2.5",
@@ -364,13 +538,23 @@ fn ActionPresetTab(
                             },
                         }
                         if editing_action().is_none() {
-                            button {
-                                class: "w-full button-primary h-6",
-                                disabled: preset().is_none(),
-                                onclick: move |_| {
-                                    on_save(None);
-                                },
-                                "Add action"
+                            div { class: "grid grid-cols-2 gap-x-2",
+                                button {
+                                    class: "button-primary h-6",
+                                    disabled: preset().is_none(),
+                                    onclick: move |_| {
+                                        on_save(None);
+                                    },
+                                    "Add action"
+                                }
+                                button {
+                                    class: "button-secondary h-6",
+                                    disabled: preset().is_none(),
+                                    onclick: move |_| {
+                                        on_add_here(());
+                                    },
+                                    "Add action here"
+                                }
                             }
                         } else {
                             div { class: "grid grid-cols-2 gap-x-2",
@@ -411,6 +595,273 @@ fn ActionPresetTab(
     }
 }
 
+#[component]
+fn ActionGlobalTab(
+    settings: ReadOnlySignal<Option<Settings>>,
+    copy_position: ReadOnlySignal<Option<(i32, i32)>>,
+    update_settings: EventHandler<Settings>,
+) -> Element {
+    let global_actions = use_memo(move || {
+        settings()
+            .map(|settings| settings.global_actions)
+            .unwrap_or_default()
+    });
+    let mut value_action = use_signal(|| Action::Key(ActionKey::default()));
+    let mut value_slot = use_signal(GlobalActionSlot::default);
+    let mut editing_index = use_signal::<Option<usize>>(|| None);
+    let disabled = use_memo(move || settings().is_none());
+    let on_update = use_callback(move |global_actions| {
+        if let Some(settings) = settings.peek().clone() {
+            update_settings(Settings {
+                global_actions,
+                ..settings
+            });
+        }
+    });
+    let on_save = use_callback(move |index: Option<usize>| {
+        let mut actions = global_actions.peek().clone();
+        let action = *value_action.peek();
+        let slot = *value_slot.peek();
+        if let Some(global) = index.and_then(|index| actions.get_mut(index)) {
+            global.action = action;
+            global.slot = slot;
+        } else {
+            let id = actions
+                .iter()
+                .map(|global| global.id + 1)
+                .max()
+                .unwrap_or_default();
+            actions.push(GlobalAction {
+                id,
+                action,
+                slot,
+                enabled: true,
+            });
+        }
+        on_update(actions);
+    });
+
+    rsx! {
+        div { class: "flex flex-col h-full",
+            ul { class: "list-disc text-xs text-gray-700 pl-4 mb-2",
+                li { "Global actions are merged into the rotation of every preset" }
+                li { "Before and After slots place the actions before or after the preset actions" }
+            }
+            div { class: "flex space-x-2 overflow-y-auto flex-1",
+                div { class: "w-1/2 overflow-y-auto scrollbar pr-2",
+                    div { class: "flex flex-col space-y-2.5",
+                        ActionEnumSelect {
+                            label: "Slot",
+                            on_input: move |slot| {
+                                value_slot.set(slot);
+                            },
+                            disabled: disabled(),
+                            value: value_slot(),
+                        }
+                        ActionEnumSelect {
+                            label: "Type",
+                            on_input: move |action: Action| {
+                                if value_action.peek().to_string() != action.to_string() {
+                                    value_action.set(action);
+                                }
+                            },
+                            disabled: disabled(),
+                            value: value_action(),
+                        }
+                        match value_action() {
+                            Action::Move(_) => rsx! {
+                                ActionMoveInput {
+                                    copy_position,
+                                    on_input: move |action| {
+                                        value_action.set(action);
+                                    },
+                                    disabled: disabled(),
+                                    value: value_action(),
+                                    exclude_linked: true,
+                                }
+                            },
+                            Action::Key(_) => rsx! {
+                                ActionKeyInput {
+                                    copy_position,
+                                    on_input: move |action| {
+                                        value_action.set(action);
+                                    },
+                                    disabled: disabled(),
+                                    value: value_action(),
+                                    exclude_linked: true,
+                                }
+                            },
+                        }
+                        if editing_index().is_none() {
+                            button {
+                                class: "w-full button-primary h-6",
+                                disabled: disabled(),
+                                onclick: move |_| {
+                                    on_save(None);
+                                },
+                                "Add action"
+                            }
+                        } else {
+                            div { class: "grid grid-cols-2 gap-x-2",
+                                button {
+                                    class: "button-primary h-6",
+                                    onclick: move |_| {
+                                        on_save(editing_index.take());
+                                    },
+                                    "Save"
+                                }
+                                button {
+                                    class: "button-secondary h-6",
+                                    onclick: move |_| {
+                                        editing_index.set(None);
+                                    },
+                                    "Cancel"
+                                }
+                            }
+                        }
+                    }
+                }
+                div { class: "flex-1 flex flex-col space-y-1 px-1 overflow-y-auto scrollbar rounded",
+                    if global_actions().is_empty() {
+                        div { class: "flex items-center justify-center text-sm text-gray-500 h-full",
+                            "No global actions"
+                        }
+                    } else {
+                        for (i , global) in global_actions().into_iter().enumerate() {
+                            ActionCheckbox {
+                                label: format!("{} preset actions", global.slot),
+                                disabled: disabled(),
+                                on_input: move |enabled| {
+                                    let mut actions = global_actions.peek().clone();
+                                    actions[i].enabled = enabled;
+                                    on_update(actions);
+                                },
+                                value: global.enabled,
+                            }
+                            ActionItem {
+                                dragging: false,
+                                draggable: false,
+                                index: i,
+                                action: global.action,
+                                on_click: move |_| {
+                                    editing_index.set(Some(i));
+                                    value_action.set(global.action);
+                                    value_slot.set(global.slot);
+                                },
+                                on_remove: move |_| {
+                                    let mut actions = global_actions.peek().clone();
+                                    actions.remove(i);
+                                    editing_index.set(None);
+                                    on_update(actions);
+                                },
+                                on_drag: move |_| {},
+                                on_drop: move |_| {},
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn ActionPresetMetadataInput(
+    on_input: EventHandler<PresetMetadata>,
+    value: PresetMetadata,
+) -> Element {
+    let mut metadata = use_signal(|| value.clone());
+
+    use_effect(use_reactive!(|value| {
+        metadata.set(value);
+    }));
+
+    rsx! {
+        div { class: "flex flex-col space-y-2.5 py-2",
+            LabeledInput {
+                label: "Author",
+                label_class: LABEL_CLASS,
+                div_class: DIV_CLASS,
+                disabled: false,
+                input {
+                    class: "{INPUT_CLASS} p-1",
+                    oninput: move |e| {
+                        metadata.write().author = e.parsed::<String>().unwrap_or_default();
+                    },
+                    value: metadata().author,
+                }
+            }
+            NumberInputU32 {
+                label: "Target Level",
+                label_class: LABEL_CLASS,
+                div_class: DIV_CLASS,
+                input_class: "{INPUT_CLASS} p-1",
+                minimum_value: 0,
+                on_input: move |target_level| {
+                    metadata.write().target_level = target_level;
+                },
+                value: metadata().target_level,
+            }
+            textarea {
+                class: "h-12 p-1 border border-gray-300 rounded text-xs text-gray-700 outline-none resize-none",
+                placeholder: "Note",
+                oninput: move |e| {
+                    metadata.write().note = e.parsed::<String>().unwrap_or_default();
+                },
+                value: metadata().note,
+            }
+            button {
+                class: "button-primary h-6",
+                onclick: move |_| {
+                    on_input(metadata.peek().clone());
+                },
+                "Save notes"
+            }
+        }
+    }
+}
+
+#[component]
+fn ActionPresetStartPointInput(
+    on_input: EventHandler<Option<Position>>,
+    value: Option<Position>,
+) -> Element {
+    rsx! {
+        div { class: "flex flex-col space-y-2.5 pb-2",
+            ActionCheckbox {
+                label: "Move to start point when rotation starts",
+                disabled: false,
+                on_input: move |checked: bool| {
+                    on_input(checked.then_some(Position::default()));
+                },
+                value: value.is_some(),
+            }
+            if let Some(position) = value {
+                NumberInputI32 {
+                    label: "Start point X",
+                    label_class: LABEL_CLASS,
+                    div_class: DIV_CLASS,
+                    input_class: "{INPUT_CLASS} p-1",
+                    on_input: move |x| {
+                        on_input(Some(Position { x, ..position }));
+                    },
+                    value: position.x,
+                }
+                NumberInputI32 {
+                    label: "Start point Y",
+                    label_class: LABEL_CLASS,
+                    div_class: DIV_CLASS,
+                    input_class: "{INPUT_CLASS} p-1",
+                    on_input: move |y| {
+                        on_input(Some(Position { y, ..position }));
+                    },
+                    value: position.y,
+                }
+            }
+        }
+    }
+}
+
 #[component]
 fn ActionItemList(
     disabled: bool,
@@ -493,9 +944,12 @@ fn ActionItem(
                     x_random_range,
                     y,
                     allow_adjusting,
+                    arrival_tolerance,
                 },
             condition,
             wait_after_move_millis,
+            y_band,
+            position_anchor,
         } = action;
         let wait_after_millis_id = use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
 
@@ -521,6 +975,8 @@ fn ActionItem(
                 span { class: KEY, "Adjust" }
                 span { class: VALUE, "{allow_adjusting}" }
             }
+            ActionArrivalToleranceItem { arrival_tolerance }
+            ActionPositionAnchorItem { position_anchor }
             div { class: DIV,
                 span { class: KEY, "Condition" }
                 span { class: VALUE, {condition.to_string()} }
@@ -529,6 +985,7 @@ fn ActionItem(
                 span { class: KEY, "Wait after" }
                 span { id: wait_after_millis_id(), class: VALUE }
             }
+            ActionYBandItem { y_band }
         }
     }
 
@@ -536,7 +993,9 @@ fn ActionItem(
     fn ActionKeyItem(action: ActionKey) -> Element {
         let ActionKey {
             key,
+            modifier,
             link_key,
+            link_key_modifier,
             count,
             position,
             condition,
@@ -547,6 +1006,10 @@ fn ActionItem(
             wait_after_use_millis,
             wait_after_use_millis_random_range,
             queue_to_front,
+            y_band,
+            hold_millis,
+            heavy,
+            position_anchor,
         } = action;
         let wait_before_use_millis_id =
             use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
@@ -590,7 +1053,14 @@ fn ActionItem(
         );
 
         rsx! {
-            if let Some(Position { x, x_random_range, y, allow_adjusting }) = position {
+            if let Some(Position {
+                x,
+                x_random_range,
+                y,
+                allow_adjusting,
+                arrival_tolerance,
+            }) = position
+            {
                 div { class: DIV,
                     span { class: KEY, "Position" }
                     span { class: VALUE, "{x}, {y}" }
@@ -603,16 +1073,23 @@ fn ActionItem(
                     span { class: KEY, "Adjust" }
                     span { class: VALUE, "{allow_adjusting}" }
                 }
+                ActionArrivalToleranceItem { arrival_tolerance }
             }
             div { class: DIV,
                 span { class: KEY, "Key" }
-                span { class: VALUE, {key.to_string()} }
+                span { class: VALUE, {modifier.chord(key)} }
             }
             if let Some(link_key) = link_key {
                 div { class: DIV,
                     span { class: KEY, "Link Key" }
                     span { class: VALUE, {link_key.to_string()} }
                 }
+                if link_key_modifier != KeyModifier::None {
+                    div { class: DIV,
+                        span { class: KEY, "Link Key Modifier" }
+                        span { class: VALUE, {link_key_modifier.to_string()} }
+                    }
+                }
             }
             div { class: DIV,
                 span { class: KEY, "Count" }
@@ -649,12 +1126,61 @@ fn ActionItem(
                 span { class: KEY, "Wait after random" }
                 span { id: wait_after_use_millis_random_range_id(), class: VALUE }
             }
+            if hold_millis > 0 {
+                div { class: DIV,
+                    span { class: KEY, "Hold" }
+                    span { class: VALUE, "{hold_millis} ms" }
+                }
+            }
+            if heavy {
+                div { class: DIV,
+                    span { class: KEY, "Heavy skill" }
+                    span { class: VALUE, "Yes" }
+                }
+            }
             if let Some(queue_to_front) = queue_to_front {
                 div { class: DIV,
                     span { class: KEY, "Queue to front" }
                     span { class: VALUE, {queue_to_front.to_string()} }
                 }
             }
+            ActionYBandItem { y_band }
+        }
+    }
+
+    #[component]
+    fn ActionArrivalToleranceItem(arrival_tolerance: Option<u32>) -> Element {
+        rsx! {
+            if let Some(arrival_tolerance) = arrival_tolerance {
+                div { class: DIV,
+                    span { class: KEY, "Arrival tolerance" }
+                    span { class: VALUE, "{arrival_tolerance}" }
+                }
+            }
+        }
+    }
+
+    #[component]
+    fn ActionPositionAnchorItem(position_anchor: Option<usize>) -> Element {
+        rsx! {
+            if let Some(position_anchor) = position_anchor {
+                div { class: DIV,
+                    span { class: KEY, "Same position as" }
+                    span { class: VALUE, "#{position_anchor + 1}" }
+                }
+            }
+        }
+    }
+
+    #[component]
+    fn ActionYBandItem(y_band: Option<YBand>) -> Element {
+        rsx! {
+            if let Some(YBand { y_min, y_max }) = y_band {
+                div { class: DIV,
+                    span { class: KEY, "Y band" }
+                    span { class: VALUE, "{y_min} - {y_max}" }
+                }
+            }
         }
     }
 
@@ -802,6 +1328,7 @@ fn PositionInput(
         x_random_range,
         y,
         allow_adjusting,
+        arrival_tolerance,
     } = value;
 
     rsx! {
@@ -856,6 +1383,34 @@ fn PositionInput(
             },
             value: allow_adjusting,
         }
+        ActionCheckbox {
+            label: "Custom arrival tolerance",
+            disabled,
+            on_input: move |checked: bool| {
+                on_input(Position {
+                    arrival_tolerance: checked.then_some(0),
+                    ..value
+                });
+            },
+            value: arrival_tolerance.is_some(),
+        }
+        if let Some(arrival_tolerance) = arrival_tolerance {
+            NumberInputU32 {
+                label: "Arrival tolerance",
+                div_class: DIV_CLASS,
+                label_class: LABEL_CLASS,
+                input_class: "{INPUT_CLASS} p-1",
+                disabled,
+                minimum_value: 0,
+                on_input: move |arrival_tolerance| {
+                    on_input(Position {
+                        arrival_tolerance: Some(arrival_tolerance),
+                        ..value
+                    });
+                },
+                value: arrival_tolerance,
+            }
+        }
     }
 }
 
@@ -874,6 +1429,8 @@ fn ActionMoveInput(
         position,
         condition,
         wait_after_move_millis,
+        y_band,
+        position_anchor,
     } = value;
 
     rsx! {
@@ -886,6 +1443,18 @@ fn ActionMoveInput(
                 disabled,
                 value: position,
             }
+            PositionAnchorInput {
+                on_input: move |position_anchor| {
+                    on_input(
+                        Action::Move(ActionMove {
+                            position_anchor,
+                            ..value
+                        }),
+                    );
+                },
+                disabled,
+                value: position_anchor,
+            }
             ActionConditionInput {
                 on_input: move |condition| {
                     on_input(Action::Move(ActionMove { condition, ..value }));
@@ -907,6 +1476,13 @@ fn ActionMoveInput(
                 },
                 value: wait_after_move_millis,
             }
+            YBandInput {
+                on_input: move |y_band| {
+                    on_input(Action::Move(ActionMove { y_band, ..value }));
+                },
+                disabled,
+                value: y_band,
+            }
         }
     }
 }
@@ -924,7 +1500,9 @@ fn ActionKeyInput(
     };
     let ActionKey {
         key,
+        modifier,
         link_key,
+        link_key_modifier,
         count,
         position,
         condition,
@@ -935,6 +1513,10 @@ fn ActionKeyInput(
         wait_after_use_millis,
         wait_after_use_millis_random_range,
         queue_to_front,
+        y_band,
+        hold_millis,
+        heavy,
+        position_anchor,
     } = value;
 
     use_effect(use_reactive!(|condition| {
@@ -974,6 +1556,18 @@ fn ActionKeyInput(
                     disabled,
                     value: position,
                 }
+                PositionAnchorInput {
+                    on_input: move |position_anchor| {
+                        on_input(
+                            Action::Key(ActionKey {
+                                position_anchor,
+                                ..value
+                            }),
+                        );
+                    },
+                    disabled,
+                    value: position_anchor,
+                }
             }
             KeyBindingInput {
                 label: "Key",
@@ -986,6 +1580,14 @@ fn ActionKeyInput(
                 },
                 value: key,
             }
+            ActionEnumSelect::<KeyModifier> {
+                label: "Key modifier",
+                on_input: move |modifier| {
+                    on_input(Action::Key(ActionKey { modifier, ..value }));
+                },
+                disabled,
+                value: modifier,
+            }
             NumberInputU32 {
                 label: "Count",
                 label_class: LABEL_CLASS,
@@ -1041,6 +1643,19 @@ fn ActionKeyInput(
                     },
                     value: link_key.key(),
                 }
+                ActionEnumSelect::<KeyModifier> {
+                    label: "Link key modifier",
+                    on_input: move |link_key_modifier| {
+                        on_input(
+                            Action::Key(ActionKey {
+                                link_key_modifier,
+                                ..value
+                            }),
+                        );
+                    },
+                    disabled,
+                    value: link_key_modifier,
+                }
             }
             ActionConditionInput {
                 on_input: move |condition| {
@@ -1133,6 +1748,104 @@ fn ActionKeyInput(
                 disabled,
                 value: wait_after_use_millis_random_range,
             }
+            ActionMillisInput {
+                label: "Hold key for (charge skills)",
+                on_input: move |hold_millis| {
+                    on_input(Action::Key(ActionKey { hold_millis, ..value }));
+                },
+                disabled,
+                value: hold_millis,
+            }
+            ActionCheckbox {
+                label: "Heavy skill (wait for class cast lock)",
+                disabled,
+                on_input: move |heavy| {
+                    on_input(Action::Key(ActionKey { heavy, ..value }));
+                },
+                value: heavy,
+            }
+            YBandInput {
+                on_input: move |y_band| {
+                    on_input(Action::Key(ActionKey { y_band, ..value }));
+                },
+                disabled,
+                value: y_band,
+            }
+        }
+    }
+}
+
+/// Inputs the 1-based number of the action to anchor the position to
+#[component]
+fn PositionAnchorInput(
+    on_input: EventHandler<Option<usize>>,
+    disabled: bool,
+    value: Option<usize>,
+) -> Element {
+    rsx! {
+        ActionCheckbox {
+            label: "Same position as action",
+            disabled,
+            on_input: move |checked: bool| {
+                on_input(checked.then_some(0));
+            },
+            value: value.is_some(),
+        }
+        if let Some(anchor) = value {
+            NumberInputU32 {
+                label: "Action number",
+                div_class: DIV_CLASS,
+                label_class: LABEL_CLASS,
+                input_class: "{INPUT_CLASS} p-1",
+                minimum_value: 1,
+                disabled,
+                on_input: move |number: u32| {
+                    on_input(Some(number.saturating_sub(1) as usize));
+                },
+                value: anchor as u32 + 1,
+            }
+        }
+    }
+}
+
+#[component]
+fn YBandInput(
+    on_input: EventHandler<Option<YBand>>,
+    disabled: bool,
+    value: Option<YBand>,
+) -> Element {
+    rsx! {
+        ActionCheckbox {
+            label: "Restrict to Y band",
+            disabled,
+            on_input: move |checked: bool| {
+                on_input(checked.then_some(YBand::default()));
+            },
+            value: value.is_some(),
+        }
+        if let Some(y_band) = value {
+            NumberInputI32 {
+                label: "Y min",
+                div_class: DIV_CLASS,
+                label_class: LABEL_CLASS,
+                input_class: "{INPUT_CLASS} p-1",
+                disabled,
+                on_input: move |y_min| {
+                    on_input(Some(YBand { y_min, ..y_band }));
+                },
+                value: y_band.y_min,
+            }
+            NumberInputI32 {
+                label: "Y max",
+                div_class: DIV_CLASS,
+                label_class: LABEL_CLASS,
+                input_class: "{INPUT_CLASS} p-1",
+                disabled,
+                on_input: move |y_max| {
+                    on_input(Some(YBand { y_max, ..y_band }));
+                },
+                value: y_band.y_max,
+            }
         }
     }
 }
@@ -1162,6 +1875,112 @@ fn ActionConditionInput(
                 value: millis,
             }
         }
+        if let ActionCondition::MpAbovePercent(percent) = value {
+            ActionPercentageInput {
+                label: "Above MP Percentage",
+                disabled,
+                on_input: move |percent| {
+                    on_input(ActionCondition::MpAbovePercent(percent));
+                },
+                value: percent,
+            }
+        }
+        match value {
+            ActionCondition::AnyOf(first, second) => rsx! {
+                ActionConditionOperandInput {
+                    label: "First Condition",
+                    disabled,
+                    on_input: move |first| {
+                        on_input(ActionCondition::AnyOf(first, second));
+                    },
+                    value: first,
+                }
+                ActionConditionOperandInput {
+                    label: "Second Condition",
+                    disabled,
+                    on_input: move |second| {
+                        on_input(ActionCondition::AnyOf(first, second));
+                    },
+                    value: second,
+                }
+            },
+            ActionCondition::AllOf(first, second) => rsx! {
+                ActionConditionOperandInput {
+                    label: "First Condition",
+                    disabled,
+                    on_input: move |first| {
+                        on_input(ActionCondition::AllOf(first, second));
+                    },
+                    value: first,
+                }
+                ActionConditionOperandInput {
+                    label: "Second Condition",
+                    disabled,
+                    on_input: move |second| {
+                        on_input(ActionCondition::AllOf(first, second));
+                    },
+                    value: second,
+                }
+            },
+            _ => rsx! {},
+        }
+    }
+}
+
+#[component]
+fn ActionConditionOperandInput(
+    label: String,
+    on_input: EventHandler<ActionConditionOperand>,
+    disabled: bool,
+    value: ActionConditionOperand,
+) -> Element {
+    rsx! {
+        ActionEnumSelect {
+            label,
+            on_input,
+            disabled,
+            value,
+        }
+        if let ActionConditionOperand::EveryMillis(millis) = value {
+            ActionMillisInput {
+                label: "Milliseconds",
+                disabled,
+                on_input: move |millis| {
+                    on_input(ActionConditionOperand::EveryMillis(millis));
+                },
+                value: millis,
+            }
+        }
+        if let ActionConditionOperand::MpAbovePercent(percent) = value {
+            ActionPercentageInput {
+                label: "Above MP Percentage",
+                disabled,
+                on_input: move |percent| {
+                    on_input(ActionConditionOperand::MpAbovePercent(percent));
+                },
+                value: percent,
+            }
+        }
+        if let ActionConditionOperand::BuffActive(kind) = value {
+            ActionEnumSelect {
+                label: "Buff",
+                disabled,
+                on_input: move |kind| {
+                    on_input(ActionConditionOperand::BuffActive(kind));
+                },
+                value: kind,
+            }
+        }
+        if let ActionConditionOperand::BuffMissing(kind) = value {
+            ActionEnumSelect {
+                label: "Buff",
+                disabled,
+                on_input: move |kind| {
+                    on_input(ActionConditionOperand::BuffMissing(kind));
+                },
+                value: kind,
+            }
+        }
     }
 }
 
@@ -1205,6 +2024,26 @@ fn ActionMillisInput(
     }
 }
 
+#[component]
+fn ActionPercentageInput(
+    label: String,
+    disabled: bool,
+    on_input: EventHandler<f32>,
+    value: f32,
+) -> Element {
+    rsx! {
+        PercentageInput {
+            label,
+            label_class: LABEL_CLASS,
+            div_class: DIV_CLASS,
+            input_class: "{INPUT_CLASS} p-1",
+            disabled,
+            on_input,
+            value,
+        }
+    }
+}
+
 #[component]
 fn ActionEnumSelect<
     T: 'static + Clone + Copy + PartialEq + Display + FromStr<Err = ParseError> + IntoEnumIterator,
@@ -1,8 +1,8 @@
 use std::{fmt::Display, str::FromStr};
 
 use backend::{
-    ActionConfiguration, Class, Configuration as ConfigurationData, IntoEnumIterator,
-    KeyBindingConfiguration, PotionMode,
+    ActionConfiguration, Class, Configuration as ConfigurationData, DOUBLE_JUMP_OFFSET_RANGE,
+    IntoEnumIterator, KeyBindingConfiguration, KeyRepeatInterval, PotionMode,
 };
 use dioxus::prelude::*;
 use rand::distr::{Alphanumeric, SampleString};
@@ -10,7 +10,9 @@ use rand::distr::{Alphanumeric, SampleString};
 use crate::{
     AppMessage,
     icons::{CheckMarkIcon, XIcon},
-    input::{Checkbox, KeyBindingInput, MillisInput, PercentageInput, use_auto_numeric},
+    input::{
+        Checkbox, KeyBindingInput, MillisInput, NumberInputI32, PercentageInput, use_auto_numeric,
+    },
     key::KeyBindingConfigurationInput,
     select::{EnumSelect, TextSelect},
     tab::Tab,
@@ -302,7 +304,80 @@ fn ConfigGameKeyBindings(
                             },
                             value: config_view().health_update_millis,
                         }
+                        ConfigMillisInput {
+                            label: "Delay Key After Damage Milliseconds",
+                            disabled: is_disabled(),
+                            on_input: move |value| {
+                                on_config(ConfigurationData {
+                                    damage_grace_millis: value,
+                                    ..config_view.peek().clone()
+                                });
+                            },
+                            value: config_view().damage_grace_millis,
+                        }
+                    },
+                }
+            }
+            div { class: "space-y-2",
+                p { class: "font-normal italic text-xs text-gray-400 mb-1",
+                    "Mana detection is required for MP conditions of actions"
+                }
+                Checkbox {
+                    label: "Detect Mana",
+                    label_class: LABEL_CLASS,
+                    div_class: DIV_CLASS,
+                    input_class: "w-44",
+                    disabled: is_disabled(),
+                    on_input: move |mana_detection| {
+                        on_config(ConfigurationData {
+                            mana_detection,
+                            ..config_view.peek().clone()
+                        });
+                    },
+                    value: config_view().mana_detection,
+                }
+            }
+            div { class: "space-y-2",
+                p { class: "font-normal italic text-xs text-gray-400 mb-1",
+                    "Double jump offsets stop earlier (positive) or later (negative) per direction"
+                }
+                ConfigDoubleJumpOffsetInput {
+                    label: "Double Jump Left Offset",
+                    disabled: is_disabled(),
+                    on_input: move |value| {
+                        on_config(ConfigurationData {
+                            double_jump_left_offset: value,
+                            ..config_view.peek().clone()
+                        });
+                    },
+                    value: config_view().double_jump_left_offset,
+                }
+                ConfigDoubleJumpOffsetInput {
+                    label: "Double Jump Right Offset",
+                    disabled: is_disabled(),
+                    on_input: move |value| {
+                        on_config(ConfigurationData {
+                            double_jump_right_offset: value,
+                            ..config_view.peek().clone()
+                        });
+                    },
+                    value: config_view().double_jump_right_offset,
+                }
+            }
+            div { class: "space-y-2",
+                p { class: "font-normal italic text-xs text-gray-400 mb-1",
+                    "Erda Shower cooldown estimates its state when the skill icon is hidden, 0 to disable"
+                }
+                ConfigMillisInput {
+                    label: "Erda Shower Cooldown Milliseconds",
+                    disabled: is_disabled(),
+                    on_input: move |value| {
+                        on_config(ConfigurationData {
+                            erda_shower_cooldown_millis: value,
+                            ..config_view.peek().clone()
+                        });
                     },
+                    value: config_view().erda_shower_cooldown_millis,
                 }
             }
             div { class: "space-y-2",
@@ -321,6 +396,60 @@ fn ConfigGameKeyBindings(
                     selected: config_view().class,
                 }
             }
+            div { class: "space-y-2",
+                p { class: "font-normal italic text-xs text-gray-400 mb-1",
+                    "Key repeat intervals reject a key pressed again too soon (e.g. jump at most once per 120ms)"
+                }
+                for (i , interval) in config_view().key_repeat_intervals.into_iter().enumerate() {
+                    div { class: "flex items-center space-x-2",
+                        div { class: "flex-1 flex flex-col space-y-1",
+                            KeyBindingInput {
+                                label: "Key",
+                                label_class: LABEL_CLASS,
+                                div_class: DIV_CLASS,
+                                input_class: INPUT_CLASS,
+                                disabled: is_disabled(),
+                                on_input: move |key| {
+                                    let mut config = config_view.peek().clone();
+                                    config.key_repeat_intervals[i].key = key;
+                                    on_config(config);
+                                },
+                                value: interval.key,
+                            }
+                            ConfigMillisInput {
+                                label: "Minimum Repeat Milliseconds",
+                                disabled: is_disabled(),
+                                on_input: move |millis| {
+                                    let mut config = config_view.peek().clone();
+                                    config.key_repeat_intervals[i].millis = millis;
+                                    on_config(config);
+                                },
+                                value: interval.millis,
+                            }
+                        }
+                        button {
+                            class: "w-5 h-5 border border-red-500 p-1",
+                            disabled: is_disabled(),
+                            onclick: move |_| {
+                                let mut config = config_view.peek().clone();
+                                config.key_repeat_intervals.remove(i);
+                                on_config(config);
+                            },
+                            XIcon { class: "w-full h-full text-red-400 fill-current" }
+                        }
+                    }
+                }
+                button {
+                    class: "w-full button-primary h-6",
+                    disabled: is_disabled(),
+                    onclick: move |_| {
+                        let mut config = config_view.peek().clone();
+                        config.key_repeat_intervals.push(KeyRepeatInterval::default());
+                        on_config(config);
+                    },
+                    "Add key repeat interval"
+                }
+            }
         }
     }
 }
@@ -798,6 +927,28 @@ fn ConfigMillisInput(
     }
 }
 
+#[component]
+fn ConfigDoubleJumpOffsetInput(
+    label: String,
+    disabled: bool,
+    on_input: EventHandler<i32>,
+    value: i32,
+) -> Element {
+    rsx! {
+        NumberInputI32 {
+            label,
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: INPUT_CLASS,
+            minimum_value: *DOUBLE_JUMP_OFFSET_RANGE.start(),
+            maximum_value: Some(*DOUBLE_JUMP_OFFSET_RANGE.end()),
+            disabled,
+            on_input,
+            value,
+        }
+    }
+}
+
 #[component]
 fn ConfigHeader(
     text: String,
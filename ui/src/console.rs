@@ -0,0 +1,70 @@
+use backend::console_command;
+use dioxus::prelude::*;
+
+/// The maximum number of lines kept in the console output
+const MAX_CONSOLE_LINES: usize = 200;
+
+#[component]
+pub fn Console() -> Element {
+    let mut input = use_signal(String::default);
+    let mut lines = use_signal::<Vec<String>>(Vec::new);
+    let submit = move || async move {
+        let command = input.peek().trim().to_string();
+        if command.is_empty() {
+            return;
+        }
+        input.set(String::default());
+        let output = match console_command(command.clone()).await {
+            Ok(output) => output,
+            Err(err) => format!("error: {err}"),
+        };
+        let mut lines = lines.write();
+        lines.push(format!("> {command}"));
+        lines.extend(output.lines().map(|line| line.to_string()));
+        let excess = lines.len().saturating_sub(MAX_CONSOLE_LINES);
+        lines.drain(..excess);
+    };
+
+    rsx! {
+        div { class: "px-2 pb-2 pt-2 flex flex-col space-y-2 h-full",
+            div { class: "flex-1 flex flex-col px-1 overflow-y-auto scrollbar rounded bg-gray-100",
+                for (i , line) in lines().into_iter().enumerate() {
+                    p {
+                        key: "{i}",
+                        class: "text-xs text-gray-700 font-mono whitespace-pre-wrap",
+                        {line}
+                    }
+                }
+            }
+            div { class: "flex space-x-2 items-center",
+                input {
+                    class: "flex-1 h-7 text-gray-700 text-xs font-mono p-1 border rounded border-gray-300 outline-none",
+                    placeholder: "halt, start, goto <x> <y>, usekey <key>, dump state",
+                    oninput: move |e| {
+                        input.set(e.parsed::<String>().unwrap_or_default());
+                    },
+                    onkeydown: move |e: Event<KeyboardData>| async move {
+                        if e.key() == Key::Enter {
+                            submit().await;
+                        }
+                    },
+                    value: input(),
+                }
+                button {
+                    class: "button-primary w-18 h-7",
+                    onclick: move |_| async move {
+                        submit().await;
+                    },
+                    "Run"
+                }
+                button {
+                    class: "button-secondary w-18 h-7",
+                    onclick: move |_| {
+                        lines.write().clear();
+                    },
+                    "Clear"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use backend::{DetectionProfile, Minimap};
+use dioxus::prelude::*;
+
+use crate::input::{Checkbox, PercentageInput};
+
+const DIV_CLASS: &str = "flex h-6 items-center space-x-2";
+const LABEL_CLASS: &str = "flex-1 text-xs text-gray-700 inline-block data-[disabled]:text-gray-400";
+const INPUT_CLASS: &str = "w-22 h-full border border-gray-300 rounded text-xs text-ellipsis outline-none disabled:text-gray-400 disabled:cursor-not-allowed";
+const CHECKBOX_INPUT_CLASS: &str = "w-22 text-xs text-gray-700 text-ellipsis rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400";
+
+#[component]
+pub fn Detection(
+    minimap: ReadOnlySignal<Option<Minimap>>,
+    on_save: EventHandler<Minimap>,
+) -> Element {
+    let profile = use_memo(move || {
+        minimap()
+            .map(|minimap| minimap.detection_profile)
+            .unwrap_or_default()
+    });
+    let save_profile = move |profile: DetectionProfile| {
+        if let Some(mut minimap) = minimap.peek().clone() {
+            minimap.detection_profile = profile;
+            on_save(minimap);
+        }
+    };
+
+    rsx! {
+        div { class: "flex flex-col space-y-2",
+            ul { class: "list-disc text-xs text-gray-700 pl-4",
+                li { "Overrides the detection thresholds when this map is active" }
+                li { "Lower the threshold for maps with dark overlays that fail detection" }
+                li { "Too low threshold can cause false positives" }
+            }
+            div { class: "h-2 border-b border-gray-300 mb-2" }
+            ThresholdInput {
+                label: "Player Threshold",
+                disabled: minimap().is_none(),
+                default_value: 75.0,
+                on_input: move |player_threshold| {
+                    save_profile(DetectionProfile {
+                        player_threshold,
+                        ..profile()
+                    });
+                },
+                value: profile().player_threshold,
+            }
+            ThresholdInput {
+                label: "Rune Threshold",
+                disabled: minimap().is_none(),
+                default_value: 75.0,
+                on_input: move |rune_threshold| {
+                    save_profile(DetectionProfile {
+                        rune_threshold,
+                        ..profile()
+                    });
+                },
+                value: profile().rune_threshold,
+            }
+            ThresholdInput {
+                label: "Portal Threshold",
+                disabled: minimap().is_none(),
+                default_value: 80.0,
+                on_input: move |portal_threshold| {
+                    save_profile(DetectionProfile {
+                        portal_threshold,
+                        ..profile()
+                    });
+                },
+                value: profile().portal_threshold,
+            }
+        }
+    }
+}
+
+#[component]
+fn ThresholdInput(
+    label: String,
+    disabled: bool,
+    default_value: f32,
+    on_input: EventHandler<Option<f32>>,
+    value: Option<f32>,
+) -> Element {
+    rsx! {
+        Checkbox {
+            label: format!("Override {label}"),
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: CHECKBOX_INPUT_CLASS,
+            disabled,
+            on_input: move |checked: bool| {
+                on_input(checked.then_some(default_value));
+            },
+            value: value.is_some(),
+        }
+        if let Some(value) = value {
+            PercentageInput {
+                label,
+                div_class: DIV_CLASS,
+                label_class: LABEL_CLASS,
+                input_class: INPUT_CLASS,
+                disabled,
+                on_input: move |value| {
+                    on_input(Some(value));
+                },
+                value,
+            }
+        }
+    }
+}
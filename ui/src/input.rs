@@ -256,15 +256,15 @@ pub fn NumberInputU32(
 
 #[component]
 pub fn NumberInputI32(
-    GenericInputProps {
-        label,
-        label_class,
-        div_class,
-        input_class,
-        disabled,
-        on_input,
-        value,
-    }: GenericInputProps<i32>,
+    label: String,
+    #[props(default = String::default())] label_class: String,
+    #[props(default = String::default())] div_class: String,
+    #[props(default = String::default())] input_class: String,
+    #[props(default = false)] disabled: bool,
+    #[props(default = 0)] minimum_value: i32,
+    #[props(default = None)] maximum_value: Option<i32>,
+    on_input: EventHandler<i32>,
+    value: i32,
 ) -> Element {
     rsx! {
         PrimIntInput {
@@ -272,7 +272,8 @@ pub fn NumberInputI32(
             label_class,
             div_class,
             input_class,
-            minimum_value: 0,
+            minimum_value,
+            maximum_value,
             disabled,
             on_input,
             value,
@@ -6,11 +6,13 @@ use std::{string::ToString, sync::Arc};
 
 use action::Actions;
 use backend::{
-    Configuration as ConfigurationData, Minimap as MinimapData, Settings as SettingsData,
-    query_configs, query_settings, update_configuration, update_settings, upsert_config,
-    upsert_settings,
+    CaptureHandleIdentity, Configuration as ConfigurationData, Language, Minimap as MinimapData,
+    Settings as SettingsData, add_client, client_ids, query_client_state, query_configs,
+    query_settings, remove_client, select_client, selected_client, update_configuration,
+    update_minimap, update_settings, upsert_config, upsert_settings,
 };
 use configuration::Configuration;
+use console::Console;
 use dioxus::{
     desktop::{
         WindowBuilder,
@@ -36,10 +38,13 @@ use tracing_log::LogTracer;
 
 mod action;
 mod configuration;
+mod console;
+mod detection;
 mod icons;
 mod input;
 mod key;
 mod minimap;
+mod navigation;
 mod notification;
 mod platform;
 mod rotation;
@@ -76,6 +81,12 @@ pub enum AppMessage {
     UpdateMinimap(MinimapData),
     UpdatePreset(String),
     UpdateSettings(SettingsData),
+    /// Adds a client botting the game window and selects it
+    AddClient(CaptureHandleIdentity),
+    /// Removes the last added client
+    RemoveClient,
+    /// Selects the client the UI controls
+    SelectClient(usize),
 }
 
 #[component]
@@ -84,12 +95,13 @@ fn App() -> Element {
     const TAB_ACTIONS: &str = "Actions";
     const TAB_SETTINGS: &str = "Settings";
     const TAB_SETTINGS_NOTIFICATIONS: &str = "Notifications";
+    const TAB_CONSOLE: &str = "Console";
 
     // TODO: Move to AppMessage?
     let (minimap_tx, minimap_rx) = mpsc::channel::<MinimapMessage>(1);
     let minimap_rx = use_signal(move || Arc::new(Mutex::new(minimap_rx)));
-    let minimap = use_signal::<Option<MinimapData>>(|| None);
-    let preset = use_signal::<Option<String>>(|| None);
+    let mut minimap = use_signal::<Option<MinimapData>>(|| None);
+    let mut preset = use_signal::<Option<String>>(|| None);
     let mut config = use_signal::<Option<ConfigurationData>>(|| None);
     let mut configs = use_resource(move || async move {
         let configs = spawn_blocking(|| query_configs().unwrap()).await.unwrap();
@@ -100,7 +112,20 @@ fn App() -> Element {
         configs
     });
     let mut settings = use_resource(|| async { spawn_blocking(query_settings).await.unwrap() });
+    let language = use_memo(move || {
+        settings()
+            .map(|settings| settings.language)
+            .unwrap_or_default()
+    });
+    let enable_console = use_memo(move || {
+        settings()
+            .map(|settings| settings.enable_console)
+            .unwrap_or_default()
+    });
     let copy_position = use_signal::<Option<(i32, i32)>>(|| None);
+    let mut clients = use_signal(client_ids);
+    let mut client = use_signal(selected_client);
+    use_context_provider::<Memo<Language>>(|| language);
     let coroutine = use_coroutine(move |mut rx: UnboundedReceiver<AppMessage>| {
         let minimap_tx = minimap_tx.clone();
         async move {
@@ -143,11 +168,97 @@ fn App() -> Element {
                         .unwrap();
                         settings.restart();
                     }
+                    AppMessage::AddClient(window) => {
+                        let Some(id) = add_client(&window) else {
+                            continue;
+                        };
+                        select_client(id);
+                        clients.set(client_ids());
+                        client.set(id);
+                        // The new client starts without any map so the current one is sent
+                        if let Some(config) = config.peek().clone() {
+                            update_configuration(config).await;
+                        }
+                        if let Some(minimap) = minimap.peek().clone() {
+                            update_minimap(preset.peek().clone(), minimap).await;
+                        }
+                        let mut new_settings = settings.peek().clone().unwrap_or_default();
+                        new_settings.additional_clients.push(window);
+                        update_settings(new_settings.clone()).await;
+                        spawn_blocking(move || {
+                            upsert_settings(&mut new_settings).unwrap();
+                        })
+                        .await
+                        .unwrap();
+                        settings.restart();
+                    }
+                    AppMessage::RemoveClient => {
+                        if !remove_client() {
+                            continue;
+                        }
+                        clients.set(client_ids());
+                        client.set(selected_client());
+                        let mut new_settings = settings.peek().clone().unwrap_or_default();
+                        new_settings
+                            .additional_clients
+                            .truncate(clients.peek().len() - 1);
+                        spawn_blocking(move || {
+                            upsert_settings(&mut new_settings).unwrap();
+                        })
+                        .await
+                        .unwrap();
+                        settings.restart();
+                    }
+                    AppMessage::SelectClient(id) => {
+                        select_client(id);
+                        client.set(selected_client());
+                        // Restored clients start without any configuration or map so the current
+                        // ones are sent
+                        let (client_config, client_minimap) = query_client_state().await;
+                        match client_config {
+                            Some(client_config) => config.set(Some(client_config)),
+                            None => {
+                                if let Some(config) = config.peek().clone() {
+                                    update_configuration(config).await;
+                                }
+                            }
+                        }
+                        match client_minimap {
+                            Some((client_preset, client_minimap)) => {
+                                preset.set(client_preset);
+                                minimap.set(Some(client_minimap));
+                            }
+                            None => {
+                                if let Some(minimap) = minimap.peek().clone() {
+                                    update_minimap(preset.peek().clone(), minimap).await;
+                                }
+                            }
+                        }
+                    }
                 }
             }
         }
     });
     let mut active_tab = use_signal(|| TAB_CONFIGURATION.to_string());
+    let tabs = use_memo(move || {
+        let mut tabs = vec![
+            TAB_CONFIGURATION.to_string(),
+            TAB_ACTIONS.to_string(),
+            TAB_SETTINGS.to_string(),
+            TAB_SETTINGS_NOTIFICATIONS.to_string(),
+        ];
+        if enable_console() {
+            tabs.push(TAB_CONSOLE.to_string());
+        }
+        tabs
+    });
+
+    // Switches away when the console is disabled while selected
+    use_effect(move || {
+        if !enable_console() && *active_tab.peek() == TAB_CONSOLE {
+            active_tab.set(TAB_SETTINGS.to_string());
+        }
+    });
     let mut script_loaded = use_signal(|| false);
 
     // Thanks dioxus
@@ -179,12 +290,7 @@ fn App() -> Element {
                     copy_position,
                 }
                 Tab {
-                    tabs: vec![
-                        TAB_CONFIGURATION.to_string(),
-                        TAB_ACTIONS.to_string(),
-                        TAB_SETTINGS.to_string(),
-                        TAB_SETTINGS_NOTIFICATIONS.to_string(),
-                    ],
+                    tabs: tabs(),
                     class: "py-2 px-3 font-medium text-sm focus:outline-none",
                     selected_class: "bg-white text-gray-800",
                     unselected_class: "hover:text-gray-700 text-gray-400 bg-gray-100",
@@ -207,11 +313,19 @@ fn App() -> Element {
                         }
                     },
                     TAB_SETTINGS => rsx! {
-                        Settings { app_coroutine: coroutine, settings }
+                        Settings {
+                            app_coroutine: coroutine,
+                            settings,
+                            clients,
+                            client,
+                        }
                     },
                     TAB_SETTINGS_NOTIFICATIONS => rsx! {
                         Notifications { app_coroutine: coroutine, settings }
                     },
+                    TAB_CONSOLE => rsx! {
+                        Console {}
+                    },
                     _ => unreachable!(),
                 }
             }
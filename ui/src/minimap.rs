@@ -1,20 +1,30 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use backend::{
-    Action, ActionKey, ActionMove, GameState, Minimap as MinimapData, RotationMode, create_minimap,
-    delete_map, minimap_frame, minimap_platforms_bound, player_state, query_maps, redetect_minimap,
-    rotate_actions, rotate_actions_halting, update_minimap, upsert_map,
+    Action, ActionKey, ActionMove, GameState, GameStateDelta, KeyBinding, ManualControl,
+    Minimap as MinimapData, RotationMode, cancel_scheduled_start, create_minimap, delete_map,
+    export_map, game_state_receiver, import_map, manual_control, map_import_receiver,
+    minimap_frame, minimap_platforms_bound, player_state, query_maps, redetect_minimap,
+    rotate_actions, rotate_actions_halting, screenshot, selected_client, submit_rune_keys,
+    toggle_auto_mob, update_minimap, upsert_map,
 };
 use dioxus::{document::EvalError, prelude::*};
 use futures_util::StreamExt;
 use serde::Serialize;
 use tokio::{
-    sync::{Mutex, mpsc::Receiver},
+    sync::{Mutex, broadcast::error::RecvError, mpsc::Receiver},
     task::spawn_blocking,
+    time::{sleep, timeout},
 };
 
 use crate::select::TextSelect;
 
+/// The duration to wait for a streamed state before checking if another client is selected
+const GAME_STATE_STREAM_TIMEOUT: Duration = Duration::from_secs(1);
+
 const MINIMAP_JS: &str = r#"
     const canvas = document.getElementById("canvas-minimap");
     const canvasCtx = canvas.getContext("2d");
@@ -53,10 +63,31 @@ const MINIMAP_JS: &str = r#"
         }
     }
 "#;
+const SCREENSHOT_JS: &str = r#"
+    const bytes = await dioxus.recv();
+    const blob = new Blob([new Uint8Array(bytes)], { type: "image/png" });
+    const url = URL.createObjectURL(blob);
+    const link = document.createElement("a");
+    link.href = url;
+    link.download = `screenshot-${Date.now()}.png`;
+    link.click();
+    URL.revokeObjectURL(url);
+"#;
+const EXPORT_MAP_JS: &str = r#"
+    const [name, json] = await dioxus.recv();
+    const blob = new Blob([json], { type: "application/json" });
+    const url = URL.createObjectURL(blob);
+    const link = document.createElement("a");
+    link.href = url;
+    link.download = `${name}.json`;
+    link.click();
+    URL.revokeObjectURL(url);
+"#;
 const MINIMAP_ACTIONS_JS: &str = r#"
     const canvas = document.getElementById("canvas-minimap-actions");
     const canvasCtx = canvas.getContext("2d");
-    const [width, height, actions, autoMobEnabled, autoMobBound, platforms] = await dioxus.recv();
+    const [width, height, actions, autoMobEnabled, autoMobBound, autoMobPolygon, platforms] =
+        await dioxus.recv();
     canvasCtx.clearRect(0, 0, canvas.width, canvas.height);
     const anyActions = actions.filter((action) => action.condition === "Any");
     const erdaActions = actions.filter((action) => action.condition === "ErdaShowerOffCooldown");
@@ -65,7 +96,16 @@ const MINIMAP_ACTIONS_JS: &str = r#"
     canvasCtx.fillStyle = "rgb(255, 153, 128)";
     canvasCtx.strokeStyle = "rgb(255, 153, 128)";
     drawActions(canvas, canvasCtx, anyActions, true);
-    if (autoMobEnabled) {
+    if (autoMobEnabled && autoMobPolygon.length >= 3) {
+        canvasCtx.beginPath();
+        for (const vertex of autoMobPolygon) {
+            const x = (vertex.x / width) * canvas.width;
+            const y = (vertex.y / height) * canvas.height;
+            canvasCtx.lineTo(x, y);
+        }
+        canvasCtx.closePath();
+        canvasCtx.stroke();
+    } else if (autoMobEnabled) {
         const x = (autoMobBound.x / width) * canvas.width;
         const y = (autoMobBound.y / height) * canvas.height;
         const w = (autoMobBound.width / width) * canvas.width;
@@ -134,11 +174,15 @@ struct ActionView {
 #[derive(Clone, Debug)]
 pub enum MinimapMessage {
     ToggleHalting,
+    ToggleAutoMob,
     RedetectMinimap,
     CreateMinimap(String),
     UpdateMinimap(MinimapData, bool),
     UpdateMinimapPreset(String),
     DeleteMinimap,
+    ImportMinimap(String),
+    ManualControl(ManualControl),
+    SubmitRuneKeys([KeyBinding; 4]),
 }
 
 #[component]
@@ -150,8 +194,11 @@ pub fn Minimap(
 ) -> Element {
     let mut halting = use_signal(|| true);
     let mut state = use_signal::<Option<GameState>>(|| None);
+    let mut rune_keys = use_signal(Vec::<KeyBinding>::new);
     let mut detected_minimap_size = use_signal::<Option<(usize, usize)>>(|| None);
+    let mut detected_map_id = use_signal::<Option<i64>>(|| None);
     let mut platforms_bound = use_signal(|| None);
+    let mut import_error = use_signal::<Option<String>>(|| None);
     let mut minimaps = use_resource(move || async move {
         let minimaps = spawn_blocking(|| query_maps().unwrap_or_default())
             .await
@@ -179,9 +226,18 @@ pub fn Minimap(
                     MinimapMessage::ToggleHalting => {
                         rotate_actions(!halting()).await;
                     }
+                    MinimapMessage::ToggleAutoMob => {
+                        toggle_auto_mob().await;
+                    }
                     MinimapMessage::RedetectMinimap => {
                         redetect_minimap().await;
                     }
+                    MinimapMessage::ManualControl(control) => {
+                        manual_control(control).await;
+                    }
+                    MinimapMessage::SubmitRuneKeys(keys) => {
+                        submit_rune_keys(keys).await;
+                    }
                     MinimapMessage::CreateMinimap(name) => {
                         if let Some(mut data) = create_minimap(name).await {
                             upsert_map(&mut data).unwrap();
@@ -191,18 +247,23 @@ pub fn Minimap(
                             preset.set(None);
                         }
                     }
-                    MinimapMessage::UpdateMinimap(mut data, save) => {
+                    MinimapMessage::UpdateMinimap(data, save) => {
                         if preset().is_none_or(|preset| !data.actions.contains_key(&preset)) {
                             preset.set(data.actions.keys().next().cloned());
                         }
                         minimap.set(Some(data.clone()));
-                        update_minimap(preset(), data.clone()).await;
+                        // Saves before updating so that the identity recorded by the backend
+                        // for maps without one is not overwritten
                         if save {
+                            let mut data = data.clone();
                             spawn_blocking(move || {
                                 upsert_map(&mut data).unwrap();
                             })
                             .await
                             .unwrap();
+                        }
+                        update_minimap(preset(), data).await;
+                        if save {
                             minimaps.restart();
                         }
                     }
@@ -223,6 +284,18 @@ pub fn Minimap(
                             minimaps.restart();
                         }
                     }
+                    MinimapMessage::ImportMinimap(json) => {
+                        match spawn_blocking(move || import_map(&json)).await.unwrap() {
+                            Ok(data) => {
+                                import_error.set(None);
+                                preset.set(data.actions.keys().next().cloned());
+                                minimap.set(Some(data.clone()));
+                                minimaps.restart();
+                                update_minimap(preset(), data).await;
+                            }
+                            Err(err) => import_error.set(Some(err.to_string())),
+                        }
+                    }
                 }
             }
         },
@@ -262,6 +335,11 @@ pub fn Minimap(
             .collect::<Vec<ActionView>>();
         let platforms_bound = platforms_bound();
         if let Some(minimap) = minimap {
+            let polygon = if minimap.auto_mob_platforms_bound {
+                vec![]
+            } else {
+                minimap.auto_mob_bound_polygon
+            };
             let bound = if let RotationMode::AutoMobbing(mobbing) = minimap.rotation_mode {
                 if minimap.auto_mob_platforms_bound {
                     platforms_bound.or(Some(mobbing.bound))
@@ -279,6 +357,7 @@ pub fn Minimap(
                         actions,
                         bound.is_some(),
                         bound.unwrap_or_default(),
+                        polygon,
                         minimap.platforms,
                     ))
                     .unwrap();
@@ -292,13 +371,81 @@ pub fn Minimap(
             }
         }
     });
+    // reload the current map replaced by an import from the sync folder, the clients holding it
+    // reload it by themselves
+    use_future(move || async move {
+        let mut receiver = map_import_receiver();
+        loop {
+            let result = receiver.recv().await;
+            let Some(id) = minimap.peek().as_ref().and_then(|minimap| minimap.id) else {
+                continue;
+            };
+            match result {
+                Ok(imported_id) if imported_id == id => (),
+                // Reloads anyway since the skipped ids may include the current map
+                Err(RecvError::Lagged(_)) => (),
+                Ok(_) => continue,
+                Err(RecvError::Closed) => return,
+            }
+            let Some(data) = spawn_blocking(move || {
+                query_maps()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|map| map.id == Some(id))
+            })
+            .await
+            .unwrap() else {
+                continue;
+            };
+            if preset().is_none_or(|preset| !data.actions.contains_key(&preset)) {
+                preset.set(data.actions.keys().next().cloned());
+            }
+            minimap.set(Some(data));
+            minimaps.restart();
+        }
+    });
+    // apply states changed at tick rate between polls
+    use_future(move || async move {
+        let mut client = selected_client();
+        let mut receiver = game_state_receiver().await;
+        loop {
+            if client != selected_client() {
+                client = selected_client();
+                receiver = game_state_receiver().await;
+            }
+            let Ok(result) = timeout(GAME_STATE_STREAM_TIMEOUT, receiver.recv()).await else {
+                continue;
+            };
+            let delta = match result {
+                Ok(delta) => delta,
+                // The skipped deltas are covered by the next full state poll
+                Err(RecvError::Lagged(_)) => continue,
+                // The stream is recreated when the client restarts
+                Err(RecvError::Closed) => {
+                    sleep(GAME_STATE_STREAM_TIMEOUT).await;
+                    receiver = game_state_receiver().await;
+                    continue;
+                }
+            };
+            if let GameStateDelta::Position(position) = delta
+                && *copy_position.peek() != position
+            {
+                copy_position.set(position);
+            }
+            state.with_mut(|state| {
+                if let Some(state) = state {
+                    delta.apply(state);
+                }
+            });
+        }
+    });
     // draw minimap and update states
     use_future(move || async move {
         let mut canvas = document::eval(MINIMAP_JS);
         loop {
             let player_state = player_state().await;
             let destinations = player_state.destinations.clone();
-            let is_halting = rotate_actions_halting().await;
+            let (is_halting, _) = rotate_actions_halting().await;
             let bound = minimap_platforms_bound().await;
             if halting() != is_halting {
                 halting.set(is_halting);
@@ -309,6 +456,24 @@ pub fn Minimap(
             if copy_position() != player_state.position {
                 copy_position.set(player_state.position);
             }
+            // Reloads the saved map once the minimap is identified as it unless the current map
+            // also matches the detected minimap
+            if detected_map_id() != player_state.detected_map_id {
+                detected_map_id.set(player_state.detected_map_id);
+                if let Some(id) = player_state.detected_map_id
+                    && let Some((width, height, fingerprint)) = player_state.detected_map_identity
+                    && minimap.peek().as_ref().is_none_or(|minimap| {
+                        minimap.id != Some(id)
+                            && minimap
+                                .identity_distance(width, height, fingerprint)
+                                .is_none()
+                    })
+                    && let Some(data) = minimaps()
+                        .and_then(|minimaps| minimaps.into_iter().find(|map| map.id == Some(id)))
+                {
+                    coroutine.send(MinimapMessage::UpdateMinimap(data, false));
+                }
+            }
             state.set(Some(player_state));
             let minimap_frame = minimap_frame().await;
             let Ok((frame, width, height)) = minimap_frame else {
@@ -384,6 +549,16 @@ pub fn Minimap(
                                 .unwrap_or("Health: Unknown".to_string())
                         }
                     }
+                    p {
+                        {
+                            state()
+                                .and_then(|state| state.mana)
+                                .map(|(current_mana, max_mana)| {
+                                    format!("Mana: {current_mana} / {max_mana}")
+                                })
+                                .unwrap_or("Mana: Unknown".to_string())
+                        }
+                    }
                     p {
                         {
                             state()
@@ -415,6 +590,179 @@ pub fn Minimap(
                                 .unwrap_or("Erda Shower: Unknown".to_string())
                         }
                     }
+                    p {
+                        {
+                            state()
+                                .map(|state| {
+                                    format!(
+                                        "Rune: {}",
+                                        if state.has_rune { "Detected" } else { "None" },
+                                    )
+                                })
+                                .unwrap_or("Rune: Unknown".to_string())
+                        }
+                    }
+                    p {
+                        {
+                            state()
+                                .map(|state| {
+                                    format!(
+                                        "Failures: Rune {}, Unstuck {}",
+                                        state.rune_failed_count,
+                                        state.unstuck_count,
+                                    )
+                                })
+                                .unwrap_or("Failures: Unknown".to_string())
+                        }
+                    }
+                    p {
+                        {
+                            state()
+                                .map(|state| format!("Stranger episodes: {}", state.stranger_episodes))
+                                .unwrap_or("Stranger episodes: Unknown".to_string())
+                        }
+                    }
+                    p {
+                        {
+                            state()
+                                .map(|state| {
+                                    format!(
+                                        "Strangers: {} now, {} seen",
+                                        state.stranger_count,
+                                        state.distinct_strangers,
+                                    )
+                                })
+                                .unwrap_or("Strangers: Unknown".to_string())
+                        }
+                    }
+                    p {
+                        {
+                            state()
+                                .map(|state| {
+                                    let buffs = state
+                                        .buffs
+                                        .iter()
+                                        .filter(|(_, active)| *active)
+                                        .map(|(name, _)| name.as_str())
+                                        .collect::<Vec<_>>();
+                                    if buffs.is_empty() {
+                                        "Buffs: None".to_string()
+                                    } else {
+                                        format!("Buffs: {}", buffs.join(", "))
+                                    }
+                                })
+                                .unwrap_or("Buffs: Unknown".to_string())
+                        }
+                    }
+                    p {
+                        {
+                            state()
+                                .map(|state| {
+                                    format!("Memory: {} MB", state.memory_usage / (1024 * 1024))
+                                })
+                                .unwrap_or("Memory: Unknown".to_string())
+                        }
+                    }
+                    p {
+                        {
+                            state()
+                                .and_then(|state| {
+                                    state
+                                        .resolution
+                                        .map(|(width, height)| {
+                                            if state.resolution_supported {
+                                                format!("Resolution: {width}x{height}")
+                                            } else {
+                                                format!("Resolution: {width}x{height} (Unsupported)")
+                                            }
+                                        })
+                                })
+                                .unwrap_or("Resolution: Unknown".to_string())
+                        }
+                    }
+                    p {
+                        {
+                            state()
+                                .map(|state| format!("Capture: {}", state.capture_health))
+                                .unwrap_or("Capture: Unknown".to_string())
+                        }
+                    }
+                    if let Some(event) = state().and_then(|state| state.halt_event) {
+                        p {
+                            {
+                                let minutes = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_millis()
+                                    .saturating_sub(event.timestamp as u128) / 60_000;
+                                format!("Stopped because: {} ({minutes} min ago)", event.reason)
+                            }
+                        }
+                    }
+                    for (i , stats) in state()
+                        .map(|state| state.ab_comparison)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .enumerate()
+                    {
+                        p {
+                            {
+                                let current = state()
+                                    .and_then(|state| state.ab_comparison_preset)
+                                    .is_some_and(|preset| preset == stats.preset);
+                                let average = stats
+                                    .average_loop_millis()
+                                    .map(|millis| format!("{:.1}s", millis as f32 / 1000.0))
+                                    .unwrap_or("-".to_string());
+                                format!(
+                                    "Preset {} {}{}: {} loops, {average} avg loop, {} mobs, {} runes",
+                                    if i == 0 { "A" } else { "B" },
+                                    stats.preset,
+                                    if current { " (current)" } else { "" },
+                                    stats.loops,
+                                    stats.mobs,
+                                    stats.runes,
+                                )
+                            }
+                        }
+                    }
+                    if state().is_some_and(|state| state.platforms_pathing_unavailable) {
+                        p { "Platforms pathing: No platforms, using direct movement" }
+                    }
+                    if let Some(state) = state() {
+                        for repair in state.platforms_repairs {
+                            p { "Platforms repaired: {repair}" }
+                        }
+                        for unreachable in state.unreachable_actions {
+                            p { "Unreachable: {unreachable}" }
+                        }
+                        for row in state.invalid_database_rows {
+                            p { "Invalid database row: {row}" }
+                        }
+                    }
+                    if let Some(state) = state() {
+                        if !state.mob_model_available || !state.minimap_model_available
+                            || !state.rune_model_available
+                        {
+                            p {
+                                {
+                                    format!(
+                                        "Models unavailable: {}",
+                                        [
+                                            (state.mob_model_available, "Mob"),
+                                            (state.minimap_model_available, "Minimap"),
+                                            (state.rune_model_available, "Rune"),
+                                        ]
+                                            .into_iter()
+                                            .filter(|(available, _)| !available)
+                                            .map(|(_, name)| name)
+                                            .collect::<Vec<_>>()
+                                            .join(", "),
+                                    )
+                                }
+                            }
+                        }
+                    }
                 }
             }
             div { class: "flex w-full space-x-6 items-center justify-center items-stretch h-7",
@@ -430,6 +778,32 @@ pub fn Minimap(
                         "Stop actions"
                     }
                 }
+                if state().is_some_and(|state| state.scheduled_start_pending) {
+                    button {
+                        class: "button-secondary",
+                        onclick: move |_| async move {
+                            cancel_scheduled_start().await;
+                        },
+                        "Cancel scheduled start"
+                    }
+                }
+                if minimap()
+                    .is_some_and(|minimap| {
+                        matches!(minimap.rotation_mode, RotationMode::AutoMobbing(_))
+                    })
+                {
+                    button {
+                        class: "button-secondary",
+                        onclick: move |_| async move {
+                            coroutine.send(MinimapMessage::ToggleAutoMob);
+                        },
+                        if state().is_some_and(|state| state.auto_mob_paused) {
+                            "Resume auto mob"
+                        } else {
+                            "Pause auto mob"
+                        }
+                    }
+                }
                 button {
                     class: "button-secondary",
                     disabled: minimap().is_none(),
@@ -438,6 +812,15 @@ pub fn Minimap(
                     },
                     "Re-detect map"
                 }
+                button {
+                    class: "button-secondary",
+                    onclick: move |_| async move {
+                        if let Ok(bytes) = screenshot().await {
+                            let _ = document::eval(SCREENSHOT_JS).send(bytes);
+                        }
+                    },
+                    "Screenshot"
+                }
                 button {
                     class: "button-danger",
                     disabled: minimap().is_none(),
@@ -447,6 +830,119 @@ pub fn Minimap(
                     "Delete map"
                 }
             }
+            div { class: "flex w-full space-x-6 items-center justify-center items-stretch h-7",
+                button {
+                    class: "button-secondary",
+                    disabled: minimap().is_none(),
+                    onclick: move |_| async move {
+                        let Some(data) = minimap.peek().clone() else {
+                            return;
+                        };
+                        if let Ok(json) = export_map(&data) {
+                            let _ = document::eval(EXPORT_MAP_JS).send((data.name, json));
+                        }
+                    },
+                    "Export map"
+                }
+                label { class: "button-secondary flex items-center cursor-pointer",
+                    "Import map"
+                    input {
+                        class: "hidden",
+                        r#type: "file",
+                        accept: ".json",
+                        onchange: move |e| async move {
+                            let Some(engine) = e.files() else {
+                                return;
+                            };
+                            for file in engine.files() {
+                                if let Some(json) = engine.read_file_to_string(&file).await {
+                                    coroutine.send(MinimapMessage::ImportMinimap(json));
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+            if let Some(error) = import_error() {
+                p { class: "text-xs text-red-700", "Failed to import map: {error}" }
+            }
+            if state().is_some_and(|state| state.rune_awaiting_keys) {
+                div { class: "flex w-full space-x-2 items-center justify-center items-stretch h-7",
+                    p { class: "flex items-center",
+                        {
+                            let arrows = rune_keys()
+                                .into_iter()
+                                .map(|key| match key {
+                                    KeyBinding::Left => "←",
+                                    KeyBinding::Up => "↑",
+                                    KeyBinding::Down => "↓",
+                                    KeyBinding::Right => "→",
+                                    _ => "",
+                                })
+                                .collect::<String>();
+                            format!("Rune arrows: {arrows}")
+                        }
+                    }
+                    for (key , label) in [
+                        (KeyBinding::Left, "←"),
+                        (KeyBinding::Up, "↑"),
+                        (KeyBinding::Down, "↓"),
+                        (KeyBinding::Right, "→"),
+                    ]
+                    {
+                        button {
+                            class: "button-primary w-12",
+                            onclick: move |_| async move {
+                                let mut keys = rune_keys.peek().clone();
+                                keys.push(key);
+                                if keys.len() == 4 {
+                                    rune_keys.set(vec![]);
+                                    coroutine
+                                        .send(
+                                            MinimapMessage::SubmitRuneKeys([
+                                                keys[0],
+                                                keys[1],
+                                                keys[2],
+                                                keys[3],
+                                            ]),
+                                        );
+                                } else {
+                                    rune_keys.set(keys);
+                                }
+                            },
+                            {label}
+                        }
+                    }
+                    button {
+                        class: "button-secondary w-16",
+                        onclick: move |_| {
+                            rune_keys.set(vec![]);
+                        },
+                        "Clear"
+                    }
+                }
+            }
+            if halting() {
+                div { class: "flex w-full space-x-2 items-center justify-center items-stretch h-7",
+                    for (control , label) in [
+                        (ManualControl::Left, "←"),
+                        (ManualControl::Up, "↑"),
+                        (ManualControl::Down, "↓"),
+                        (ManualControl::Right, "→"),
+                        (ManualControl::Jump, "Jump"),
+                    ]
+                    {
+                        button {
+                            class: "button-secondary w-12",
+                            disabled: minimap().is_none(),
+                            onclick: move |_| async move {
+                                coroutine.send(MinimapMessage::ManualControl(control));
+                            },
+                            {label}
+                        }
+                    }
+                }
+            }
         }
     }
 }
@@ -0,0 +1,169 @@
+use std::time::Instant;
+
+use backend::{Minimap, NavigationStep, key_receiver};
+use dioxus::prelude::*;
+
+use crate::input::{KeyBindingInput, MillisInput};
+
+const DIV_CLASS: &str = "flex h-6 items-center space-x-2";
+const INPUT_CLASS: &str = "w-26 h-6 px-1.5 border border-gray-300 rounded text-xs text-ellipsis outline-none disabled:text-gray-400 disabled:cursor-not-allowed";
+
+#[component]
+pub fn Navigation(
+    minimap: ReadOnlySignal<Option<Minimap>>,
+    on_save: EventHandler<Minimap>,
+) -> Element {
+    let mut recording = use_signal::<Option<(Vec<NavigationStep>, Instant)>>(|| None);
+
+    use_future(move || async move {
+        let mut key_receiver = key_receiver().await;
+        loop {
+            let Ok(key) = key_receiver.recv().await else {
+                continue;
+            };
+            if recording.peek().is_none() {
+                continue;
+            }
+            recording.with_mut(|recording| {
+                let (steps, last_instant) = recording.as_mut().unwrap();
+                if let Some(step) = steps.last_mut() {
+                    step.wait_after_millis = last_instant.elapsed().as_millis() as u64;
+                }
+                steps.push(NavigationStep {
+                    key,
+                    wait_after_millis: 0,
+                });
+                *last_instant = Instant::now();
+            });
+        }
+    });
+
+    rsx! {
+        div { class: "flex flex-col space-y-2",
+            ul { class: "list-disc text-xs text-gray-700 pl-4",
+                li { "Return macro is played when the map changes and the current map is not this map" }
+                li { "Actions are resumed once this map is detected again" }
+                li { "Recording captures keys pressed in game with the delays between them" }
+                li { "Mouse clicks are not recorded, use keys to talk to NPCs or enter portals" }
+            }
+            div { class: "h-2 border-b border-gray-300 mb-2" }
+            div { class: "flex space-x-2",
+                button {
+                    class: "button-primary h-6 w-36",
+                    disabled: minimap().is_none(),
+                    onclick: move |_| {
+                        let taken = recording.write().take();
+                        if let Some((steps, _)) = taken {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.return_macro = steps;
+                                on_save(minimap);
+                            }
+                        } else {
+                            recording.set(Some((vec![], Instant::now())));
+                        }
+                    },
+                    if recording().is_some() {
+                        "Stop Recording"
+                    } else {
+                        "Start Recording"
+                    }
+                }
+                button {
+                    class: "button-danger h-6 w-36",
+                    disabled: minimap().is_none() || recording().is_some(),
+                    onclick: move |_| {
+                        if let Some(mut minimap) = minimap.peek().clone() {
+                            minimap.return_macro.clear();
+                            on_save(minimap);
+                        }
+                    },
+                    "Clear"
+                }
+            }
+            div { class: "flex items-center justify-between text-xs text-gray-700 border-b border-gray-300 mt-3 mb-2",
+                p { class: "w-26", "Key" }
+                p { class: "w-26", "Wait After" }
+                div { class: "w-18" }
+            }
+            if let Some((steps, _)) = recording() {
+                for step in steps {
+                    NavigationStepInput {
+                        disabled: true,
+                        on_delete: move |_| {},
+                        on_input: move |_| {},
+                        value: step,
+                    }
+                }
+            } else if let Some(Minimap { return_macro, .. }) = minimap() {
+                for (i , step) in return_macro.into_iter().enumerate() {
+                    NavigationStepInput {
+                        disabled: false,
+                        on_delete: move |_| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.return_macro.remove(i);
+                                on_save(minimap);
+                            }
+                        },
+                        on_input: move |value| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                *minimap.return_macro.get_mut(i).unwrap() = value;
+                                on_save(minimap);
+                            }
+                        },
+                        value: step,
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn NavigationStepInput(
+    disabled: bool,
+    on_delete: EventHandler,
+    on_input: EventHandler<NavigationStep>,
+    value: NavigationStep,
+) -> Element {
+    let NavigationStep {
+        key,
+        wait_after_millis,
+    } = value;
+
+    rsx! {
+        div { class: "flex items-center justify-between text-xs text-gray-700",
+            KeyBindingInput {
+                label: "",
+                label_class: "hidden",
+                div_class: DIV_CLASS,
+                input_class: INPUT_CLASS,
+                disabled,
+                on_input: move |key| {
+                    on_input(NavigationStep { key, ..value });
+                },
+                value: key,
+            }
+            MillisInput {
+                label: "",
+                label_class: "hidden",
+                input_class: INPUT_CLASS,
+                disabled,
+                on_input: move |wait_after_millis| {
+                    on_input(NavigationStep {
+                        wait_after_millis,
+                        ..value
+                    });
+                },
+                value: wait_after_millis,
+            }
+            button {
+                class: "button-danger h-6 w-18",
+                disabled,
+                onclick: move |_| {
+                    on_delete(());
+                },
+                "Delete"
+            }
+        }
+    }
+}
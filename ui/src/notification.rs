@@ -1,11 +1,17 @@
-use backend::{Notifications as NotificationsData, Settings};
+use backend::{Notifications as NotificationsData, QuietHours, Settings, test_notification};
 use dioxus::prelude::*;
 
 use crate::{
     AppMessage,
+    input::NumberInputU32,
     settings::{SettingsCheckbox, SettingsTextInput},
 };
 
+const QUIET_HOURS_DIV_CLASS: &str = "flex items-center space-x-4";
+const QUIET_HOURS_LABEL_CLASS: &str =
+    "text-xs text-gray-700 flex-1 inline-block data-[disabled]:text-gray-400";
+const QUIET_HOURS_INPUT_CLASS: &str = "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400";
+
 #[component]
 pub fn Notifications(
     app_coroutine: Coroutine<AppMessage>,
@@ -13,12 +19,20 @@ pub fn Notifications(
 ) -> Element {
     let settings_view = use_memo(move || settings().unwrap_or_default());
     let notifications_view = use_memo(move || settings_view().notifications);
+    let quiet_hours_view = use_memo(move || notifications_view().quiet_hours);
     let on_notifications = move |updated| {
         app_coroutine.send(AppMessage::UpdateSettings(Settings {
             notifications: updated,
             ..settings_view.peek().clone()
         }));
     };
+    let on_quiet_hours = move |quiet_hours| {
+        on_notifications(NotificationsData {
+            quiet_hours,
+            ..notifications_view.peek().clone()
+        });
+    };
+    let mut test_status = use_signal(String::default);
 
     rsx! {
         div { class: "px-2 pb-2 pt-2 flex flex-col space-y-3 overflow-y-auto scrollbar h-full",
@@ -112,6 +126,130 @@ pub fn Notifications(
                 },
                 value: notifications_view().notify_on_player_friend_appear,
             }
+            SettingsCheckbox {
+                label: "Notify If Halted",
+                on_input: move |notify_on_halt| {
+                    on_notifications(NotificationsData {
+                        notify_on_halt,
+                        ..notifications_view.peek().clone()
+                    });
+                },
+                value: notifications_view().notify_on_halt,
+            }
+            SettingsCheckbox {
+                label: "Notify If Restarted After Error",
+                on_input: move |notify_on_restart| {
+                    on_notifications(NotificationsData {
+                        notify_on_restart,
+                        ..notifications_view.peek().clone()
+                    });
+                },
+                value: notifications_view().notify_on_restart,
+            }
+            SettingsCheckbox {
+                label: "Notify If Game Freezes",
+                on_input: move |notify_on_freeze| {
+                    on_notifications(NotificationsData {
+                        notify_on_freeze,
+                        ..notifications_view.peek().clone()
+                    });
+                },
+                value: notifications_view().notify_on_freeze,
+            }
+            SettingsCheckbox {
+                label: "Notify If Run Time Quota Reached",
+                on_input: move |notify_on_quota_reached| {
+                    on_notifications(NotificationsData {
+                        notify_on_quota_reached,
+                        ..notifications_view.peek().clone()
+                    });
+                },
+                value: notifications_view().notify_on_quota_reached,
+            }
+            SettingsCheckbox {
+                label: "Notify And Wait For Rune Arrows If Unsure",
+                on_input: move |notify_on_rune_low_confidence| {
+                    on_notifications(NotificationsData {
+                        notify_on_rune_low_confidence,
+                        ..notifications_view.peek().clone()
+                    });
+                },
+                value: notifications_view().notify_on_rune_low_confidence,
+            }
+            SettingsCheckbox {
+                label: "Also Announce With Text-To-Speech",
+                on_input: move |text_to_speech| {
+                    on_notifications(NotificationsData {
+                        text_to_speech,
+                        ..notifications_view.peek().clone()
+                    });
+                },
+                value: notifications_view().text_to_speech,
+            }
+            SettingsCheckbox {
+                label: "Quiet Hours For Non-Critical Notifications",
+                on_input: move |enabled| {
+                    on_quiet_hours(QuietHours {
+                        enabled,
+                        ..*quiet_hours_view.peek()
+                    });
+                },
+                value: quiet_hours_view().enabled,
+            }
+            NumberInputU32 {
+                label: "Quiet Hours Start (Local Hour)",
+                label_class: QUIET_HOURS_LABEL_CLASS,
+                div_class: QUIET_HOURS_DIV_CLASS,
+                input_class: QUIET_HOURS_INPUT_CLASS,
+                disabled: !quiet_hours_view().enabled,
+                minimum_value: 0,
+                on_input: move |start_hour: u32| {
+                    on_quiet_hours(QuietHours {
+                        start_hour: start_hour.min(23),
+                        ..*quiet_hours_view.peek()
+                    });
+                },
+                value: quiet_hours_view().start_hour,
+            }
+            NumberInputU32 {
+                label: "Quiet Hours End (Local Hour)",
+                label_class: QUIET_HOURS_LABEL_CLASS,
+                div_class: QUIET_HOURS_DIV_CLASS,
+                input_class: QUIET_HOURS_INPUT_CLASS,
+                disabled: !quiet_hours_view().enabled,
+                minimum_value: 0,
+                on_input: move |end_hour: u32| {
+                    on_quiet_hours(QuietHours {
+                        end_hour: end_hour.min(23),
+                        ..*quiet_hours_view.peek()
+                    });
+                },
+                value: quiet_hours_view().end_hour,
+            }
+            SettingsCheckbox {
+                label: "Send Summary After Quiet Hours",
+                on_input: move |batch| {
+                    on_quiet_hours(QuietHours {
+                        batch,
+                        ..*quiet_hours_view.peek()
+                    });
+                },
+                value: quiet_hours_view().batch,
+            }
+            button {
+                class: "button-primary h-8",
+                onclick: move |_| async move {
+                    let status = match test_notification().await {
+                        Ok(()) => "Test notification scheduled".to_string(),
+                        Err(err) => format!("Failed to send test notification: {err}"),
+                    };
+                    test_status.set(status);
+                },
+                "Send Test Notification"
+            }
+            if !test_status().is_empty() {
+                p { class: "text-xs text-gray-700", {test_status()} }
+            }
         }
     }
 }
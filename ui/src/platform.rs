@@ -1,11 +1,13 @@
 use backend::{
-    KeyBindingConfiguration, MAX_PLATFORMS_COUNT, Minimap, Platform, Settings, key_receiver,
+    KeyBindingConfiguration, MAX_PLATFORMS_COUNT, Minimap, PathingProfile, Platform, Portal,
+    PortalUsage, Settings, key_receiver, player_state,
 };
 use dioxus::prelude::*;
 
 use crate::{
     icons::PositionIcon,
     input::{Checkbox, NumberInputI32},
+    select::{EnumSelect, Select},
 };
 
 const DIV_CLASS: &str = "flex h-6 items-center space-x-2";
@@ -22,6 +24,17 @@ pub fn Platforms(
         let minimap = minimap();
         minimap.is_none() || minimap.unwrap().platforms.len() >= MAX_PLATFORMS_COUNT
     });
+    let pathing_profile = use_memo(move || {
+        minimap()
+            .map(|minimap| minimap.pathing_profile)
+            .unwrap_or_default()
+    });
+    let save_pathing_profile = move |profile: PathingProfile| {
+        if let Some(mut minimap) = minimap.peek().clone() {
+            minimap.pathing_profile = profile;
+            on_save(minimap);
+        }
+    };
 
     use_future(move || async move {
         let mut key_receiver = key_receiver().await;
@@ -52,7 +65,8 @@ pub fn Platforms(
                 }
 
                 let KeyBindingConfiguration { key, enabled } = settings.platform_add_key;
-                if enabled && key == received_key {
+                // Same as the add button, pathing cannot hold more than the maximum platforms
+                if enabled && key == received_key && !*add_platform_disabled.peek() {
                     if let Some(mut minimap) = minimap.peek().clone() {
                         minimap.platforms.push(*editing.peek());
                         on_save(minimap);
@@ -126,6 +140,54 @@ pub fn Platforms(
                 },
                 value: minimap().map(|data| data.auto_mob_platforms_bound).unwrap_or_default(),
             }
+            PathingThresholdInput {
+                label: "Double Jump Threshold",
+                disabled: minimap().is_none(),
+                default_value: 25,
+                on_input: move |double_jump_threshold| {
+                    save_pathing_profile(PathingProfile {
+                        double_jump_threshold,
+                        ..pathing_profile()
+                    });
+                },
+                value: pathing_profile().double_jump_threshold,
+            }
+            PathingThresholdInput {
+                label: "Jump Threshold",
+                disabled: minimap().is_none(),
+                default_value: 7,
+                on_input: move |jump_threshold| {
+                    save_pathing_profile(PathingProfile {
+                        jump_threshold,
+                        ..pathing_profile()
+                    });
+                },
+                value: pathing_profile().jump_threshold,
+            }
+            PathingThresholdInput {
+                label: "Up Jump Only Grapple Threshold",
+                disabled: minimap().is_none(),
+                default_value: 24,
+                on_input: move |grappling_threshold| {
+                    save_pathing_profile(PathingProfile {
+                        grappling_threshold,
+                        ..pathing_profile()
+                    });
+                },
+                value: pathing_profile().grappling_threshold,
+            }
+            PathingThresholdInput {
+                label: "Grapple Threshold",
+                disabled: minimap().is_none(),
+                default_value: 41,
+                on_input: move |grappling_max_threshold| {
+                    save_pathing_profile(PathingProfile {
+                        grappling_max_threshold,
+                        ..pathing_profile()
+                    });
+                },
+                value: pathing_profile().grappling_max_threshold,
+            }
             div { class: "flex items-center justify-between text-xs text-gray-700 border-b border-gray-300 mt-3 mb-2 data-[disabled]:text-gray-400",
                 p { class: "w-26", "X Start" }
                 p { class: "w-26", "X End" }
@@ -171,6 +233,101 @@ pub fn Platforms(
                 },
                 value: editing(),
             }
+            Portals { minimap, on_save }
+        }
+    }
+}
+
+#[component]
+fn Portals(minimap: ReadOnlySignal<Option<Minimap>>, on_save: EventHandler<Minimap>) -> Element {
+    const SELECT_CLASS: &str = "w-26 h-6 border border-gray-300 rounded text-xs text-ellipsis outline-none disabled:text-gray-400 disabled:cursor-not-allowed";
+
+    rsx! {
+        div { class: "flex items-center justify-between text-xs text-gray-700",
+            p { class: "w-26", "Portal" }
+            p { class: "w-26", "Usage" }
+            p { class: "w-26", "Destination" }
+            div { class: "w-18" }
+        }
+        if let Some(Minimap { portals, .. }) = minimap() {
+            for (i , portal) in portals.clone().into_iter().enumerate() {
+                div { class: "flex items-center justify-between text-xs text-gray-700",
+                    p { class: "w-26", {format!("#{i} ({}, {})", portal.x, portal.y)} }
+                    EnumSelect {
+                        select_class: SELECT_CLASS,
+                        on_select: move |usage: PortalUsage| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.portals[i].usage = usage;
+                                on_save(minimap);
+                            }
+                        },
+                        selected: portal.usage,
+                    }
+                    Select {
+                        select_class: SELECT_CLASS,
+                        disabled: portal.usage != PortalUsage::Shortcut,
+                        options: [(None, "None".to_string())]
+                            .into_iter()
+                            .chain(
+                                portals
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(j, _)| *j != i)
+                                    .map(|(j, other)| {
+                                        (Some(j), format!("#{j} ({}, {})", other.x, other.y))
+                                    }),
+                            )
+                            .collect::<Vec<_>>(),
+                        on_select: move |(_, destination): (usize, Option<usize>)| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.portals[i].destination = destination;
+                                on_save(minimap);
+                            }
+                        },
+                        selected: portal.destination,
+                    }
+                    button {
+                        class: "button-danger h-6 w-18",
+                        onclick: move |_| {
+                            if let Some(mut minimap) = minimap.peek().clone() {
+                                minimap.portals.remove(i);
+                                for portal in minimap.portals.iter_mut() {
+                                    portal.destination = match portal.destination {
+                                        Some(j) if j == i => None,
+                                        Some(j) if j > i => Some(j - 1),
+                                        destination => destination,
+                                    };
+                                }
+                                on_save(minimap);
+                            }
+                        },
+                        "Delete"
+                    }
+                }
+            }
+        }
+        button {
+            class: "button-primary h-6",
+            disabled: minimap().is_none(),
+            onclick: move |_| async move {
+                let detected = player_state().await.portals;
+                if let Some(mut minimap) = minimap.peek().clone() {
+                    let new_portals = detected
+                        .into_iter()
+                        .filter(|portal| {
+                            !minimap
+                                .portals
+                                .iter()
+                                .any(|saved| saved.is_same_position(portal.x, portal.y))
+                        })
+                        .collect::<Vec<Portal>>();
+                    if !new_portals.is_empty() {
+                        minimap.portals.extend(new_portals);
+                        on_save(minimap);
+                    }
+                }
+            },
+            "Add detected portals"
         }
     }
 }
@@ -199,6 +356,43 @@ fn PlatformCheckbox(
     }
 }
 
+#[component]
+fn PathingThresholdInput(
+    label: String,
+    disabled: bool,
+    default_value: i32,
+    on_input: EventHandler<Option<i32>>,
+    value: Option<i32>,
+) -> Element {
+    const LABEL_CLASS: &str =
+        "w-64 text-xs text-gray-700 inline-block data-[disabled]:text-gray-400";
+    const INPUT_CLASS: &str = "w-26 h-6 px-1.5 border border-gray-300 rounded text-xs text-ellipsis outline-none disabled:text-gray-400 disabled:cursor-not-allowed";
+
+    rsx! {
+        PlatformCheckbox {
+            label: format!("Override {label}"),
+            disabled,
+            on_input: move |checked: bool| {
+                on_input(checked.then_some(default_value));
+            },
+            value: value.is_some(),
+        }
+        if let Some(value) = value {
+            NumberInputI32 {
+                label,
+                label_class: LABEL_CLASS,
+                div_class: DIV_CLASS,
+                input_class: INPUT_CLASS,
+                disabled,
+                on_input: move |value| {
+                    on_input(Some(value));
+                },
+                value,
+            }
+        }
+    }
+}
+
 #[component]
 fn PlatformInput(
     copy_position: ReadOnlySignal<Option<(i32, i32)>>,
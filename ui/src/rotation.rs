@@ -1,11 +1,17 @@
-use backend::{AutoMobbing, Bound, RotationMode};
+use backend::{
+    AutoMobbing, AutoMobbingTarget, Bound, BoundVertex, Position, RotationMode,
+    clear_auto_mob_learned,
+};
 use dioxus::prelude::*;
 
 use crate::{
     input::{Checkbox, KeyBindingInput, MillisInput, NumberInputI32, NumberInputU32},
-    select::EnumSelect,
+    select::{EnumSelect, Select},
 };
 
+/// The default number of loops before switching when a preset to compare is first selected
+const AB_COMPARISON_LOOPS_DEFAULT: u32 = 5;
+
 const DIV_CLASS: &str = "flex py-2 border-b border-gray-100 space-x-2";
 const LABEL_CLASS: &str = "flex-1 text-xs text-gray-700 inline-block data-[disabled]:text-gray-400";
 const INPUT_CLASS: &str = "w-36 px-1.5 h-6 border border-gray-300 rounded text-xs text-ellipsis outline-none disabled:text-gray-400 disabled:cursor-not-allowed";
@@ -15,14 +21,29 @@ pub fn Rotations(
     disabled: bool,
     on_rotation_mode: EventHandler<RotationMode>,
     on_reset_on_erda: EventHandler<bool>,
+    on_erda_snap_to_platform: EventHandler<bool>,
+    on_warm_up: EventHandler<bool>,
+    on_warm_up_position: EventHandler<Option<Position>>,
+    on_auto_mob_bound_polygon: EventHandler<Vec<BoundVertex>>,
+    on_ab_comparison: EventHandler<(Option<String>, u32)>,
     rotation_mode: RotationMode,
     reset_on_erda: bool,
+    erda_snap_to_platform: bool,
+    warm_up: bool,
+    warm_up_position: Option<Position>,
+    auto_mob_bound_polygon: Vec<BoundVertex>,
+    presets: Vec<String>,
+    preset: Option<String>,
+    ab_comparison_preset: Option<String>,
+    ab_comparison_loops: u32,
 ) -> Element {
     let auto_mobbing = if let RotationMode::AutoMobbing(mobbing) = rotation_mode {
         mobbing
     } else {
         AutoMobbing::default()
     };
+    let ab_comparison_enabled = ab_comparison_preset.is_some();
+    let compared_preset = ab_comparison_preset.clone();
 
     rsx! {
         div { class: "flex flex-col space-y-2",
@@ -32,9 +53,31 @@ pub fn Rotations(
                 li {
                     "When reset rotation on Erda condotion is ticked, all Any condition actions will restart from the beginning"
                 }
+                li {
+                    "Nearest first picks the closest Any condition action not yet executed in the current loop"
+                }
+                li {
+                    "When snap Erda condition to platform midpoint is ticked, Erda condition actions on a platform move to its midpoint"
+                }
+                li {
+                    "When warm up is ticked, all buffs are casted and Erda Shower is detected before the first loop every time the rotation starts"
+                }
+                li {
+                    "When compare with preset is set, the selected preset and the compared preset alternate every N loops with their statistics shown below the minimap"
+                }
                 li { "Mob detected outside of bound is ignored" }
+                li {
+                    "Balancing time across platforms prefers mobs on platforms the player has spent less time on"
+                }
+                li { "Minimap dots target uses red mob dots on minimap instead of the whole screen" }
                 li { "Auto mobbing X,Y origin is top-left of minimap" }
+                li {
+                    "Learned reachable platforms and pathing points are saved to the map every minute while auto mobbing"
+                }
                 li { "Overrides the below bound if auto mobbing bound by platforms enabled" }
+                li {
+                    "Polygon bound with at least 3 vertices replaces the rectangle bound for non-rectangular areas"
+                }
             }
             div { class: "h-2 border-b border-gray-300 mb-2" }
             EnumSelect {
@@ -59,6 +102,100 @@ pub fn Rotations(
                 },
                 value: reset_on_erda,
             }
+            Checkbox {
+                label: "Snap Erda Condition To Platform Midpoint",
+                label_class: LABEL_CLASS,
+                div_class: DIV_CLASS,
+                input_class: "w-36 text-xs text-gray-700 text-ellipsis rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400",
+                disabled,
+                on_input: move |checked| {
+                    on_erda_snap_to_platform(checked);
+                },
+                value: erda_snap_to_platform,
+            }
+            Checkbox {
+                label: "Warm Up On Rotation Start",
+                label_class: LABEL_CLASS,
+                div_class: DIV_CLASS,
+                input_class: "w-36 text-xs text-gray-700 text-ellipsis rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400",
+                disabled,
+                on_input: move |checked| {
+                    on_warm_up(checked);
+                },
+                value: warm_up,
+            }
+            Checkbox {
+                label: "Warm Up Walk To Start Point",
+                label_class: LABEL_CLASS,
+                div_class: DIV_CLASS,
+                input_class: "w-36 text-xs text-gray-700 text-ellipsis rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400",
+                disabled: disabled || !warm_up,
+                on_input: move |checked: bool| {
+                    on_warm_up_position(checked.then_some(Position::default()));
+                },
+                value: warm_up_position.is_some(),
+            }
+            if let Some(position) = warm_up_position {
+                NumberInputI32 {
+                    label: "Start Point X",
+                    div_class: DIV_CLASS,
+                    label_class: LABEL_CLASS,
+                    input_class: INPUT_CLASS,
+                    disabled: disabled || !warm_up,
+                    on_input: move |x| {
+                        on_warm_up_position(Some(Position { x, ..position }));
+                    },
+                    value: position.x,
+                }
+                NumberInputI32 {
+                    label: "Start Point Y",
+                    div_class: DIV_CLASS,
+                    label_class: LABEL_CLASS,
+                    input_class: INPUT_CLASS,
+                    disabled: disabled || !warm_up,
+                    on_input: move |y| {
+                        on_warm_up_position(Some(Position { y, ..position }));
+                    },
+                    value: position.y,
+                }
+            }
+            Select {
+                label: "Compare With Preset",
+                div_class: DIV_CLASS,
+                label_class: LABEL_CLASS,
+                select_class: INPUT_CLASS,
+                disabled: disabled || preset.is_none(),
+                options: [(None, "None".to_string())]
+                    .into_iter()
+                    .chain(
+                        presets
+                            .iter()
+                            .filter(|other| Some(*other) != preset.as_ref())
+                            .map(|other| (Some(other.clone()), other.clone())),
+                    )
+                    .collect::<Vec<_>>(),
+                on_select: move |(_, compared): (usize, Option<String>)| {
+                    let loops = if ab_comparison_loops == 0 {
+                        AB_COMPARISON_LOOPS_DEFAULT
+                    } else {
+                        ab_comparison_loops
+                    };
+                    on_ab_comparison((compared, loops));
+                },
+                selected: ab_comparison_preset,
+            }
+            NumberInputU32 {
+                label: "Compare Loops Before Switching",
+                div_class: DIV_CLASS,
+                label_class: LABEL_CLASS,
+                input_class: INPUT_CLASS,
+                disabled: disabled || !ab_comparison_enabled,
+                minimum_value: 1,
+                on_input: move |loops| {
+                    on_ab_comparison((compared_preset.clone(), loops));
+                },
+                value: ab_comparison_loops.max(1),
+            }
             AutoMobbingInput {
                 disabled: disabled || !matches!(rotation_mode, RotationMode::AutoMobbing(_)),
                 on_input: move |mobbing| {
@@ -66,6 +203,85 @@ pub fn Rotations(
                 },
                 value: auto_mobbing,
             }
+            AutoMobbingPolygonInput {
+                disabled: disabled || !matches!(rotation_mode, RotationMode::AutoMobbing(_)),
+                on_input: move |polygon| {
+                    on_auto_mob_bound_polygon(polygon);
+                },
+                value: auto_mob_bound_polygon,
+            }
+            button {
+                class: "button-danger h-6",
+                disabled: disabled || !matches!(rotation_mode, RotationMode::AutoMobbing(_)),
+                onclick: move |_| async move {
+                    clear_auto_mob_learned().await;
+                },
+                "Clear Learned Auto Mobbing Data"
+            }
+        }
+    }
+}
+
+#[component]
+fn AutoMobbingPolygonInput(
+    disabled: bool,
+    on_input: EventHandler<Vec<BoundVertex>>,
+    value: Vec<BoundVertex>,
+) -> Element {
+    let value = use_memo(use_reactive!(|value| value));
+
+    rsx! {
+        for (i , vertex) in value().into_iter().enumerate() {
+            div { class: "flex items-end space-x-2",
+                div { class: "flex-1",
+                    NumberInputI32 {
+                        label: format!("Polygon Vertex {} X", i + 1),
+                        div_class: DIV_CLASS,
+                        label_class: LABEL_CLASS,
+                        input_class: INPUT_CLASS,
+                        disabled,
+                        on_input: move |x| {
+                            let mut polygon = value.peek().clone();
+                            polygon[i] = BoundVertex { x, ..vertex };
+                            on_input(polygon);
+                        },
+                        value: vertex.x,
+                    }
+                    NumberInputI32 {
+                        label: format!("Polygon Vertex {} Y", i + 1),
+                        div_class: DIV_CLASS,
+                        label_class: LABEL_CLASS,
+                        input_class: INPUT_CLASS,
+                        disabled,
+                        on_input: move |y| {
+                            let mut polygon = value.peek().clone();
+                            polygon[i] = BoundVertex { y, ..vertex };
+                            on_input(polygon);
+                        },
+                        value: vertex.y,
+                    }
+                }
+                button {
+                    class: "button-danger h-6 w-16 mb-2",
+                    disabled,
+                    onclick: move |_| {
+                        let mut polygon = value.peek().clone();
+                        polygon.remove(i);
+                        on_input(polygon);
+                    },
+                    "Remove"
+                }
+            }
+        }
+        button {
+            class: "button-primary h-6",
+            disabled,
+            onclick: move |_| {
+                let mut polygon = value.peek().clone();
+                polygon.push(polygon.last().copied().unwrap_or_default());
+                on_input(polygon);
+            },
+            "Add Polygon Vertex"
         }
     }
 }
@@ -79,12 +295,30 @@ fn AutoMobbingInput(
     let AutoMobbing {
         bound,
         key,
+        airborne_key,
         key_count,
         key_wait_before_millis,
         key_wait_after_millis,
+        split_bound_by_friend,
+        target,
+        farm_minutes,
+        rest_minutes,
+        balance_platforms,
+        same_y_preference,
     } = value;
 
     rsx! {
+        EnumSelect {
+            label: "Target",
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            select_class: INPUT_CLASS,
+            disabled,
+            on_select: move |target: AutoMobbingTarget| {
+                on_input(AutoMobbing { target, ..value });
+            },
+            selected: target,
+        }
         KeyBindingInput {
             label: "Key",
             label_class: LABEL_CLASS,
@@ -96,6 +330,36 @@ fn AutoMobbingInput(
             },
             value: key,
         }
+        Checkbox {
+            label: "Use Airborne Key",
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: "w-36 text-xs text-gray-700 text-ellipsis rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400",
+            disabled,
+            on_input: move |checked: bool| {
+                on_input(AutoMobbing {
+                    airborne_key: checked.then_some(key),
+                    ..value
+                });
+            },
+            value: airborne_key.is_some(),
+        }
+        if let Some(airborne_key) = airborne_key {
+            KeyBindingInput {
+                label: "Airborne Key",
+                label_class: LABEL_CLASS,
+                div_class: DIV_CLASS,
+                input_class: INPUT_CLASS,
+                disabled,
+                on_input: move |airborne_key| {
+                    on_input(AutoMobbing {
+                        airborne_key: Some(airborne_key),
+                        ..value
+                    });
+                },
+                value: airborne_key,
+            }
+        }
         NumberInputU32 {
             label: "Key Count",
             div_class: DIV_CLASS,
@@ -192,5 +456,78 @@ fn AutoMobbingInput(
             },
             value: bound.height,
         }
+        Checkbox {
+            label: "Split Bound Away From Friend",
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: "w-36 text-xs text-gray-700 text-ellipsis rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400",
+            disabled,
+            on_input: move |split_bound_by_friend| {
+                on_input(AutoMobbing {
+                    split_bound_by_friend,
+                    ..value
+                });
+            },
+            value: split_bound_by_friend,
+        }
+        Checkbox {
+            label: "Balance Time Across Platforms",
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: "w-36 text-xs text-gray-700 text-ellipsis rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400",
+            disabled,
+            on_input: move |balance_platforms| {
+                on_input(AutoMobbing {
+                    balance_platforms,
+                    ..value
+                });
+            },
+            value: balance_platforms,
+        }
+        NumberInputU32 {
+            label: "Same Y Preference (0-100)",
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: INPUT_CLASS,
+            disabled,
+            minimum_value: 0,
+            on_input: move |same_y_preference: u32| {
+                on_input(AutoMobbing {
+                    same_y_preference: same_y_preference.min(100),
+                    ..value
+                });
+            },
+            value: same_y_preference,
+        }
+        NumberInputU32 {
+            label: "Farm Minutes Before Resting",
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: INPUT_CLASS,
+            disabled,
+            minimum_value: 0,
+            on_input: move |farm_minutes| {
+                on_input(AutoMobbing {
+                    farm_minutes,
+                    ..value
+                });
+            },
+            value: farm_minutes,
+        }
+        NumberInputU32 {
+            label: "Rest Minutes",
+            div_class: DIV_CLASS,
+            label_class: LABEL_CLASS,
+            input_class: INPUT_CLASS,
+            disabled,
+            minimum_value: 0,
+            on_input: move |rest_minutes| {
+                on_input(AutoMobbing {
+                    rest_minutes,
+                    ..value
+                });
+            },
+            value: rest_minutes,
+        }
     }
 }
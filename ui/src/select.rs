@@ -1,6 +1,6 @@
 use std::{fmt::Display, str::FromStr};
 
-use backend::IntoEnumIterator;
+use backend::{IntoEnumIterator, Language, localize};
 use dioxus::prelude::*;
 
 use crate::input::LabeledInput;
@@ -33,9 +33,17 @@ pub fn EnumSelect<T: 'static + Clone + PartialEq + Display + FromStr + IntoEnumI
     selected: T,
     #[props(default = Vec::new())] excludes: Vec<T>,
 ) -> Element {
+    // Provided by App so that display strings follow Settings language
+    let language = try_use_context::<Memo<Language>>()
+        .map(|language| language())
+        .unwrap_or_default();
     let options = T::iter()
         .filter(|variant| !excludes.contains(variant))
-        .map(|variant| (variant.to_string(), variant.to_string()))
+        .map(|variant| {
+            let variant = variant.to_string();
+            let label = localize(language, &variant);
+            (variant, label)
+        })
         .collect::<Vec<_>>();
     let selected = selected.to_string();
 
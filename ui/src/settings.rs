@@ -1,21 +1,25 @@
 use std::{fmt::Display, str::FromStr};
 
 use backend::{
-    CaptureMode, InputMethod, IntoEnumIterator, KeyBindingConfiguration, Settings as SettingsData,
-    query_capture_handles, select_capture_handle,
+    CaptureHandle, CaptureHandleIdentity, CaptureMode, InputMethod, IntoEnumIterator,
+    KeyBindingConfiguration, Language, OtherPlayerColors, Schedule, Settings as SettingsData,
+    WindowLayout, calibrate_buffs_region, query_capture_handles, query_client_windows,
+    select_capture_handle,
 };
 #[cfg(debug_assertions)]
 use backend::{capture_image, infer_minimap, infer_rune, record_images, test_spin_rune};
 use dioxus::prelude::*;
+use tokio::task::spawn_blocking;
 
 use crate::{
     AppMessage,
-    input::{Checkbox, LabeledInput},
+    input::{Checkbox, LabeledInput, MillisInput, NumberInputI32, NumberInputU32},
     key::KeyBindingConfigurationInput,
     select::{EnumSelect, Select},
 };
 
 const TOGGLE_ACTIONS: &str = "Start/Stop Actions";
+const TOGGLE_AUTO_MOB: &str = "Pause/Resume Auto Mobbing";
 const PLATFORM_START: &str = "Mark Platform Start";
 const PLATFORM_END: &str = "Mark Platform End";
 const PLATFORM_ADD: &str = "Add Platform";
@@ -29,12 +33,21 @@ const SELECT_CLASS: &str = "w-44 h-7 text-xs text-gray-700 text-ellipsis border
 pub fn Settings(
     app_coroutine: Coroutine<AppMessage>,
     settings: ReadOnlySignal<Option<SettingsData>>,
+    clients: ReadOnlySignal<Vec<usize>>,
+    client: ReadOnlySignal<usize>,
 ) -> Element {
     let settings_view = use_memo(move || settings().unwrap_or_default());
     let active = use_signal(|| None);
     let on_settings = move |updated| {
         app_coroutine.send(AppMessage::UpdateSettings(updated));
     };
+    let on_schedule = move |schedule| {
+        on_settings(SettingsData {
+            schedule,
+            ..settings_view.peek().clone()
+        });
+    };
+    let mut calibrate_failed = use_signal(|| false);
     #[cfg(debug_assertions)]
     let mut recording = use_signal(|| false);
 
@@ -42,7 +55,13 @@ pub fn Settings(
         div { class: "px-2 pb-2 pt-2 flex flex-col overflow-y-auto scrollbar h-full",
             ul { class: "list-disc text-xs text-gray-700 pl-4",
                 li { class: "mb-1", "Platform keys must have a Map created and Platforms tab opened" }
+                li { class: "mb-1",
+                    "Sync Folder exports saved maps and configurations and imports them on next startup (e.g. a OneDrive folder shared between PCs)"
+                }
                 li { class: "mb-1", "BltBltArea can stay behind other windows but cannot be minimized" }
+                li { class: "mb-1",
+                    "Only Send Keys To Window (e.g. MapleStory) stops keys from being typed into other apps like Discord if the wrong capture handle is selected"
+                }
                 li { class: "mb-1 font-bold",
                     "BitBltArea relies on high-quality game images for detection (e.g. no blurry)"
                 }
@@ -78,6 +97,376 @@ pub fn Settings(
                     },
                     value: settings_view().stop_on_fail_or_change_map,
                 }
+                SettingsCheckbox {
+                    label: "Export Activity CSV Per Minute",
+                    on_input: move |export_activity_csv| {
+                        on_settings(SettingsData {
+                            export_activity_csv,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().export_activity_csv,
+                }
+                SettingsCheckbox {
+                    label: "Half Resolution Detection",
+                    on_input: move |half_resolution_detection| {
+                        on_settings(SettingsData {
+                            half_resolution_detection,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().half_resolution_detection,
+                }
+                SettingsCheckbox {
+                    label: "Detect Other Players By Color If Templates Fail",
+                    on_input: move |enabled| {
+                        on_settings(SettingsData {
+                            other_player_colors: OtherPlayerColors {
+                                enabled,
+                                ..settings_view.peek().other_player_colors
+                            },
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().other_player_colors.enabled,
+                }
+                if cfg!(feature = "audio-capture") {
+                    SettingsCheckbox {
+                        label: "Listen For Rune Spawn Chime In Game Audio",
+                        on_input: move |rune_chime_detection| {
+                            on_settings(SettingsData {
+                                rune_chime_detection,
+                                ..settings_view.peek().clone()
+                            });
+                        },
+                        value: settings_view().rune_chime_detection,
+                    }
+                }
+                NumberInputU32 {
+                    label: "Daily Run Time Quota (Minutes, 0 = Unlimited)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 0,
+                    on_input: move |daily_quota_minutes| {
+                        on_settings(SettingsData {
+                            daily_quota_minutes,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().daily_quota_minutes,
+                }
+                NumberInputU32 {
+                    label: "Weekly Run Time Quota (Minutes, 0 = Unlimited)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 0,
+                    on_input: move |weekly_quota_minutes| {
+                        on_settings(SettingsData {
+                            weekly_quota_minutes,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().weekly_quota_minutes,
+                }
+                SettingsCheckbox {
+                    label: "Start Actions At Scheduled Time",
+                    on_input: move |start_enabled| {
+                        on_schedule(Schedule {
+                            start_enabled,
+                            ..settings_view.peek().schedule
+                        });
+                    },
+                    value: settings_view().schedule.start_enabled,
+                }
+                NumberInputU32 {
+                    label: "Scheduled Start (Local Hour)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    disabled: !settings_view().schedule.start_enabled,
+                    minimum_value: 0,
+                    on_input: move |start_hour: u32| {
+                        on_schedule(Schedule {
+                            start_hour: start_hour.min(23),
+                            ..settings_view.peek().schedule
+                        });
+                    },
+                    value: settings_view().schedule.start_hour,
+                }
+                NumberInputU32 {
+                    label: "Scheduled Start (Minute)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    disabled: !settings_view().schedule.start_enabled,
+                    minimum_value: 0,
+                    on_input: move |start_minute: u32| {
+                        on_schedule(Schedule {
+                            start_minute: start_minute.min(59),
+                            ..settings_view.peek().schedule
+                        });
+                    },
+                    value: settings_view().schedule.start_minute,
+                }
+                NumberInputU32 {
+                    label: "Stop After Rotating (Minutes, 0 = Never)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 0,
+                    on_input: move |stop_after_minutes| {
+                        on_schedule(Schedule {
+                            stop_after_minutes,
+                            ..settings_view.peek().schedule
+                        });
+                    },
+                    value: settings_view().schedule.stop_after_minutes,
+                }
+                NumberInputU32 {
+                    label: "Break Every (Minutes, 0 = Disabled)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 0,
+                    on_input: move |break_every_minutes| {
+                        on_schedule(Schedule {
+                            break_every_minutes,
+                            ..settings_view.peek().schedule
+                        });
+                    },
+                    value: settings_view().schedule.break_every_minutes,
+                }
+                NumberInputU32 {
+                    label: "Break Minimum (Minutes)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    disabled: settings_view().schedule.break_every_minutes == 0,
+                    minimum_value: 0,
+                    on_input: move |break_min_minutes| {
+                        on_schedule(Schedule {
+                            break_min_minutes,
+                            ..settings_view.peek().schedule
+                        });
+                    },
+                    value: settings_view().schedule.break_min_minutes,
+                }
+                NumberInputU32 {
+                    label: "Break Maximum (Minutes)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    disabled: settings_view().schedule.break_every_minutes == 0,
+                    minimum_value: 0,
+                    on_input: move |break_max_minutes| {
+                        on_schedule(Schedule {
+                            break_max_minutes,
+                            ..settings_view.peek().schedule
+                        });
+                    },
+                    value: settings_view().schedule.break_max_minutes,
+                }
+                NumberInputU32 {
+                    label: "Keep Alive While Halting Every (Minutes, 0 = Disabled)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 0,
+                    on_input: move |keep_alive_minutes| {
+                        on_settings(SettingsData {
+                            keep_alive_minutes,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().keep_alive_minutes,
+                }
+                NumberInputU32 {
+                    label: "Safe Mode After Start (Seconds, 0 = Disabled)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 0,
+                    on_input: move |safe_mode_seconds| {
+                        on_settings(SettingsData {
+                            safe_mode_seconds,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().safe_mode_seconds,
+                }
+                NumberInputU32 {
+                    label: "Stranger Detections Before Reacting",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 1,
+                    on_input: move |stranger_grace_count| {
+                        on_settings(SettingsData {
+                            stranger_grace_count,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().stranger_grace_count,
+                }
+                MillisInput {
+                    label: "Stranger Detections Window",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    on_input: move |stranger_grace_window_millis| {
+                        on_settings(SettingsData {
+                            stranger_grace_window_millis,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().stranger_grace_window_millis,
+                }
+                NumberInputU32 {
+                    label: "Max Key Presses Per Second (0 = Unlimited)",
+                    label_class: SELECT_LABEL_CLASS,
+                    div_class: SELECT_DIV_CLASS,
+                    input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                    minimum_value: 0,
+                    on_input: move |max_keys_per_second| {
+                        on_settings(SettingsData {
+                            max_keys_per_second,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().max_keys_per_second,
+                }
+                SettingsCheckbox {
+                    label: "Halt Actions If Max Key Presses Exceeded",
+                    on_input: move |halt_on_key_rate_exceeded| {
+                        on_settings(SettingsData {
+                            halt_on_key_rate_exceeded,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().halt_on_key_rate_exceeded,
+                }
+                SettingsCheckbox {
+                    label: "Enable Developer Console",
+                    on_input: move |enable_console| {
+                        on_settings(SettingsData {
+                            enable_console,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().enable_console,
+                }
+                SettingsCheckbox {
+                    label: "Move And Resize Game Window Before Capturing",
+                    on_input: move |enabled| {
+                        on_settings(SettingsData {
+                            window_layout: WindowLayout {
+                                enabled,
+                                ..settings_view.peek().window_layout
+                            },
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().window_layout.enabled,
+                }
+                SettingsWindowLayoutInput {
+                    label: "Window X",
+                    disabled: !settings_view().window_layout.enabled,
+                    minimum_value: i32::MIN,
+                    on_input: move |x| {
+                        on_settings(SettingsData {
+                            window_layout: WindowLayout {
+                                x,
+                                ..settings_view.peek().window_layout
+                            },
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().window_layout.x,
+                }
+                SettingsWindowLayoutInput {
+                    label: "Window Y",
+                    disabled: !settings_view().window_layout.enabled,
+                    minimum_value: i32::MIN,
+                    on_input: move |y| {
+                        on_settings(SettingsData {
+                            window_layout: WindowLayout {
+                                y,
+                                ..settings_view.peek().window_layout
+                            },
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().window_layout.y,
+                }
+                SettingsWindowLayoutInput {
+                    label: "Window Width",
+                    disabled: !settings_view().window_layout.enabled,
+                    minimum_value: 1,
+                    on_input: move |width| {
+                        on_settings(SettingsData {
+                            window_layout: WindowLayout {
+                                width,
+                                ..settings_view.peek().window_layout
+                            },
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().window_layout.width,
+                }
+                SettingsWindowLayoutInput {
+                    label: "Window Height",
+                    disabled: !settings_view().window_layout.enabled,
+                    minimum_value: 1,
+                    on_input: move |height| {
+                        on_settings(SettingsData {
+                            window_layout: WindowLayout {
+                                height,
+                                ..settings_view.peek().window_layout
+                            },
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().window_layout.height,
+                }
+                button {
+                    class: "button-primary h-8",
+                    onclick: move |_| async move {
+                        match calibrate_buffs_region().await {
+                            Some(settings) => {
+                                calibrate_failed.set(false);
+                                app_coroutine.send(AppMessage::UpdateSettings(settings));
+                            }
+                            None => calibrate_failed.set(true),
+                        }
+                    },
+                    "Calibrate Buffs Region"
+                }
+                p { class: "text-xs text-gray-700",
+                    if calibrate_failed() {
+                        "No active buff found for calibrating, make sure at least one supported buff is active"
+                    } else {
+                        {
+                            format!(
+                                "Buffs region offset: ({}, {})",
+                                settings_view().buffs_region_offset_x,
+                                settings_view().buffs_region_offset_y,
+                            )
+                        }
+                    }
+                }
+                SettingsEnumSelect::<Language> {
+                    label: "Language",
+                    on_select: move |language| {
+                        on_settings(SettingsData {
+                            language,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    disabled: false,
+                    selected: settings_view().language,
+                }
                 SettingsEnumSelect::<CaptureMode> {
                     label: "Capture Mode",
                     on_select: move |capture_mode| {
@@ -89,8 +478,29 @@ pub fn Settings(
                     disabled: false,
                     selected: settings_view().capture_mode,
                 }
-                SettingsCaptureHandleSelect { settings_view }
+                SettingsTextInput {
+                    label: "Sync Folder",
+                    on_input: move |sync_folder_path| {
+                        on_settings(SettingsData {
+                            sync_folder_path,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().sync_folder_path,
+                }
+                SettingsClientSelect { app_coroutine, clients, client }
+                SettingsCaptureHandleSelect { app_coroutine, settings_view, clients, client }
                 SettingsInputMethodSelect { app_coroutine, settings_view }
+                SettingsTextInput {
+                    label: "Only Send Keys To Window Title / Class (Empty = Any)",
+                    on_input: move |foreground_interlock| {
+                        on_settings(SettingsData {
+                            foreground_interlock,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().foreground_interlock,
+                }
                 KeyBindingConfigurationInput {
                     label: TOGGLE_ACTIONS,
                     label_active: active,
@@ -104,6 +514,29 @@ pub fn Settings(
                     },
                     value: Some(settings_view().toggle_actions_key),
                 }
+                SettingsCheckbox {
+                    label: "Toggle Actions Key Works Without Focus",
+                    on_input: move |toggle_actions_key_global| {
+                        on_settings(SettingsData {
+                            toggle_actions_key_global,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: settings_view().toggle_actions_key_global,
+                }
+                KeyBindingConfigurationInput {
+                    label: TOGGLE_AUTO_MOB,
+                    label_active: active,
+                    is_toggleable: true,
+                    is_disabled: false,
+                    on_input: move |key: Option<KeyBindingConfiguration>| {
+                        on_settings(SettingsData {
+                            toggle_auto_mob_key: key.unwrap(),
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: Some(settings_view().toggle_auto_mob_key),
+                }
                 KeyBindingConfigurationInput {
                     label: PLATFORM_START,
                     label_active: active,
@@ -146,6 +579,20 @@ pub fn Settings(
                 {
                     #[cfg(debug_assertions)]
                     rsx! {
+                        NumberInputU32 {
+                            label: "Pre-recording Memory Budget (MB)",
+                            label_class: SELECT_LABEL_CLASS,
+                            div_class: SELECT_DIV_CLASS,
+                            input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none",
+                            minimum_value: 0,
+                            on_input: move |frame_history_budget_mb| {
+                                on_settings(SettingsData {
+                                    frame_history_budget_mb,
+                                    ..settings_view.peek().clone()
+                                });
+                            },
+                            value: settings_view().frame_history_budget_mb,
+                        }
                         SettingsDebugButton {
                             label: "Capture Color Image",
                             on_click: move |_| async {
@@ -251,6 +698,28 @@ pub fn SettingsTextInput(label: String, on_input: EventHandler<String>, value: S
     }
 }
 
+#[component]
+fn SettingsWindowLayoutInput(
+    label: String,
+    disabled: bool,
+    minimum_value: i32,
+    on_input: EventHandler<i32>,
+    value: i32,
+) -> Element {
+    rsx! {
+        NumberInputI32 {
+            label,
+            label_class: SELECT_LABEL_CLASS,
+            div_class: SELECT_DIV_CLASS,
+            input_class: "w-44 h-7 text-xs text-gray-700 p-1 border border-gray-300 rounded outline-none disabled:cursor-not-allowed disabled:text-gray-400",
+            disabled,
+            minimum_value,
+            on_input,
+            value,
+        }
+    }
+}
+
 #[component]
 fn SettingsInputMethodSelect(
     app_coroutine: Coroutine<AppMessage>,
@@ -311,16 +780,137 @@ fn SettingsEnumSelect<T: 'static + Clone + PartialEq + Display + FromStr + IntoE
 }
 
 #[component]
-fn SettingsCaptureHandleSelect(settings_view: Memo<SettingsData>) -> Element {
+fn SettingsClientSelect(
+    app_coroutine: Coroutine<AppMessage>,
+    clients: ReadOnlySignal<Vec<usize>>,
+    client: ReadOnlySignal<usize>,
+) -> Element {
+    const CLIENT_REMOVE: usize = usize::MAX;
+    const WINDOW_NOT_SELECTED: usize = usize::MAX;
+    const WINDOWS_REFRESH: usize = usize::MAX - 1;
+
+    // Only windows with a process id can be found again when restoring the client on start
+    let mut windows = use_resource(move || async move {
+        spawn_blocking(query_client_windows)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|window| window.process_id.is_some())
+            .collect::<Vec<_>>()
+    });
+
+    rsx! {
+        Select::<usize> {
+            label: "Client",
+            div_class: SELECT_DIV_CLASS,
+            label_class: SELECT_LABEL_CLASS,
+            select_class: SELECT_CLASS,
+            options: clients()
+                .into_iter()
+                .enumerate()
+                .map(|(i, id)| (id, format!("Client {}", i + 1)))
+                .chain(
+                    (clients().len() > 1)
+                        .then(|| (CLIENT_REMOVE, "Remove last client".to_string())),
+                )
+                .collect::<Vec<_>>(),
+            disabled: false,
+            on_select: move |(_, id)| {
+                match id {
+                    CLIENT_REMOVE => app_coroutine.send(AppMessage::RemoveClient),
+                    id => app_coroutine.send(AppMessage::SelectClient(id)),
+                }
+            },
+            selected: client(),
+        }
+        // New clients must bot an explicitly selected window so that two clients never bot the
+        // same default game window
+        Select::<usize> {
+            label: "Add Client",
+            div_class: SELECT_DIV_CLASS,
+            label_class: SELECT_LABEL_CLASS,
+            select_class: SELECT_CLASS,
+            options: [(WINDOW_NOT_SELECTED, "Select window...".to_string())]
+                .into_iter()
+                .chain(
+                    windows()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(capture_handle_label)
+                        .enumerate(),
+                )
+                .chain([(WINDOWS_REFRESH, "Refresh windows...".to_string())])
+                .collect::<Vec<_>>(),
+            disabled: false,
+            on_select: move |(_, i)| {
+                if i == WINDOWS_REFRESH {
+                    windows.restart();
+                    return;
+                }
+                let window = windows
+                    .peek()
+                    .as_ref()
+                    .and_then(|windows| windows.get(i).cloned())
+                    .and_then(|window| {
+                        Some(CaptureHandleIdentity {
+                            title: window.title,
+                            process_id: window.process_id?,
+                        })
+                    });
+                if let Some(window) = window {
+                    app_coroutine.send(AppMessage::AddClient(window));
+                }
+            },
+            selected: WINDOW_NOT_SELECTED,
+        }
+        if clients().len() > 1 {
+            p { class: "text-xs text-gray-700",
+                "Each client bots the game window of its capture handle, the map and actions shown are of the selected client"
+            }
+        }
+    }
+}
+
+#[component]
+fn SettingsCaptureHandleSelect(
+    app_coroutine: Coroutine<AppMessage>,
+    settings_view: Memo<SettingsData>,
+    clients: ReadOnlySignal<Vec<usize>>,
+    client: ReadOnlySignal<usize>,
+) -> Element {
     const HANDLE_NOT_SELECTED: usize = usize::MAX;
     const HANDLES_REFRESH: usize = usize::MAX - 1;
 
     let mut selected_capture_handle = use_signal(|| None);
+    // Re-queries the selected handle when another client is selected
     let mut capture_handles = use_resource(move || async move {
-        let (names, selected) = query_capture_handles().await;
+        let _ = client();
+        let (handles, selected) = query_capture_handles().await;
         selected_capture_handle.set(selected);
-        names
+        handles
+    });
+    let multiple_game_windows = use_memo(move || {
+        capture_handles()
+            .map(|handles| handles.iter().filter(|handle| handle.is_game).count() > 1)
+            .unwrap_or_default()
     });
+    // Remembers the selection by title and process id so it is restored on start
+    let on_capture_handle = move |handle: Option<CaptureHandle>| {
+        let capture_handle = handle.and_then(|handle| {
+            Some(CaptureHandleIdentity {
+                title: handle.title,
+                process_id: handle.process_id?,
+            })
+        });
+        let index = clients
+            .peek()
+            .iter()
+            .position(|id| *id == *client.peek())
+            .unwrap_or_default();
+        let mut settings = settings_view.peek().clone();
+        settings.set_client_capture_handle(index, capture_handle);
+        app_coroutine.send(AppMessage::UpdateSettings(settings));
+    };
 
     use_effect(move || {
         let index = selected_capture_handle();
@@ -336,10 +926,10 @@ fn SettingsCaptureHandleSelect(settings_view: Memo<SettingsData>) -> Element {
             label_class: SELECT_LABEL_CLASS,
             select_class: SELECT_CLASS,
             options: match capture_handles() {
-                Some(names) => {
+                Some(handles) => {
                     [(HANDLE_NOT_SELECTED, "Default".to_string())]
                         .into_iter()
-                        .chain(names.into_iter().enumerate())
+                        .chain(handles.into_iter().map(capture_handle_label).enumerate())
                         .chain([(HANDLES_REFRESH, "Refresh handles...".to_string())])
                         .collect()
                 }
@@ -349,13 +939,37 @@ fn SettingsCaptureHandleSelect(settings_view: Memo<SettingsData>) -> Element {
             on_select: move |(_, i)| {
                 if i == HANDLE_NOT_SELECTED {
                     selected_capture_handle.set(None);
+                    on_capture_handle(None);
                 } else if i == HANDLES_REFRESH {
                     capture_handles.restart();
                 } else {
                     selected_capture_handle.set(Some(i));
+                    on_capture_handle(
+                        capture_handles
+                            .peek()
+                            .as_ref()
+                            .and_then(|handles| handles.get(i).cloned()),
+                    );
                 }
             },
             selected: selected_capture_handle().unwrap_or(HANDLE_NOT_SELECTED),
         }
+        if multiple_game_windows() && selected_capture_handle().is_none() {
+            p { class: "text-xs text-gray-700",
+                "Multiple game windows found, select the capture handle of the game to use"
+            }
+        }
+    }
+}
+
+fn capture_handle_label(handle: CaptureHandle) -> String {
+    let process_id = handle
+        .process_id
+        .map(|process_id| process_id.to_string())
+        .unwrap_or("?".to_string());
+    if handle.is_game {
+        format!("{} (PID {process_id}, Game)", handle.title)
+    } else {
+        format!("{} (PID {process_id})", handle.title)
     }
 }